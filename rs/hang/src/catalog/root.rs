@@ -242,7 +242,8 @@ impl CatalogConsumer {
 
 	/// Wait until the catalog track is closed.
 	pub async fn closed(&self) -> Result<()> {
-		Ok(self.track.closed().await?)
+		self.track.closed().await?;
+		Ok(())
 	}
 }
 
@@ -1,27 +1,456 @@
-use super::Result;
-use tokio::io::AsyncWrite;
+use super::{Error, Result};
+use crate::catalog::{AudioCodec, AudioConfig, VideoCodec, VideoConfig};
+use crate::model::{Frame, Timestamp};
+use bytes::{BufMut, Bytes, BytesMut};
+use mp4_atom::{Atom, Encode};
 
-use crate::{catalog, media};
+// The track ID used for every exported track; each track gets its own init segment.
+const TRACK_ID: u32 = 1;
 
-// Converts Karp -> fMP4
-pub struct Export<W: AsyncWrite + Unpin> {
-	output: W,
-	broadcast: media::BroadcastConsumer,
+// Sample flags: sample_depends_on=2 for keyframes, sample_depends_on=1 and sample_is_non_sync_sample otherwise.
+const SAMPLE_SYNC: u32 = 0x0200_0000;
+const SAMPLE_NON_SYNC: u32 = 0x0101_0000;
+
+/// Converts a hang track into fMP4/CMAF fragments.
+///
+/// This is the inverse of [super::Import], one track at a time.
+/// The init segment is generated from the catalog, and each call to [Export::fragment] produces a `moof` and `mdat` pair.
+///
+/// ## Supported Codecs
+///
+/// **Video:**
+/// - H.264 (AVC1), with a description
+/// - H.265 (HEV1/HVC1), with a description
+///
+/// **Audio:**
+/// - AAC (MP4A)
+/// - Opus
+pub struct Export {
+	// The encoded ftyp and moov atoms.
+	init: Bytes,
+
+	// The number of units per second used for timestamps.
+	timescale: u32,
+
+	// Audio frames are always keyframes.
+	video: bool,
+
+	// The moof sequence number, incremented for each fragment.
+	sequence: u32,
 }
 
-// TODO
-impl<W: AsyncWrite + Unpin> Export<W> {
-	pub async fn init(input: moq_lite::BroadcastConsumer, output: W) -> Result<Self> {
-		let broadcast = media::BroadcastConsumer::load(input).await?;
-		Ok(Self { broadcast, output })
+impl Export {
+	/// Create an exporter for a video rendition in the catalog.
+	pub fn video(config: &VideoConfig) -> Result<Self> {
+		let description = config.description.clone().ok_or(Error::MissingCodec)?;
+		let mut description = description.as_ref();
+
+		let visual = mp4_atom::Visual {
+			width: config.coded_width.unwrap_or_default() as _,
+			height: config.coded_height.unwrap_or_default() as _,
+			data_reference_index: 1,
+			..Default::default()
+		};
+
+		let codec: mp4_atom::Codec = match &config.codec {
+			VideoCodec::H264(_) => mp4_atom::Avc1 {
+				visual,
+				avcc: mp4_atom::Avcc::decode_body(&mut description)?,
+				..Default::default()
+			}
+			.into(),
+			VideoCodec::H265(h265) if h265.in_band => mp4_atom::Hev1 {
+				visual,
+				hvcc: mp4_atom::Hvcc::decode_body(&mut description)?,
+				..Default::default()
+			}
+			.into(),
+			VideoCodec::H265(_) => mp4_atom::Hvc1 {
+				visual,
+				hvcc: mp4_atom::Hvcc::decode_body(&mut description)?,
+				..Default::default()
+			}
+			.into(),
+			codec => return Err(Error::UnsupportedCodec(codec.to_string())),
+		};
+
+		let timescale = 90_000;
+		let trak = Self::trak(codec, timescale, true, &config.coded_width, &config.coded_height);
+
+		Ok(Self {
+			init: Self::encode_init(trak)?,
+			timescale,
+			video: true,
+			sequence: 0,
+		})
+	}
+
+	/// Create an exporter for an audio rendition in the catalog.
+	pub fn audio(config: &AudioConfig) -> Result<Self> {
+		let audio = mp4_atom::Audio {
+			data_reference_index: 1,
+			channel_count: config.channel_count as _,
+			sample_size: 16,
+			// The sample rate is a 16.16 fixed point, so higher rates are truncated as is tradition.
+			sample_rate: (config.sample_rate.min(u16::MAX as u32) as u16).into(),
+		};
+
+		let codec: mp4_atom::Codec = match &config.codec {
+			AudioCodec::AAC(aac) => {
+				let freq_index = aac_frequency_index(config.sample_rate)
+					.ok_or_else(|| Error::UnsupportedCodec(format!("aac at {}Hz", config.sample_rate)))?;
+				let bitrate = config.bitrate.unwrap_or_default().min(u32::MAX as u64) as u32;
+
+				mp4_atom::Mp4a {
+					audio,
+					esds: mp4_atom::Esds {
+						es_desc: mp4_atom::esds::EsDescriptor {
+							es_id: TRACK_ID as _,
+							dec_config: mp4_atom::esds::DecoderConfig {
+								object_type_indication: 0x40,
+								stream_type: 0x05,
+								max_bitrate: bitrate,
+								avg_bitrate: bitrate,
+								dec_specific: mp4_atom::esds::DecoderSpecific {
+									profile: aac.profile,
+									freq_index,
+									chan_conf: config.channel_count as _,
+								},
+								..Default::default()
+							},
+							..Default::default()
+						},
+					},
+					btrt: None,
+					taic: None,
+				}
+				.into()
+			}
+			AudioCodec::Opus => mp4_atom::Opus {
+				audio,
+				dops: mp4_atom::Dops {
+					output_channel_count: config.channel_count as _,
+					pre_skip: 0,
+					input_sample_rate: config.sample_rate,
+					output_gain: 0,
+				},
+			}
+			.into(),
+			codec => return Err(Error::UnsupportedCodec(codec.to_string())),
+		};
+
+		let timescale = config.sample_rate;
+		let trak = Self::trak(codec, timescale, false, &None, &None);
+
+		Ok(Self {
+			init: Self::encode_init(trak)?,
+			timescale,
+			video: false,
+			sequence: 0,
+		})
+	}
+
+	fn trak(
+		codec: mp4_atom::Codec,
+		timescale: u32,
+		video: bool,
+		width: &Option<u32>,
+		height: &Option<u32>,
+	) -> mp4_atom::Trak {
+		mp4_atom::Trak {
+			tkhd: mp4_atom::Tkhd {
+				track_id: TRACK_ID,
+				enabled: true,
+				volume: if video { 0.into() } else { 1.into() },
+				width: (width.unwrap_or_default() as u16).into(),
+				height: (height.unwrap_or_default() as u16).into(),
+				..Default::default()
+			},
+			mdia: mp4_atom::Mdia {
+				mdhd: mp4_atom::Mdhd {
+					timescale,
+					language: "und".into(),
+					..Default::default()
+				},
+				hdlr: mp4_atom::Hdlr {
+					handler: if video { b"vide" } else { b"soun" }.into(),
+					name: if video { "VideoHandler" } else { "SoundHandler" }.into(),
+				},
+				minf: mp4_atom::Minf {
+					vmhd: video.then(Default::default),
+					smhd: (!video).then(Default::default),
+					dinf: mp4_atom::Dinf {
+						dref: mp4_atom::Dref {
+							urls: vec![mp4_atom::Url::default()],
+						},
+					},
+					stbl: mp4_atom::Stbl {
+						stsd: mp4_atom::Stsd { codecs: vec![codec] },
+						stco: Some(Default::default()),
+						..Default::default()
+					},
+				},
+			},
+			..Default::default()
+		}
 	}
 
-	pub async fn run(self) -> Result<()> {
-		todo!();
-		Ok(())
+	fn encode_init(trak: mp4_atom::Trak) -> Result<Bytes> {
+		let ftyp = mp4_atom::Ftyp {
+			major_brand: b"iso6".into(),
+			minor_version: 0,
+			compatible_brands: vec![b"iso6".into(), b"cmfc".into(), b"mp41".into()],
+		};
+
+		let moov = mp4_atom::Moov {
+			mvhd: mp4_atom::Mvhd {
+				next_track_id: TRACK_ID + 1,
+				..Default::default()
+			},
+			mvex: Some(mp4_atom::Mvex {
+				mehd: None,
+				trex: vec![mp4_atom::Trex {
+					track_id: TRACK_ID,
+					default_sample_description_index: 1,
+					..Default::default()
+				}],
+			}),
+			trak: vec![trak],
+			..Default::default()
+		};
+
+		let mut buf = BytesMut::new();
+		ftyp.encode(&mut buf)?;
+		moov.encode(&mut buf)?;
+
+		Ok(buf.freeze())
+	}
+
+	/// The init segment (`ftyp` and `moov`) for this track.
+	pub fn init(&self) -> Bytes {
+		self.init.clone()
+	}
+
+	/// The number of units per second used for timestamps within each fragment.
+	pub fn timescale(&self) -> u32 {
+		self.timescale
 	}
 
-	pub fn catalog(&self) -> &catalog::Broadcast {
-		self.broadcast.catalog()
+	/// Encode the frames as a single fragment (`moof` and `mdat`).
+	///
+	/// Each frame lasts until the timestamp of the next frame, and the last frame lasts until `end`.
+	pub fn fragment(&mut self, frames: &[Frame], end: Timestamp) -> Result<Bytes> {
+		let first = frames.first().ok_or(Error::InvalidSize)?;
+
+		self.sequence += 1;
+
+		let base = self.units(first.timestamp);
+		let mut entries = Vec::with_capacity(frames.len());
+		let mut size = 0;
+
+		for (index, frame) in frames.iter().enumerate() {
+			let next = frames.get(index + 1).map(|next| next.timestamp).unwrap_or(end);
+			let duration = self.units(next).saturating_sub(self.units(frame.timestamp));
+			let flags = match !self.video || frame.keyframe {
+				true => SAMPLE_SYNC,
+				false => SAMPLE_NON_SYNC,
+			};
+
+			entries.push((duration as u32, frame.payload.len() as u32, flags));
+			size += frame.payload.len();
+		}
+
+		// We encode the moof by hand because mp4-atom writes a stray first_sample_flags field in the trun.
+		// mfhd + tfhd + tfdt + trun header, then 12 bytes per sample.
+		let trun_size = 8 + 4 + 4 + 4 + entries.len() * 12;
+		let traf_size = 8 + 16 + 20 + trun_size;
+		let moof_size = 8 + 16 + traf_size;
+		let mdat_size = 8 + size;
+
+		let mut buf = BytesMut::with_capacity(moof_size + mdat_size);
+
+		buf.put_u32(moof_size as u32);
+		buf.put_slice(b"moof");
+
+		buf.put_u32(16);
+		buf.put_slice(b"mfhd");
+		buf.put_u32(0);
+		buf.put_u32(self.sequence);
+
+		buf.put_u32(traf_size as u32);
+		buf.put_slice(b"traf");
+
+		// default-base-is-moof, so data offsets are relative to the start of the moof.
+		buf.put_u32(16);
+		buf.put_slice(b"tfhd");
+		buf.put_u32(0x02_0000);
+		buf.put_u32(TRACK_ID);
+
+		buf.put_u32(20);
+		buf.put_slice(b"tfdt");
+		buf.put_u32(0x0100_0000);
+		buf.put_u64(base);
+
+		// data-offset-present, sample-duration-present, sample-size-present, sample-flags-present
+		buf.put_u32(trun_size as u32);
+		buf.put_slice(b"trun");
+		buf.put_u32(0x0000_0701);
+		buf.put_u32(entries.len() as u32);
+		buf.put_i32((moof_size + 8) as i32);
+
+		for (duration, size, flags) in entries {
+			buf.put_u32(duration);
+			buf.put_u32(size);
+			buf.put_u32(flags);
+		}
+
+		buf.put_u32(mdat_size as u32);
+		buf.put_slice(b"mdat");
+		for frame in frames {
+			buf.put_slice(&frame.payload);
+		}
+
+		Ok(buf.freeze())
+	}
+
+	// Convert a timestamp into the track timescale.
+	fn units(&self, timestamp: Timestamp) -> u64 {
+		(timestamp.as_micros() * self.timescale as u128 / 1_000_000) as u64
+	}
+}
+
+// The index into the table of sampling frequencies in the AudioSpecificConfig.
+fn aac_frequency_index(sample_rate: u32) -> Option<u8> {
+	const RATES: [u32; 13] = [
+		96000, 88200, 64000, 48000, 44100, 32000, 24000, 22050, 16000, 12000, 11025, 8000, 7350,
+	];
+
+	RATES
+		.iter()
+		.position(|&rate| rate == sample_rate)
+		.map(|index| index as u8)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::catalog::{AAC, H264};
+	use mp4_atom::{Decode, Mdat, Moof, Moov};
+	use std::time::Duration;
+
+	fn frame(millis: u64, keyframe: bool, payload: &'static [u8]) -> Frame {
+		Frame {
+			timestamp: Duration::from_millis(millis),
+			keyframe,
+			payload: Bytes::from_static(payload),
+		}
+	}
+
+	#[test]
+	fn test_video() {
+		let avcc = mp4_atom::Avcc {
+			configuration_version: 1,
+			avc_profile_indication: 0x42,
+			profile_compatibility: 0,
+			avc_level_indication: 0x1f,
+			length_size: 4,
+			sequence_parameter_sets: vec![vec![0x67, 0x64, 0x00, 0x1f]],
+			picture_parameter_sets: vec![vec![0x68, 0xee, 0x3c, 0x80]],
+			ext: None,
+		};
+
+		let mut description = BytesMut::new();
+		avcc.encode_body(&mut description).unwrap();
+
+		let config = VideoConfig {
+			codec: H264 {
+				profile: 0x42,
+				constraints: 0,
+				level: 0x1f,
+			}
+			.into(),
+			description: Some(description.freeze()),
+			coded_width: Some(1280),
+			coded_height: Some(720),
+			display_ratio_width: None,
+			display_ratio_height: None,
+			bitrate: None,
+			framerate: None,
+			optimize_for_latency: None,
+		};
+
+		let mut export = Export::video(&config).unwrap();
+
+		let mut init = export.init();
+		let _ftyp = mp4_atom::Ftyp::decode(&mut init).unwrap();
+		let moov = Moov::decode(&mut init).unwrap();
+		assert_eq!(moov.trak.len(), 1);
+		match &moov.trak[0].mdia.minf.stbl.stsd.codecs[0] {
+			mp4_atom::Codec::Avc1(avc1) => {
+				assert_eq!(avc1.avcc, avcc);
+				assert_eq!(avc1.visual.width, 1280);
+			}
+			codec => panic!("unexpected codec: {codec:?}"),
+		}
+
+		let frames = [frame(1000, true, b"key"), frame(1040, false, b"delta")];
+		let mut fragment = export.fragment(&frames, Duration::from_millis(1080)).unwrap();
+		let len = fragment.len();
+
+		let moof = Moof::decode(&mut fragment).unwrap();
+		let mdat = Mdat::decode(&mut fragment).unwrap();
+		assert_eq!(mdat.data, b"keydelta");
+
+		assert_eq!(moof.mfhd.sequence_number, 1);
+		let traf = &moof.traf[0];
+		assert_eq!(traf.tfdt.as_ref().unwrap().base_media_decode_time, 90_000);
+
+		let trun = traf.trun.as_ref().unwrap();
+		assert_eq!(trun.data_offset, Some((len - mdat.data.len()) as i32));
+		assert_eq!(trun.entries.len(), 2);
+		assert_eq!(trun.entries[0].duration, Some(3600));
+		assert_eq!(trun.entries[0].size, Some(3));
+		assert_eq!(trun.entries[0].flags, Some(SAMPLE_SYNC));
+		assert_eq!(trun.entries[1].flags, Some(SAMPLE_NON_SYNC));
+	}
+
+	#[test]
+	fn test_audio() {
+		let config = AudioConfig {
+			codec: AAC { profile: 2 }.into(),
+			sample_rate: 48000,
+			channel_count: 2,
+			bitrate: Some(128_000),
+			description: None,
+		};
+
+		let mut export = Export::audio(&config).unwrap();
+		assert_eq!(export.timescale(), 48000);
+
+		let mut init = export.init();
+		let _ftyp = mp4_atom::Ftyp::decode(&mut init).unwrap();
+		let moov = Moov::decode(&mut init).unwrap();
+		match &moov.trak[0].mdia.minf.stbl.stsd.codecs[0] {
+			mp4_atom::Codec::Mp4a(mp4a) => {
+				let specific = mp4a.esds.es_desc.dec_config.dec_specific;
+				assert_eq!(specific.profile, 2);
+				assert_eq!(specific.freq_index, 3);
+				assert_eq!(specific.chan_conf, 2);
+			}
+			codec => panic!("unexpected codec: {codec:?}"),
+		}
+
+		let frames = [frame(0, false, b"a"), frame(20, false, b"b")];
+		let mut fragment = export.fragment(&frames, Duration::from_millis(40)).unwrap();
+		let moof = Moof::decode(&mut fragment).unwrap();
+		let trun = moof.traf[0].trun.as_ref().unwrap();
+		assert_eq!(trun.entries[1].duration, Some(960));
+		assert_eq!(trun.entries[1].flags, Some(SAMPLE_SYNC));
+
+		let unsupported = AudioConfig {
+			codec: AudioCodec::Unknown("flac".to_string()),
+			..config
+		};
+		assert!(matches!(Export::audio(&unsupported), Err(Error::UnsupportedCodec(_))));
 	}
 }
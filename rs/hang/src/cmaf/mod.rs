@@ -1,7 +1,7 @@
 mod error;
-//mod export;
+mod export;
 mod import;
 
 pub use error::*;
-//pub use export::*;
+pub use export::*;
 pub use import::*;
@@ -198,7 +198,8 @@ impl TrackConsumer {
 
 	/// Wait until the track is closed.
 	pub async fn closed(&self) -> Result<(), Error> {
-		Ok(self.inner.closed().await?)
+		self.inner.closed().await?;
+		Ok(())
 	}
 }
 
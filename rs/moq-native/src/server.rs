@@ -119,10 +119,9 @@ impl Server {
 			.with_no_client_auth()
 			.with_cert_resolver(Arc::new(serve));
 
-		tls.alpn_protocols = vec![
-			web_transport_quinn::ALPN.as_bytes().to_vec(),
-			moq_lite::ALPN.as_bytes().to_vec(),
-		];
+		tls.alpn_protocols = vec![web_transport_quinn::ALPN.as_bytes().to_vec()];
+		tls.alpn_protocols
+			.extend(moq_lite::ALPNS.iter().map(|alpn| alpn.as_bytes().to_vec()));
 		tls.key_log = Arc::new(rustls::KeyLogFile::new());
 
 		let tls: quinn::crypto::rustls::QuicServerConfig = tls.try_into()?;
@@ -213,7 +212,7 @@ impl Server {
 					.context("failed to receive WebTransport request")?;
 				Ok(Request::WebTransport(request))
 			}
-			moq_lite::ALPN => Ok(Request::Quic(QuicRequest::accept(conn))),
+			alpn if moq_lite::ALPNS.contains(&alpn) => Ok(Request::Quic(QuicRequest::accept(conn))),
 			_ => anyhow::bail!("unsupported ALPN: {alpn}"),
 		}
 	}
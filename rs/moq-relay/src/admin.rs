@@ -0,0 +1,371 @@
+use std::{
+	collections::BTreeMap,
+	net,
+	path::PathBuf,
+	sync::{
+		atomic::{AtomicU64, Ordering},
+		Arc, Mutex,
+	},
+};
+
+use anyhow::Context;
+use axum::{
+	extract::{FromRequestParts, Path, State},
+	http::{header, request::Parts, StatusCode},
+	routing::{delete, get, post},
+	Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Notify;
+
+use crate::{Cluster, ClusterMode, Metrics, RecordError, Recorder, RecordingInfo, RemoteHealth, RemoteLink};
+
+#[derive(clap::Args, Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct AdminConfig {
+	/// Serve the admin API under /admin, requiring the bearer token in this file.
+	/// The admin API is disabled unless this is set.
+	#[arg(long = "web-admin-token", id = "web-admin-token", env = "MOQ_WEB_ADMIN_TOKEN")]
+	pub token: Option<PathBuf>,
+}
+
+/// A live session, as listed by the admin API.
+#[derive(Clone, Debug, Serialize)]
+pub struct SessionInfo {
+	pub id: u64,
+	pub transport: &'static str,
+	pub remote: Option<net::SocketAddr>,
+	pub root: String,
+	pub publish: Vec<String>,
+	pub subscribe: Vec<String>,
+}
+
+struct SessionEntry {
+	info: SessionInfo,
+	close: Arc<Notify>,
+}
+
+/// The live sessions, so they can be listed and closed via the admin API.
+#[derive(Clone, Default)]
+pub struct Sessions {
+	next: Arc<AtomicU64>,
+	active: Arc<Mutex<BTreeMap<u64, SessionEntry>>>,
+
+	// Counters shared by every accepted session.
+	stats: moq_lite::Stats,
+
+	// Relay-wide gauges, updated by connections and authentication.
+	metrics: Metrics,
+}
+
+impl Sessions {
+	/// The counters to attach to each accepted session, aggregated for metrics.
+	pub fn stats(&self) -> &moq_lite::Stats {
+		&self.stats
+	}
+
+	/// The gauges served at `/metrics`.
+	pub fn metrics(&self) -> &Metrics {
+		&self.metrics
+	}
+
+	/// Allocate a unique connection ID, shared by every transport.
+	pub fn next_id(&self) -> u64 {
+		self.next.fetch_add(1, Ordering::Relaxed)
+	}
+
+	/// Register a session until the returned handle is dropped.
+	pub fn register(&self, info: SessionInfo) -> SessionHandle {
+		let id = info.id;
+		let close = Arc::new(Notify::new());
+
+		self.active.lock().unwrap().insert(
+			id,
+			SessionEntry {
+				info,
+				close: close.clone(),
+			},
+		);
+
+		SessionHandle {
+			id,
+			close,
+			active: self.active.clone(),
+		}
+	}
+
+	pub fn list(&self) -> Vec<SessionInfo> {
+		self.active
+			.lock()
+			.unwrap()
+			.values()
+			.map(|entry| entry.info.clone())
+			.collect()
+	}
+
+	/// Ask a session to close, returning false if it doesn't exist.
+	pub fn close(&self, id: u64) -> bool {
+		match self.active.lock().unwrap().get(&id) {
+			Some(entry) => {
+				// Stores a permit, so the session closes even if it isn't waiting yet.
+				entry.close.notify_one();
+				true
+			}
+			None => false,
+		}
+	}
+}
+
+/// Unregisters a session when dropped.
+pub struct SessionHandle {
+	id: u64,
+	close: Arc<Notify>,
+	active: Arc<Mutex<BTreeMap<u64, SessionEntry>>>,
+}
+
+impl SessionHandle {
+	/// Block until the session is closed via the admin API.
+	pub async fn closed(&self) {
+		self.close.notified().await
+	}
+}
+
+impl Drop for SessionHandle {
+	fn drop(&mut self) {
+		self.active.lock().unwrap().remove(&self.id);
+	}
+}
+
+pub struct Admin {
+	token: String,
+	sessions: Sessions,
+	cluster: Cluster,
+	recorder: Option<Recorder>,
+}
+
+impl Admin {
+	/// Load the token from disk, returning None if the admin API is disabled.
+	pub fn new(
+		config: &AdminConfig,
+		sessions: Sessions,
+		cluster: Cluster,
+		recorder: Option<Recorder>,
+	) -> anyhow::Result<Option<Self>> {
+		let path = match &config.token {
+			Some(path) => path,
+			None => return Ok(None),
+		};
+
+		let token = std::fs::read_to_string(path).context("failed to read admin token")?;
+		let token = token.trim().to_string();
+		anyhow::ensure!(!token.is_empty(), "empty admin token");
+
+		Ok(Some(Self {
+			token,
+			sessions,
+			cluster,
+			recorder,
+		}))
+	}
+
+	/// The routes to nest under /admin.
+	pub fn router<S>(self) -> Router<S> {
+		Router::new()
+			.route("/sessions", get(list_sessions))
+			.route("/sessions/{id}", delete(close_session))
+			.route("/broadcasts", get(list_broadcasts))
+			.route("/broadcasts/{*path}", delete(unpublish_broadcast))
+			.route("/cluster", get(cluster_topology))
+			.route("/recordings", get(list_recordings))
+			.route("/recordings/{*path}", post(start_recording).delete(stop_recording))
+			.with_state(Arc::new(self))
+	}
+}
+
+// Rejects the request unless it has the admin bearer token.
+struct Authorized;
+
+impl FromRequestParts<Arc<Admin>> for Authorized {
+	type Rejection = StatusCode;
+
+	async fn from_request_parts(parts: &mut Parts, state: &Arc<Admin>) -> Result<Self, Self::Rejection> {
+		let token = parts
+			.headers
+			.get(header::AUTHORIZATION)
+			.and_then(|value| value.to_str().ok())
+			.and_then(|value| value.strip_prefix("Bearer "))
+			.ok_or(StatusCode::UNAUTHORIZED)?;
+
+		match constant_time_eq(token.as_bytes(), state.token.as_bytes()) {
+			true => Ok(Self),
+			false => Err(StatusCode::UNAUTHORIZED),
+		}
+	}
+}
+
+// Compare without returning early, so the token can't be guessed byte by byte.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+	a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+async fn list_sessions(_: Authorized, State(admin): State<Arc<Admin>>) -> Json<Vec<SessionInfo>> {
+	Json(admin.sessions.list())
+}
+
+async fn close_session(_: Authorized, State(admin): State<Arc<Admin>>, Path(id): Path<u64>) -> StatusCode {
+	match admin.sessions.close(id) {
+		true => StatusCode::NO_CONTENT,
+		false => StatusCode::NOT_FOUND,
+	}
+}
+
+#[derive(Serialize)]
+struct BroadcastInfo {
+	path: String,
+	/// "primary" if published by a local client, or "secondary" if by another cluster node.
+	origin: &'static str,
+	subscribers: u64,
+}
+
+async fn list_broadcasts(_: Authorized, State(admin): State<Arc<Admin>>) -> Json<Vec<BroadcastInfo>> {
+	let subscribers = admin.sessions.stats().subscribers();
+	let mut broadcasts = Vec::new();
+
+	for (origin, consumer) in [
+		("primary", &admin.cluster.primary.consumer),
+		("secondary", &admin.cluster.secondary.consumer),
+	] {
+		let mut consumer = consumer.consume();
+		while let Some((path, active)) = consumer.try_announced() {
+			if active.is_some() {
+				broadcasts.push(BroadcastInfo {
+					subscribers: subscribers.get(path.as_str()).copied().unwrap_or_default(),
+					path: path.to_string(),
+					origin,
+				});
+			}
+		}
+	}
+
+	Json(broadcasts)
+}
+
+async fn unpublish_broadcast(_: Authorized, State(admin): State<Arc<Admin>>, Path(path): Path<String>) -> StatusCode {
+	match admin.cluster.unpublish(&path) {
+		true => {
+			tracing::info!(broadcast = %path, "unpublished by admin");
+			StatusCode::NO_CONTENT
+		}
+		false => StatusCode::NOT_FOUND,
+	}
+}
+
+#[derive(Serialize)]
+struct RemoteInfo {
+	node: String,
+	link: RemoteLink,
+	#[serde(flatten)]
+	health: RemoteHealth,
+}
+
+#[derive(Serialize)]
+struct ClusterInfo {
+	/// Our advertised hostname, if any.
+	advertise: Option<String>,
+	/// How broadcasts are routed between nodes.
+	mode: ClusterMode,
+	/// True if we're one of the root nodes.
+	root: bool,
+	/// The root nodes we connect to, excluding ourselves.
+	roots: Vec<String>,
+	/// Every other node we're connected to, including the roots.
+	remotes: Vec<RemoteInfo>,
+	/// The prefixes owned by each other node, only used in lazy mode.
+	#[serde(skip_serializing_if = "BTreeMap::is_empty")]
+	owners: BTreeMap<String, Vec<String>>,
+}
+
+async fn cluster_topology(_: Authorized, State(admin): State<Arc<Admin>>) -> Json<ClusterInfo> {
+	let remotes = admin
+		.cluster
+		.remotes()
+		.into_iter()
+		.map(|(node, link, health)| RemoteInfo { node, link, health })
+		.collect();
+
+	Json(ClusterInfo {
+		advertise: admin.cluster.advertise().map(String::from),
+		mode: admin.cluster.mode(),
+		root: admin.cluster.is_root(),
+		roots: admin.cluster.roots().into_iter().map(String::from).collect(),
+		remotes,
+		owners: admin.cluster.owners(),
+	})
+}
+
+async fn list_recordings(
+	_: Authorized,
+	State(admin): State<Arc<Admin>>,
+) -> Result<Json<Vec<RecordingInfo>>, StatusCode> {
+	let recorder = admin.recorder.as_ref().ok_or(StatusCode::NOT_FOUND)?;
+	Ok(Json(recorder.list()))
+}
+
+async fn start_recording(
+	_: Authorized,
+	State(admin): State<Arc<Admin>>,
+	Path(path): Path<String>,
+) -> Result<(StatusCode, Json<RecordingInfo>), StatusCode> {
+	let recorder = admin.recorder.as_ref().ok_or(StatusCode::NOT_FOUND)?;
+	match recorder.start(&path) {
+		Ok(info) => Ok((StatusCode::CREATED, Json(info))),
+		Err(RecordError::NotFound) => Err(StatusCode::NOT_FOUND),
+		Err(RecordError::Duplicate) => Err(StatusCode::CONFLICT),
+	}
+}
+
+async fn stop_recording(_: Authorized, State(admin): State<Arc<Admin>>, Path(path): Path<String>) -> StatusCode {
+	match admin.recorder.as_ref().is_some_and(|recorder| recorder.stop(&path)) {
+		true => StatusCode::NO_CONTENT,
+		false => StatusCode::NOT_FOUND,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_sessions() {
+		let sessions = Sessions::default();
+		let id = sessions.next_id();
+		assert_eq!(sessions.next_id(), id + 1);
+
+		let handle = sessions.register(SessionInfo {
+			id,
+			transport: "quic",
+			remote: None,
+			root: "demo".to_string(),
+			publish: vec!["".to_string()],
+			subscribe: Vec::new(),
+		});
+
+		assert_eq!(sessions.list().len(), 1);
+		assert!(sessions.close(id));
+		assert!(!sessions.close(id + 1));
+
+		// The close is remembered even though nothing was waiting.
+		futures::executor::block_on(handle.closed());
+
+		drop(handle);
+		assert!(sessions.list().is_empty());
+		assert!(!sessions.close(id));
+	}
+
+	#[test]
+	fn test_constant_time_eq() {
+		assert!(constant_time_eq(b"secret", b"secret"));
+		assert!(!constant_time_eq(b"secret", b"secreT"));
+		assert!(!constant_time_eq(b"secret", b"secrets"));
+	}
+}
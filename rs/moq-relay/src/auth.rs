@@ -4,6 +4,8 @@ use axum::http;
 use moq_lite::{AsPath, Path, PathOwned};
 use serde::{Deserialize, Serialize};
 
+use crate::Metrics;
+
 #[derive(thiserror::Error, Debug, Clone)]
 pub enum AuthError {
 	#[error("authentication is disabled")]
@@ -19,6 +21,18 @@ pub enum AuthError {
 	IncorrectRoot,
 }
 
+impl AuthError {
+	/// A short label for the variant, used by [crate::Metrics].
+	pub fn kind(&self) -> &'static str {
+		match self {
+			Self::UnexpectedToken => "unexpected_token",
+			Self::ExpectedToken => "expected_token",
+			Self::DecodeFailed => "decode_failed",
+			Self::IncorrectRoot => "incorrect_root",
+		}
+	}
+}
+
 impl From<AuthError> for http::StatusCode {
 	fn from(_: AuthError) -> Self {
 		http::StatusCode::UNAUTHORIZED
@@ -64,6 +78,7 @@ pub struct AuthToken {
 pub struct Auth {
 	key: Option<Arc<moq_token::Key>>,
 	public: Option<PathOwned>,
+	metrics: Metrics,
 }
 
 impl Auth {
@@ -84,12 +99,27 @@ impl Auth {
 		Ok(Self {
 			key: key.map(Arc::new),
 			public: public.map(|p| p.as_path().to_owned()),
+			metrics: Metrics::default(),
 		})
 	}
 
+	/// Count authentication failures in the provided metrics.
+	pub fn with_metrics(mut self, metrics: Metrics) -> Self {
+		self.metrics = metrics;
+		self
+	}
+
 	// Parse the token from the user provided URL, returning the claims if successful.
 	// If no token is provided, then the claims will use the public path if it is set.
 	pub fn verify(&self, path: &str, token: Option<&str>) -> Result<AuthToken, AuthError> {
+		let res = self.verify_token(path, token);
+		if let Err(err) = &res {
+			self.metrics.auth_failure(err);
+		}
+		res
+	}
+
+	fn verify_token(&self, path: &str, token: Option<&str>) -> Result<AuthToken, AuthError> {
 		// Find the token in the query parameters.
 		// ?jwt=...
 		let claims = if let Some(token) = token {
@@ -153,6 +183,22 @@ impl Auth {
 			cluster: claims.cluster,
 		})
 	}
+
+	/// Verify a session, where the [moq_lite::Authorization] setup extension takes precedence over the URL.
+	///
+	/// The path and token come from the URL, which are empty for raw QUIC.
+	/// An extension without a token falls back to the token in the URL.
+	pub fn verify_session(
+		&self,
+		path: &str,
+		token: Option<&str>,
+		extension: Option<moq_lite::Authorization>,
+	) -> Result<AuthToken, AuthError> {
+		match extension {
+			Some(auth) => self.verify(auth.root.as_str(), auth.token.as_deref().or(token)),
+			None => self.verify(path, token),
+		}
+	}
 }
 
 #[cfg(test)]
@@ -578,4 +624,61 @@ mod tests {
 
 		Ok(())
 	}
+
+	#[test]
+	fn test_verify_session() -> anyhow::Result<()> {
+		let (key_file, key) = create_test_key()?;
+		let auth = Auth::new(AuthConfig {
+			key: Some(key_file.path().to_string_lossy().to_string()),
+			public: Some("anon".to_string()),
+		})?;
+
+		let claims = moq_token::Claims {
+			root: "room/123".to_string(),
+			subscribe: vec!["".to_string()],
+			..Default::default()
+		};
+		let jwt = key.encode(&claims)?;
+
+		// A token in the URL.
+		let token = auth.verify_session("/room/123", Some(&jwt), None)?;
+		assert_eq!(token.root, "room/123".as_path());
+
+		// A token in the extension, as used by raw QUIC without a URL.
+		let extension = moq_lite::Authorization {
+			root: "room/123".into(),
+			token: Some(jwt.clone()),
+		};
+		let token = auth.verify_session("", None, Some(extension))?;
+		assert_eq!(token.root, "room/123".as_path());
+
+		// A WebTransport URL without a token doesn't need to be public when the extension is used.
+		let extension = moq_lite::Authorization {
+			root: "room/123".into(),
+			token: Some(jwt.clone()),
+		};
+		let token = auth.verify_session("/room/123", None, Some(extension))?;
+		assert_eq!(token.root, "room/123".as_path());
+
+		// The extension takes precedence over an anonymous URL.
+		let extension = moq_lite::Authorization {
+			root: "room/123".into(),
+			token: Some(jwt.clone()),
+		};
+		let token = auth.verify_session("/anon", None, Some(extension))?;
+		assert_eq!(token.root, "room/123".as_path());
+
+		// An extension for a root that doesn't match the token is rejected, even with a valid URL.
+		let extension = moq_lite::Authorization {
+			root: "room/456".into(),
+			token: Some(jwt.clone()),
+		};
+		assert!(auth.verify_session("/room/123", Some(&jwt), Some(extension)).is_err());
+
+		// No credentials at all are rejected outside the public path.
+		assert!(auth.verify_session("", None, None).is_err());
+		assert!(auth.verify_session("/room/123", None, None).is_err());
+
+		Ok(())
+	}
 }
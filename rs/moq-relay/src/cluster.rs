@@ -1,20 +1,35 @@
-use std::{collections::HashMap, path::PathBuf, sync::Arc};
+use std::{
+	collections::{BTreeMap, HashMap},
+	path::PathBuf,
+	sync::{
+		atomic::{AtomicU64, Ordering},
+		Arc, Mutex,
+	},
+	time::Duration,
+};
 
 use anyhow::Context;
-use moq_lite::{AsPath, Broadcast, BroadcastConsumer, BroadcastProducer, Origin, OriginConsumer, OriginProducer};
+use moq_lite::{
+	AsPath, Broadcast, BroadcastConsumer, BroadcastProducer, Origin, OriginConsumer, OriginProducer, PathOwned, Track,
+	TrackProducer, TrackStatusRequest,
+};
+use tokio::sync::watch;
 use tracing::Instrument;
 use url::Url;
 
-use crate::AuthToken;
+use crate::{AuthToken, RouteGuard, Routes, OWNED_TRACK};
 
 #[serde_with::serde_as]
 #[derive(clap::Args, Clone, Debug, serde::Serialize, serde::Deserialize, Default)]
 #[serde_with::skip_serializing_none]
 #[serde(default, deny_unknown_fields)]
 pub struct ClusterConfig {
-	/// Connect to this hostname in order to discover other nodes.
-	#[arg(long = "cluster-connect", env = "MOQ_CLUSTER_CONNECT")]
-	pub connect: Option<String>,
+	/// Connect to these root nodes in order to discover other nodes.
+	/// A node is a root if its advertised hostname is in this list; roots connect to each other.
+	#[arg(long = "cluster-connect", value_delimiter = ',', env = "MOQ_CLUSTER_CONNECT")]
+	#[serde_as(as = "serde_with::OneOrMany<_>")]
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	pub connect: Vec<String>,
 
 	/// Use the token in this file when connecting to other nodes.
 	#[arg(long = "cluster-token", env = "MOQ_CLUSTER_TOKEN")]
@@ -34,6 +49,41 @@ pub struct ClusterConfig {
 		env = "MOQ_CLUSTER_PREFIX"
 	)]
 	pub prefix: String,
+
+	/// How broadcasts are routed between nodes.
+	/// Defaults to "mesh".
+	#[arg(long = "cluster-mode", id = "cluster-mode", value_enum, env = "MOQ_CLUSTER_MODE")]
+	pub mode: Option<ClusterMode>,
+
+	/// The maximum delay between attempts to reconnect to another node, in seconds.
+	/// Defaults to 300 (5 minutes).
+	#[arg(long = "cluster-backoff", id = "cluster-backoff", env = "MOQ_CLUSTER_BACKOFF")]
+	pub backoff: Option<u64>,
+
+	/// Give up on a node after this many consecutive failed attempts, until it's announced again.
+	/// Roots are never given up on. Defaults to retrying forever.
+	#[arg(long = "cluster-retries", id = "cluster-retries", env = "MOQ_CLUSTER_RETRIES")]
+	pub retries: Option<u32>,
+}
+
+// The default maximum delay between reconnects.
+const BACKOFF_MAX: Duration = Duration::from_secs(300);
+
+// Return how long to wait before reconnecting, doubling with each consecutive failure.
+fn backoff(failures: u32, max: Duration) -> Duration {
+	let delay = 1u64.checked_shl(failures).unwrap_or(u64::MAX);
+	Duration::from_secs(delay).min(max)
+}
+
+/// How broadcasts are routed between cluster nodes.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClusterMode {
+	/// Connect to every other node and replicate all of their announcements.
+	#[default]
+	Mesh,
+	/// Only announce the prefixes each node owns, connecting to the owner when a local subscriber requests a broadcast.
+	Lazy,
 }
 
 #[derive(Clone)]
@@ -41,8 +91,8 @@ pub struct Cluster {
 	config: ClusterConfig,
 	client: moq_native::Client,
 
-	// Advertises ourselves as an origin to other nodes.
-	noop: moq_lite::Produce<BroadcastProducer, BroadcastConsumer>,
+	// Advertises ourselves as an origin to other nodes, along with the prefixes we own.
+	myself: moq_lite::Produce<BroadcastProducer, BroadcastConsumer>,
 
 	// Broadcasts announced by local clients (users).
 	pub primary: Arc<moq_lite::Produce<OriginProducer, OriginConsumer>>,
@@ -50,29 +100,265 @@ pub struct Cluster {
 	// Broadcasts announced by remote servers (cluster).
 	pub secondary: Arc<moq_lite::Produce<OriginProducer, OriginConsumer>>,
 
+	// Broadcasts announced by remote servers that connected to us, a subset of secondary.
+	registered: Arc<moq_lite::Produce<OriginProducer, OriginConsumer>>,
+
+	// Broadcasts served to remote servers that connect to us.
+	// This is our primary broadcasts plus the origin of each node we're connected to, so other nodes can discover them.
+	advertised: Arc<moq_lite::Produce<OriginProducer, OriginConsumer>>,
+
 	// Broadcasts announced by local clients and remote servers.
 	pub combined: Arc<moq_lite::Produce<OriginProducer, OriginConsumer>>,
+
+	// The state of each connection to another node, keyed by hostname and purpose.
+	remotes: Remotes,
+
+	// The prefixes claimed by local publishers and owned by other nodes, used for lazy routing.
+	routes: Routes,
+}
+
+/// The purpose of a connection to another cluster node.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RemoteLink {
+	/// Exchange every broadcast, used in mesh mode.
+	Mesh,
+	/// Exchange only origin announcements, used to discover nodes in lazy mode.
+	Discovery,
+	/// Subscribe to a broadcast requested by a local subscriber from the node that owns it.
+	/// Keyed by the node and the broadcast path, as there's a connection per broadcast.
+	Upstream,
+}
+
+impl RemoteLink {
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			Self::Mesh => "mesh",
+			Self::Discovery => "discovery",
+			Self::Upstream => "upstream",
+		}
+	}
+}
+
+/// The state of a connection to another cluster node.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RemoteState {
+	Connecting,
+	Connected,
+	/// Waiting to reconnect after an error.
+	Backoff,
+	/// Gave up after too many failed attempts, until the node is announced again.
+	Failed,
+}
+
+impl RemoteState {
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			Self::Connecting => "connecting",
+			Self::Connected => "connected",
+			Self::Backoff => "backoff",
+			Self::Failed => "failed",
+		}
+	}
+}
+
+/// The health of a connection to another cluster node.
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct RemoteHealth {
+	pub state: RemoteState,
+	/// The number of consecutive failed attempts, reset when a connection is established.
+	pub failures: u32,
+	/// The most recent error, if any.
+	pub error: Option<String>,
+
+	// Identifies the guard that owns this entry, as a task may be replaced before it's done aborting.
+	#[serde(skip)]
+	id: u64,
+}
+
+type Remotes = Arc<Mutex<BTreeMap<(String, RemoteLink), RemoteHealth>>>;
+
+// Removes a remote from the cluster state when its task exits or is aborted, unless it failed.
+struct RemoteGuard {
+	remotes: Remotes,
+	node: String,
+	link: RemoteLink,
+	id: u64,
+}
+
+impl RemoteGuard {
+	// Start tracking a connection, replacing any previous failure.
+	fn new(remotes: Remotes, node: &str, link: RemoteLink) -> Self {
+		static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+		let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+
+		let health = RemoteHealth {
+			state: RemoteState::Connecting,
+			failures: 0,
+			error: None,
+			id,
+		};
+		remotes.lock().unwrap().insert((node.to_string(), link), health);
+
+		Self {
+			remotes,
+			node: node.to_string(),
+			link,
+			id,
+		}
+	}
+
+	fn update<T>(&self, f: impl FnOnce(&mut RemoteHealth) -> T) -> T {
+		let key = (self.node.clone(), self.link);
+		let mut remotes = self.remotes.lock().unwrap();
+
+		match remotes.get_mut(&key) {
+			Some(health) if health.id == self.id => f(health),
+			// Forgotten or replaced while this task was still running, so the update goes nowhere.
+			_ => f(&mut RemoteHealth {
+				state: RemoteState::Connecting,
+				failures: 0,
+				error: None,
+				id: self.id,
+			}),
+		}
+	}
+
+	fn set(&self, state: RemoteState) {
+		self.update(|health| {
+			health.state = state;
+
+			// A successful connection resets the backoff.
+			if state == RemoteState::Connected {
+				health.failures = 0;
+			}
+		})
+	}
+
+	// Record a failed attempt, returning the number of consecutive failures.
+	fn fail(&self, err: &anyhow::Error) -> u32 {
+		self.update(|health| {
+			health.failures += 1;
+			health.error = Some(format!("{err:#}"));
+			health.failures
+		})
+	}
+
+	fn failures(&self) -> u32 {
+		self.update(|health| health.failures)
+	}
+}
+
+impl Drop for RemoteGuard {
+	fn drop(&mut self) {
+		let key = (self.node.clone(), self.link);
+		let mut remotes = self.remotes.lock().unwrap();
+
+		// Keep failed nodes around so they're visible until announced again.
+		if remotes
+			.get(&key)
+			.is_some_and(|health| health.id == self.id && health.state != RemoteState::Failed)
+		{
+			remotes.remove(&key);
+		}
+	}
 }
 
 impl Cluster {
 	pub fn new(config: ClusterConfig, client: moq_native::Client) -> Self {
-		Cluster {
+		let mut myself = Broadcast::produce();
+		let owned = myself.producer.create_track(Track::new(OWNED_TRACK));
+
+		let cluster = Cluster {
 			config,
 			client,
-			noop: Broadcast::produce(),
+			myself,
 			primary: Arc::new(Origin::produce()),
 			secondary: Arc::new(Origin::produce()),
+			registered: Arc::new(Origin::produce()),
+			advertised: Arc::new(Origin::produce()),
 			combined: Arc::new(Origin::produce()),
+			remotes: Default::default(),
+			routes: Routes::new(owned),
+		};
+
+		// In lazy mode, a broadcast is only fetched from its owner when a local subscriber requests it.
+		if cluster.mode() == ClusterMode::Lazy {
+			cluster.combined.producer.request_missing();
+		}
+
+		cluster
+	}
+
+	/// Return how broadcasts are routed between nodes.
+	pub fn mode(&self) -> ClusterMode {
+		self.config.mode.unwrap_or_default()
+	}
+
+	/// Return the hostname we advertise to other nodes, if any.
+	pub fn advertise(&self) -> Option<&str> {
+		self.config.advertise.as_deref()
+	}
+
+	/// Return true if we're a root node, either because we're listed as one or there are none.
+	pub fn is_root(&self) -> bool {
+		self.config.connect.is_empty()
+			|| self
+				.config
+				.connect
+				.iter()
+				.any(|root| Some(root.as_str()) == self.advertise())
+	}
+
+	/// Return the root nodes we connect to, excluding ourselves.
+	pub fn roots(&self) -> Vec<&str> {
+		self.config
+			.connect
+			.iter()
+			.map(String::as_str)
+			.filter(|root| Some(*root) != self.advertise())
+			.collect()
+	}
+
+	/// Return the health of each connection to another node, including the roots.
+	pub fn remotes(&self) -> Vec<(String, RemoteLink, RemoteHealth)> {
+		let remotes = self.remotes.lock().unwrap();
+		remotes
+			.iter()
+			.map(|((node, link), health)| (node.clone(), *link, health.clone()))
+			.collect()
+	}
+
+	// Forget about a connection that's no longer needed, including one that failed.
+	fn forget(&self, node: &str, link: RemoteLink) {
+		self.remotes.lock().unwrap().remove(&(node.to_string(), link));
+	}
+
+	/// Return the prefixes owned by each other node, only used for lazy routing.
+	pub fn owners(&self) -> BTreeMap<String, Vec<String>> {
+		self.routes.owners()
+	}
+
+	/// Claim the publish prefixes of a local session for lazy routing, until the guard is dropped.
+	///
+	/// Returns None when not using lazy routing, for other cluster nodes, or if the session doesn't publish.
+	pub fn route(&self, token: &AuthToken, publish: bool) -> Option<RouteGuard> {
+		if self.mode() != ClusterMode::Lazy || token.cluster || !publish {
+			return None;
 		}
+
+		let claims = token.publish.iter().map(|prefix| token.root.join(prefix)).collect();
+		Some(self.routes.register(claims))
 	}
 
 	// For a given auth token, return the origin that should be used for the session.
 	pub fn subscriber(&self, token: &AuthToken) -> Option<OriginConsumer> {
 		// These broadcasts will be served to the session (when it subscribes).
-		// If this is a cluster node, then only publish our primary broadcasts.
+		// If this is a cluster node, then only publish our primary broadcasts and the origins registered with us.
 		// Otherwise publish everything.
 		let subscribe_origin = match token.cluster {
-			true => &self.primary,
+			true => &self.advertised,
 			false => &self.combined,
 		};
 
@@ -82,10 +368,10 @@ impl Cluster {
 	}
 
 	pub fn publisher(&self, token: &AuthToken) -> Option<OriginProducer> {
-		// If this is a cluster node, then add its broadcasts to the secondary origin.
-		// That way we won't publish them to other cluster nodes.
+		// If this is a cluster node, then add its broadcasts to the secondary origin (via registered).
+		// That way we won't publish them to other cluster nodes, except for its origin announcement.
 		let publish_origin = match token.cluster {
-			true => &self.secondary,
+			true => &self.registered,
 			false => &self.primary,
 		};
 
@@ -100,29 +386,47 @@ impl Cluster {
 			.or_else(|| self.secondary.consumer.consume_broadcast(broadcast))
 	}
 
-	pub async fn run(self) -> anyhow::Result<()> {
-		let connect = match self.config.connect.clone() {
-			// If we're using a root node, then we have to connect to it.
-			Some(connect) if Some(&connect) != self.config.advertise.as_ref() => connect,
-			// Otherwise, we're the root node so we wait for other nodes to connect to us.
-			_ => {
-				tracing::info!("running as root, accepting leaf nodes");
-				self.run_combined().await?;
-				anyhow::bail!("combined connection closed");
-			}
-		};
+	/// Unpublish a broadcast from every origin, returning false if it wasn't found.
+	pub fn unpublish(&self, broadcast: &str) -> bool {
+		// The other origins don't remove broadcasts until they're closed, so unpublish it there too.
+		let mut removed = false;
+		for origin in [
+			&self.primary,
+			&self.secondary,
+			&self.registered,
+			&self.advertised,
+			&self.combined,
+		] {
+			removed |= origin.producer.unpublish_broadcast(broadcast);
+		}
 
+		removed
+	}
+
+	pub async fn run(self) -> anyhow::Result<()> {
 		let prefix = self.config.prefix.as_path();
+		let lazy = self.mode() == ClusterMode::Lazy;
+
+		if self.is_root() {
+			tracing::info!(roots = ?self.roots(), mode = ?self.mode(), "running as root, accepting leaf nodes");
+		}
 
-		// Announce ourselves as an origin to the root node.
-		if let Some(myself) = self.config.advertise.as_ref() {
-			tracing::info!(%self.config.prefix, %myself, "announcing as leaf");
+		// Announce ourselves as an origin to the root nodes, which advertise us to every other node.
+		// Roots only need to be announced in lazy mode, so other nodes know the prefixes they own.
+		if let Some(myself) = self.config.advertise.as_ref().filter(|_| lazy || !self.is_root()) {
+			tracing::info!(%self.config.prefix, %myself, "announcing as origin");
 			let name = prefix.join(myself);
 			self.primary
 				.producer
-				.publish_broadcast(&name, self.noop.consumer.clone());
+				.publish_broadcast(&name, self.myself.consumer.clone());
 		}
 
+		// In lazy mode, we only exchange origin announcements with the roots and other nodes.
+		let link = match lazy {
+			true => RemoteLink::Discovery,
+			false => RemoteLink::Mesh,
+		};
+
 		// If the token is provided, read it from the disk and use it in the query parameter.
 		// TODO put this in an AUTH header once WebTransport supports it.
 		let token = match &self.config.token {
@@ -130,18 +434,29 @@ impl Cluster {
 			None => "".to_string(),
 		};
 
-		let noop = self.noop.consumer.clone();
-
 		// Despite returning a Result, we should NEVER return an Ok
 		tokio::select! {
-			res = self.clone().run_remote(&connect, token.clone(), noop) => {
-				res.context("failed to connect to root")?;
-				anyhow::bail!("connection to root closed");
+			res = self.clone().run_roots(token.clone(), link) => {
+				res.context("failed to connect to roots")?;
+				anyhow::bail!("connection to roots closed");
 			}
-			res = self.clone().run_remotes(token) => {
+			// In lazy mode, only the roots connect to every node so they can advertise it.
+			res = self.clone().run_remotes(token.clone(), link), if !lazy || self.is_root() => {
 				res.context("failed to connect to remotes")?;
 				anyhow::bail!("connection to remotes closed");
 			}
+			res = self.clone().run_owners(), if lazy => {
+				res.context("failed to run owners")?;
+				anyhow::bail!("owners closed");
+			}
+			res = self.clone().run_requests(token), if lazy => {
+				res.context("failed to run requests")?;
+				anyhow::bail!("requests closed");
+			}
+			res = self.clone().run_advertised() => {
+				res.context("failed to run advertised")?;
+				anyhow::bail!("advertised connection closed");
+			}
 			res = self.run_combined() => {
 				res.context("failed to run combined")?;
 				anyhow::bail!("combined connection closed");
@@ -168,34 +483,106 @@ impl Cluster {
 		}
 	}
 
-	async fn run_remotes(self, token: String) -> anyhow::Result<()> {
+	// Shovel broadcasts from nodes that connected to us into the secondary origin,
+	// and advertise our primary broadcasts along with their origin announcements.
+	//
+	// A node's origin is only advertised while we're connected to it, never when we learned it from another node.
+	// Otherwise two roots would keep advertising a dead node to each other.
+	async fn run_advertised(self) -> anyhow::Result<()> {
+		let prefix = self.config.prefix.as_path();
+
+		let mut primary = self.primary.consumer.consume();
+		let mut registered = self.registered.consumer.consume();
+
+		loop {
+			tokio::select! {
+				biased;
+				Some((name, broadcast)) = primary.announced() => {
+					if let Some(broadcast) = broadcast {
+						self.advertised.producer.publish_broadcast(&name, broadcast);
+					}
+				}
+				Some((name, broadcast)) = registered.announced() => {
+					if let Some(broadcast) = broadcast {
+						if name.has_prefix(&prefix) {
+							self.advertised.producer.publish_broadcast(&name, broadcast.clone());
+						}
+						self.secondary.producer.publish_broadcast(&name, broadcast);
+					}
+				}
+				else => return Ok(()),
+			}
+		}
+	}
+
+	// Register with every root node, reconnecting forever.
+	async fn run_roots(self, token: String, link: RemoteLink) -> anyhow::Result<()> {
+		let mut tasks = tokio::task::JoinSet::new();
+
+		for root in self.roots() {
+			let this = self.clone();
+			let token = token.clone();
+			let root = root.to_string();
+
+			tasks.spawn(async move { this.run_remote(&root, token, link).await }.in_current_span());
+		}
+
+		if let Some(res) = tasks.join_next().await {
+			return res?;
+		}
+
+		// We're the only root, so there's nothing to connect to.
+		std::future::pending().await
+	}
+
+	async fn run_remotes(self, token: String, link: RemoteLink) -> anyhow::Result<()> {
+		let prefix = self.config.prefix.as_path();
+
 		// Subscribe to available origins.
 		let mut origins = self
 			.secondary
 			.consumer
-			.consume_only(&[self.config.prefix.as_path()])
+			.consume_only(std::slice::from_ref(&prefix))
 			.context("no authorized origins")?;
 
 		// Cancel tasks when the origin is closed.
 		let mut active: HashMap<String, tokio::task::AbortHandle> = HashMap::new();
 
 		// Discover other origins.
-		// NOTE: The root node will connect to all other nodes as a client, ignoring the existing (server) connection.
+		// NOTE: Every node will connect to all other nodes as a client, ignoring the existing (server) connection.
 		// This ensures that nodes are advertising a valid hostname before any tracks get announced.
-		while let Some((node, origin)) = origins.announced().await {
-			if Some(node.as_str()) == self.config.advertise.as_deref() {
-				// Skip ourselves.
+		while let Some((path, origin)) = origins.announced().await {
+			let node = match path.strip_prefix(&prefix) {
+				Some(node) if !node.is_empty() => node.to_string(),
+				_ => continue,
+			};
+
+			if Some(node.as_str()) == self.config.advertise.as_deref() || self.config.connect.contains(&node) {
+				// Skip ourselves and the roots, which we're already connected to.
 				continue;
 			}
 
-			let origin = match origin {
-				Some(origin) => origin,
-				None => {
-					tracing::info!(%node, "origin cancelled");
-					active.remove(node.as_str()).unwrap().abort();
+			if origin.is_none() {
+				// The same origin is announced by multiple nodes, so it's reannounced when one of them goes away.
+				// Keep the connection unless no node is announcing it any longer.
+				if self.secondary.consumer.consume_broadcast(&path).is_some() {
 					continue;
 				}
-			};
+
+				tracing::info!(%node, "origin cancelled");
+				if let Some(handle) = active.remove(&node) {
+					handle.abort();
+				}
+				self.forget(&node, link);
+
+				continue;
+			}
+
+			if active.get(&node).is_some_and(|handle| !handle.is_finished()) {
+				// Already connected, announced again by another node.
+				// A node we gave up on is restarted instead.
+				continue;
+			}
 
 			tracing::info!(%node, "discovered origin");
 
@@ -205,51 +592,286 @@ impl Cluster {
 
 			let handle = tokio::spawn(
 				async move {
-					match this.run_remote(node2.as_str(), token, origin).await {
-						Ok(()) => tracing::info!(%node2, "origin closed"),
-						Err(err) => tracing::warn!(%err, %node2, "origin error"),
+					if let Err(err) = this.run_remote(&node2, token, link).await {
+						tracing::warn!(%err, %node2, "origin error");
 					}
 				}
 				.in_current_span(),
 			);
 
-			active.insert(node.to_string(), handle.abort_handle());
+			active.insert(node, handle.abort_handle());
 		}
 
 		Ok(())
 	}
 
-	#[tracing::instrument("remote", skip_all, err, fields(%node))]
-	async fn run_remote(mut self, node: &str, token: String, origin: BroadcastConsumer) -> anyhow::Result<()> {
-		let url = Url::parse(&format!("https://{node}/?jwt={token}"))?;
-		let mut backoff = 1;
+	// Learn the prefixes owned by each node from its origin announcement, used for lazy routing.
+	async fn run_owners(self) -> anyhow::Result<()> {
+		let prefix = self.config.prefix.as_path();
 
-		loop {
-			let res = tokio::select! {
-				biased;
-				_ = origin.closed() => break,
-				res = self.run_remote_once(&url) => res,
+		let mut origins = self
+			.secondary
+			.consumer
+			.consume_only(std::slice::from_ref(&prefix))
+			.context("no authorized origins")?;
+
+		let mut active: HashMap<String, tokio::task::AbortHandle> = HashMap::new();
+
+		while let Some((path, origin)) = origins.announced().await {
+			let node = match path.strip_prefix(&prefix) {
+				Some(node) if !node.is_empty() => node.to_string(),
+				_ => continue,
 			};
 
-			if let Err(err) = res {
-				backoff *= 2;
-				tracing::error!(%err, "remote error");
+			// The task notices when the origin goes away, so we only care about new announcements.
+			if origin.is_none() || Some(node.as_str()) == self.config.advertise.as_deref() {
+				continue;
 			}
 
-			let timeout = tokio::time::Duration::from_secs(backoff);
-			if timeout > tokio::time::Duration::from_secs(300) {
-				// 5 minutes of backoff is enough, just give up.
-				// TODO Reset the backoff if the connect is successful for some period of time.
-				anyhow::bail!("remote connection keep failing, giving up");
+			if active.get(&node).is_some_and(|handle| !handle.is_finished()) {
+				continue;
 			}
 
-			tokio::time::sleep(timeout).await;
+			let handle = tokio::spawn(self.clone().run_owner(node.clone(), path.to_owned()).in_current_span());
+			active.insert(node, handle.abort_handle());
 		}
 
 		Ok(())
 	}
 
-	async fn run_remote_once(&mut self, url: &Url) -> anyhow::Result<()> {
+	// Read the prefixes owned by a node until no other node announces it.
+	async fn run_owner(self, node: String, path: PathOwned) {
+		let track = Track::new(OWNED_TRACK);
+		let mut previous: Option<BroadcastConsumer> = None;
+
+		// The origin may be announced by multiple nodes, so resubscribe when one of them goes away.
+		while let Some(broadcast) = self.secondary.consumer.consume_broadcast(&path) {
+			if previous.as_ref().is_some_and(|previous| previous.is_clone(&broadcast)) {
+				// The closed broadcast hasn't been removed from the origin yet.
+				tokio::task::yield_now().await;
+				continue;
+			}
+
+			let mut owned = broadcast.subscribe_track(&track);
+			while let Ok(Some(mut group)) = owned.next_group().await {
+				let frame = match group.read_frame().await {
+					Ok(Some(frame)) => frame,
+					_ => continue,
+				};
+
+				match serde_json::from_slice::<Vec<String>>(&frame) {
+					Ok(owned) => {
+						tracing::debug!(%node, ?owned, "owned prefixes");
+						let owned = owned.iter().map(|prefix| prefix.as_path().to_owned()).collect();
+						self.routes.set_owner(&node, Some(owned));
+					}
+					Err(err) => tracing::warn!(%err, %node, "invalid owned prefixes"),
+				}
+			}
+
+			// The track also ends when the node doesn't own anything, ex. it's using mesh mode.
+			broadcast.closed().await;
+			previous = Some(broadcast);
+		}
+
+		self.routes.set_owner(&node, None);
+	}
+
+	// Serve each broadcast requested by a local subscriber that no local or remote publisher has announced.
+	async fn run_requests(self, token: String) -> anyhow::Result<()> {
+		while let Some((path, broadcast)) = self.combined.producer.requested_broadcast().await {
+			tokio::spawn(
+				self.clone()
+					.run_upstream(token.clone(), path, broadcast)
+					.in_current_span(),
+			);
+		}
+
+		Ok(())
+	}
+
+	// Fetch a requested broadcast from the node that owns it, closing the connection when it's no longer used.
+	#[tracing::instrument("upstream", skip_all, fields(%path))]
+	async fn run_upstream(self, token: String, path: PathOwned, broadcast: BroadcastProducer) {
+		// The owner's broadcast, or None until it's announced.
+		// Requested tracks are aborted once the sender is dropped, ex. there's no owner.
+		let (remote, announced) = watch::channel(None);
+
+		let upstream = match self.routes.owner(&path) {
+			Some(node) => {
+				tracing::info!(%node, "opening upstream");
+
+				let this = self.clone();
+				let token = token.clone();
+				let node2 = node.clone();
+				let path = path.clone();
+
+				let handle = tokio::spawn(
+					async move {
+						if let Err(err) = this.run_remote_upstream(&node2, token, &path, remote).await {
+							tracing::warn!(%err, %node2, "upstream error");
+						}
+					}
+					.in_current_span(),
+				);
+
+				Some((node, handle))
+			}
+			None => {
+				tracing::debug!("no owner for requested broadcast");
+				drop(remote);
+				None
+			}
+		};
+
+		Self::serve_upstream(broadcast, announced).await;
+
+		if let Some((node, handle)) = upstream {
+			tracing::info!(%node, "closing upstream");
+			handle.abort();
+			self.forget(&format!("{node}/{path}"), RemoteLink::Upstream);
+		}
+	}
+
+	// Serve the tracks of a requested broadcast from the owner's broadcast, until there are no consumers left.
+	async fn serve_upstream(mut broadcast: BroadcastProducer, remote: watch::Receiver<Option<BroadcastConsumer>>) {
+		let mut tracks = tokio::task::JoinSet::new();
+		let mut statuses = tokio::task::JoinSet::new();
+
+		loop {
+			tokio::select! {
+				Some(track) = broadcast.requested_track() => {
+					tracks.spawn(Self::forward_track(track, remote.clone()));
+				}
+				Some(request) = broadcast.requested_status() => {
+					statuses.spawn(Self::forward_status(request, remote.clone()));
+				}
+				Some(_) = tracks.join_next() => {}
+				Some(_) = statuses.join_next() => {}
+				// A subscriber may hold a track without the broadcast, so wait for the tracks too.
+				_ = broadcast.unused(), if tracks.is_empty() => break,
+			}
+		}
+
+		// Any new consumers will request the broadcast again.
+		broadcast.close();
+	}
+
+	// Forward a requested track from the owner's broadcast, waiting for it to be announced.
+	async fn forward_track(mut track: TrackProducer, mut remote: watch::Receiver<Option<BroadcastConsumer>>) {
+		let remote = async {
+			let remote = remote.wait_for(Option::is_some).await.ok()?;
+			remote.clone()
+		};
+
+		let broadcast = tokio::select! {
+			broadcast = remote => broadcast,
+			_ = track.unused() => return,
+		};
+
+		let broadcast = match broadcast {
+			Some(broadcast) => broadcast,
+			None => return track.abort(moq_lite::Error::NotFound),
+		};
+
+		let mut upstream = broadcast.subscribe_track(&track.info);
+
+		loop {
+			tokio::select! {
+				res = upstream.next_group() => match res {
+					Ok(Some(group)) => {
+						track.insert_group(group);
+					}
+					Ok(None) => break,
+					Err(err) => return track.abort(err),
+				},
+				_ = track.unused() => return,
+			}
+		}
+
+		match upstream.closed().await {
+			Ok(end) => track.end(end),
+			Err(err) => track.abort(err),
+		}
+	}
+
+	// Answer a status query from the owner's broadcast, if it has been announced.
+	async fn forward_status(request: TrackStatusRequest, remote: watch::Receiver<Option<BroadcastConsumer>>) {
+		let remote = remote.borrow().clone();
+
+		let status = match remote {
+			Some(remote) => remote.track_status(&request.name).await,
+			None => Err(moq_lite::Error::NotFound),
+		};
+
+		request.respond(status);
+	}
+
+	// Connect to another node, reconnecting with a backoff until aborted or out of retries.
+	#[tracing::instrument("remote", skip_all, err, fields(%node, link = link.as_str()))]
+	async fn run_remote(self, node: &str, token: String, link: RemoteLink) -> anyhow::Result<()> {
+		// Discovery connections are scoped to the cluster prefix, so no other announcements are sent.
+		let path = match link {
+			RemoteLink::Discovery => self.config.prefix.as_str(),
+			_ => "",
+		};
+
+		let url = Url::parse(&format!("https://{node}/{path}?jwt={token}"))?;
+		let remote = RemoteGuard::new(self.remotes.clone(), node, link);
+
+		self.reconnect(node, &remote, || self.run_remote_once(&url, &remote))
+			.await
+	}
+
+	// Connect to the owner of a requested broadcast, scoped to its path so nothing else is announced.
+	#[tracing::instrument("remote", skip_all, err, fields(%node, %path, link = "upstream"))]
+	async fn run_remote_upstream(
+		&self,
+		node: &str,
+		token: String,
+		path: &PathOwned,
+		broadcast: watch::Sender<Option<BroadcastConsumer>>,
+	) -> anyhow::Result<()> {
+		let url = Url::parse(&format!("https://{node}/{path}?jwt={token}"))?;
+		let remote = RemoteGuard::new(self.remotes.clone(), &format!("{node}/{path}"), RemoteLink::Upstream);
+
+		self.reconnect(node, &remote, || self.run_upstream_once(&url, &remote, &broadcast))
+			.await
+	}
+
+	// Run a connection until it fails, then wait with a backoff before trying again.
+	async fn reconnect<F, Fut>(&self, node: &str, remote: &RemoteGuard, mut connect: F) -> anyhow::Result<()>
+	where
+		F: FnMut() -> Fut,
+		Fut: std::future::Future<Output = anyhow::Result<()>>,
+	{
+		let max = self.config.backoff.map(Duration::from_secs).unwrap_or(BACKOFF_MAX);
+
+		// Roots are never announced, so there's no way to restart them if we gave up.
+		let retries = match self.config.connect.iter().any(|root| root == node) {
+			true => None,
+			false => self.config.retries,
+		};
+
+		loop {
+			remote.set(RemoteState::Connecting);
+
+			if let Err(err) = connect().await {
+				let failures = remote.fail(&err);
+				tracing::error!(%err, %failures, "remote error");
+
+				if retries.is_some_and(|retries| failures >= retries) {
+					remote.set(RemoteState::Failed);
+					anyhow::bail!("giving up after {failures} failed attempts");
+				}
+			}
+
+			remote.set(RemoteState::Backoff);
+			tokio::time::sleep(backoff(remote.failures(), max)).await;
+		}
+	}
+
+	async fn run_remote_once(&self, url: &Url, remote: &RemoteGuard) -> anyhow::Result<()> {
 		tracing::info!(%url, "connecting to remote");
 
 		// Connect to the remote node.
@@ -259,13 +881,291 @@ impl Cluster {
 			.await
 			.context("failed to connect to remote")?;
 
-		let publish = Some(self.primary.consumer.consume());
-		let subscribe = Some(self.secondary.producer.clone());
+		// Use a separate origin so we can tell which origin announcement came from the remote itself.
+		let subscribe = Origin::produce();
 
-		let session = moq_lite::Session::connect(conn, publish, subscribe)
+		let (publish, producer) = match remote.link {
+			RemoteLink::Discovery => {
+				// The remote strips the prefix from paths, so we do the same.
+				let prefix = self.config.prefix.as_path();
+				let publish = self.primary.producer.with_root(&prefix).context("invalid prefix")?;
+				let producer = subscribe.producer.with_root(&prefix).context("invalid prefix")?;
+				(Some(publish.consume()), producer)
+			}
+			_ => (Some(self.primary.consumer.consume()), subscribe.producer),
+		};
+
+		let session = moq_lite::Session::connect(conn, publish, Some(producer))
 			.await
 			.context("failed to establish session")?;
 
-		session.closed().await.map_err(Into::into)
+		remote.set(RemoteState::Connected);
+
+		tokio::select! {
+			res = session.closed() => res.map_err(Into::into),
+			_ = self.forward(subscribe.consumer, &remote.node) => Ok(()),
+		}
+	}
+
+	async fn run_upstream_once(
+		&self,
+		url: &Url,
+		remote: &RemoteGuard,
+		broadcast: &watch::Sender<Option<BroadcastConsumer>>,
+	) -> anyhow::Result<()> {
+		tracing::info!(%url, "connecting to upstream");
+
+		let conn = self
+			.client
+			.connect(url.clone())
+			.await
+			.context("failed to connect to remote")?;
+
+		// We only need the requested broadcast, the owner doesn't need ours.
+		let subscribe = Origin::produce();
+		let session = moq_lite::Session::connect(conn, None, Some(subscribe.producer))
+			.await
+			.context("failed to establish session")?;
+
+		remote.set(RemoteState::Connected);
+
+		// The remote strips the path, so the broadcast is announced at the root.
+		let mut origin = subscribe.consumer;
+		loop {
+			tokio::select! {
+				res = session.closed() => return res.map_err(Into::into),
+				Some((path, announced)) = origin.announced() => {
+					if path.is_empty() {
+						broadcast.send_replace(announced);
+					}
+				}
+			}
+		}
+	}
+
+	// Shovel broadcasts from a remote node into the secondary origin, advertising the remote's own origin.
+	async fn forward(&self, mut origin: OriginConsumer, node: &str) {
+		let myself = self.config.prefix.as_path().join(node);
+
+		while let Some((path, broadcast)) = origin.announced().await {
+			if let Some(broadcast) = broadcast {
+				if path.as_str() == myself.as_str() {
+					self.advertised.producer.publish_broadcast(&path, broadcast.clone());
+				}
+				self.secondary.producer.publish_broadcast(&path, broadcast);
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn cluster(config: &str) -> Cluster {
+		let config: ClusterConfig = toml::from_str(config).unwrap();
+		let client = moq_native::ClientConfig::default().init().unwrap();
+		Cluster::new(config, client)
+	}
+
+	#[tokio::test]
+	async fn test_roots() {
+		let root = cluster("");
+		assert!(root.is_root());
+		assert!(root.roots().is_empty());
+
+		// A single root is still supported.
+		let leaf = cluster(r#"connect = "root:443""#);
+		assert!(!leaf.is_root());
+		assert_eq!(leaf.roots(), ["root:443"]);
+
+		let root = cluster(
+			r#"
+			connect = ["root1:443", "root2:443"]
+			advertise = "root2:443"
+			"#,
+		);
+		assert!(root.is_root());
+		assert_eq!(root.roots(), ["root1:443"]);
+	}
+
+	#[tokio::test]
+	async fn test_route() {
+		let token = |cluster| AuthToken {
+			root: "room".into(),
+			subscribe: vec!["".into()],
+			publish: vec!["alice".into()],
+			cluster,
+		};
+
+		// Only used for lazy routing.
+		let mesh = cluster("");
+		assert!(mesh.route(&token(false), true).is_none());
+
+		let lazy = cluster(r#"mode = "lazy""#);
+		assert!(lazy.route(&token(true), true).is_none());
+		assert!(lazy.route(&token(false), false).is_none());
+
+		let mut owned = lazy.myself.consumer.subscribe_track(&Track::new(OWNED_TRACK));
+		let _route = lazy.route(&token(false), true).unwrap();
+
+		let mut group = owned.next_group().await.unwrap().unwrap();
+		let frame = group.read_frame().await.unwrap().unwrap();
+		assert_eq!(frame.as_ref(), br#"["room/alice"]"#);
+	}
+
+	#[tokio::test]
+	async fn test_requested() {
+		let lazy = cluster(r#"mode = "lazy""#);
+		tokio::spawn(lazy.clone().run_requests(String::new()));
+
+		// Nobody owns the broadcast, so its tracks aren't found.
+		let broadcast = lazy.combined.consumer.consume_broadcast("room/alice").unwrap();
+		let track = broadcast.subscribe_track(&Track::new("video"));
+		assert!(matches!(track.closed().await, Err(moq_lite::Error::NotFound)));
+
+		// Missing broadcasts are only requested in lazy mode.
+		let mesh = cluster("");
+		assert!(mesh.combined.consumer.consume_broadcast("room/alice").is_none());
+	}
+
+	#[tokio::test]
+	async fn test_serve_upstream() {
+		let requested = Broadcast::produce();
+		let (remote, announced) = watch::channel(None);
+		let serve = tokio::spawn(Cluster::serve_upstream(requested.producer, announced));
+
+		// The track waits for the owner's broadcast to be announced.
+		let mut track = requested.consumer.subscribe_track(&Track::new("video"));
+		tokio::task::yield_now().await;
+
+		let mut owner = Broadcast::produce();
+		let mut video = owner.producer.create_track(Track::new("video"));
+		video.write_frame(b"hello".as_slice());
+		remote.send_replace(Some(owner.consumer.clone()));
+
+		let mut group = track.next_group().await.unwrap().unwrap();
+		let frame = group.read_frame().await.unwrap().unwrap();
+		assert_eq!(frame.as_ref(), b"hello");
+
+		// Finished once there are no consumers left.
+		drop(track);
+		drop(requested.consumer);
+		tokio::time::timeout(Duration::from_secs(1), serve)
+			.await
+			.unwrap()
+			.unwrap();
+	}
+
+	#[tokio::test]
+	async fn test_advertised() {
+		let cluster = cluster(
+			r#"
+			connect = ["root1:443", "root2:443"]
+			prefix = "internal/origins"
+			"#,
+		);
+		tokio::spawn(cluster.clone().run_advertised());
+		let cluster2 = cluster.clone();
+
+		let user = Broadcast::produce();
+		let node = Broadcast::produce();
+		let remote = Broadcast::produce();
+
+		cluster
+			.primary
+			.producer
+			.publish_broadcast("demo", user.consumer.clone());
+
+		// Registered by a node that connected to us.
+		let registered = cluster.registered.producer.clone();
+		registered.publish_broadcast("internal/origins/leaf1:443", node.consumer.clone());
+		registered.publish_broadcast("remote", remote.consumer.clone());
+
+		// Learned from another node, so it's not advertised again.
+		let secondary = cluster.secondary.producer.clone();
+		secondary.publish_broadcast("internal/origins/leaf2:443", node.consumer.clone());
+
+		// Unless it was announced by the node itself.
+		let origin = Origin::produce();
+		origin
+			.producer
+			.publish_broadcast("internal/origins/leaf3:443", node.consumer.clone());
+		origin
+			.producer
+			.publish_broadcast("internal/origins/leaf4:443", node.consumer.clone());
+		tokio::spawn(async move { cluster2.forward(origin.consumer, "leaf3:443").await });
+
+		tokio::task::yield_now().await;
+
+		let advertised = &cluster.advertised.consumer;
+		assert!(advertised.consume_broadcast("demo").is_some());
+		assert!(advertised.consume_broadcast("internal/origins/leaf1:443").is_some());
+		assert!(advertised.consume_broadcast("internal/origins/leaf2:443").is_none());
+		assert!(advertised.consume_broadcast("internal/origins/leaf3:443").is_some());
+		assert!(advertised.consume_broadcast("internal/origins/leaf4:443").is_none());
+		assert!(advertised.consume_broadcast("remote").is_none());
+
+		let secondary = &cluster.secondary.consumer;
+		assert!(secondary.consume_broadcast("internal/origins/leaf1:443").is_some());
+		assert!(secondary.consume_broadcast("remote").is_some());
+		assert!(secondary.consume_broadcast("internal/origins/leaf4:443").is_some());
+	}
+
+	#[test]
+	fn test_backoff() {
+		let max = Duration::from_secs(300);
+		assert_eq!(backoff(0, max), Duration::from_secs(1));
+		assert_eq!(backoff(3, max), Duration::from_secs(8));
+		assert_eq!(backoff(9, max), max);
+		assert_eq!(backoff(100, max), max);
+	}
+
+	#[tokio::test]
+	async fn test_health() {
+		let cluster = cluster("");
+		let key = ("leaf:443".to_string(), RemoteLink::Mesh);
+
+		let guard = |cluster: &Cluster| RemoteGuard::new(cluster.remotes.clone(), &key.0, key.1);
+
+		let remote = guard(&cluster);
+		assert_eq!(remote.fail(&anyhow::anyhow!("refused")), 1);
+		assert_eq!(remote.fail(&anyhow::anyhow!("refused")), 2);
+
+		// A successful connection resets the failures.
+		remote.set(RemoteState::Connected);
+		assert_eq!(remote.failures(), 0);
+
+		// Aborted connections are removed.
+		drop(remote);
+		assert!(cluster.remotes().is_empty());
+
+		// Failed connections are kept until forgotten.
+		let remote = guard(&cluster);
+		remote.fail(&anyhow::anyhow!("refused"));
+		remote.set(RemoteState::Failed);
+		drop(remote);
+
+		let remotes = cluster.remotes();
+		assert_eq!(remotes.len(), 1);
+		assert_eq!(remotes[0].2.state, RemoteState::Failed);
+		assert_eq!(remotes[0].2.failures, 1);
+		assert_eq!(remotes[0].2.error.as_deref(), Some("refused"));
+
+		// Restarting the connection starts from scratch.
+		let old = guard(&cluster);
+		let new = guard(&cluster);
+		assert_eq!(new.failures(), 0);
+
+		// The replaced connection doesn't touch the new entry.
+		old.fail(&anyhow::anyhow!("aborted"));
+		drop(old);
+		assert_eq!(cluster.remotes()[0].2.failures, 0);
+
+		new.set(RemoteState::Failed);
+		drop(new);
+
+		cluster.forget(&key.0, key.1);
+		assert!(cluster.remotes().is_empty());
 	}
 }
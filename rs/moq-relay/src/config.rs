@@ -1,7 +1,7 @@
 use clap::Parser;
 use serde::{Deserialize, Serialize};
 
-use crate::{AuthConfig, ClusterConfig, WebConfig};
+use crate::{AuthConfig, ClusterConfig, RecordConfig, WebConfig};
 
 #[derive(Parser, Clone, Debug, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
@@ -35,6 +35,11 @@ pub struct Config {
 	#[serde(default)]
 	pub web: WebConfig,
 
+	/// Optionally record broadcasts to disk.
+	#[command(flatten)]
+	#[serde(default)]
+	pub record: RecordConfig,
+
 	/// If provided, load the configuration from this file.
 	#[serde(default)]
 	pub file: Option<String>,
@@ -1,4 +1,4 @@
-use crate::{Auth, Cluster};
+use crate::{Auth, Cluster, SessionInfo, Sessions};
 
 use moq_native::Request;
 
@@ -7,15 +7,22 @@ pub struct Connection {
 	pub request: Request,
 	pub cluster: Cluster,
 	pub auth: Auth,
+	pub sessions: Sessions,
 }
 
 impl Connection {
 	#[tracing::instrument("conn", skip_all, fields(id = self.id))]
 	pub async fn run(self) -> anyhow::Result<()> {
+		let transport = match &self.request {
+			Request::WebTransport(_) => "webtransport",
+			Request::Quic(_) => "quic",
+		};
+		let _connection = self.sessions.metrics().connection(transport);
+
 		let (path, token) = match &self.request {
 			Request::WebTransport(request) => {
 				// Extract the path and token from the URL.
-				let path = request.url().path();
+				let path = request.url().path().to_string();
 				let token = request
 					.url()
 					.query_pairs()
@@ -23,19 +30,44 @@ impl Connection {
 					.map(|(_, v)| v.to_string());
 				(path, token)
 			}
-			Request::Quic(_conn) => ("", None),
+			// Raw QUIC has no URL, so the client must use the Authorization extension instead.
+			Request::Quic(_conn) => (String::new(), None),
+		};
+
+		// Reject an invalid token in the URL before accepting, so the client gets an HTTP error.
+		// Without one, the client can still authorize via the setup extension, just like raw QUIC.
+		if token.is_some() {
+			if let Err(err) = self.auth.verify(&path, token.as_deref()) {
+				let _ = self.request.close(err.clone().into()).await;
+				return Err(err.into());
+			}
+		}
+
+		// Accept the connection and wait for the client's setup message.
+		let session = self.request.ok().await?;
+		let remote = session.remote_address();
+		let request = moq_lite::Session::request(session.clone()).await?;
+
+		// Tell the client if its extensions are malformed instead of dropping the connection.
+		let (authorization, role) = match (request.authorization(), request.role()) {
+			(Ok(authorization), Ok(role)) => (authorization, role),
+			(Err(err), _) | (_, Err(err)) => {
+				request.close(err.clone());
+				return Err(err.into());
+			}
 		};
-		// Verify the URL before accepting the connection.
-		let token = match self.auth.verify(path, token.as_deref()) {
+
+		let token = match self.auth.verify_session(&path, token.as_deref(), authorization) {
 			Ok(token) => token,
 			Err(err) => {
-				let _ = self.request.close(err.clone().into()).await;
+				request.close(moq_lite::Error::Unauthorized);
 				return Err(err.into());
 			}
 		};
 
-		let publish = self.cluster.publisher(&token);
-		let subscribe = self.cluster.subscriber(&token);
+		// Only grant what the client declared it would do, so a subscriber can't sneak in a broadcast.
+		let publish = self.cluster.publisher(&token).filter(|_| role.is_publisher());
+		let subscribe = self.cluster.subscriber(&token).filter(|_| role.is_subscriber());
 
 		match (&publish, &subscribe) {
 			(Some(publish), Some(subscribe)) => {
@@ -47,18 +79,48 @@ impl Connection {
 			(None, Some(subscribe)) => {
 				tracing::info!(root = %token.root, subscribe = %subscribe.allowed().map(|p| p.as_str()).collect::<Vec<_>>().join(","), "subscriber accepted")
 			}
-			_ => anyhow::bail!("invalid session; no allowed paths"),
+			_ => {
+				request.close(moq_lite::Error::Unauthorized);
+				anyhow::bail!("invalid session; no allowed paths");
+			}
 		}
 
-		// Accept the connection.
-		let session = self.request.ok().await?;
+		let _session = self.sessions.metrics().session(token.root.as_str());
+		let _route = self.cluster.route(&token, publish.is_some());
+
+		let handle = self.sessions.register(SessionInfo {
+			id: self.id,
+			transport,
+			remote: Some(remote),
+			root: token.root.to_string(),
+			publish: publish
+				.iter()
+				.flat_map(|p| p.allowed())
+				.map(|p| p.to_string())
+				.collect(),
+			subscribe: subscribe
+				.iter()
+				.flat_map(|s| s.allowed())
+				.map(|p| p.to_string())
+				.collect(),
+		});
 
 		// NOTE: subscribe and publish seem backwards because of how relays work.
 		// We publish the tracks the client is allowed to subscribe to.
 		// We subscribe to the tracks the client is allowed to publish.
-		let session = moq_lite::Session::accept(session, subscribe, publish).await?;
+		let session = request
+			.stats(self.sessions.stats().clone())
+			.ok(subscribe, publish)
+			.await?;
 
-		// Wait until the session is closed.
-		session.closed().await.map_err(Into::into)
+		// Wait until the session is closed, or an admin closes it.
+		tokio::select! {
+			res = session.closed() => res.map_err(Into::into),
+			_ = handle.closed() => {
+				tracing::info!("closed by admin");
+				session.close(moq_lite::Error::Cancel);
+				Ok(())
+			}
+		}
 	}
 }
@@ -0,0 +1,788 @@
+use std::{
+	collections::{HashMap, VecDeque},
+	fmt::Write,
+	future::Future,
+	sync::{Arc, Mutex},
+	time::Duration,
+};
+
+use axum::{
+	extract::{Path, Query, State},
+	http::{header, StatusCode},
+	response::{IntoResponse, Response},
+	routing::get,
+	Router,
+};
+use bytes::{Bytes, BytesMut};
+use hang::{cmaf::Export, Catalog, CatalogConsumer, Frame};
+use moq_lite::{BroadcastConsumer, OriginConsumer};
+use serde::Deserialize;
+use tokio::{sync::watch, time::Instant};
+
+use crate::{Auth, Cluster};
+
+/// The maximum duration of a partial segment.
+const PART_TARGET: Duration = Duration::from_millis(500);
+
+/// The minimum duration of a segment; segments end on the first keyframe after this.
+const SEGMENT_TARGET: Duration = Duration::from_secs(2);
+
+/// The number of complete segments kept in each playlist.
+const WINDOW: usize = 6;
+
+/// Only the most recent segments list their parts.
+const WINDOW_PARTS: usize = 3;
+
+/// How long a broadcast or track is muxed after the last request.
+const IDLE: Duration = Duration::from_secs(30);
+
+/// How long a blocking request waits before giving up.
+const BLOCK_TIMEOUT: Duration = Duration::from_secs(6);
+
+// Used for the BANDWIDTH attribute when the catalog doesn't include a bitrate.
+const DEFAULT_VIDEO_BITRATE: u64 = 2_000_000;
+const DEFAULT_AUDIO_BITRATE: u64 = 128_000;
+
+type Broadcasts = Arc<Mutex<HashMap<String, HlsBroadcast>>>;
+
+/// Serves broadcasts with a hang catalog as Low-Latency HLS.
+///
+/// Tracks are muxed into fMP4 on demand, and stop once there are no more requests.
+pub struct Hls {
+	auth: Auth,
+	cluster: Cluster,
+	broadcasts: Broadcasts,
+}
+
+impl Hls {
+	pub fn new(auth: Auth, cluster: Cluster) -> Self {
+		Self {
+			auth,
+			cluster,
+			broadcasts: Default::default(),
+		}
+	}
+
+	/// The routes to nest under /hls.
+	pub fn router<S>(self) -> Router<S> {
+		Router::new().route("/{*path}", get(serve)).with_state(Arc::new(self))
+	}
+
+	// Return the existing muxer for the broadcast, or start reading its catalog.
+	fn broadcast(&self, path: &str, origin: &OriginConsumer) -> Option<HlsBroadcast> {
+		let mut broadcasts = self.broadcasts.lock().unwrap();
+		if let Some(broadcast) = broadcasts.get(path) {
+			broadcast.touch();
+			return Some(broadcast.clone());
+		}
+
+		// NOTE: The origin is already scoped to the broadcast.
+		let consumer = origin.consume_broadcast("")?;
+		let catalog = CatalogConsumer::new(consumer.subscribe_track(&Catalog::default_track()));
+
+		let (tx, rx) = watch::channel(None);
+		let broadcast = HlsBroadcast {
+			consumer,
+			catalog: rx,
+			tracks: Default::default(),
+			accessed: Arc::new(Mutex::new(Instant::now())),
+		};
+
+		broadcasts.insert(path.to_string(), broadcast.clone());
+		tracing::info!(broadcast = %path, "serving HLS");
+
+		let broadcasts = self.broadcasts.clone();
+		let accessed = broadcast.accessed.clone();
+		let path = path.to_string();
+
+		tokio::spawn(async move {
+			until_idle(&accessed, run_catalog(catalog, tx)).await;
+			tracing::info!(broadcast = %path, "stopped serving HLS");
+
+			// Remove the entry unless it was replaced by a newer broadcast.
+			let mut broadcasts = broadcasts.lock().unwrap();
+			if broadcasts
+				.get(&path)
+				.is_some_and(|broadcast| Arc::ptr_eq(&broadcast.accessed, &accessed))
+			{
+				broadcasts.remove(&path);
+			}
+		});
+
+		Some(broadcast)
+	}
+}
+
+#[derive(Clone)]
+struct HlsBroadcast {
+	consumer: BroadcastConsumer,
+	catalog: watch::Receiver<Option<Catalog>>,
+	tracks: Arc<Mutex<HashMap<String, HlsTrack>>>,
+	accessed: Arc<Mutex<Instant>>,
+}
+
+impl HlsBroadcast {
+	fn touch(&self) {
+		*self.accessed.lock().unwrap() = Instant::now();
+	}
+
+	// Wait for the first catalog, returning 404 if the broadcast doesn't have one.
+	async fn catalog(&self) -> Result<Catalog, StatusCode> {
+		let mut catalog = self.catalog.clone();
+		let catalog = tokio::time::timeout(BLOCK_TIMEOUT, catalog.wait_for(Option::is_some))
+			.await
+			.map_err(|_| StatusCode::NOT_FOUND)?
+			.map_err(|_| StatusCode::NOT_FOUND)?;
+
+		Ok(catalog.clone().unwrap())
+	}
+
+	// Return the existing muxer for the track, or start a new one.
+	async fn track(&self, name: &str) -> Result<HlsTrack, StatusCode> {
+		if let Some(track) = self.tracks.lock().unwrap().get(name) {
+			track.touch();
+			return Ok(track.clone());
+		}
+
+		let catalog = self.catalog().await?;
+
+		let video = catalog.video.as_ref().and_then(|video| {
+			let config = video.renditions.get(name)?;
+			Some((Export::video(config), video.priority))
+		});
+		let audio = catalog.audio.as_ref().and_then(|audio| {
+			let config = audio.renditions.get(name)?;
+			Some((Export::audio(config), audio.priority))
+		});
+
+		let (export, priority) = video.or(audio).ok_or(StatusCode::NOT_FOUND)?;
+		let export = export.map_err(|err| {
+			tracing::debug!(%err, track = %name, "unsupported HLS track");
+			StatusCode::NOT_FOUND
+		})?;
+
+		let mut tracks = self.tracks.lock().unwrap();
+
+		// Another request could have started the track while we were waiting for the catalog.
+		if let Some(track) = tracks.get(name) {
+			return Ok(track.clone());
+		}
+
+		let (tx, rx) = watch::channel(Playlist::default());
+		let track = HlsTrack {
+			init: export.init(),
+			playlist: rx,
+			accessed: Arc::new(Mutex::new(Instant::now())),
+		};
+
+		tracks.insert(name.to_string(), track.clone());
+
+		let consumer = self.consumer.subscribe_track(&moq_lite::Track {
+			name: name.to_string(),
+			priority,
+		});
+		let mut consumer = hang::TrackConsumer::new(consumer);
+		consumer.set_latency(SEGMENT_TARGET);
+
+		let segmenter = Segmenter::new(export, tx);
+		let tracks = self.tracks.clone();
+		let accessed = track.accessed.clone();
+		let name = name.to_string();
+
+		tokio::spawn(async move {
+			// Keep serving the ended playlist until there are no more requests.
+			let task = async {
+				if let Err(err) = run_track(consumer, segmenter).await {
+					tracing::debug!(%err, track = %name, "HLS track failed");
+				}
+				std::future::pending::<()>().await;
+			};
+			until_idle(&accessed, task).await;
+
+			let mut tracks = tracks.lock().unwrap();
+			if tracks
+				.get(&name)
+				.is_some_and(|track| Arc::ptr_eq(&track.accessed, &accessed))
+			{
+				tracks.remove(&name);
+			}
+		});
+
+		Ok(track)
+	}
+}
+
+#[derive(Clone)]
+struct HlsTrack {
+	init: Bytes,
+	playlist: watch::Receiver<Playlist>,
+	accessed: Arc<Mutex<Instant>>,
+}
+
+impl HlsTrack {
+	fn touch(&self) {
+		*self.accessed.lock().unwrap() = Instant::now();
+	}
+
+	// Wait until the playlist contains the requested segment or part, for blocking playlist reloads.
+	async fn playlist(&self, msn: Option<u64>, part: Option<usize>, query: &str) -> Result<String, StatusCode> {
+		if let Some(msn) = msn {
+			// The spec requires rejecting requests more than two segments in the future.
+			if msn > self.playlist.borrow().next().0 + 2 {
+				return Err(StatusCode::BAD_REQUEST);
+			}
+		}
+
+		let playlist = self
+			.wait(|playlist| match msn {
+				Some(msn) => playlist.contains(msn, part),
+				None => !playlist.segments.is_empty(),
+			})
+			.await?;
+
+		Ok(playlist.render(query))
+	}
+
+	// Wait for a segment or part to be available, for preload hints.
+	async fn media(&self, msn: u64, part: Option<usize>) -> Result<Bytes, StatusCode> {
+		if msn > self.playlist.borrow().next().0 + 2 {
+			return Err(StatusCode::NOT_FOUND);
+		}
+
+		let playlist = self.wait(|playlist| playlist.contains(msn, part)).await?;
+		let segment = playlist.get(msn).ok_or(StatusCode::NOT_FOUND)?;
+
+		match part {
+			Some(part) => Ok(segment.parts.get(part).ok_or(StatusCode::NOT_FOUND)?.data.clone()),
+			None => {
+				let mut data = BytesMut::new();
+				for part in &segment.parts {
+					data.extend_from_slice(&part.data);
+				}
+				Ok(data.freeze())
+			}
+		}
+	}
+
+	// Wait until the playlist is ready or ended, timing out with 503 as the spec suggests.
+	async fn wait(&self, mut ready: impl FnMut(&Playlist) -> bool) -> Result<watch::Ref<'_, Playlist>, StatusCode> {
+		let mut playlist = self.playlist.clone();
+
+		tokio::time::timeout(
+			BLOCK_TIMEOUT,
+			playlist.wait_for(|playlist| playlist.ended || ready(playlist)),
+		)
+		.await
+		.map_err(|_| StatusCode::SERVICE_UNAVAILABLE)?
+		.map_err(|_| StatusCode::NOT_FOUND)?;
+
+		Ok(self.playlist.borrow())
+	}
+}
+
+#[derive(Default)]
+struct Playlist {
+	// The complete segments, followed by the segment in progress.
+	segments: VecDeque<Segment>,
+
+	// The media sequence number of the first segment.
+	sequence: u64,
+
+	// The longest segment so far.
+	target: Duration,
+
+	ended: bool,
+}
+
+#[derive(Default)]
+struct Segment {
+	parts: Vec<Part>,
+	duration: Duration,
+	complete: bool,
+}
+
+struct Part {
+	data: Bytes,
+	duration: Duration,
+	independent: bool,
+}
+
+impl Playlist {
+	fn push(&mut self, part: Part, complete: bool) {
+		if self.segments.back().is_none_or(|segment| segment.complete) {
+			self.segments.push_back(Segment::default());
+		}
+
+		let segment = self.segments.back_mut().unwrap();
+		segment.duration += part.duration;
+		segment.parts.push(part);
+
+		if complete {
+			segment.complete = true;
+			self.target = self.target.max(segment.duration);
+
+			if self.segments.len() > WINDOW {
+				self.segments.pop_front();
+				self.sequence += 1;
+			}
+		}
+	}
+
+	fn get(&self, msn: u64) -> Option<&Segment> {
+		let index = msn.checked_sub(self.sequence)?;
+		self.segments.get(index as usize)
+	}
+
+	// Returns true if the segment (or part) is available, or will never be.
+	fn contains(&self, msn: u64, part: Option<usize>) -> bool {
+		if msn < self.sequence {
+			return true;
+		}
+
+		match (self.get(msn), part) {
+			(Some(segment), _) if segment.complete => true,
+			(Some(segment), Some(part)) => part < segment.parts.len(),
+			_ => false,
+		}
+	}
+
+	// The media sequence number and part index of the next part.
+	fn next(&self) -> (u64, usize) {
+		let msn = self.sequence + self.segments.len() as u64;
+		match self.segments.back() {
+			Some(segment) if !segment.complete => (msn - 1, segment.parts.len()),
+			_ => (msn, 0),
+		}
+	}
+
+	fn render(&self, query: &str) -> String {
+		let target = self.target.max(SEGMENT_TARGET).as_secs_f64().ceil();
+
+		let mut out = String::new();
+		writeln!(out, "#EXTM3U").unwrap();
+		writeln!(out, "#EXT-X-VERSION:9").unwrap();
+		writeln!(out, "#EXT-X-TARGETDURATION:{target}").unwrap();
+		writeln!(
+			out,
+			"#EXT-X-SERVER-CONTROL:CAN-BLOCK-RELOAD=YES,PART-HOLD-BACK={:.3}",
+			(PART_TARGET * 3).as_secs_f64()
+		)
+		.unwrap();
+		writeln!(out, "#EXT-X-PART-INF:PART-TARGET={:.3}", PART_TARGET.as_secs_f64()).unwrap();
+		writeln!(out, "#EXT-X-MEDIA-SEQUENCE:{}", self.sequence).unwrap();
+		writeln!(out, "#EXT-X-MAP:URI=\"init.mp4{query}\"").unwrap();
+
+		let parts_from = self.segments.len().saturating_sub(WINDOW_PARTS);
+
+		for (index, segment) in self.segments.iter().enumerate() {
+			let msn = self.sequence + index as u64;
+
+			if index >= parts_from {
+				for (index, part) in segment.parts.iter().enumerate() {
+					write!(
+						out,
+						"#EXT-X-PART:DURATION={:.5},URI=\"{msn}.{index}.m4s{query}\"",
+						part.duration.as_secs_f64()
+					)
+					.unwrap();
+					if part.independent {
+						write!(out, ",INDEPENDENT=YES").unwrap();
+					}
+					writeln!(out).unwrap();
+				}
+			}
+
+			if segment.complete {
+				writeln!(out, "#EXTINF:{:.5},", segment.duration.as_secs_f64()).unwrap();
+				writeln!(out, "{msn}.m4s{query}").unwrap();
+			}
+		}
+
+		if self.ended {
+			writeln!(out, "#EXT-X-ENDLIST").unwrap();
+		} else {
+			let (msn, part) = self.next();
+			writeln!(out, "#EXT-X-PRELOAD-HINT:TYPE=PART,URI=\"{msn}.{part}.m4s{query}\"").unwrap();
+		}
+
+		out
+	}
+}
+
+// Splits hang frames into parts and segments.
+struct Segmenter {
+	export: Export,
+	playlist: watch::Sender<Playlist>,
+
+	// The frames in the current part.
+	pending: Vec<Frame>,
+
+	// The duration of the flushed parts in the current segment.
+	duration: Duration,
+
+	// The gap between the last two frames, used to estimate the duration of the final frame.
+	interval: Duration,
+}
+
+impl Segmenter {
+	fn new(export: Export, playlist: watch::Sender<Playlist>) -> Self {
+		Self {
+			export,
+			playlist,
+			pending: Vec::new(),
+			duration: Duration::ZERO,
+			interval: Duration::ZERO,
+		}
+	}
+
+	fn push(&mut self, frame: Frame) -> hang::cmaf::Result<()> {
+		match (self.pending.first(), self.pending.last()) {
+			(Some(first), Some(last)) => {
+				let span = frame.timestamp.saturating_sub(first.timestamp);
+				self.interval = frame.timestamp.saturating_sub(last.timestamp);
+
+				// Segments can only end on a keyframe, once they're long enough.
+				let boundary = frame.keyframe && self.duration + span >= SEGMENT_TARGET;
+
+				// Assume the next frame arrives after the same interval, so parts don't exceed the target.
+				if boundary || span + self.interval > PART_TARGET {
+					self.flush(frame.timestamp, boundary)?;
+				}
+			}
+			// Wait for a keyframe before starting the first segment.
+			_ if !frame.keyframe => return Ok(()),
+			_ => {}
+		}
+
+		self.pending.push(frame);
+		Ok(())
+	}
+
+	fn finish(&mut self) -> hang::cmaf::Result<()> {
+		if let Some(last) = self.pending.last() {
+			let end = last.timestamp + self.interval;
+			self.flush(end, true)?;
+		}
+
+		self.playlist.send_modify(|playlist| playlist.ended = true);
+		Ok(())
+	}
+
+	fn flush(&mut self, end: Duration, complete: bool) -> hang::cmaf::Result<()> {
+		let frames = std::mem::take(&mut self.pending);
+		let data = self.export.fragment(&frames, end)?;
+
+		let part = Part {
+			data,
+			duration: end.saturating_sub(frames[0].timestamp),
+			independent: frames[0].keyframe,
+		};
+
+		self.duration = match complete {
+			true => Duration::ZERO,
+			false => self.duration + part.duration,
+		};
+
+		self.playlist.send_modify(|playlist| playlist.push(part, complete));
+		Ok(())
+	}
+}
+
+async fn run_catalog(mut catalog: CatalogConsumer, tx: watch::Sender<Option<Catalog>>) {
+	while let Ok(Some(next)) = catalog.next().await {
+		tx.send_replace(Some(next));
+	}
+}
+
+async fn run_track(mut track: hang::TrackConsumer, mut segmenter: Segmenter) -> anyhow::Result<()> {
+	while let Some(frame) = track.read().await? {
+		segmenter.push(frame)?;
+	}
+
+	segmenter.finish()?;
+	Ok(())
+}
+
+// Run the task until it completes or there hasn't been a request for IDLE.
+async fn until_idle<F: Future<Output = ()>>(accessed: &Mutex<Instant>, task: F) {
+	tokio::pin!(task);
+
+	loop {
+		let deadline = *accessed.lock().unwrap() + IDLE;
+
+		tokio::select! {
+			_ = &mut task => return,
+			_ = tokio::time::sleep_until(deadline) => {
+				if *accessed.lock().unwrap() + IDLE <= Instant::now() {
+					return;
+				}
+			}
+		}
+	}
+}
+
+// The multivariant playlist, listing each supported rendition in the catalog.
+fn multivariant(catalog: &Catalog, query: &str) -> String {
+	let mut video: Vec<_> = catalog
+		.video
+		.iter()
+		.flat_map(|video| &video.renditions)
+		.filter(|(_, config)| Export::video(config).is_ok())
+		.collect();
+	video.sort_by_key(|(name, _)| name.as_str());
+
+	let mut audio: Vec<_> = catalog
+		.audio
+		.iter()
+		.flat_map(|audio| &audio.renditions)
+		.filter(|(_, config)| Export::audio(config).is_ok())
+		.collect();
+	audio.sort_by_key(|(name, _)| name.as_str());
+
+	let mut out = String::new();
+	writeln!(out, "#EXTM3U").unwrap();
+	writeln!(out, "#EXT-X-VERSION:9").unwrap();
+	writeln!(out, "#EXT-X-INDEPENDENT-SEGMENTS").unwrap();
+
+	for (index, (name, _)) in audio.iter().enumerate() {
+		let default = if index == 0 { "YES" } else { "NO" };
+		writeln!(
+			out,
+			"#EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID=\"audio\",NAME=\"{name}\",DEFAULT={default},AUTOSELECT=YES,URI=\"{name}/playlist.m3u8{query}\""
+		)
+		.unwrap();
+	}
+
+	let first_audio = audio.first().map(|(name, config)| {
+		let bitrate = config.bitrate.unwrap_or(DEFAULT_AUDIO_BITRATE);
+		(name, config.codec.to_string(), bitrate)
+	});
+
+	for (name, config) in &video {
+		let mut codecs = config.codec.to_string();
+		let mut bandwidth = config.bitrate.unwrap_or(DEFAULT_VIDEO_BITRATE);
+
+		if let Some((_, codec, bitrate)) = &first_audio {
+			codecs = format!("{codecs},{codec}");
+			bandwidth += bitrate;
+		}
+
+		write!(out, "#EXT-X-STREAM-INF:BANDWIDTH={bandwidth},CODECS=\"{codecs}\"").unwrap();
+		if let (Some(width), Some(height)) = (config.coded_width, config.coded_height) {
+			write!(out, ",RESOLUTION={width}x{height}").unwrap();
+		}
+		if let Some(framerate) = config.framerate {
+			write!(out, ",FRAME-RATE={framerate:.3}").unwrap();
+		}
+		if first_audio.is_some() {
+			write!(out, ",AUDIO=\"audio\"").unwrap();
+		}
+		writeln!(out).unwrap();
+		writeln!(out, "{name}/playlist.m3u8{query}").unwrap();
+	}
+
+	// An audio-only broadcast still needs a variant stream.
+	if let (true, Some((name, codec, bandwidth))) = (video.is_empty(), &first_audio) {
+		writeln!(
+			out,
+			"#EXT-X-STREAM-INF:BANDWIDTH={bandwidth},CODECS=\"{codec}\",AUDIO=\"audio\""
+		)
+		.unwrap();
+		writeln!(out, "{name}/playlist.m3u8{query}").unwrap();
+	}
+
+	out
+}
+
+#[derive(Debug, Deserialize)]
+struct HlsParams {
+	jwt: Option<String>,
+
+	#[serde(rename = "_HLS_msn")]
+	msn: Option<u64>,
+
+	#[serde(rename = "_HLS_part")]
+	part: Option<usize>,
+}
+
+/// Serves `{broadcast}/master.m3u8`, or `{broadcast}/{track}/` followed by
+/// `playlist.m3u8`, `init.mp4`, `{msn}.m4s` or `{msn}.{part}.m4s`.
+async fn serve(
+	State(hls): State<Arc<Hls>>,
+	Path(path): Path<String>,
+	Query(params): Query<HlsParams>,
+) -> axum::response::Result<Response> {
+	let (broadcast, track, file) = match path.strip_suffix("/master.m3u8") {
+		Some(broadcast) => (broadcast, None, "master.m3u8"),
+		None => {
+			let (path, file) = path.rsplit_once('/').ok_or(StatusCode::NOT_FOUND)?;
+			let (broadcast, track) = path.rsplit_once('/').ok_or(StatusCode::NOT_FOUND)?;
+			(broadcast, Some(track), file)
+		}
+	};
+
+	let token = hls.auth.verify(broadcast, params.jwt.as_deref())?;
+	let origin = hls.cluster.subscriber(&token).ok_or(StatusCode::UNAUTHORIZED)?;
+	let broadcast = hls.broadcast(broadcast, &origin).ok_or(StatusCode::NOT_FOUND)?;
+
+	// The URIs in the playlists are relative, so they need the token too.
+	let query = match &params.jwt {
+		Some(jwt) => format!("?jwt={jwt}"),
+		None => String::new(),
+	};
+
+	let track = match track {
+		Some(track) => broadcast.track(track).await?,
+		None => {
+			let catalog = broadcast.catalog().await?;
+			return Ok(playlist_response(multivariant(&catalog, &query)));
+		}
+	};
+
+	match file {
+		"playlist.m3u8" => {
+			let playlist = track.playlist(params.msn, params.part, &query).await?;
+			Ok(playlist_response(playlist))
+		}
+		"init.mp4" => Ok(media_response(track.init.clone())),
+		file => {
+			let name = file.strip_suffix(".m4s").ok_or(StatusCode::NOT_FOUND)?;
+			let (msn, part) = match name.split_once('.') {
+				Some((msn, part)) => (msn, Some(part.parse().map_err(|_| StatusCode::NOT_FOUND)?)),
+				None => (name, None),
+			};
+			let msn = msn.parse().map_err(|_| StatusCode::NOT_FOUND)?;
+
+			Ok(media_response(track.media(msn, part).await?))
+		}
+	}
+}
+
+fn playlist_response(playlist: String) -> Response {
+	(
+		[
+			(header::CONTENT_TYPE, "application/vnd.apple.mpegurl"),
+			(header::CACHE_CONTROL, "no-cache"),
+		],
+		playlist,
+	)
+		.into_response()
+}
+
+fn media_response(data: Bytes) -> Response {
+	([(header::CONTENT_TYPE, "video/mp4")], data).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use hang::catalog::{Audio, AudioConfig, AAC};
+
+	fn config() -> AudioConfig {
+		AudioConfig {
+			codec: AAC { profile: 2 }.into(),
+			sample_rate: 48000,
+			channel_count: 2,
+			bitrate: None,
+			description: None,
+		}
+	}
+
+	fn frame(millis: u64, keyframe: bool) -> Frame {
+		Frame {
+			timestamp: Duration::from_millis(millis),
+			keyframe,
+			payload: Bytes::from_static(b"frame"),
+		}
+	}
+
+	#[test]
+	fn test_segmenter() {
+		let (tx, rx) = watch::channel(Playlist::default());
+		let mut segmenter = Segmenter::new(Export::audio(&config()).unwrap(), tx);
+
+		// Frames before the first keyframe are dropped.
+		segmenter.push(frame(0, false)).unwrap();
+
+		// 100ms frames with a keyframe every second.
+		for index in 1..=35 {
+			segmenter.push(frame(index * 100, index % 10 == 0)).unwrap();
+		}
+
+		{
+			let playlist = rx.borrow();
+			assert_eq!(playlist.segments.len(), 2);
+
+			// The first segment ended on the first keyframe after two seconds.
+			let segment = &playlist.segments[0];
+			assert!(segment.complete);
+			assert_eq!(segment.duration, Duration::from_secs(2));
+			assert_eq!(segment.parts.len(), 4);
+			assert!(segment.parts[0].independent);
+			assert!(!segment.parts[1].independent);
+			assert!(segment.parts.iter().all(|part| part.duration <= PART_TARGET));
+
+			// The second segment is still in progress.
+			assert!(!playlist.segments[1].complete);
+			assert_eq!(playlist.next(), (1, 1));
+			assert!(playlist.contains(1, Some(0)));
+			assert!(!playlist.contains(1, Some(1)));
+
+			let rendered = playlist.render("?jwt=abc");
+			assert!(rendered.contains("#EXT-X-MAP:URI=\"init.mp4?jwt=abc\"\n"));
+			assert!(rendered.contains("#EXT-X-PART:DURATION=0.50000,URI=\"0.0.m4s?jwt=abc\",INDEPENDENT=YES\n"));
+			assert!(rendered.contains("#EXTINF:2.00000,\n0.m4s?jwt=abc\n"));
+			assert!(rendered.contains("#EXT-X-PRELOAD-HINT:TYPE=PART,URI=\"1.1.m4s?jwt=abc\"\n"));
+		}
+
+		segmenter.finish().unwrap();
+
+		let playlist = rx.borrow();
+		assert!(playlist.ended);
+		assert!(playlist.segments[1].complete);
+		assert!(playlist.render("").ends_with("#EXT-X-ENDLIST\n"));
+	}
+
+	#[test]
+	fn test_window() {
+		let mut playlist = Playlist::default();
+		for _ in 0..WINDOW + 2 {
+			let part = Part {
+				data: Bytes::new(),
+				duration: Duration::from_secs(3),
+				independent: true,
+			};
+			playlist.push(part, true);
+		}
+
+		assert_eq!(playlist.segments.len(), WINDOW);
+		assert_eq!(playlist.sequence, 2);
+		assert!(playlist.get(1).is_none());
+		assert!(playlist.contains(1, None));
+		assert!(playlist.render("").contains("#EXT-X-TARGETDURATION:3\n"));
+	}
+
+	#[test]
+	fn test_multivariant() {
+		let mut renditions = HashMap::new();
+		renditions.insert("audio1".to_string(), config());
+
+		let catalog = Catalog {
+			audio: Some(Audio {
+				renditions,
+				priority: 2,
+				captions: None,
+				speaking: None,
+			}),
+			..Default::default()
+		};
+
+		let playlist = multivariant(&catalog, "");
+		assert!(playlist.contains(
+			"#EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID=\"audio\",NAME=\"audio1\",DEFAULT=YES,AUTOSELECT=YES,URI=\"audio1/playlist.m3u8\"\n"
+		));
+		assert!(playlist.contains(
+			"#EXT-X-STREAM-INF:BANDWIDTH=128000,CODECS=\"mp4a.40.2\",AUDIO=\"audio\"\naudio1/playlist.m3u8\n"
+		));
+	}
+}
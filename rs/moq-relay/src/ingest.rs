@@ -0,0 +1,242 @@
+use std::{
+	collections::HashMap,
+	future::Future,
+	sync::{Arc, Mutex},
+	time::Duration,
+};
+
+use moq_lite::{Broadcast, BroadcastProducer, OriginProducer, Track, TrackProducer};
+use tokio::sync::watch;
+
+/// How long a broadcast stays published after its last upload finishes.
+///
+/// This lets a publisher send a group per request without the broadcast being unannounced in between.
+const LINGER: Duration = Duration::from_secs(10);
+
+/// Broadcasts published over HTTP, kept alive while there are uploads.
+#[derive(Clone, Default)]
+pub struct Ingest {
+	broadcasts: Arc<Mutex<HashMap<String, IngestBroadcast>>>,
+}
+
+struct IngestBroadcast {
+	producer: BroadcastProducer,
+	tracks: HashMap<String, TrackProducer>,
+
+	// The number of active uploads.
+	uploads: usize,
+
+	// Incremented each time the uploads reach zero, so a stale linger timer is ignored.
+	generation: u64,
+
+	// Set when the broadcast is closed, so active uploads are aborted.
+	closed: watch::Sender<bool>,
+}
+
+impl IngestBroadcast {
+	fn close(mut self) {
+		self.closed.send_replace(true);
+
+		for (_, track) in self.tracks.drain() {
+			track.close();
+		}
+		self.producer.close();
+	}
+}
+
+impl Ingest {
+	/// Start an upload to the given track, publishing the broadcast to the origin if it's new.
+	///
+	/// The path is used to deduplicate uploads, and the broadcast is published at the root of the origin.
+	/// Returns None if the origin doesn't allow the broadcast to be published.
+	pub fn upload(&self, path: &str, origin: &OriginProducer, track: &str) -> Option<IngestUpload> {
+		let mut broadcasts = self.broadcasts.lock().unwrap();
+
+		let broadcast = match broadcasts.get_mut(path) {
+			Some(broadcast) => broadcast,
+			None => {
+				let broadcast = Broadcast::produce();
+				if !origin.publish_broadcast("", broadcast.consumer) {
+					return None;
+				}
+
+				tracing::info!(broadcast = %path, "publishing over HTTP");
+
+				broadcasts.entry(path.to_string()).or_insert(IngestBroadcast {
+					producer: broadcast.producer,
+					tracks: HashMap::new(),
+					uploads: 0,
+					generation: 0,
+					closed: watch::channel(false).0,
+				})
+			}
+		};
+
+		let producer = &mut broadcast.producer;
+		let track = broadcast
+			.tracks
+			.entry(track.to_string())
+			.or_insert_with(|| producer.create_track(Track::new(track)))
+			.clone();
+
+		broadcast.uploads += 1;
+
+		Some(IngestUpload {
+			ingest: self.clone(),
+			path: path.to_string(),
+			track,
+			closed: broadcast.closed.subscribe(),
+		})
+	}
+
+	/// End a broadcast immediately, aborting any active uploads, returning false if it doesn't exist.
+	pub fn close(&self, path: &str) -> bool {
+		let broadcast = self.broadcasts.lock().unwrap().remove(path);
+		match broadcast {
+			Some(broadcast) => {
+				broadcast.close();
+				true
+			}
+			None => false,
+		}
+	}
+
+	fn finish(&self, path: &str) {
+		let mut broadcasts = self.broadcasts.lock().unwrap();
+		let broadcast = match broadcasts.get_mut(path) {
+			Some(broadcast) => broadcast,
+			// Closed while the upload was active.
+			None => return,
+		};
+
+		broadcast.uploads -= 1;
+		if broadcast.uploads > 0 {
+			return;
+		}
+
+		broadcast.generation += 1;
+		let generation = broadcast.generation;
+
+		let ingest = self.clone();
+		let path = path.to_string();
+
+		tokio::spawn(async move {
+			tokio::time::sleep(LINGER).await;
+			ingest.expire(&path, generation);
+		});
+	}
+
+	// Close the broadcast unless there was another upload since the timer started.
+	fn expire(&self, path: &str, generation: u64) {
+		let mut broadcasts = self.broadcasts.lock().unwrap();
+		match broadcasts.get(path) {
+			Some(broadcast) if broadcast.uploads == 0 && broadcast.generation == generation => {}
+			_ => return,
+		}
+
+		tracing::info!(broadcast = %path, "HTTP publisher idle");
+		broadcasts.remove(path).unwrap().close();
+	}
+}
+
+/// An active upload to a track, keeping the broadcast alive until dropped.
+pub struct IngestUpload {
+	ingest: Ingest,
+	path: String,
+	track: TrackProducer,
+	closed: watch::Receiver<bool>,
+}
+
+impl IngestUpload {
+	/// The track being uploaded to, which may be closed at any time via [Ingest::close].
+	pub fn track(&mut self) -> &mut TrackProducer {
+		&mut self.track
+	}
+
+	/// Write to the track, returning None if the broadcast was closed.
+	pub fn write<T>(&mut self, f: impl FnOnce(&mut TrackProducer) -> T) -> Option<T> {
+		// Hold the lock so the broadcast can't be closed while writing.
+		let _broadcasts = self.ingest.broadcasts.lock().unwrap();
+
+		match self.is_closed() {
+			true => None,
+			false => Some(f(&mut self.track)),
+		}
+	}
+
+	/// Returns true if the broadcast was closed via [Ingest::close].
+	pub fn is_closed(&self) -> bool {
+		*self.closed.borrow()
+	}
+
+	/// Block until the broadcast is closed via [Ingest::close].
+	pub fn closed(&self) -> impl Future<Output = ()> {
+		let mut closed = self.closed.clone();
+		async move {
+			closed.wait_for(|closed| *closed).await.ok();
+		}
+	}
+}
+
+impl Drop for IngestUpload {
+	fn drop(&mut self) {
+		self.ingest.finish(&self.path);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use moq_lite::Origin;
+
+	#[tokio::test(start_paused = true)]
+	async fn test_linger() {
+		let origin = Origin::produce();
+		let ingest = Ingest::default();
+
+		let publisher = origin.producer.with_root("demo").unwrap();
+		let mut upload = ingest.upload("demo", &publisher, "video").unwrap();
+		upload.track().write_frame(b"hello".as_slice());
+
+		let broadcast = origin.consumer.consume_broadcast("demo").expect("not published");
+		let mut track = broadcast.subscribe_track(&Track::new("video"));
+		let mut group = track.next_group().await.unwrap().unwrap();
+		assert_eq!(group.read_frame().await.unwrap().unwrap().as_ref(), b"hello");
+
+		// The broadcast stays published between uploads.
+		drop(upload);
+		tokio::time::sleep(LINGER / 2).await;
+		let upload = ingest.upload("demo", &publisher, "video").unwrap();
+		drop(upload);
+
+		// The timer restarts after the second upload.
+		tokio::time::sleep(LINGER / 2 + Duration::from_millis(1)).await;
+		assert!(origin.consumer.consume_broadcast("demo").is_some());
+
+		tokio::time::sleep(LINGER).await;
+		assert!(origin.consumer.consume_broadcast("demo").is_none());
+		assert!(track.next_group().await.unwrap().is_none());
+		assert!(!ingest.close("demo"));
+	}
+
+	#[tokio::test]
+	async fn test_close() {
+		let origin = Origin::produce();
+		let ingest = Ingest::default();
+
+		let publisher = origin.producer.with_root("demo").unwrap();
+		let mut upload = ingest.upload("demo", &publisher, "video").unwrap();
+		assert_eq!(upload.write(|track| track.append_group().info.sequence), Some(0));
+
+		// Active uploads are aborted rather than writing to the closed broadcast.
+		assert!(ingest.close("demo"));
+		assert!(upload.is_closed());
+		upload.closed().await;
+		assert_eq!(upload.write(|track| track.append_group().info.sequence), None);
+		drop(upload);
+
+		// A new upload starts a new broadcast.
+		let upload = ingest.upload("demo", &publisher, "video").unwrap();
+		assert!(!upload.is_closed());
+	}
+}
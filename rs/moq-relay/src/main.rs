@@ -1,13 +1,27 @@
+mod admin;
 mod auth;
 mod cluster;
 mod config;
 mod connection;
+#[cfg(feature = "hls")]
+mod hls;
+mod ingest;
+mod metrics;
+mod record;
+mod route;
 mod web;
 
+pub use admin::*;
 pub use auth::*;
 pub use cluster::*;
 pub use config::*;
 pub use connection::*;
+#[cfg(feature = "hls")]
+pub use hls::*;
+pub use ingest::*;
+pub use metrics::*;
+pub use record::*;
+pub use route::*;
 pub use web::*;
 
 #[tokio::main]
@@ -17,12 +31,21 @@ async fn main() -> anyhow::Result<()> {
 	let addr = config.server.listen.unwrap_or("[::]:443".parse().unwrap());
 	let mut server = config.server.init()?;
 	let client = config.client.init()?;
-	let auth = config.auth.init()?;
 	let fingerprints = server.fingerprints().to_vec();
 
+	let sessions = Sessions::default();
+	let auth = config.auth.init()?.with_metrics(sessions.metrics().clone());
+
 	let cluster = Cluster::new(config.cluster, client);
 	let cloned = cluster.clone();
 	tokio::spawn(async move { cloned.run().await.expect("cluster failed") });
+	tokio::spawn(sessions.metrics().clone().run(cluster.clone()));
+
+	// Record broadcasts to disk, if configured.
+	let recorder = Recorder::new(config.record, &cluster);
+	if let Some(recorder) = recorder.clone() {
+		tokio::spawn(recorder.run());
+	}
 
 	// Create a web server too.
 	let web = Web::new(
@@ -30,7 +53,10 @@ async fn main() -> anyhow::Result<()> {
 			auth: auth.clone(),
 			cluster: cluster.clone(),
 			fingerprints,
-			conn_id: Default::default(),
+			sessions: sessions.clone(),
+			ingest: Ingest::default(),
+			recorder,
+			publish_max: config.web.publish_max(),
 		},
 		config.web,
 	);
@@ -41,17 +67,15 @@ async fn main() -> anyhow::Result<()> {
 
 	tracing::info!(%addr, "listening");
 
-	let mut conn_id = 0;
-
 	while let Some(request) = server.accept().await {
 		let conn = Connection {
-			id: conn_id,
+			id: sessions.next_id(),
 			request,
 			cluster: cluster.clone(),
 			auth: auth.clone(),
+			sessions: sessions.clone(),
 		};
 
-		conn_id += 1;
 		tokio::spawn(async move {
 			let err = conn.run().await;
 			if let Err(err) = err {
@@ -0,0 +1,274 @@
+use std::{
+	collections::BTreeMap,
+	fmt::Write,
+	sync::{Arc, Mutex},
+};
+
+use moq_lite::OriginConsumer;
+
+use crate::{AuthError, Cluster, Sessions};
+
+// A gauge keyed by a label, shared with the guards that decrement it.
+type Gauge = Arc<Mutex<BTreeMap<String, u64>>>;
+
+// The origins whose broadcasts are counted.
+const ORIGINS: [&str; 3] = ["primary", "secondary", "combined"];
+
+/// Relay-wide counters, served in the Prometheus text format at `/metrics`.
+#[derive(Clone, Default)]
+pub struct Metrics {
+	// Active connections by transport.
+	connections: Gauge,
+
+	// Active sessions by auth root.
+	sessions: Gauge,
+
+	// Active broadcasts by origin, updated as they're announced.
+	broadcasts: Arc<Mutex<BTreeMap<&'static str, u64>>>,
+
+	// Authentication failures by AuthError variant.
+	auth_failures: Arc<Mutex<BTreeMap<&'static str, u64>>>,
+}
+
+impl Metrics {
+	/// Count an active connection using the given transport until the guard is dropped.
+	pub fn connection(&self, transport: &str) -> Active {
+		Active::new(self.connections.clone(), transport)
+	}
+
+	/// Count an active session for the given auth root until the guard is dropped.
+	pub fn session(&self, root: &str) -> Active {
+		Active::new(self.sessions.clone(), root)
+	}
+
+	pub fn auth_failure(&self, err: &AuthError) {
+		*self.auth_failures.lock().unwrap().entry(err.kind()).or_default() += 1;
+	}
+
+	/// Count the active broadcasts in each origin of the cluster, until the origins are closed.
+	pub async fn run(self, cluster: Cluster) {
+		tokio::join!(
+			self.count_broadcasts("primary", cluster.primary.consumer.consume()),
+			self.count_broadcasts("secondary", cluster.secondary.consumer.consume()),
+			self.count_broadcasts("combined", cluster.combined.consumer.consume()),
+		);
+	}
+
+	async fn count_broadcasts(&self, origin: &'static str, mut consumer: OriginConsumer) {
+		while let Some((_, broadcast)) = consumer.announced().await {
+			let mut broadcasts = self.broadcasts.lock().unwrap();
+			let count = broadcasts.entry(origin).or_default();
+
+			// A broadcast is always unannounced before it's announced again.
+			match broadcast {
+				Some(_) => *count += 1,
+				None => *count = count.saturating_sub(1),
+			}
+		}
+	}
+
+	/// Render every metric in the Prometheus text exposition format.
+	pub fn render(&self, cluster: &Cluster, registry: &Sessions) -> String {
+		let mut out = String::new();
+
+		let connections = self.connections.lock().unwrap().clone();
+		gauge(&mut out, "connections", "Active connections by transport.");
+		for (transport, count) in connections {
+			sample(&mut out, "connections", &[("transport", &transport)], count);
+		}
+
+		let sessions = self.sessions.lock().unwrap().clone();
+		gauge(&mut out, "sessions", "Active sessions by auth root.");
+		for (root, count) in sessions {
+			sample(&mut out, "sessions", &[("root", &root)], count);
+		}
+
+		let broadcasts = self.broadcasts.lock().unwrap().clone();
+		gauge(&mut out, "broadcasts", "Active broadcasts by origin.");
+		for origin in ORIGINS {
+			let count = broadcasts.get(origin).copied().unwrap_or_default();
+			sample(&mut out, "broadcasts", &[("origin", origin)], count);
+		}
+
+		let stats = registry.stats();
+
+		gauge(&mut out, "subscriptions", "Subscriptions currently served to sessions.");
+		sample(&mut out, "subscriptions", &[], stats.subscriptions());
+
+		counter(&mut out, "bytes_sent_total", "Bytes written to sessions.");
+		sample(&mut out, "bytes_sent_total", &[], stats.bytes_sent());
+
+		counter(&mut out, "bytes_received_total", "Bytes read from sessions.");
+		sample(&mut out, "bytes_received_total", &[], stats.bytes_received());
+
+		counter(
+			&mut out,
+			"groups_dropped_total",
+			"Groups skipped or aborted instead of delivered.",
+		);
+		sample(&mut out, "groups_dropped_total", &[], stats.groups_dropped());
+
+		let failures = self.auth_failures.lock().unwrap().clone();
+		counter(&mut out, "auth_failures_total", "Authentication failures by reason.");
+		for (reason, count) in failures {
+			sample(&mut out, "auth_failures_total", &[("reason", reason)], count);
+		}
+
+		gauge(
+			&mut out,
+			"cluster_remotes",
+			"Connections to other cluster nodes by state, 1 for the current state.",
+		);
+		let remotes = cluster.remotes();
+		for (node, link, health) in &remotes {
+			sample(
+				&mut out,
+				"cluster_remotes",
+				&[
+					("node", node),
+					("link", link.as_str()),
+					("state", health.state.as_str()),
+				],
+				1,
+			);
+		}
+
+		gauge(
+			&mut out,
+			"cluster_remote_failures",
+			"Consecutive failed attempts to connect to other cluster nodes.",
+		);
+		for (node, link, health) in &remotes {
+			sample(
+				&mut out,
+				"cluster_remote_failures",
+				&[("node", node), ("link", link.as_str())],
+				health.failures as u64,
+			);
+		}
+
+		out
+	}
+}
+
+fn gauge(out: &mut String, name: &str, help: &str) {
+	header(out, name, help, "gauge");
+}
+
+fn counter(out: &mut String, name: &str, help: &str) {
+	header(out, name, help, "counter");
+}
+
+fn header(out: &mut String, name: &str, help: &str, kind: &str) {
+	writeln!(out, "# HELP moq_relay_{name} {help}").unwrap();
+	writeln!(out, "# TYPE moq_relay_{name} {kind}").unwrap();
+}
+
+fn sample(out: &mut String, name: &str, labels: &[(&str, &str)], value: u64) {
+	write!(out, "moq_relay_{name}").unwrap();
+
+	if !labels.is_empty() {
+		let labels: Vec<_> = labels
+			.iter()
+			.map(|(key, value)| format!("{key}=\"{}\"", escape(value)))
+			.collect();
+		write!(out, "{{{}}}", labels.join(",")).unwrap();
+	}
+
+	writeln!(out, " {value}").unwrap();
+}
+
+// Escape a label value as required by the text format.
+fn escape(value: &str) -> String {
+	value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// Decrements a gauge when dropped, removing the label once it reaches zero.
+pub struct Active {
+	gauge: Gauge,
+	label: String,
+}
+
+impl Active {
+	fn new(gauge: Gauge, label: &str) -> Self {
+		*gauge.lock().unwrap().entry(label.to_string()).or_default() += 1;
+		Self {
+			gauge,
+			label: label.to_string(),
+		}
+	}
+}
+
+impl Drop for Active {
+	fn drop(&mut self) {
+		let mut gauge = self.gauge.lock().unwrap();
+		if let Some(count) = gauge.get_mut(&self.label) {
+			*count -= 1;
+			if *count == 0 {
+				gauge.remove(&self.label);
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_active() {
+		let metrics = Metrics::default();
+
+		let first = metrics.session("test/active");
+		let second = metrics.session("test/active");
+		assert_eq!(metrics.sessions.lock().unwrap().get("test/active"), Some(&2));
+
+		drop(first);
+		assert_eq!(metrics.sessions.lock().unwrap().get("test/active"), Some(&1));
+
+		// The label is removed so roots don't accumulate forever.
+		drop(second);
+		assert_eq!(metrics.sessions.lock().unwrap().get("test/active"), None);
+	}
+
+	#[tokio::test]
+	async fn test_broadcasts() {
+		let metrics = Metrics::default();
+		let origin = moq_lite::Origin::produce();
+		tokio::spawn({
+			let metrics = metrics.clone();
+			let consumer = origin.consumer.consume();
+			async move { metrics.count_broadcasts("primary", consumer).await }
+		});
+
+		let count = || metrics.broadcasts.lock().unwrap().get("primary").copied();
+
+		let first = moq_lite::Broadcast::produce();
+		let second = moq_lite::Broadcast::produce();
+		origin.producer.publish_broadcast("a", first.consumer.clone());
+		origin.producer.publish_broadcast("b", second.consumer.clone());
+		tokio::task::yield_now().await;
+		assert_eq!(count(), Some(2));
+
+		// Replacing a broadcast doesn't change the count.
+		let replaced = moq_lite::Broadcast::produce();
+		origin.producer.publish_broadcast("a", replaced.consumer.clone());
+		tokio::task::yield_now().await;
+		assert_eq!(count(), Some(2));
+
+		origin.producer.unpublish_broadcast("a");
+		tokio::task::yield_now().await;
+		assert_eq!(count(), Some(1));
+	}
+
+	#[test]
+	fn test_sample() {
+		let mut out = String::new();
+		sample(&mut out, "sessions", &[("root", "a\"b")], 3);
+		sample(&mut out, "subscriptions", &[], 0);
+		assert_eq!(
+			out,
+			"moq_relay_sessions{root=\"a\\\"b\"} 3\nmoq_relay_subscriptions 0\n"
+		);
+	}
+}
@@ -0,0 +1,784 @@
+//! Records broadcasts to disk.
+//!
+//! Each recording is a directory named after the broadcast path, containing segment files named after the
+//! unix time (in milliseconds) they were started. A segment is a sequence of records, each with a one byte kind,
+//! a big-endian u32 length, and then the body:
+//!
+//! - `0` header: JSON with the archive `version`, the `broadcast` path, and the `started` time of the segment.
+//! - `1` catalog: the hang catalog as JSON, written at the start of each segment and whenever it changes.
+//! - `2` frame: big-endian u64 microseconds since the segment started, u64 group sequence, u64 frame index within the
+//!   group, a u8 track name length and the track name, followed by the frame payload.
+
+use std::{
+	collections::{HashMap, HashSet},
+	path::{Component, Path, PathBuf},
+	sync::{
+		atomic::{AtomicU64, Ordering},
+		Arc, Mutex,
+	},
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Context;
+use bytes::{BufMut, Bytes, BytesMut};
+use hang::{Catalog, CatalogConsumer};
+use moq_lite::{BroadcastConsumer, OriginConsumer, Track, TrackConsumer};
+use serde::{Deserialize, Serialize};
+use tokio::{
+	io::AsyncWriteExt,
+	sync::{mpsc, oneshot},
+	task::JoinSet,
+	time::Instant,
+};
+
+use crate::Cluster;
+
+const VERSION: u64 = 1;
+
+const RECORD_HEADER: u8 = 0;
+const RECORD_CATALOG: u8 = 1;
+const RECORD_FRAME: u8 = 2;
+
+// The file extension used for segments.
+const EXTENSION: &str = "moqr";
+
+// The default duration of each segment file.
+const SEGMENT: Duration = Duration::from_secs(600);
+
+// How often to delete expired segments, including those of broadcasts that are no longer recorded.
+const SWEEP: Duration = Duration::from_secs(60);
+
+// The number of frames buffered while waiting for the disk, before pausing the tracks.
+const BUFFER: usize = 1024;
+
+#[derive(clap::Args, Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct RecordConfig {
+	/// Write recordings to this directory.
+	/// Recording is disabled unless this is set.
+	#[arg(long = "record-dir", id = "record-dir", env = "MOQ_RECORD_DIR")]
+	pub dir: Option<PathBuf>,
+
+	/// Automatically record any broadcast starting with these prefixes.
+	/// Otherwise, recordings are only started via the admin API.
+	#[arg(
+		long = "record-prefix",
+		id = "record-prefix",
+		env = "MOQ_RECORD_PREFIX",
+		value_delimiter = ','
+	)]
+	pub prefix: Vec<String>,
+
+	/// Start a new segment file after this many seconds.
+	/// Defaults to 600 (10 minutes).
+	#[arg(long = "record-segment", id = "record-segment", env = "MOQ_RECORD_SEGMENT")]
+	pub segment: Option<u64>,
+
+	/// Delete segment files that haven't been written for this many seconds.
+	/// The whole directory is checked every minute, including broadcasts that are no longer recorded.
+	/// Recordings are kept forever unless this is set.
+	#[arg(long = "record-retention", id = "record-retention", env = "MOQ_RECORD_RETENTION")]
+	pub retention: Option<u64>,
+}
+
+/// An active recording, as listed by the admin API.
+#[derive(Clone, Debug, Serialize)]
+pub struct RecordingInfo {
+	pub path: String,
+	/// The unix time in milliseconds when the recording started.
+	pub started: u64,
+	/// True if the recording was started because the path matched a configured prefix.
+	pub automatic: bool,
+}
+
+struct RecordingEntry {
+	id: u64,
+	info: RecordingInfo,
+
+	// Dropped to stop the recording.
+	_stop: oneshot::Sender<()>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RecordError {
+	#[error("broadcast not found")]
+	NotFound,
+
+	#[error("already recording")]
+	Duplicate,
+}
+
+/// Records broadcasts from the combined origin, either matching the configured prefixes or on request.
+#[derive(Clone)]
+pub struct Recorder {
+	config: RecordConfig,
+	dir: PathBuf,
+	origin: OriginConsumer,
+	next: Arc<AtomicU64>,
+	active: Arc<Mutex<HashMap<String, RecordingEntry>>>,
+}
+
+impl Recorder {
+	/// Returns None if recording is disabled.
+	pub fn new(config: RecordConfig, cluster: &Cluster) -> Option<Self> {
+		Some(Self {
+			dir: config.dir.clone()?,
+			origin: cluster.combined.consumer.consume(),
+			config,
+			next: Default::default(),
+			active: Default::default(),
+		})
+	}
+
+	/// Record every broadcast matching the configured prefixes, as they're announced.
+	/// Expired segments are periodically deleted from the entire directory.
+	pub async fn run(self) {
+		let retention = self.config.retention.map(Duration::from_secs);
+		tokio::join!(self.run_prefixes(), sweep(self.dir.clone(), retention));
+	}
+
+	async fn run_prefixes(&self) {
+		let prefixes: Vec<moq_lite::Path> = self
+			.config
+			.prefix
+			.iter()
+			.map(|prefix| moq_lite::Path::new(prefix))
+			.collect();
+		if prefixes.is_empty() {
+			return;
+		}
+
+		let mut origin = match self.origin.consume_only(&prefixes) {
+			Some(origin) => origin,
+			None => return,
+		};
+
+		while let Some((path, broadcast)) = origin.announced().await {
+			// The recording ends on its own when the broadcast is closed.
+			if let Some(broadcast) = broadcast {
+				// A reannounced broadcast replaces the previous recording.
+				self.active.lock().unwrap().remove(path.as_str());
+				self.record(path.as_str(), broadcast, true).ok();
+			}
+		}
+	}
+
+	/// Start recording a broadcast that is currently live.
+	pub fn start(&self, path: &str) -> Result<RecordingInfo, RecordError> {
+		let broadcast = self.origin.consume_broadcast(path).ok_or(RecordError::NotFound)?;
+		self.record(path, broadcast, false)
+	}
+
+	/// Stop a recording, returning false if it doesn't exist.
+	pub fn stop(&self, path: &str) -> bool {
+		self.active.lock().unwrap().remove(path).is_some()
+	}
+
+	pub fn list(&self) -> Vec<RecordingInfo> {
+		let mut list: Vec<_> = self
+			.active
+			.lock()
+			.unwrap()
+			.values()
+			.map(|entry| entry.info.clone())
+			.collect();
+		list.sort_by(|a, b| a.path.cmp(&b.path));
+		list
+	}
+
+	fn record(&self, path: &str, broadcast: BroadcastConsumer, automatic: bool) -> Result<RecordingInfo, RecordError> {
+		let mut active = self.active.lock().unwrap();
+		if active.contains_key(path) {
+			return Err(RecordError::Duplicate);
+		}
+
+		let id = self.next.fetch_add(1, Ordering::Relaxed);
+		let info = RecordingInfo {
+			path: path.to_string(),
+			started: unix_millis(SystemTime::now()),
+			automatic,
+		};
+
+		let (stop, stopped) = oneshot::channel();
+		active.insert(
+			path.to_string(),
+			RecordingEntry {
+				id,
+				info: info.clone(),
+				_stop: stop,
+			},
+		);
+
+		let archive = Archive {
+			dir: self.dir.join(path),
+			broadcast: path.to_string(),
+			segment: self.config.segment.map(Duration::from_secs).unwrap_or(SEGMENT),
+			retention: self.config.retention.map(Duration::from_secs),
+			catalog: None,
+			current: None,
+		};
+
+		let recordings = self.active.clone();
+		let path = path.to_string();
+
+		tokio::spawn(async move {
+			tracing::info!(broadcast = %path, "recording started");
+
+			match record(broadcast, archive, stopped).await {
+				Ok(()) => tracing::info!(broadcast = %path, "recording finished"),
+				Err(err) => tracing::warn!(broadcast = %path, %err, "recording failed"),
+			}
+
+			let mut recordings = recordings.lock().unwrap();
+			if recordings.get(&path).is_some_and(|entry| entry.id == id) {
+				recordings.remove(&path);
+			}
+		});
+
+		Ok(info)
+	}
+}
+
+// A frame received from one of the tracks.
+struct RecordFrame {
+	track: Arc<str>,
+	group: u64,
+	index: u64,
+	received: Instant,
+	payload: Bytes,
+}
+
+async fn record(
+	broadcast: BroadcastConsumer,
+	mut archive: Archive,
+	mut stopped: oneshot::Receiver<()>,
+) -> anyhow::Result<()> {
+	// The broadcast path could contain anything, so make sure it can't escape the recording directory.
+	let path = Path::new(&archive.broadcast);
+	anyhow::ensure!(
+		path.components().all(|c| matches!(c, Component::Normal(_))),
+		"invalid broadcast path"
+	);
+
+	let mut catalog = CatalogConsumer::new(broadcast.subscribe_track(&Catalog::default_track()));
+	let (tx, mut rx) = mpsc::channel(BUFFER);
+
+	// Aborted when dropped, so every track stops with the recording.
+	let mut readers = JoinSet::new();
+	let mut subscribed = HashSet::new();
+
+	// Returns true if the recording was explicitly stopped.
+	let stop = loop {
+		tokio::select! {
+			biased;
+			_ = &mut stopped => break true,
+			Some(frame) = rx.recv() => archive.frame(frame).await?,
+			next = catalog.next() => {
+				let next = match next {
+					Ok(Some(next)) => next,
+					Ok(None) => break false,
+					// The broadcast was closed abruptly, but we still want to finish the segment.
+					Err(err) => {
+						tracing::debug!(%err, broadcast = %archive.broadcast, "catalog ended");
+						break false;
+					}
+				};
+
+				archive.catalog(&next).await?;
+
+				for track in catalog_tracks(&next) {
+					if subscribed.insert(track.name.clone()) {
+						let consumer = broadcast.subscribe_track(&track);
+						readers.spawn(read_track(consumer, track.name.into(), tx.clone()));
+					}
+				}
+			}
+		}
+	};
+
+	// The readers hold the remaining senders, so the channel closes once they're all finished.
+	drop(tx);
+
+	// Record the rest of each track unless the recording is stopped first.
+	if !stop {
+		loop {
+			tokio::select! {
+				biased;
+				_ = &mut stopped => break,
+				frame = rx.recv() => match frame {
+					Some(frame) => archive.frame(frame).await?,
+					None => break,
+				},
+			}
+		}
+	}
+
+	readers.abort_all();
+
+	// Write any frames that were already received.
+	while let Ok(frame) = rx.try_recv() {
+		archive.frame(frame).await?;
+	}
+
+	archive.close().await
+}
+
+async fn read_track(mut track: TrackConsumer, name: Arc<str>, tx: mpsc::Sender<RecordFrame>) {
+	while let Ok(Some(mut group)) = track.next_group().await {
+		let mut index = 0;
+
+		while let Ok(Some(payload)) = group.read_frame().await {
+			let frame = RecordFrame {
+				track: name.clone(),
+				group: group.info.sequence,
+				index,
+				received: Instant::now(),
+				payload,
+			};
+
+			if tx.send(frame).await.is_err() {
+				return;
+			}
+
+			index += 1;
+		}
+	}
+}
+
+// Every track referenced by the catalog.
+fn catalog_tracks(catalog: &Catalog) -> Vec<Track> {
+	let mut tracks = Vec::new();
+
+	if let Some(video) = &catalog.video {
+		for name in video.renditions.keys() {
+			tracks.push(Track {
+				name: name.clone(),
+				priority: video.priority,
+			});
+		}
+		tracks.extend(video.detection.iter().map(|detection| detection.track.clone()));
+	}
+
+	if let Some(audio) = &catalog.audio {
+		for name in audio.renditions.keys() {
+			tracks.push(Track {
+				name: name.clone(),
+				priority: audio.priority,
+			});
+		}
+		tracks.extend(audio.captions.iter().map(|captions| captions.track.clone()));
+		tracks.extend(audio.speaking.iter().map(|speaking| speaking.track.clone()));
+	}
+
+	if let Some(location) = &catalog.location {
+		tracks.extend(location.updates.iter().cloned());
+		tracks.extend(location.peers.values().cloned());
+	}
+
+	if let Some(chat) = &catalog.chat {
+		tracks.extend(chat.message.iter().cloned());
+		tracks.extend(chat.typing.iter().cloned());
+	}
+
+	tracks.extend(catalog.preview.iter().cloned());
+	tracks
+}
+
+// Writes records to segment files, rotating and deleting them as configured.
+struct Archive {
+	dir: PathBuf,
+	broadcast: String,
+	segment: Duration,
+	retention: Option<Duration>,
+
+	// The latest catalog, repeated at the start of each segment.
+	catalog: Option<Bytes>,
+
+	current: Option<Segment>,
+}
+
+struct Segment {
+	file: tokio::fs::File,
+	started: Instant,
+}
+
+impl Segment {
+	async fn write(&mut self, kind: u8, body: &[u8]) -> anyhow::Result<()> {
+		let mut buf = BytesMut::with_capacity(5 + body.len());
+		buf.put_u8(kind);
+		buf.put_u32(body.len().try_into().context("record too large")?);
+		buf.put_slice(body);
+
+		self.file.write_all(&buf).await?;
+		Ok(())
+	}
+}
+
+impl Archive {
+	async fn catalog(&mut self, catalog: &Catalog) -> anyhow::Result<()> {
+		let catalog = Bytes::from(catalog.to_vec()?);
+		self.catalog = Some(catalog.clone());
+
+		// A new segment starts with the catalog anyway.
+		if self.current.is_some() {
+			self.write(RECORD_CATALOG, &catalog).await?;
+		}
+
+		Ok(())
+	}
+
+	async fn frame(&mut self, frame: RecordFrame) -> anyhow::Result<()> {
+		let started = self.segment().await?.started;
+		let time = frame.received.saturating_duration_since(started).as_micros() as u64;
+
+		let name = frame.track.as_bytes();
+		let mut body = BytesMut::with_capacity(25 + name.len() + frame.payload.len());
+		body.put_u64(time);
+		body.put_u64(frame.group);
+		body.put_u64(frame.index);
+		body.put_u8(name.len().try_into().context("track name too long")?);
+		body.put_slice(name);
+		body.put_slice(&frame.payload);
+
+		self.write(RECORD_FRAME, &body).await
+	}
+
+	async fn write(&mut self, kind: u8, body: &[u8]) -> anyhow::Result<()> {
+		self.segment().await?.write(kind, body).await
+	}
+
+	// Return the current segment, starting a new one if it's too old.
+	async fn segment(&mut self) -> anyhow::Result<&mut Segment> {
+		if let Some(current) = &mut self.current {
+			if current.started.elapsed() < self.segment {
+				return Ok(self.current.as_mut().unwrap());
+			}
+
+			current.file.flush().await?;
+		}
+
+		tokio::fs::create_dir_all(&self.dir).await?;
+
+		let now = SystemTime::now();
+		let started = unix_millis(now);
+		let path = self.dir.join(format!("{started}.{EXTENSION}"));
+		let file = tokio::fs::File::create(&path)
+			.await
+			.with_context(|| format!("failed to create {}", path.display()))?;
+
+		tracing::debug!(path = %path.display(), "new recording segment");
+
+		let mut segment = Segment {
+			file,
+			started: Instant::now(),
+		};
+
+		let header = serde_json::json!({
+			"version": VERSION,
+			"broadcast": self.broadcast,
+			"started": started,
+		});
+		segment.write(RECORD_HEADER, header.to_string().as_bytes()).await?;
+
+		if let Some(catalog) = &self.catalog {
+			segment.write(RECORD_CATALOG, catalog).await?;
+		}
+
+		if let Some(retention) = self.retention {
+			expire(&self.dir, retention, now).await?;
+		}
+
+		Ok(self.current.insert(segment))
+	}
+
+	async fn close(mut self) -> anyhow::Result<()> {
+		if let Some(current) = &mut self.current {
+			current.file.flush().await?;
+		}
+
+		Ok(())
+	}
+}
+
+// Periodically delete expired segments, as broadcasts that are no longer recorded never rotate.
+async fn sweep(dir: PathBuf, retention: Option<Duration>) {
+	let retention = match retention {
+		Some(retention) => retention,
+		None => return,
+	};
+
+	let mut interval = tokio::time::interval(SWEEP);
+	loop {
+		interval.tick().await;
+
+		if let Err(err) = expire(&dir, retention, SystemTime::now()).await {
+			tracing::warn!(dir = %dir.display(), %err, "failed to delete expired recordings");
+		}
+	}
+}
+
+// Delete any segments in the directory or its subdirectories that haven't been modified within the retention period.
+async fn expire(dir: &Path, retention: Duration, now: SystemTime) -> anyhow::Result<()> {
+	let mut dirs = vec![dir.to_path_buf()];
+
+	while let Some(dir) = dirs.pop() {
+		// The directory doesn't exist until the first recording starts.
+		let mut entries = match tokio::fs::read_dir(&dir).await {
+			Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+			res => res?,
+		};
+
+		while let Some(entry) = entries.next_entry().await? {
+			let path = entry.path();
+			let metadata = entry.metadata().await?;
+
+			if metadata.is_dir() {
+				dirs.push(path);
+				continue;
+			}
+
+			if path.extension().is_none_or(|extension| extension != EXTENSION) {
+				continue;
+			}
+
+			if now.duration_since(metadata.modified()?).unwrap_or_default() > retention {
+				tracing::debug!(path = %path.display(), "deleting expired recording segment");
+				tokio::fs::remove_file(&path).await?;
+			}
+		}
+	}
+
+	Ok(())
+}
+
+fn unix_millis(time: SystemTime) -> u64 {
+	time.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use bytes::Buf;
+	use hang::catalog::{Audio, AudioCodec, AudioConfig};
+	use hang::CatalogProducer;
+	use moq_lite::{Broadcast, BroadcastProducer, TrackProducer};
+
+	// Split a segment into (kind, body) records.
+	fn records(mut data: Bytes) -> Vec<(u8, Bytes)> {
+		let mut records = Vec::new();
+		while data.has_remaining() {
+			let kind = data.get_u8();
+			let size = data.get_u32() as usize;
+			records.push((kind, data.split_to(size)));
+		}
+		records
+	}
+
+	fn segments(dir: &std::path::Path) -> Vec<PathBuf> {
+		let mut segments: Vec<_> = std::fs::read_dir(dir)
+			.unwrap()
+			.map(|entry| entry.unwrap().path())
+			.collect();
+		segments.sort();
+		segments
+	}
+
+	// Publish a catalog with a single audio track.
+	fn publish_audio(broadcast: &mut BroadcastProducer) -> (CatalogProducer, TrackProducer) {
+		let mut catalog = Catalog::default().produce();
+		broadcast.insert_track(catalog.consumer.track.clone());
+
+		let track = broadcast.create_track(Track::new("audio"));
+
+		let mut renditions = HashMap::new();
+		renditions.insert(
+			"audio".to_string(),
+			AudioConfig {
+				codec: AudioCodec::Opus,
+				sample_rate: 48000,
+				channel_count: 2,
+				bitrate: None,
+				description: None,
+			},
+		);
+		catalog.producer.set_audio(Some(Audio {
+			renditions,
+			priority: 1,
+			captions: None,
+			speaking: None,
+		}));
+		catalog.producer.publish();
+
+		(catalog.producer, track)
+	}
+
+	fn archive(dir: &std::path::Path) -> Archive {
+		Archive {
+			dir: dir.join("demo"),
+			broadcast: "demo".to_string(),
+			segment: SEGMENT,
+			retention: None,
+			catalog: None,
+			current: None,
+		}
+	}
+
+	#[tokio::test]
+	async fn test_record() {
+		let dir = tempfile::tempdir().unwrap();
+
+		let mut broadcast = Broadcast::produce();
+		let (_catalog, mut track) = publish_audio(&mut broadcast.producer);
+
+		let (stop, stopped) = oneshot::channel();
+		let task = tokio::spawn(record(broadcast.consumer, archive(dir.path()), stopped));
+
+		// Wait for the recording to subscribe to the track.
+		tokio::time::sleep(Duration::from_millis(50)).await;
+		let mut group = track.append_group();
+		group.write_frame(Bytes::from_static(b"one"));
+		group.write_frame(Bytes::from_static(b"two"));
+		group.close();
+		tokio::time::sleep(Duration::from_millis(50)).await;
+
+		drop(stop);
+		task.await.unwrap().unwrap();
+
+		let segments = segments(&dir.path().join("demo"));
+		assert_eq!(segments.len(), 1);
+
+		let records = records(std::fs::read(&segments[0]).unwrap().into());
+		assert_eq!(records.len(), 4);
+
+		let (kind, header) = &records[0];
+		assert_eq!(*kind, RECORD_HEADER);
+		let header: serde_json::Value = serde_json::from_slice(header).unwrap();
+		assert_eq!(header["version"], VERSION);
+		assert_eq!(header["broadcast"], "demo");
+
+		let (kind, body) = &records[1];
+		assert_eq!(*kind, RECORD_CATALOG);
+		let recorded = Catalog::from_slice(body).unwrap();
+		assert!(recorded.audio.unwrap().renditions.contains_key("audio"));
+
+		for (index, payload) in [b"one", b"two"].iter().enumerate() {
+			let (kind, body) = &records[index + 2];
+			assert_eq!(*kind, RECORD_FRAME);
+
+			let mut body = body.clone();
+			let _time = body.get_u64();
+			assert_eq!(body.get_u64(), 0);
+			assert_eq!(body.get_u64(), index as u64);
+			let size = body.get_u8() as usize;
+			assert_eq!(body.split_to(size).as_ref(), b"audio");
+			assert_eq!(body.as_ref(), payload.as_slice());
+		}
+	}
+
+	#[tokio::test]
+	async fn test_finish() {
+		let dir = tempfile::tempdir().unwrap();
+
+		let mut broadcast = Broadcast::produce();
+		let (catalog, mut track) = publish_audio(&mut broadcast.producer);
+
+		let (_stop, stopped) = oneshot::channel();
+		let task = tokio::spawn(record(broadcast.consumer, archive(dir.path()), stopped));
+
+		// Wait for the recording to subscribe to the track.
+		tokio::time::sleep(Duration::from_millis(50)).await;
+
+		// The catalog ends before the frames are read, but they're still recorded.
+		drop(catalog);
+		let mut group = track.append_group();
+		group.write_frame(Bytes::from_static(b"one"));
+		group.write_frame(Bytes::from_static(b"two"));
+		group.close();
+		track.close();
+
+		tokio::time::timeout(Duration::from_secs(1), task)
+			.await
+			.expect("recording didn't finish")
+			.unwrap()
+			.unwrap();
+
+		let segments = segments(&dir.path().join("demo"));
+		assert_eq!(segments.len(), 1);
+
+		let records = records(std::fs::read(&segments[0]).unwrap().into());
+		let frames: Vec<_> = records.iter().filter(|(kind, _)| *kind == RECORD_FRAME).collect();
+		assert_eq!(frames.len(), 2);
+	}
+
+	#[tokio::test]
+	async fn test_retention() {
+		let dir = tempfile::tempdir().unwrap();
+
+		// An old segment from a previous recording, and an unrelated file.
+		let old = dir.path().join(format!("1.{EXTENSION}"));
+		let file = std::fs::File::create(&old).unwrap();
+		file.set_modified(SystemTime::now() - Duration::from_secs(120)).unwrap();
+		std::fs::write(dir.path().join("notes.txt"), "keep").unwrap();
+
+		let mut archive = Archive {
+			dir: dir.path().to_path_buf(),
+			broadcast: "demo".to_string(),
+			segment: Duration::ZERO,
+			retention: Some(Duration::from_secs(60)),
+			catalog: Some(Bytes::from_static(b"{}")),
+			current: None,
+		};
+
+		archive.segment().await.unwrap();
+		archive.close().await.unwrap();
+
+		let segments = segments(dir.path());
+		assert_eq!(segments.len(), 2);
+		assert!(!segments.contains(&old));
+		assert!(segments.contains(&dir.path().join("notes.txt")));
+
+		// Every segment starts with the header and the latest catalog.
+		let current = segments
+			.iter()
+			.find(|path| path.extension().unwrap() == EXTENSION)
+			.unwrap();
+		let records = records(std::fs::read(current).unwrap().into());
+		assert_eq!(records.len(), 2);
+		assert_eq!(records[1], (RECORD_CATALOG, Bytes::from_static(b"{}")));
+	}
+
+	#[tokio::test]
+	async fn test_sweep() {
+		let dir = tempfile::tempdir().unwrap();
+
+		// Segments left behind by nested broadcasts that are no longer being recorded.
+		let nested = dir.path().join("room/alice");
+		std::fs::create_dir_all(&nested).unwrap();
+
+		let old = [
+			dir.path().join(format!("1.{EXTENSION}")),
+			nested.join(format!("1.{EXTENSION}")),
+		];
+		for path in &old {
+			let file = std::fs::File::create(path).unwrap();
+			file.set_modified(SystemTime::now() - Duration::from_secs(120)).unwrap();
+		}
+
+		let fresh = nested.join(format!("2.{EXTENSION}"));
+		std::fs::File::create(&fresh).unwrap();
+
+		// The first sweep runs immediately.
+		let task = tokio::spawn(sweep(dir.path().to_path_buf(), Some(Duration::from_secs(60))));
+		tokio::time::sleep(Duration::from_millis(50)).await;
+		task.abort();
+
+		assert!(old.iter().all(|path| !path.exists()));
+		assert!(fresh.exists());
+
+		// Nothing is deleted without a retention period.
+		let file = std::fs::File::create(&old[0]).unwrap();
+		file.set_modified(SystemTime::now() - Duration::from_secs(120)).unwrap();
+		sweep(dir.path().to_path_buf(), None).await;
+		assert!(old[0].exists());
+	}
+}
@@ -0,0 +1,192 @@
+use std::{
+	collections::{BTreeMap, BTreeSet, HashMap},
+	sync::{Arc, Mutex},
+};
+
+use moq_lite::{Path, PathOwned, TrackProducer};
+
+/// The track within a node's origin announcement that lists the prefixes it owns, as a JSON array.
+pub const OWNED_TRACK: &str = "owned.json";
+
+/// The state used for lazy routing, shared between the cluster and local sessions.
+///
+/// Local publishers claim the prefixes they're allowed to publish, which are advertised to other nodes.
+/// A broadcast requested by a local subscriber is fetched from the node that owns it.
+#[derive(Clone)]
+pub struct Routes {
+	state: Arc<Mutex<RoutesState>>,
+}
+
+struct RoutesState {
+	// The prefixes claimed by local publishers, with a reference count.
+	claims: HashMap<PathOwned, usize>,
+
+	// The prefixes owned by each remote node.
+	owners: HashMap<String, Vec<PathOwned>>,
+
+	// Advertises our claims to other nodes.
+	owned: TrackProducer,
+}
+
+impl RoutesState {
+	fn publish(&mut self) {
+		let owned: BTreeSet<&str> = self.claims.keys().map(|prefix| prefix.as_str()).collect();
+		let json = serde_json::to_vec(&owned).expect("failed to encode owned prefixes");
+		self.owned.write_frame(json);
+	}
+}
+
+impl Routes {
+	pub fn new(owned: TrackProducer) -> Self {
+		let mut state = RoutesState {
+			claims: HashMap::new(),
+			owners: HashMap::new(),
+			owned,
+		};
+
+		// Start with an empty list so other nodes don't wait for our first publisher.
+		state.publish();
+
+		Self {
+			state: Arc::new(Mutex::new(state)),
+		}
+	}
+
+	/// Claim prefixes on behalf of a local publisher, until the guard is dropped.
+	pub fn register(&self, claims: Vec<PathOwned>) -> RouteGuard {
+		let mut state = self.state.lock().unwrap();
+
+		let mut claimed = false;
+		for prefix in &claims {
+			let count = state.claims.entry(prefix.clone()).or_default();
+			claimed |= *count == 0;
+			*count += 1;
+		}
+
+		if claimed {
+			state.publish();
+		}
+
+		RouteGuard {
+			routes: self.clone(),
+			claims,
+		}
+	}
+
+	/// Set the prefixes owned by a remote node, or remove the node if None.
+	pub fn set_owner(&self, node: &str, owned: Option<Vec<PathOwned>>) {
+		let mut state = self.state.lock().unwrap();
+		match owned {
+			Some(owned) => state.owners.insert(node.to_string(), owned),
+			None => state.owners.remove(node),
+		};
+	}
+
+	/// Return the remote node that owns a broadcast, preferring the most specific prefix.
+	pub fn owner(&self, broadcast: &Path) -> Option<String> {
+		let state = self.state.lock().unwrap();
+
+		state
+			.owners
+			.iter()
+			.flat_map(|(node, owned)| owned.iter().map(move |prefix| (node, prefix)))
+			.filter(|(_, prefix)| broadcast.has_prefix(*prefix))
+			.max_by_key(|(_, prefix)| prefix.len())
+			.map(|(node, _)| node.clone())
+	}
+
+	/// Return the prefixes owned by each remote node.
+	pub fn owners(&self) -> BTreeMap<String, Vec<String>> {
+		let state = self.state.lock().unwrap();
+		state
+			.owners
+			.iter()
+			.map(|(node, owned)| (node.clone(), owned.iter().map(|p| p.to_string()).collect()))
+			.collect()
+	}
+
+	fn unregister(&self, claims: &[PathOwned]) {
+		let mut state = self.state.lock().unwrap();
+
+		let mut released = false;
+		for prefix in claims {
+			let count = state.claims.get_mut(prefix).expect("claim not found");
+			*count -= 1;
+			if *count == 0 {
+				state.claims.remove(prefix);
+				released = true;
+			}
+		}
+
+		if released {
+			state.publish();
+		}
+	}
+}
+
+/// Keeps the prefixes of a local publisher claimed until dropped.
+pub struct RouteGuard {
+	routes: Routes,
+	claims: Vec<PathOwned>,
+}
+
+impl Drop for RouteGuard {
+	fn drop(&mut self) {
+		self.routes.unregister(&self.claims);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use moq_lite::{AsPath, Track};
+
+	fn paths(paths: &[&str]) -> Vec<PathOwned> {
+		paths.iter().map(|p| p.as_path().to_owned()).collect()
+	}
+
+	#[tokio::test]
+	async fn test_owner() {
+		let routes = Routes::new(Track::new(OWNED_TRACK).produce().producer);
+		let owner = |broadcast: &str| routes.owner(&broadcast.as_path());
+
+		routes.set_owner("a", Some(paths(&["room"])));
+		routes.set_owner("b", Some(paths(&["room/bob", "other"])));
+
+		assert_eq!(owner("room/alice").as_deref(), Some("a"));
+		assert_eq!(owner("other/cam").as_deref(), Some("b"));
+		assert_eq!(owner("elsewhere"), None);
+
+		// The most specific prefix wins.
+		assert_eq!(owner("room/bob/cam").as_deref(), Some("b"));
+
+		routes.set_owner("b", None);
+		assert_eq!(owner("room/bob/cam").as_deref(), Some("a"));
+		assert_eq!(owner("other/cam"), None);
+	}
+
+	#[tokio::test]
+	async fn test_owned() {
+		let track = Track::new(OWNED_TRACK).produce();
+		let mut consumer = track.consumer;
+		let routes = Routes::new(track.producer);
+
+		let mut read = async || {
+			let mut group = consumer.next_group().await.unwrap().unwrap();
+			let frame = group.read_frame().await.unwrap().unwrap();
+			serde_json::from_slice::<Vec<String>>(&frame).unwrap()
+		};
+
+		assert!(read().await.is_empty());
+
+		let first = routes.register(paths(&["room/alice"]));
+		assert_eq!(read().await, ["room/alice"]);
+
+		// Claiming the same prefix again doesn't publish an update.
+		let second = routes.register(paths(&["room/alice"]));
+		drop(first);
+
+		drop(second);
+		assert!(read().await.is_empty());
+	}
+}
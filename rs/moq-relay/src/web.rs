@@ -1,32 +1,26 @@
 use futures::{SinkExt, StreamExt};
-use std::{
-	net,
-	path::PathBuf,
-	pin::Pin,
-	sync::{
-		atomic::{AtomicU64, Ordering},
-		Arc,
-	},
-	task::{ready, Context, Poll},
-};
+use std::{net, path::PathBuf, pin::Pin, sync::Arc, time::Duration};
 use web_transport_ws::tungstenite;
 
 use axum::{
 	body::Body,
-	extract::{Path, Query, State, WebSocketUpgrade},
-	http::{Method, StatusCode},
-	response::{IntoResponse, Response},
-	routing::{any, get},
-	Router,
+	extract::{ws::rejection::WebSocketUpgradeRejection, Path, Query, State, WebSocketUpgrade},
+	http::{header, HeaderMap, HeaderName, Method, StatusCode},
+	response::{
+		sse::{Event, KeepAlive, Sse},
+		IntoResponse, Response,
+	},
+	routing::{any, delete, get, post},
+	Json, Router,
 };
-use bytes::Bytes;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
 use clap::Parser;
 use moq_lite::{OriginConsumer, OriginProducer};
 use serde::{Deserialize, Serialize};
 use std::future::Future;
 use tower_http::cors::{Any, CorsLayer};
 
-use crate::{Auth, Cluster};
+use crate::{Admin, AdminConfig, Auth, Cluster, Ingest, Recorder, SessionHandle, SessionInfo, Sessions};
 
 #[derive(Debug, Deserialize)]
 struct Params {
@@ -48,6 +42,26 @@ pub struct WebConfig {
 	#[arg(long = "web-ws", env = "MOQ_WEB_WS", default_value = "true")]
 	#[serde(default = "default_true")]
 	pub ws: bool,
+
+	/// The maximum size of a frame published over HTTP, in bytes.
+	/// Larger requests are rejected with 413. Defaults to 4 MiB.
+	#[arg(long = "web-publish-max", env = "MOQ_WEB_PUBLISH_MAX")]
+	pub publish_max: Option<usize>,
+
+	#[command(flatten)]
+	#[serde(default)]
+	pub admin: AdminConfig,
+}
+
+const DEFAULT_PUBLISH_MAX: usize = 4 * 1024 * 1024;
+
+/// How long to wait for a remote publisher to answer a status query.
+const STATUS_TIMEOUT: Duration = Duration::from_secs(10);
+
+impl WebConfig {
+	pub fn publish_max(&self) -> usize {
+		self.publish_max.unwrap_or(DEFAULT_PUBLISH_MAX)
+	}
 }
 
 #[derive(clap::Args, Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
@@ -76,7 +90,10 @@ pub struct WebState {
 	pub auth: Auth,
 	pub cluster: Cluster,
 	pub fingerprints: Vec<String>,
-	pub conn_id: AtomicU64,
+	pub sessions: Sessions,
+	pub ingest: Ingest,
+	pub recorder: Option<Recorder>,
+	pub publish_max: usize,
 }
 
 // Run a HTTP server using Axum
@@ -99,14 +116,42 @@ impl Web {
 			.route("/certificate.sha256", get(fingerprint))
 			.route("/announced", get(serve_announced))
 			.route("/announced/{*prefix}", get(serve_announced))
-			.route("/fetch/{*path}", get(serve_fetch));
+			.route("/fetch/{*path}", get(serve_fetch))
+			.route("/status/{*path}", get(serve_status))
+			.route("/publish/{*path}", post(serve_publish).put(serve_publish))
+			.route("/unpublish/{*broadcast}", delete(serve_unpublish))
+			.route("/metrics", get(serve_metrics));
+
+		// If an admin token is configured, add the admin API.
+		let admin = Admin::new(
+			&self.config.admin,
+			self.state.sessions.clone(),
+			self.state.cluster.clone(),
+			self.state.recorder.clone(),
+		)?;
+		let app = match admin {
+			Some(admin) => app.nest("/admin", admin.router()),
+			None => app,
+		};
+
+		// Serve hang broadcasts as LL-HLS.
+		#[cfg(feature = "hls")]
+		let app = app.nest(
+			"/hls",
+			crate::Hls::new(self.state.auth.clone(), self.state.cluster.clone()).router(),
+		);
 
 		// If WebSocket is enabled, add the WebSocket route.
 		let app = match self.config.ws {
 			true => app.route("/{*path}", any(serve_ws)),
 			false => app,
 		}
-		.layer(CorsLayer::new().allow_origin(Any).allow_methods([Method::GET]))
+		.layer(CorsLayer::new().allow_origin(Any).allow_methods([
+			Method::GET,
+			Method::POST,
+			Method::PUT,
+			Method::DELETE,
+		]))
 		.with_state(Arc::new(self.state))
 		.into_make_service();
 
@@ -157,7 +202,27 @@ async fn serve_ws(
 	}
 
 	Ok(ws.on_upgrade(async move |socket| {
-		let id = state.conn_id.fetch_add(1, Ordering::Relaxed);
+		let id = state.sessions.next_id();
+		let _connection = state.sessions.metrics().connection("websocket");
+		let _session = state.sessions.metrics().session(token.root.as_str());
+		let _route = state.cluster.route(&token, publish.is_some());
+
+		let handle = state.sessions.register(SessionInfo {
+			id,
+			transport: "websocket",
+			remote: None,
+			root: token.root.to_string(),
+			publish: publish
+				.iter()
+				.flat_map(|p| p.allowed())
+				.map(|p| p.to_string())
+				.collect(),
+			subscribe: subscribe
+				.iter()
+				.flat_map(|s| s.allowed())
+				.map(|p| p.to_string())
+				.collect(),
+		});
 
 		// Unfortunately, we need to convert from Axum to Tungstenite.
 		// Axum uses Tungstenite internally, but it's not exposed to avoid semvar issues.
@@ -169,7 +234,7 @@ async fn serve_ws(
 				tungstenite::Error::ConnectionClosed
 			})
 			.with(tungstenite_to_axum);
-		let _ = handle_socket(id, socket, publish, subscribe).await;
+		let _ = handle_socket(id, socket, publish, subscribe, handle, state.sessions.stats().clone()).await;
 	}))
 }
 
@@ -179,6 +244,8 @@ async fn handle_socket<T>(
 	socket: T,
 	publish: Option<OriginProducer>,
 	subscribe: Option<OriginConsumer>,
+	handle: SessionHandle,
+	stats: moq_lite::Stats,
 ) -> anyhow::Result<()>
 where
 	T: futures::Stream<Item = Result<tungstenite::Message, tungstenite::Error>>
@@ -187,18 +254,45 @@ where
 		+ Unpin
 		+ 'static,
 {
-	// Wrap the WebSocket in a WebTransport compatibility layer.
+	// Wrap the WebSocket in a WebTransport compatibility layer, which doesn't support datagrams.
 	let ws = web_transport_ws::Session::new(socket, true);
-	let session = moq_lite::Session::accept(ws, subscribe, publish).await?;
-	session.closed().await.map_err(Into::into)
+	let session = moq_lite::Session::request(ws)
+		.await?
+		.datagrams(false)
+		.stats(stats)
+		.ok(subscribe, publish)
+		.await?;
+
+	tokio::select! {
+		res = session.closed() => res.map_err(Into::into),
+		_ = handle.closed() => {
+			tracing::info!("closed by admin");
+			session.close(moq_lite::Error::Cancel);
+			Ok(())
+		}
+	}
+}
+
+/// Serve the relay metrics in the Prometheus text format.
+async fn serve_metrics(State(state): State<Arc<WebState>>) -> impl IntoResponse {
+	(
+		[(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+		state.sessions.metrics().render(&state.cluster, &state.sessions),
+	)
 }
 
 /// Serve the announced broadcasts for a given prefix.
+///
+/// By default this returns a newline-separated snapshot of the active broadcasts.
+/// Requests that accept `text/event-stream` or upgrade to a WebSocket instead receive live updates,
+/// starting with every active broadcast and followed by each announce and unannounce.
 async fn serve_announced(
 	path: Option<Path<String>>,
 	Query(params): Query<Params>,
 	State(state): State<Arc<WebState>>,
-) -> axum::response::Result<String> {
+	headers: HeaderMap,
+	ws: Result<WebSocketUpgrade, WebSocketUpgradeRejection>,
+) -> axum::response::Result<Response> {
 	let prefix = match path {
 		Some(Path(prefix)) => prefix,
 		None => String::new(),
@@ -210,6 +304,26 @@ async fn serve_announced(
 		None => return Err(StatusCode::UNAUTHORIZED.into()),
 	};
 
+	if let Ok(ws) = ws {
+		return Ok(ws.on_upgrade(async move |socket| serve_announced_ws(socket, origin).await));
+	}
+
+	let stream = headers
+		.get_all(header::ACCEPT)
+		.iter()
+		.filter_map(|value| value.to_str().ok())
+		.any(|value| value.contains("text/event-stream"));
+
+	if stream {
+		let events = futures::stream::unfold(origin, async |mut origin| {
+			let (path, active) = origin.announced().await?;
+			let event = AnnouncedEvent::new(path, active.is_some());
+			Some((Event::default().event(event.kind()).json_data(&event), origin))
+		});
+
+		return Ok(Sse::new(events).keep_alive(KeepAlive::default()).into_response());
+	}
+
 	let mut broadcasts = Vec::new();
 
 	while let Some((suffix, active)) = origin.try_announced() {
@@ -218,36 +332,90 @@ async fn serve_announced(
 		}
 	}
 
-	Ok(broadcasts.iter().map(|p| p.to_string()).collect::<Vec<_>>().join("\n"))
+	Ok(broadcasts
+		.iter()
+		.map(|p| p.to_string())
+		.collect::<Vec<_>>()
+		.join("\n")
+		.into_response())
 }
 
-/// Serve the latest group for a given track
+// Send each announcement as a JSON text message until the origin or the socket closes.
+async fn serve_announced_ws(mut socket: axum::extract::ws::WebSocket, mut origin: OriginConsumer) {
+	loop {
+		tokio::select! {
+			announced = origin.announced() => {
+				let Some((path, active)) = announced else { break };
+				let event = AnnouncedEvent::new(path, active.is_some());
+				let text = match serde_json::to_string(&event) {
+					Ok(text) => text,
+					Err(err) => {
+						tracing::warn!(%err, "failed to encode announcement");
+						break;
+					}
+				};
+
+				if socket.send(axum::extract::ws::Message::Text(text.into())).await.is_err() {
+					break;
+				}
+			}
+			msg = socket.recv() => match msg {
+				// Ignore anything the client sends, other than closing.
+				Some(Ok(axum::extract::ws::Message::Close(_))) | Some(Err(_)) | None => break,
+				Some(Ok(_)) => {}
+			},
+		}
+	}
+}
+
+/// A live announcement, relative to the requested prefix.
+#[derive(Debug, Serialize)]
+struct AnnouncedEvent {
+	path: String,
+	active: bool,
+}
+
+impl AnnouncedEvent {
+	fn new(path: moq_lite::PathOwned, active: bool) -> Self {
+		Self {
+			path: path.to_string(),
+			active,
+		}
+	}
+
+	// The SSE event name.
+	fn kind(&self) -> &'static str {
+		match self.active {
+			true => "announce",
+			false => "unannounce",
+		}
+	}
+}
+
+/// Serve one or more groups for a given track.
+///
+/// By default this returns the latest group, but the query parameters can instead select a specific sequence,
+/// long-poll for the next group after a sequence, or stream a range of groups.
 async fn serve_fetch(
 	Path(path): Path<String>,
-	Query(params): Query<Params>,
+	Query(params): Query<FetchParams>,
 	State(state): State<Arc<WebState>>,
-) -> axum::response::Result<ServeGroup> {
-	// The path containts a broadcast/track
-	let mut path: Vec<&str> = path.split("/").collect();
-	let track = path.pop().unwrap().to_string();
+) -> axum::response::Result<Response> {
+	let (broadcast, track) = split_track(&path)?;
 
-	// We need at least a broadcast and a track.
-	if path.is_empty() {
-		return Err(StatusCode::BAD_REQUEST.into());
-	}
+	let range = params.range().ok_or(StatusCode::BAD_REQUEST)?;
 
-	let broadcast = path.join("/");
-	let token = state.auth.verify(&broadcast, params.jwt.as_deref())?;
+	let token = state.auth.verify(broadcast, params.jwt.as_deref())?;
 
 	let origin = match state.cluster.subscriber(&token) {
 		Some(origin) => origin,
 		None => return Err(StatusCode::UNAUTHORIZED.into()),
 	};
 
-	tracing::info!(%broadcast, %track, "fetching track");
+	tracing::info!(%broadcast, %track, ?range, "fetching track");
 
 	let track = moq_lite::Track {
-		name: track,
+		name: track.to_string(),
 		priority: 0,
 	};
 
@@ -255,72 +423,418 @@ async fn serve_fetch(
 	let broadcast = origin.consume_broadcast("").ok_or(StatusCode::NOT_FOUND)?;
 	let mut track = broadcast.subscribe_track(&track);
 
+	if let Some(start) = range.start {
+		track.start_at(start);
+	}
+
+	// Wait for the first group, so we can return an error status if there isn't one.
 	let group = match track.next_group().await {
 		Ok(Some(group)) => group,
 		Ok(None) => return Err(StatusCode::NOT_FOUND.into()),
 		Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR.into()),
 	};
 
-	Ok(ServeGroup::new(group))
+	// Only the latest group is cached, so older groups may no longer be available.
+	if range.exact && Some(group.info.sequence) != range.start {
+		return Err(StatusCode::GONE.into());
+	}
+
+	let sequence = group.info.sequence;
+	let content_type = match params.framed {
+		true => "application/vnd.moq.frames",
+		false => "application/octet-stream",
+	};
+
+	let track = match range.single {
+		true => None,
+		false => Some(track),
+	};
+
+	Ok((
+		[
+			(header::CONTENT_TYPE, content_type.to_string()),
+			(HeaderName::from_static("moq-group"), sequence.to_string()),
+		],
+		ServeGroups::new(group, track, range.end, params.framed),
+	)
+		.into_response())
 }
 
-struct ServeGroup {
-	group: moq_lite::GroupConsumer,
-	frame: Option<moq_lite::FrameConsumer>,
+#[derive(Debug, Deserialize)]
+struct FetchParams {
+	jwt: Option<String>,
+
+	/// Fetch the group with this sequence, waiting for it if it hasn't arrived yet.
+	sequence: Option<u64>,
+
+	/// Long-poll for the first group with a sequence greater than this.
+	after: Option<u64>,
+
+	/// Stream every group starting at this sequence, waiting for new groups until `end` or the track ends.
+	start: Option<u64>,
+
+	/// The last group to stream, inclusive.
+	end: Option<u64>,
+
+	/// Prefix each frame with its group sequence and size, preserving the boundaries.
+	#[serde(default)]
+	framed: bool,
 }
 
-impl ServeGroup {
-	fn new(group: moq_lite::GroupConsumer) -> Self {
-		Self { group, frame: None }
+impl FetchParams {
+	// Returns None if the parameters conflict.
+	fn range(&self) -> Option<FetchRange> {
+		let range = match (self.sequence, self.after, self.start, self.end) {
+			(None, None, None, None) => FetchRange::default(),
+			(Some(sequence), None, None, None) => FetchRange {
+				start: Some(sequence),
+				end: Some(sequence),
+				single: true,
+				exact: true,
+			},
+			(None, Some(after), None, None) => FetchRange {
+				start: Some(after.checked_add(1)?),
+				single: true,
+				..Default::default()
+			},
+			(None, None, Some(start), end) if end.is_none_or(|end| end >= start) => FetchRange {
+				start: Some(start),
+				end,
+				single: false,
+				exact: false,
+			},
+			_ => return None,
+		};
+
+		Some(range)
 	}
+}
 
-	async fn next(&mut self) -> moq_lite::Result<Option<Bytes>> {
-		loop {
-			if let Some(frame) = self.frame.as_mut() {
-				let data = frame.read_all().await?;
-				self.frame.take();
-				return Ok(Some(data));
+#[derive(Debug, PartialEq, Eq)]
+struct FetchRange {
+	// The first group to serve, or the latest group if None.
+	start: Option<u64>,
+	// The last group to serve, inclusive.
+	end: Option<u64>,
+	// Serve only the first group.
+	single: bool,
+	// The first group must have the start sequence.
+	exact: bool,
+}
+
+impl Default for FetchRange {
+	fn default() -> Self {
+		Self {
+			start: None,
+			end: None,
+			single: true,
+			exact: false,
+		}
+	}
+}
+
+/// Publish to a given track over HTTP.
+///
+/// By default the body is written as a single frame in a new group.
+/// With `framed=true`, the body is streamed in the same format as a framed fetch and each new sequence starts a new group.
+/// The broadcast stays published for the duration of the upload, and briefly afterwards so it can span multiple requests.
+async fn serve_publish(
+	Path(path): Path<String>,
+	Query(params): Query<PublishParams>,
+	State(state): State<Arc<WebState>>,
+	body: Body,
+) -> axum::response::Result<Response> {
+	let (broadcast, track) = split_track(&path)?;
+
+	if params.framed && params.sequence.is_some() {
+		return Err(StatusCode::BAD_REQUEST.into());
+	}
+
+	let token = state.auth.verify(broadcast, params.jwt.as_deref())?;
+
+	let origin = match state.cluster.publisher(&token) {
+		Some(origin) => origin,
+		None => return Err(StatusCode::UNAUTHORIZED.into()),
+	};
+
+	// NOTE: The auth token is already scoped to the broadcast.
+	let mut upload = state
+		.ingest
+		.upload(broadcast, &origin, track)
+		.ok_or(StatusCode::UNAUTHORIZED)?;
+
+	// The broadcast may be unpublished during the upload, in which case the client gets an error.
+	let closed = upload.closed();
+
+	if params.framed {
+		tokio::select! {
+			biased;
+			_ = closed => return Err(StatusCode::GONE.into()),
+			res = read_framed(body, upload.track(), state.publish_max) => res?,
+		}
+
+		// Any frames after the broadcast was closed were dropped.
+		if upload.is_closed() {
+			return Err(StatusCode::GONE.into());
+		}
+
+		return Ok(StatusCode::NO_CONTENT.into_response());
+	}
+
+	let frame = tokio::select! {
+		biased;
+		_ = closed => return Err(StatusCode::GONE.into()),
+		res = read_body(body, state.publish_max) => res?,
+	};
+
+	let sequence = upload
+		.write(|track| {
+			let mut group = match params.sequence {
+				Some(sequence) => track.create_group(sequence.into()).ok_or(StatusCode::CONFLICT)?,
+				None => track.append_group(),
+			};
+
+			let sequence = group.info.sequence;
+			group.write_frame(frame);
+			group.close();
+
+			Ok::<_, StatusCode>(sequence)
+		})
+		.ok_or(StatusCode::GONE)??;
+
+	Ok((
+		StatusCode::NO_CONTENT,
+		[(HeaderName::from_static("moq-group"), sequence.to_string())],
+	)
+		.into_response())
+}
+
+/// End a broadcast published over HTTP, rather than waiting for it to go idle.
+async fn serve_unpublish(
+	Path(broadcast): Path<String>,
+	Query(params): Query<PublishParams>,
+	State(state): State<Arc<WebState>>,
+) -> axum::response::Result<StatusCode> {
+	let token = state.auth.verify(&broadcast, params.jwt.as_deref())?;
+	if state.cluster.publisher(&token).is_none() {
+		return Err(StatusCode::UNAUTHORIZED.into());
+	}
+
+	match state.ingest.close(&broadcast) {
+		true => Ok(StatusCode::NO_CONTENT),
+		false => Err(StatusCode::NOT_FOUND.into()),
+	}
+}
+
+#[derive(Debug, Deserialize)]
+struct PublishParams {
+	jwt: Option<String>,
+
+	/// Write the frame to the group with this sequence instead of the next one.
+	sequence: Option<u64>,
+
+	/// Parse the body as frames prefixed with their group sequence and size, both as big-endian u64.
+	#[serde(default)]
+	framed: bool,
+}
+
+// The path contains a broadcast and a track, which must both be present.
+fn split_track(path: &str) -> Result<(&str, &str), StatusCode> {
+	path.rsplit_once('/').ok_or(StatusCode::BAD_REQUEST)
+}
+
+// Read the entire body, failing if it's larger than the maximum frame size.
+async fn read_body(body: Body, max: usize) -> Result<Bytes, StatusCode> {
+	let mut stream = body.into_data_stream();
+	let mut buf = BytesMut::new();
+
+	while let Some(chunk) = stream.next().await {
+		let chunk = chunk.map_err(|_| StatusCode::BAD_REQUEST)?;
+		if buf.len() + chunk.len() > max {
+			return Err(StatusCode::PAYLOAD_TOO_LARGE);
+		}
+
+		buf.extend_from_slice(&chunk);
+	}
+
+	Ok(buf.freeze())
+}
+
+// Write each frame in the body to the track, starting a new group whenever the sequence changes.
+// Each frame is buffered until complete, so any frame larger than the maximum is rejected.
+async fn read_framed(body: Body, track: &mut moq_lite::TrackProducer, max: usize) -> Result<(), StatusCode> {
+	let mut stream = body.into_data_stream();
+	let mut buf = BytesMut::new();
+	let mut group: Option<moq_lite::GroupProducer> = None;
+
+	// Frames for groups that are older than the latest are skipped.
+	let mut skipped = None;
+
+	loop {
+		while let Some((sequence, frame)) = decode_framed(&mut buf, max)? {
+			if skipped == Some(sequence) {
+				continue;
 			}
 
-			self.frame = self.group.next_frame().await?;
-			if self.frame.is_none() {
-				return Ok(None);
+			if group.as_ref().map(|group| group.info.sequence) != Some(sequence) {
+				if let Some(group) = group.take() {
+					group.close();
+				}
+
+				group = track.create_group(sequence.into());
+				skipped = group.is_none().then_some(sequence);
+			}
+
+			if let Some(group) = group.as_mut() {
+				group.write_frame(frame);
 			}
 		}
+
+		match stream.next().await {
+			Some(Ok(chunk)) => buf.extend_from_slice(&chunk),
+			Some(Err(_)) => return Err(StatusCode::BAD_REQUEST),
+			None => break,
+		}
+	}
+
+	if let Some(group) = group {
+		group.close();
+	}
+
+	// Any leftover bytes are a truncated frame.
+	match buf.is_empty() {
+		true => Ok(()),
+		false => Err(StatusCode::BAD_REQUEST),
 	}
 }
 
-impl IntoResponse for ServeGroup {
-	fn into_response(self) -> Response {
-		Response::new(Body::new(self))
+// Split the next complete frame from the buffer, the inverse of encode_framed.
+fn decode_framed(buf: &mut BytesMut, max: usize) -> Result<Option<(u64, Bytes)>, StatusCode> {
+	if buf.len() < 16 {
+		return Ok(None);
 	}
+
+	let sequence = u64::from_be_bytes(buf[0..8].try_into().unwrap());
+	let size = u64::from_be_bytes(buf[8..16].try_into().unwrap());
+	let size = usize::try_from(size)
+		.ok()
+		.filter(|size| *size <= max)
+		.ok_or(StatusCode::PAYLOAD_TOO_LARGE)?;
+
+	if buf.len() - 16 < size {
+		return Ok(None);
+	}
+
+	buf.advance(16);
+	Ok(Some((sequence, buf.split_to(size).freeze())))
 }
 
-impl http_body::Body for ServeGroup {
-	type Data = Bytes;
-	type Error = ServeGroupError;
+/// Serve the status of a given track without subscribing to it.
+async fn serve_status(
+	Path(path): Path<String>,
+	Query(params): Query<Params>,
+	State(state): State<Arc<WebState>>,
+) -> axum::response::Result<Json<moq_lite::TrackStatus>> {
+	let (broadcast, track) = split_track(&path)?;
+
+	let token = state.auth.verify(broadcast, params.jwt.as_deref())?;
+
+	let origin = match state.cluster.subscriber(&token) {
+		Some(origin) => origin,
+		None => return Err(StatusCode::UNAUTHORIZED.into()),
+	};
+
+	// NOTE: The auth token is already scoped to the broadcast.
+	let broadcast = origin.consume_broadcast("").ok_or(StatusCode::NOT_FOUND)?;
+
+	// The query may be forwarded to a remote publisher, which might never answer.
+	let status = tokio::time::timeout(STATUS_TIMEOUT, broadcast.track_status(track))
+		.await
+		.map_err(|_| StatusCode::GATEWAY_TIMEOUT)?;
+
+	match status {
+		Ok(status) => Ok(Json(status)),
+		Err(moq_lite::Error::NotFound) => Err(StatusCode::NOT_FOUND.into()),
+		Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR.into()),
+	}
+}
+
+// Serves the frames of each group as the body, optionally followed by more groups from the track.
+struct ServeGroups {
+	group: Option<moq_lite::GroupConsumer>,
+	track: Option<moq_lite::TrackConsumer>,
+	end: Option<u64>,
+	framed: bool,
+}
+
+impl ServeGroups {
+	fn new(
+		group: moq_lite::GroupConsumer,
+		track: Option<moq_lite::TrackConsumer>,
+		end: Option<u64>,
+		framed: bool,
+	) -> Self {
+		Self {
+			group: Some(group),
+			track,
+			end,
+			framed,
+		}
+	}
+
+	async fn next(&mut self) -> moq_lite::Result<Option<Bytes>> {
+		loop {
+			if let Some(group) = self.group.as_mut() {
+				if let Some(data) = group.read_frame().await? {
+					return Ok(Some(match self.framed {
+						true => encode_framed(group.info.sequence, data),
+						false => data,
+					}));
+				}
 
-	fn poll_frame(
-		self: Pin<&mut Self>,
-		cx: &mut Context<'_>,
-	) -> Poll<Option<Result<http_body::Frame<Self::Data>, Self::Error>>> {
-		let this = self.get_mut();
+				let sequence = group.info.sequence;
+				self.group = None;
 
-		// Use `poll_fn` to turn the async function into a Future
-		let future = this.next();
-		tokio::pin!(future);
+				// Don't wait for another group if we've reached the end.
+				if self.end.is_some_and(|end| sequence >= end) {
+					self.track = None;
+				}
+			}
+
+			let track = match self.track.as_mut() {
+				Some(track) => track,
+				None => return Ok(None),
+			};
 
-		match ready!(future.poll(cx)) {
-			Ok(Some(data)) => {
-				let frame = http_body::Frame::data(data);
-				Poll::Ready(Some(Ok(frame)))
+			match track.next_group().await? {
+				Some(group) if self.end.is_none_or(|end| group.info.sequence <= end) => self.group = Some(group),
+				_ => self.track = None,
 			}
-			Ok(None) => Poll::Ready(None),
-			Err(e) => Poll::Ready(Some(Err(ServeGroupError(e)))),
 		}
 	}
 }
 
+// Prefix the frame with the group sequence and the frame size, both as big-endian u64.
+fn encode_framed(sequence: u64, data: Bytes) -> Bytes {
+	let mut buf = BytesMut::with_capacity(16 + data.len());
+	buf.put_u64(sequence);
+	buf.put_u64(data.len() as u64);
+	buf.extend_from_slice(&data);
+	buf.freeze()
+}
+
+impl IntoResponse for ServeGroups {
+	fn into_response(self) -> Response {
+		// NOTE: The stream owns the pending future, so it's woken when the next frame arrives.
+		let stream = futures::stream::try_unfold(self, async |mut this| {
+			let data = this.next().await.map_err(ServeGroupError)?;
+			Ok::<_, ServeGroupError>(data.map(|data| (data, this)))
+		});
+
+		Response::new(Body::from_stream(stream))
+	}
+}
+
 #[derive(Debug, thiserror::Error)]
 #[error(transparent)]
 struct ServeGroupError(moq_lite::Error);
@@ -378,3 +892,174 @@ fn tungstenite_to_axum(
 fn default_true() -> bool {
 	true
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn params(query: &str) -> FetchParams {
+		let uri: axum::http::Uri = format!("/fetch/demo/video?{query}").parse().unwrap();
+		Query::try_from_uri(&uri).unwrap().0
+	}
+
+	#[test]
+	fn test_fetch_range() {
+		assert_eq!(params("").range(), Some(FetchRange::default()));
+		assert_eq!(
+			params("sequence=3").range(),
+			Some(FetchRange {
+				start: Some(3),
+				end: Some(3),
+				single: true,
+				exact: true,
+			})
+		);
+		assert_eq!(
+			params("after=3&framed=true").range(),
+			Some(FetchRange {
+				start: Some(4),
+				end: None,
+				single: true,
+				exact: false,
+			})
+		);
+		assert_eq!(
+			params("start=3&end=5").range(),
+			Some(FetchRange {
+				start: Some(3),
+				end: Some(5),
+				single: false,
+				exact: false,
+			})
+		);
+
+		// Conflicting or invalid parameters.
+		assert_eq!(params("sequence=3&after=2").range(), None);
+		assert_eq!(params("end=5").range(), None);
+		assert_eq!(params("start=5&end=3").range(), None);
+		assert_eq!(params(&format!("after={}", u64::MAX)).range(), None);
+	}
+
+	#[test]
+	fn test_encode_framed() {
+		let encoded = encode_framed(2, Bytes::from_static(b"hi"));
+		assert_eq!(encoded.as_ref(), b"\0\0\0\0\0\0\0\x02\0\0\0\0\0\0\0\x02hi");
+	}
+
+	#[test]
+	fn test_decode_framed() {
+		let mut buf = BytesMut::new();
+		buf.extend_from_slice(&encode_framed(2, Bytes::from_static(b"hi")));
+		buf.extend_from_slice(&encode_framed(3, Bytes::from_static(b"there")));
+
+		// A partial frame is left in the buffer until the rest arrives.
+		let partial = buf.split_off(buf.len() - 2);
+		assert_eq!(decode_framed(&mut buf, 16), Ok(Some((2, Bytes::from_static(b"hi")))));
+		assert_eq!(decode_framed(&mut buf, 16), Ok(None));
+
+		buf.unsplit(partial);
+		assert_eq!(decode_framed(&mut buf, 16), Ok(Some((3, Bytes::from_static(b"there")))));
+		assert!(buf.is_empty());
+
+		// An oversized frame is rejected from its header, before it's buffered.
+		let mut buf = BytesMut::from(&encode_framed(4, Bytes::from_static(b"too big"))[..16]);
+		assert_eq!(decode_framed(&mut buf, 4), Err(StatusCode::PAYLOAD_TOO_LARGE));
+	}
+
+	#[tokio::test]
+	async fn test_announced_events() {
+		use tower::ServiceExt;
+
+		let key_file = tempfile::NamedTempFile::new().unwrap();
+		let key = moq_token::Key::generate(moq_token::Algorithm::HS256, None);
+		key.to_file(key_file.path()).unwrap();
+
+		let auth = Auth::new(crate::AuthConfig {
+			key: Some(key_file.path().to_string_lossy().to_string()),
+			public: None,
+		})
+		.unwrap();
+
+		let cluster = Cluster::new(
+			toml::from_str("").unwrap(),
+			moq_native::ClientConfig::default().init().unwrap(),
+		);
+
+		let state = WebState {
+			auth,
+			cluster: cluster.clone(),
+			fingerprints: Vec::new(),
+			sessions: Sessions::default(),
+			ingest: Ingest::default(),
+			recorder: None,
+			publish_max: DEFAULT_PUBLISH_MAX,
+		};
+
+		let app = Router::new()
+			.route("/announced/{*prefix}", get(serve_announced))
+			.with_state(Arc::new(state));
+
+		let claims = moq_token::Claims {
+			root: "room".to_string(),
+			subscribe: vec!["".to_string()],
+			..Default::default()
+		};
+		let jwt = key.encode(&claims).unwrap();
+
+		let request = axum::http::Request::get(format!("/announced/room?jwt={jwt}"))
+			.header(header::ACCEPT, "text/event-stream")
+			.body(Body::empty())
+			.unwrap();
+		let response = app.oneshot(request).await.unwrap();
+		assert_eq!(response.status(), StatusCode::OK);
+
+		let mut events = response.into_body().into_data_stream();
+		let mut next = async || {
+			let chunk = events.next().await.unwrap().unwrap();
+			String::from_utf8(chunk.to_vec()).unwrap()
+		};
+
+		// A broadcast outside of the token's root is never sent.
+		let other = moq_lite::Broadcast::produce();
+		cluster.combined.producer.publish_broadcast("lobby/bob", other.consumer);
+
+		let broadcast = moq_lite::Broadcast::produce();
+		cluster
+			.combined
+			.producer
+			.publish_broadcast("room/alice", broadcast.consumer);
+
+		// Paths are relative to the token's root.
+		assert_eq!(
+			next().await,
+			"event: announce\ndata: {\"path\":\"alice\",\"active\":true}\n\n"
+		);
+
+		drop(broadcast.producer);
+		assert_eq!(
+			next().await,
+			"event: unannounce\ndata: {\"path\":\"alice\",\"active\":false}\n\n"
+		);
+	}
+
+	#[test]
+	fn test_split_track() {
+		assert_eq!(split_track("room/alice/video"), Ok(("room/alice", "video")));
+		assert_eq!(split_track("video"), Err(StatusCode::BAD_REQUEST));
+	}
+
+	#[tokio::test]
+	async fn test_publish_max() {
+		let body = || Body::from(Bytes::from_static(b"hello"));
+		assert_eq!(read_body(body(), 5).await, Ok(Bytes::from_static(b"hello")));
+		assert_eq!(read_body(body(), 4).await, Err(StatusCode::PAYLOAD_TOO_LARGE));
+
+		let mut track = moq_lite::Track::new("video").produce().producer;
+		let framed = || Body::from(encode_framed(0, Bytes::from_static(b"hello")));
+		assert_eq!(read_framed(framed(), &mut track, 5).await, Ok(()));
+		assert_eq!(
+			read_framed(framed(), &mut track, 4).await,
+			Err(StatusCode::PAYLOAD_TOO_LARGE)
+		);
+	}
+}
@@ -3,12 +3,17 @@ use std::io::Cursor;
 
 use crate::coding::*;
 
+/// A typed setup extension, identified by a unique ID.
+///
+/// Applications can define their own, offering them via [crate::Session::connect_with].
+/// Unknown extensions are ignored by the peer.
 pub trait Extension: Encode + Decode {
 	fn id() -> u64;
 }
 
+/// The setup extensions sent by a client or server, keyed by [Extension::id].
 #[derive(Default, Debug, Clone)]
-pub struct Extensions(HashMap<u64, Vec<u8>>);
+pub struct Extensions(pub(crate) HashMap<u64, Vec<u8>>);
 
 impl Decode for Extensions {
 	fn decode<R: bytes::Buf>(mut r: &mut R) -> Result<Self, DecodeError> {
@@ -52,6 +57,10 @@ impl Extensions {
 		})
 	}
 
+	pub fn contains<E: Extension>(&self) -> bool {
+		self.0.contains_key(&E::id())
+	}
+
 	pub fn set<E: Extension>(&mut self, e: E) {
 		let mut value = Vec::new();
 		e.encode(&mut value);
@@ -2,40 +2,58 @@ use std::{cmp, io, sync::Arc};
 
 use bytes::{Buf, Bytes, BytesMut};
 
-use crate::{coding::*, Error};
+use crate::{coding::*, Error, Stats};
 
 pub struct Reader<S: web_transport_trait::RecvStream> {
 	stream: S,
 	buffer: BytesMut,
+	stats: Option<Stats>,
 }
 
 impl<S: web_transport_trait::RecvStream> Reader<S> {
-	pub fn new(stream: S) -> Self {
+	pub fn new(stream: S, stats: Option<Stats>) -> Self {
 		Self {
 			stream,
 			buffer: Default::default(),
+			stats,
+		}
+	}
+
+	fn received(&self, size: usize) {
+		if let Some(stats) = &self.stats {
+			stats.received(size);
 		}
 	}
 
 	pub async fn decode<T: Decode>(&mut self) -> Result<T, Error> {
+		self.decode_with(|r| T::decode(r)).await
+	}
+
+	/// Decode using the provided function, for encodings that depend on context such as the negotiated version.
+	///
+	/// The function is called again with more data whenever it returns [DecodeError::Short].
+	pub async fn decode_with<T, F>(&mut self, mut decode: F) -> Result<T, Error>
+	where
+		F: FnMut(&mut io::Cursor<&BytesMut>) -> Result<T, DecodeError>,
+	{
 		loop {
 			let mut cursor = io::Cursor::new(&self.buffer);
-			match T::decode(&mut cursor) {
+			match decode(&mut cursor) {
 				Ok(msg) => {
 					self.buffer.advance(cursor.position() as usize);
 					return Ok(msg);
 				}
 				Err(DecodeError::Short) => {
 					// Try to read more data
-					if self
+					match self
 						.stream
 						.read_buf(&mut self.buffer)
 						.await
 						.map_err(|e| Error::Transport(Arc::new(e)))?
-						.is_none()
 					{
+						Some(size) => self.received(size),
 						// Stream closed while we still need more data
-						return Err(Error::Decode(DecodeError::Short));
+						None => return Err(Error::Decode(DecodeError::Short)),
 					}
 				}
 				Err(e) => return Err(Error::Decode(e)),
@@ -45,9 +63,17 @@ impl<S: web_transport_trait::RecvStream> Reader<S> {
 
 	// Decode optional messages at the end of a stream
 	pub async fn decode_maybe<T: Decode>(&mut self) -> Result<Option<T>, Error> {
+		self.decode_maybe_with(|r| T::decode(r)).await
+	}
+
+	/// Decode an optional message at the end of a stream, see [Self::decode_with].
+	pub async fn decode_maybe_with<T, F>(&mut self, decode: F) -> Result<Option<T>, Error>
+	where
+		F: FnMut(&mut io::Cursor<&BytesMut>) -> Result<T, DecodeError>,
+	{
 		match self.closed().await {
 			Ok(()) => Ok(None),
-			Err(Error::Decode(DecodeError::ExpectedEnd)) => Ok(Some(self.decode().await?)),
+			Err(Error::Decode(DecodeError::ExpectedEnd)) => Ok(Some(self.decode_with(decode).await?)),
 			Err(e) => Err(e),
 		}
 	}
@@ -60,23 +86,31 @@ impl<S: web_transport_trait::RecvStream> Reader<S> {
 			return Ok(Some(data));
 		}
 
-		self.stream
+		let chunk = self
+			.stream
 			.read_chunk(max)
 			.await
-			.map_err(|e| Error::Transport(Arc::new(e)))
+			.map_err(|e| Error::Transport(Arc::new(e)))?;
+
+		if let Some(chunk) = &chunk {
+			self.received(chunk.len());
+		}
+
+		Ok(chunk)
 	}
 
 	/// Wait until the stream is closed, erroring if there are any additional bytes.
 	pub async fn closed(&mut self) -> Result<(), Error> {
-		if self.buffer.is_empty()
-			&& self
+		if self.buffer.is_empty() {
+			match self
 				.stream
 				.read_buf(&mut self.buffer)
 				.await
 				.map_err(|e| Error::Transport(Arc::new(e)))?
-				.is_none()
-		{
-			return Ok(());
+			{
+				Some(size) => self.received(size),
+				None => return Ok(()),
+			}
 		}
 
 		Err(DecodeError::ExpectedEnd.into())
@@ -1,7 +1,7 @@
 use std::sync::Arc;
 
 use crate::coding::{Reader, Writer};
-use crate::Error;
+use crate::{Error, Stats};
 
 pub struct Stream<S: web_transport_trait::Session> {
 	pub writer: Writer<S::SendStream>,
@@ -9,23 +9,23 @@ pub struct Stream<S: web_transport_trait::Session> {
 }
 
 impl<S: web_transport_trait::Session> Stream<S> {
-	pub async fn open(session: &S) -> Result<Self, Error> {
+	pub async fn open(session: &S, stats: Option<Stats>) -> Result<Self, Error> {
 		let (send, recv) = session.open_bi().await.map_err(|err| Error::Transport(Arc::new(err)))?;
 
-		let writer = Writer::new(send);
-		let reader = Reader::new(recv);
+		let writer = Writer::new(send, stats.clone());
+		let reader = Reader::new(recv, stats);
 
 		Ok(Stream { writer, reader })
 	}
 
-	pub async fn accept(session: &S) -> Result<Self, Error> {
+	pub async fn accept(session: &S, stats: Option<Stats>) -> Result<Self, Error> {
 		let (send, recv) = session
 			.accept_bi()
 			.await
 			.map_err(|err| Error::Transport(Arc::new(err)))?;
 
-		let writer = Writer::new(send);
-		let reader = Reader::new(recv);
+		let writer = Writer::new(send, stats.clone());
+		let reader = Reader::new(recv, stats);
 
 		Ok(Stream { writer, reader })
 	}
@@ -24,7 +24,10 @@ impl Version {
 
 	/// <https://www.ietf.org/archive/id/draft-ietf-moq-transport-07.html>
 	pub const IETF_07: Version = Version(0xff000007);
-	pub const IETF_LATEST: Version = Self::IETF_07;
+
+	/// <https://www.ietf.org/archive/id/draft-ietf-moq-transport-11.html>
+	pub const IETF_11: Version = Version(0xff00000b);
+	pub const IETF_LATEST: Version = Self::IETF_11;
 
 	/// <https://www.ietf.org/archive/id/draft-lcurley-moq-transfork-00.html>
 	pub const FORK_00: Version = Version(0xff0bad00);
@@ -43,7 +46,8 @@ impl Version {
 
 	pub const LITE_00: Version = Version(0xff0dad00);
 	pub const LITE_01: Version = Version(0xff0dad01);
-	pub const LITE_LATEST: Version = Self::LITE_01;
+	pub const LITE_02: Version = Version(0xff0dad02);
+	pub const LITE_LATEST: Version = Self::LITE_02;
 }
 
 /// A version number negotiated during the setup.
@@ -53,7 +57,8 @@ pub struct Alpn(pub &'static str);
 impl Alpn {
 	pub const LITE_00: Alpn = Alpn("moql-00");
 	pub const LITE_01: Alpn = Alpn("moql-01");
-	pub const LITE_LATEST: Alpn = Self::LITE_01;
+	pub const LITE_02: Alpn = Alpn("moql-02");
+	pub const LITE_LATEST: Alpn = Self::LITE_02;
 }
 
 impl From<u64> for Version {
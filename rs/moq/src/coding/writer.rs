@@ -1,18 +1,26 @@
 use std::sync::Arc;
 
-use crate::{coding::*, Error};
+use crate::{coding::*, Error, Stats};
 
 // A wrapper around a SendStream that will reset on Drop
 pub struct Writer<S: web_transport_trait::SendStream> {
 	stream: S,
 	buffer: bytes::BytesMut,
+	stats: Option<Stats>,
 }
 
 impl<S: web_transport_trait::SendStream> Writer<S> {
-	pub fn new(stream: S) -> Self {
+	pub fn new(stream: S, stats: Option<Stats>) -> Self {
 		Self {
 			stream,
 			buffer: Default::default(),
+			stats,
+		}
+	}
+
+	fn sent(&self, size: usize) {
+		if let Some(stats) = &self.stats {
+			stats.sent(size);
 		}
 	}
 
@@ -21,10 +29,12 @@ impl<S: web_transport_trait::SendStream> Writer<S> {
 		msg.encode(&mut self.buffer);
 
 		while !self.buffer.is_empty() {
-			self.stream
+			let size = self
+				.stream
 				.write_buf(&mut self.buffer)
 				.await
 				.map_err(|e| Error::Transport(Arc::new(e)))?;
+			self.sent(size);
 		}
 
 		Ok(())
@@ -32,10 +42,13 @@ impl<S: web_transport_trait::SendStream> Writer<S> {
 
 	// Not public to avoid accidental partial writes.
 	async fn write<Buf: bytes::Buf + Send>(&mut self, buf: &mut Buf) -> Result<usize, Error> {
-		self.stream
+		let size = self
+			.stream
 			.write_buf(buf)
 			.await
-			.map_err(|e| Error::Transport(Arc::new(e)))
+			.map_err(|e| Error::Transport(Arc::new(e)))?;
+		self.sent(size);
+		Ok(size)
 	}
 
 	// NOTE: We use Buf so we don't perform a copy when using Quinn.
@@ -1,31 +1,48 @@
-//! IETF moq-transport-07 announce messages
+//! IETF moq-transport announce messages
 
 use std::borrow::Cow;
 
 use crate::{coding::*, Path};
 
-use super::util::{decode_namespace, encode_namespace};
+use super::{
+	util::{decode_namespace, decode_parameters, encode_namespace},
+	Message, MessageId, Version,
+};
 
 /// Announce message (0x06)
 /// Sent by the publisher to announce the availability of a namespace.
 #[derive(Clone, Debug)]
 pub struct Announce<'a> {
+	/// Only encoded for draft-11.
+	pub request_id: u64,
 	pub track_namespace: Path<'a>,
 }
 
 impl<'a> Message for Announce<'a> {
-	fn decode<R: bytes::Buf>(r: &mut R) -> Result<Self, DecodeError> {
+	const ID: MessageId = MessageId::Announce;
+
+	fn decode<R: bytes::Buf>(r: &mut R, version: Version) -> Result<Self, DecodeError> {
+		let request_id = match version.request_ids() {
+			true => u64::decode(r)?,
+			false => 0,
+		};
+
 		let track_namespace = decode_namespace(r)?;
 
-		let num_params = u8::decode(r)?;
-		if num_params > 0 {
-			return Err(DecodeError::InvalidValue);
-		}
+		// We don't support any parameters yet.
+		decode_parameters(r, version)?;
 
-		Ok(Self { track_namespace })
+		Ok(Self {
+			request_id,
+			track_namespace,
+		})
 	}
 
-	fn encode<W: bytes::BufMut>(&self, w: &mut W) {
+	fn encode<W: bytes::BufMut>(&self, w: &mut W, version: Version) {
+		if version.request_ids() {
+			self.request_id.encode(w);
+		}
+
 		encode_namespace(w, &self.track_namespace);
 		0u8.encode(w); // number of parameters
 	}
@@ -34,41 +51,71 @@ impl<'a> Message for Announce<'a> {
 /// AnnounceOk message (0x07)
 #[derive(Clone, Debug)]
 pub struct AnnounceOk<'a> {
+	/// Only encoded for draft-11.
+	pub request_id: u64,
+	/// Only encoded for draft-07.
 	pub track_namespace: Path<'a>,
 }
 
 impl<'a> Message for AnnounceOk<'a> {
-	fn encode<W: bytes::BufMut>(&self, w: &mut W) {
-		encode_namespace(w, &self.track_namespace);
+	const ID: MessageId = MessageId::AnnounceOk;
+
+	fn encode<W: bytes::BufMut>(&self, w: &mut W, version: Version) {
+		match version.request_ids() {
+			true => self.request_id.encode(w),
+			false => encode_namespace(w, &self.track_namespace),
+		}
 	}
 
-	fn decode<R: bytes::Buf>(r: &mut R) -> Result<Self, DecodeError> {
-		let track_namespace = decode_namespace(r)?;
-		Ok(Self { track_namespace })
+	fn decode<R: bytes::Buf>(r: &mut R, version: Version) -> Result<Self, DecodeError> {
+		Ok(match version.request_ids() {
+			true => Self {
+				request_id: u64::decode(r)?,
+				track_namespace: Path::new(""),
+			},
+			false => Self {
+				request_id: 0,
+				track_namespace: decode_namespace(r)?,
+			},
+		})
 	}
 }
 
 /// AnnounceError message (0x08)
 #[derive(Clone, Debug)]
 pub struct AnnounceError<'a> {
+	/// Only encoded for draft-11.
+	pub request_id: u64,
+	/// Only encoded for draft-07.
 	pub track_namespace: Path<'a>,
 	pub error_code: u64,
 	pub reason_phrase: Cow<'a, str>,
 }
 
 impl<'a> Message for AnnounceError<'a> {
-	fn encode<W: bytes::BufMut>(&self, w: &mut W) {
-		encode_namespace(w, &self.track_namespace);
+	const ID: MessageId = MessageId::AnnounceError;
+
+	fn encode<W: bytes::BufMut>(&self, w: &mut W, version: Version) {
+		match version.request_ids() {
+			true => self.request_id.encode(w),
+			false => encode_namespace(w, &self.track_namespace),
+		}
+
 		self.error_code.encode(w);
 		self.reason_phrase.encode(w);
 	}
 
-	fn decode<R: bytes::Buf>(r: &mut R) -> Result<Self, DecodeError> {
-		let track_namespace = decode_namespace(r)?;
+	fn decode<R: bytes::Buf>(r: &mut R, version: Version) -> Result<Self, DecodeError> {
+		let (request_id, track_namespace) = match version.request_ids() {
+			true => (u64::decode(r)?, Path::new("")),
+			false => (0, decode_namespace(r)?),
+		};
+
 		let error_code = u64::decode(r)?;
 		let reason_phrase = Cow::<str>::decode(r)?;
 
 		Ok(Self {
+			request_id,
 			track_namespace,
 			error_code,
 			reason_phrase,
@@ -83,11 +130,13 @@ pub struct Unannounce<'a> {
 }
 
 impl<'a> Message for Unannounce<'a> {
-	fn encode<W: bytes::BufMut>(&self, w: &mut W) {
+	const ID: MessageId = MessageId::Unannounce;
+
+	fn encode<W: bytes::BufMut>(&self, w: &mut W, _version: Version) {
 		encode_namespace(w, &self.track_namespace);
 	}
 
-	fn decode<R: bytes::Buf>(r: &mut R) -> Result<Self, DecodeError> {
+	fn decode<R: bytes::Buf>(r: &mut R, _version: Version) -> Result<Self, DecodeError> {
 		let track_namespace = decode_namespace(r)?;
 		Ok(Self { track_namespace })
 	}
@@ -102,13 +151,15 @@ pub struct AnnounceCancel<'a> {
 }
 
 impl<'a> Message for AnnounceCancel<'a> {
-	fn encode<W: bytes::BufMut>(&self, w: &mut W) {
+	const ID: MessageId = MessageId::AnnounceCancel;
+
+	fn encode<W: bytes::BufMut>(&self, w: &mut W, _version: Version) {
 		encode_namespace(w, &self.track_namespace);
 		self.error_code.encode(w);
 		self.reason_phrase.encode(w);
 	}
 
-	fn decode<R: bytes::Buf>(r: &mut R) -> Result<Self, DecodeError> {
+	fn decode<R: bytes::Buf>(r: &mut R, _version: Version) -> Result<Self, DecodeError> {
 		let track_namespace = decode_namespace(r)?;
 		let error_code = u64::decode(r)?;
 		let reason_phrase = Cow::<str>::decode(r)?;
@@ -124,57 +175,66 @@ impl<'a> Message for AnnounceCancel<'a> {
 #[cfg(test)]
 mod tests {
 	use super::*;
+	use crate::ietf::decode_message;
 	use bytes::BytesMut;
 
-	fn encode_message<M: Message>(msg: &M) -> Vec<u8> {
+	fn encode_message<M: Message>(msg: &M, version: Version) -> Vec<u8> {
 		let mut buf = BytesMut::new();
-		msg.encode(&mut buf);
+		msg.encode(&mut buf, version);
 		buf.to_vec()
 	}
 
-	fn decode_message<M: Message>(bytes: &[u8]) -> Result<M, DecodeError> {
-		let mut buf = bytes::Bytes::from(bytes.to_vec());
-		M::decode(&mut buf)
+	fn round_trip<M: Message>(msg: &M, version: Version) -> M {
+		decode_message(encode_message(msg, version).into(), version).unwrap()
 	}
 
 	#[test]
 	fn test_announce_round_trip() {
 		let msg = Announce {
+			request_id: 4,
 			track_namespace: Path::new("test/broadcast"),
 		};
 
-		let encoded = encode_message(&msg);
-		let decoded: Announce = decode_message(&encoded).unwrap();
+		let decoded = round_trip(&msg, Version::Draft07);
+		assert_eq!(decoded.track_namespace.as_str(), "test/broadcast");
+		assert_eq!(decoded.request_id, 0);
 
+		let decoded = round_trip(&msg, Version::Draft11);
 		assert_eq!(decoded.track_namespace.as_str(), "test/broadcast");
+		assert_eq!(decoded.request_id, 4);
 	}
 
 	#[test]
 	fn test_announce_ok() {
 		let msg = AnnounceOk {
+			request_id: 7,
 			track_namespace: Path::new("foo"),
 		};
 
-		let encoded = encode_message(&msg);
-		let decoded: AnnounceOk = decode_message(&encoded).unwrap();
-
+		let decoded = round_trip(&msg, Version::Draft07);
 		assert_eq!(decoded.track_namespace.as_str(), "foo");
+
+		let decoded = round_trip(&msg, Version::Draft11);
+		assert_eq!(decoded.request_id, 7);
 	}
 
 	#[test]
 	fn test_announce_error() {
 		let msg = AnnounceError {
+			request_id: 2,
 			track_namespace: Path::new("test"),
 			error_code: 404,
 			reason_phrase: "Unauthorized".into(),
 		};
 
-		let encoded = encode_message(&msg);
-		let decoded: AnnounceError = decode_message(&encoded).unwrap();
-
+		let decoded = round_trip(&msg, Version::Draft07);
 		assert_eq!(decoded.track_namespace.as_str(), "test");
 		assert_eq!(decoded.error_code, 404);
 		assert_eq!(decoded.reason_phrase, "Unauthorized");
+
+		let decoded = round_trip(&msg, Version::Draft11);
+		assert_eq!(decoded.request_id, 2);
+		assert_eq!(decoded.error_code, 404);
 	}
 
 	#[test]
@@ -183,9 +243,7 @@ mod tests {
 			track_namespace: Path::new("old/stream"),
 		};
 
-		let encoded = encode_message(&msg);
-		let decoded: Unannounce = decode_message(&encoded).unwrap();
-
+		let decoded = round_trip(&msg, Version::Draft11);
 		assert_eq!(decoded.track_namespace.as_str(), "old/stream");
 	}
 
@@ -197,24 +255,36 @@ mod tests {
 			reason_phrase: "Shutdown".into(),
 		};
 
-		let encoded = encode_message(&msg);
-		let decoded: AnnounceCancel = decode_message(&encoded).unwrap();
-
+		let decoded = round_trip(&msg, Version::Draft07);
 		assert_eq!(decoded.track_namespace.as_str(), "canceled");
 		assert_eq!(decoded.error_code, 1);
 		assert_eq!(decoded.reason_phrase, "Shutdown");
 	}
 
 	#[test]
-	fn test_announce_rejects_parameters() {
+	fn test_announce_parameters() {
+		#[rustfmt::skip]
+		let bytes = vec![
+			0x01, // namespace length
+			0x04, 0x74, 0x65, 0x73, 0x74, // "test"
+			0x01, // num_params = 1
+			0x02, 0x01, 0x05, // delivery timeout = 5
+		];
+
+		let decoded: Announce = decode_message(bytes.into(), Version::Draft07).unwrap();
+		assert_eq!(decoded.track_namespace.as_str(), "test");
+	}
+
+	#[test]
+	fn test_announce_rejects_truncated_parameters() {
 		#[rustfmt::skip]
 		let invalid_bytes = vec![
 			0x01, // namespace length
 			0x04, 0x74, 0x65, 0x73, 0x74, // "test"
-			0x01, // INVALID: num_params = 1
+			0x01, // INVALID: num_params = 1, but none follow
 		];
 
-		let result: Result<Announce, _> = decode_message(&invalid_bytes);
+		let result: Result<Announce, _> = decode_message(invalid_bytes.into(), Version::Draft07);
 		assert!(result.is_err());
 	}
 }
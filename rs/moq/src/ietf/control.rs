@@ -1,25 +1,290 @@
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
-use crate::{
-	coding::{self, Encode},
-	ietf, Error,
-};
+use tokio::sync::watch;
+
+use crate::{ietf, session::SessionOptions, Error, Stats};
+
+/// The request limits advertised by each endpoint in the setup messages.
+///
+/// None means the endpoint didn't include [ietf::MaxRequests].
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct RequestLimits {
+	pub local: Option<ietf::MaxRequests>,
+	pub remote: Option<ietf::MaxRequests>,
+}
 
 #[derive(Clone)]
 pub(super) struct Control {
 	tx: tokio::sync::mpsc::UnboundedSender<Vec<u8>>,
+	version: ietf::Version,
+
+	// The next request ID to use, shared by the publisher and subscriber.
+	// The lock is held while waiting for the peer, so requests are sent in order.
+	request_id: Arc<tokio::sync::Mutex<u64>>,
+
+	// The maximum request ID (exclusive) allowed by the peer.
+	request_max: Arc<watch::Sender<u64>>,
+
+	// The maximum request ID (exclusive) we allow the peer to use, or None if unlimited.
+	peer_max: Arc<Mutex<Option<u64>>>,
+
+	// Only clients can receive GOAWAY.
+	client: bool,
+
+	// Whether the transport supports datagrams, otherwise objects are always sent on streams.
+	datagrams: bool,
+
+	// Count the traffic and subscriptions for the session, if provided.
+	stats: Option<Stats>,
+
+	// The new session URI sent by the server via GOAWAY.
+	goaway: Arc<watch::Sender<Option<String>>>,
 }
 
 impl Control {
-	pub fn new(tx: tokio::sync::mpsc::UnboundedSender<Vec<u8>>) -> Self {
-		Self { tx }
+	pub fn new(
+		tx: tokio::sync::mpsc::UnboundedSender<Vec<u8>>,
+		version: ietf::Version,
+		client: bool,
+		limits: RequestLimits,
+		options: SessionOptions,
+	) -> Self {
+		// Draft-11 splits request IDs between the client (even) and the server (odd).
+		let first = match version.request_ids() {
+			true => !client as u64,
+			false => 0,
+		};
+
+		// Draft-07 peers that don't advertise a limit don't support MAX_SUBSCRIBE_ID, so neither side is limited.
+		let limited = version.request_ids() || limits.remote.is_some();
+
+		let (request_max, peer_max) = match limited {
+			// The default limit is zero, so the peer can't make any requests unless advertised.
+			true => (
+				limits.remote.map_or(0, |max| max.0),
+				Some(limits.local.map_or(0, |max| max.0)),
+			),
+			false => (u64::MAX, None),
+		};
+
+		Self {
+			tx,
+			version,
+			request_id: Arc::new(tokio::sync::Mutex::new(first)),
+			request_max: Arc::new(watch::Sender::new(request_max)),
+			peer_max: Arc::new(Mutex::new(peer_max)),
+			client,
+			datagrams: options.datagrams,
+			stats: options.stats,
+			goaway: Arc::new(watch::Sender::new(None)),
+		}
+	}
+
+	pub fn version(&self) -> ietf::Version {
+		self.version
+	}
+
+	pub fn datagrams(&self) -> bool {
+		self.datagrams
+	}
+
+	pub fn stats(&self) -> Option<&Stats> {
+		self.stats.as_ref()
+	}
+
+	/// Send a new request with the next Request ID, or Subscribe ID for draft-07.
+	///
+	/// Waits until the peer allows another request, letting them know if we're blocked.
+	pub async fn request<M: ietf::Message, F: FnOnce(u64) -> M>(&self, msg: F) -> Result<u64, Error> {
+		let mut request_id = self.request_id.lock().await;
+		let id = *request_id;
+
+		let mut max = self.request_max.subscribe();
+		if *max.borrow() <= id {
+			tracing::debug!(%id, max = %*max.borrow(), "requests blocked");
+
+			if self.version.request_ids() {
+				let max_request_id = *max.borrow();
+				self.send(ietf::RequestsBlocked { max_request_id })?;
+			}
+
+			max.wait_for(|max| *max > id).await.map_err(|_| Error::Cancel)?;
+		}
+
+		self.send(msg(id))?;
+		*request_id += self.version.request_step();
+
+		Ok(id)
+	}
+
+	/// The peer raised the maximum request ID we may use.
+	pub fn recv_max_request_id(&self, msg: ietf::MaxRequestId) -> Result<(), Error> {
+		let mut res = Ok(());
+
+		self.request_max.send_if_modified(|max| {
+			match msg.request_id {
+				update if update < *max => res = Err(Error::ProtocolViolation),
+				update if update > *max => {
+					*max = update;
+					return true;
+				}
+				_ => {}
+			}
+			false
+		});
+
+		res
 	}
 
-	pub fn send(&self, id: ietf::MessageId, msg: impl coding::Message) -> Result<(), Error> {
+	/// The peer would like to make more requests.
+	pub fn recv_requests_blocked(&self, msg: ietf::RequestsBlocked) -> Result<(), Error> {
+		// We raise the limit as requests complete, so there's nothing else to do.
+		tracing::debug!(max = %msg.max_request_id, "peer requests blocked");
+		Ok(())
+	}
+
+	/// Validate a request ID received from the peer, returning an error if it exceeds our limit.
+	pub fn recv_request(&self, id: u64) -> Result<(), Error> {
+		match *self.peer_max.lock().unwrap() {
+			Some(max) if id >= max => Err(Error::ProtocolViolation),
+			_ => Ok(()),
+		}
+	}
+
+	/// A request made by the peer has completed, allowing them to make another.
+	pub fn request_done(&self) {
+		// Hold the lock while sending so updates can't be reordered.
+		let mut max = self.peer_max.lock().unwrap();
+		if let Some(max) = max.as_mut() {
+			*max += self.version.request_step();
+			self.send(ietf::MaxRequestId { request_id: *max }).ok();
+		}
+	}
+
+	/// The server would like us to migrate to a new session.
+	pub fn recv_goaway(&self, msg: ietf::GoAway<'_>) -> Result<(), Error> {
+		let uri = msg.new_session_uri.to_string();
+		tracing::info!(%uri, "goaway");
+
+		// Servers can't receive GOAWAY, and clients only receive it once.
+		if !self.client || self.goaway.borrow().is_some() {
+			return Err(Error::ProtocolViolation);
+		}
+
+		self.goaway.send_replace(Some(uri));
+
+		Ok(())
+	}
+
+	/// Returns a channel that is set to the new session URI when GOAWAY is received.
+	pub fn goaway(&self) -> watch::Receiver<Option<String>> {
+		self.goaway.subscribe()
+	}
+
+	pub fn send<M: ietf::Message>(&self, msg: M) -> Result<(), Error> {
 		let mut buf = Vec::new();
-		id.encode(&mut buf);
-		msg.encode(&mut buf);
+		ietf::encode_message(&msg, &mut buf, self.version)?;
 		self.tx.send(buf).map_err(|e| Error::Transport(Arc::new(e)))?;
 		Ok(())
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use futures::FutureExt;
+
+	fn control(limits: RequestLimits) -> (Control, tokio::sync::mpsc::UnboundedReceiver<Vec<u8>>) {
+		let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+		(
+			Control::new(tx, ietf::Version::Draft11, true, limits, Default::default()),
+			rx,
+		)
+	}
+
+	fn track_status(request_id: u64) -> ietf::TrackStatusRequest<'static> {
+		ietf::TrackStatusRequest {
+			request_id,
+			track_namespace: crate::Path::new("test"),
+			track_name: "video".into(),
+		}
+	}
+
+	#[tokio::test]
+	async fn test_request_blocked() {
+		let (control, mut rx) = control(RequestLimits {
+			local: None,
+			remote: Some(ietf::MaxRequests(2)),
+		});
+
+		// The client uses even IDs, so only one request is allowed.
+		assert_eq!(control.request(track_status).await.unwrap(), 0);
+		assert_eq!(rx.recv().await.unwrap()[0], 0x0d);
+
+		let mut blocked = Box::pin(control.request(track_status));
+		assert!(blocked.as_mut().now_or_never().is_none());
+
+		// We let the peer know that we're blocked.
+		assert_eq!(rx.recv().await.unwrap()[0], 0x1a);
+
+		// The limit can't decrease.
+		let err = control.recv_max_request_id(ietf::MaxRequestId { request_id: 1 });
+		assert!(matches!(err, Err(Error::ProtocolViolation)));
+
+		control
+			.recv_max_request_id(ietf::MaxRequestId { request_id: 4 })
+			.unwrap();
+		assert_eq!(blocked.await.unwrap(), 2);
+	}
+
+	#[test]
+	fn test_peer_limit() {
+		let (control, mut rx) = control(RequestLimits {
+			local: Some(ietf::MaxRequests(3)),
+			remote: None,
+		});
+
+		// The server uses odd IDs.
+		control.recv_request(1).unwrap();
+		assert!(matches!(control.recv_request(3), Err(Error::ProtocolViolation)));
+
+		// Completing a request allows another.
+		control.request_done();
+		assert_eq!(rx.try_recv().unwrap(), vec![0x15, 0x00, 0x01, 0x05]);
+		control.recv_request(3).unwrap();
+	}
+
+	#[test]
+	fn test_draft07_unlimited() {
+		let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+		let control = Control::new(
+			tx,
+			ietf::Version::Draft07,
+			false,
+			RequestLimits::default(),
+			Default::default(),
+		);
+
+		// Older peers that don't advertise a limit aren't limited either.
+		control.recv_request(1000).unwrap();
+		control.request_done();
+		assert!(rx.try_recv().is_err());
+	}
+
+	#[test]
+	fn test_goaway() {
+		let (control, _rx) = control(RequestLimits::default());
+		let goaway = control.goaway();
+
+		let msg = ietf::GoAway {
+			new_session_uri: "https://example.com/new".into(),
+		};
+
+		control.recv_goaway(msg.clone()).unwrap();
+		assert_eq!(goaway.borrow().as_deref(), Some("https://example.com/new"));
+
+		// Only one GOAWAY is allowed.
+		assert!(matches!(control.recv_goaway(msg), Err(Error::ProtocolViolation)));
+	}
+}
@@ -0,0 +1,353 @@
+//! IETF moq-transport fetch messages
+
+use std::borrow::Cow;
+
+use crate::{coding::*, Path};
+
+use super::{
+	util::{decode_namespace, decode_parameters, encode_namespace},
+	Message, MessageId, Version,
+};
+
+// We always deliver groups in ascending order (0x01) within a fetch, but accept any requested order.
+const GROUP_ORDER: u8 = 0x01;
+
+const FETCH_STANDALONE: u64 = 0x01;
+const FETCH_RELATIVE_JOINING: u64 = 0x02;
+const FETCH_ABSOLUTE_JOINING: u64 = 0x03;
+
+/// The range of objects requested by a [Fetch].
+#[derive(Clone, Debug)]
+pub enum FetchType<'a> {
+	/// Fetch a range of objects from the given track.
+	Standalone {
+		track_namespace: Path<'a>,
+		track_name: Cow<'a, str>,
+		/// The first group and object ID.
+		start: (u64, u64),
+		/// The last group ID and the last object ID plus one, or zero for the entire group.
+		end: (u64, u64),
+	},
+
+	/// Fetch the groups preceding an existing subscription, only supported by draft-11.
+	///
+	/// The fetch starts `group_offset` groups before the largest group of the subscription.
+	RelativeJoining { subscribe_id: u64, group_offset: u64 },
+
+	/// Fetch the groups preceding an existing subscription, starting at an absolute group ID.
+	///
+	/// Only supported by draft-11.
+	AbsoluteJoining { subscribe_id: u64, start_group: u64 },
+}
+
+/// Fetch message (0x16)
+/// Sent by the subscriber to request a range of past objects.
+#[derive(Clone, Debug)]
+pub struct Fetch<'a> {
+	/// The Subscribe ID in draft-07, or the Request ID in draft-11.
+	pub request_id: u64,
+	pub subscriber_priority: u8,
+	pub fetch_type: FetchType<'a>,
+}
+
+impl<'a> Message for Fetch<'a> {
+	const ID: MessageId = MessageId::Fetch;
+
+	fn encode<W: bytes::BufMut>(&self, w: &mut W, version: Version) {
+		self.request_id.encode(w);
+
+		if version == Version::Draft07 {
+			// Draft-07 only supports standalone fetches, with the track before the priority.
+			let FetchType::Standalone {
+				track_namespace,
+				track_name,
+				..
+			} = &self.fetch_type
+			else {
+				panic!("joining fetch requires draft-11");
+			};
+
+			encode_namespace(w, track_namespace);
+			track_name.encode(w);
+		}
+
+		self.subscriber_priority.encode(w);
+		GROUP_ORDER.encode(w);
+
+		match &self.fetch_type {
+			FetchType::Standalone {
+				track_namespace,
+				track_name,
+				start,
+				end,
+			} => {
+				if version == Version::Draft11 {
+					FETCH_STANDALONE.encode(w);
+					encode_namespace(w, track_namespace);
+					track_name.encode(w);
+				}
+
+				start.0.encode(w);
+				start.1.encode(w);
+				end.0.encode(w);
+				end.1.encode(w);
+			}
+			FetchType::RelativeJoining {
+				subscribe_id,
+				group_offset,
+			} => {
+				FETCH_RELATIVE_JOINING.encode(w);
+				subscribe_id.encode(w);
+				group_offset.encode(w);
+			}
+			FetchType::AbsoluteJoining {
+				subscribe_id,
+				start_group,
+			} => {
+				FETCH_ABSOLUTE_JOINING.encode(w);
+				subscribe_id.encode(w);
+				start_group.encode(w);
+			}
+		}
+
+		0u8.encode(w); // no parameters
+	}
+
+	fn decode<R: bytes::Buf>(r: &mut R, version: Version) -> Result<Self, DecodeError> {
+		let request_id = u64::decode(r)?;
+
+		let track = match version {
+			Version::Draft07 => Some((decode_namespace(r)?, Cow::<str>::decode(r)?)),
+			Version::Draft11 => None,
+		};
+
+		let subscriber_priority = u8::decode(r)?;
+
+		let group_order = u8::decode(r)?;
+		if group_order > 0x02 {
+			return Err(DecodeError::InvalidValue);
+		}
+
+		let kind = match version {
+			Version::Draft07 => FETCH_STANDALONE,
+			Version::Draft11 => u64::decode(r)?,
+		};
+
+		let fetch_type = match kind {
+			FETCH_STANDALONE => {
+				let (track_namespace, track_name) = match track {
+					Some(track) => track,
+					None => (decode_namespace(r)?, Cow::<str>::decode(r)?),
+				};
+
+				let start = (u64::decode(r)?, u64::decode(r)?);
+				let end = (u64::decode(r)?, u64::decode(r)?);
+
+				FetchType::Standalone {
+					track_namespace,
+					track_name,
+					start,
+					end,
+				}
+			}
+			FETCH_RELATIVE_JOINING => FetchType::RelativeJoining {
+				subscribe_id: u64::decode(r)?,
+				group_offset: u64::decode(r)?,
+			},
+			FETCH_ABSOLUTE_JOINING => FetchType::AbsoluteJoining {
+				subscribe_id: u64::decode(r)?,
+				start_group: u64::decode(r)?,
+			},
+			_ => return Err(DecodeError::InvalidValue),
+		};
+
+		// We don't support any parameters yet.
+		decode_parameters(r, version)?;
+
+		Ok(Self {
+			request_id,
+			subscriber_priority,
+			fetch_type,
+		})
+	}
+}
+
+/// FetchOk message (0x18)
+#[derive(Clone, Debug)]
+pub struct FetchOk {
+	pub request_id: u64,
+	/// True if the fetch includes the final object of the track.
+	pub end_of_track: bool,
+	/// The largest group/object ID tuple available.
+	pub largest: (u64, u64),
+}
+
+impl Message for FetchOk {
+	const ID: MessageId = MessageId::FetchOk;
+
+	fn encode<W: bytes::BufMut>(&self, w: &mut W, _version: Version) {
+		self.request_id.encode(w);
+		GROUP_ORDER.encode(w);
+		(self.end_of_track as u8).encode(w);
+		self.largest.0.encode(w);
+		self.largest.1.encode(w);
+		0u8.encode(w); // no parameters
+	}
+
+	fn decode<R: bytes::Buf>(r: &mut R, version: Version) -> Result<Self, DecodeError> {
+		let request_id = u64::decode(r)?;
+		let _group_order = u8::decode(r)?; // Don't care about group order
+
+		let end_of_track = match u8::decode(r)? {
+			0 => false,
+			1 => true,
+			_ => return Err(DecodeError::InvalidValue),
+		};
+
+		let largest = (u64::decode(r)?, u64::decode(r)?);
+
+		// We don't support any parameters yet.
+		decode_parameters(r, version)?;
+
+		Ok(Self {
+			request_id,
+			end_of_track,
+			largest,
+		})
+	}
+}
+
+/// FetchError message (0x19)
+#[derive(Clone, Debug)]
+pub struct FetchError<'a> {
+	pub request_id: u64,
+	pub error_code: u64,
+	pub reason_phrase: Cow<'a, str>,
+}
+
+impl<'a> Message for FetchError<'a> {
+	const ID: MessageId = MessageId::FetchError;
+
+	fn encode<W: bytes::BufMut>(&self, w: &mut W, _version: Version) {
+		self.request_id.encode(w);
+		self.error_code.encode(w);
+		self.reason_phrase.encode(w);
+	}
+
+	fn decode<R: bytes::Buf>(r: &mut R, _version: Version) -> Result<Self, DecodeError> {
+		let request_id = u64::decode(r)?;
+		let error_code = u64::decode(r)?;
+		let reason_phrase = Cow::<str>::decode(r)?;
+
+		Ok(Self {
+			request_id,
+			error_code,
+			reason_phrase,
+		})
+	}
+}
+
+/// FetchCancel message (0x17)
+#[derive(Clone, Debug)]
+pub struct FetchCancel {
+	pub request_id: u64,
+}
+
+impl Message for FetchCancel {
+	const ID: MessageId = MessageId::FetchCancel;
+
+	fn encode<W: bytes::BufMut>(&self, w: &mut W, _version: Version) {
+		self.request_id.encode(w);
+	}
+
+	fn decode<R: bytes::Buf>(r: &mut R, _version: Version) -> Result<Self, DecodeError> {
+		let request_id = u64::decode(r)?;
+		Ok(Self { request_id })
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::ietf::decode_message;
+	use bytes::BytesMut;
+
+	fn round_trip<M: Message>(msg: &M, version: Version) -> M {
+		let mut buf = BytesMut::new();
+		msg.encode(&mut buf, version);
+		decode_message(buf.freeze(), version).unwrap()
+	}
+
+	#[test]
+	fn test_fetch_standalone() {
+		let msg = Fetch {
+			request_id: 3,
+			subscriber_priority: 1,
+			fetch_type: FetchType::Standalone {
+				track_namespace: Path::new("test/broadcast"),
+				track_name: "video".into(),
+				start: (5, 0),
+				end: (7, 0),
+			},
+		};
+
+		for version in [Version::Draft07, Version::Draft11] {
+			let decoded = round_trip(&msg, version);
+			assert_eq!(decoded.request_id, 3);
+
+			let FetchType::Standalone {
+				track_namespace,
+				track_name,
+				start,
+				end,
+			} = decoded.fetch_type
+			else {
+				panic!("expected standalone fetch");
+			};
+
+			assert_eq!(track_namespace.as_str(), "test/broadcast");
+			assert_eq!(track_name, "video");
+			assert_eq!(start, (5, 0));
+			assert_eq!(end, (7, 0));
+		}
+	}
+
+	#[test]
+	fn test_fetch_joining() {
+		let msg = Fetch {
+			request_id: 4,
+			subscriber_priority: 0,
+			fetch_type: FetchType::RelativeJoining {
+				subscribe_id: 2,
+				group_offset: 0,
+			},
+		};
+
+		let mut buf = BytesMut::new();
+		msg.encode(&mut buf, Version::Draft11);
+		assert_eq!(buf.to_vec(), vec![0x04, 0x00, 0x01, 0x02, 0x02, 0x00, 0x00]);
+
+		let decoded = round_trip(&msg, Version::Draft11);
+		assert!(matches!(
+			decoded.fetch_type,
+			FetchType::RelativeJoining {
+				subscribe_id: 2,
+				group_offset: 0
+			}
+		));
+	}
+
+	#[test]
+	fn test_fetch_ok() {
+		let msg = FetchOk {
+			request_id: 1,
+			end_of_track: true,
+			largest: (9, 3),
+		};
+
+		let decoded = round_trip(&msg, Version::Draft11);
+		assert_eq!(decoded.request_id, 1);
+		assert!(decoded.end_of_track);
+		assert_eq!(decoded.largest, (9, 3));
+	}
+}
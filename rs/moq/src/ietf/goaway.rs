@@ -1,9 +1,11 @@
-//! IETF moq-transport-07 goaway message
+//! IETF moq-transport goaway message
 
 use std::borrow::Cow;
 
 use crate::coding::*;
 
+use super::{Message, MessageId, Version};
+
 /// GoAway message (0x10)
 #[derive(Clone, Debug)]
 pub struct GoAway<'a> {
@@ -11,11 +13,13 @@ pub struct GoAway<'a> {
 }
 
 impl<'a> Message for GoAway<'a> {
-	fn encode<W: bytes::BufMut>(&self, w: &mut W) {
+	const ID: MessageId = MessageId::GoAway;
+
+	fn encode<W: bytes::BufMut>(&self, w: &mut W, _version: Version) {
 		self.new_session_uri.encode(w);
 	}
 
-	fn decode<R: bytes::Buf>(r: &mut R) -> Result<Self, DecodeError> {
+	fn decode<R: bytes::Buf>(r: &mut R, _version: Version) -> Result<Self, DecodeError> {
 		let new_session_uri = Cow::<str>::decode(r)?;
 		Ok(Self { new_session_uri })
 	}
@@ -28,13 +32,12 @@ mod tests {
 
 	fn encode_message<M: Message>(msg: &M) -> Vec<u8> {
 		let mut buf = BytesMut::new();
-		msg.encode(&mut buf);
+		msg.encode(&mut buf, Version::Draft11);
 		buf.to_vec()
 	}
 
 	fn decode_message<M: Message>(bytes: &[u8]) -> Result<M, DecodeError> {
-		let mut buf = bytes::Bytes::from(bytes.to_vec());
-		M::decode(&mut buf)
+		crate::ietf::decode_message(bytes.to_vec().into(), Version::Draft11)
 	}
 
 	#[test]
@@ -1,5 +1,13 @@
-use crate::coding::{Decode, DecodeError, Encode};
+use bytes::{Buf, BufMut, Bytes};
 
+use crate::{
+	coding::{Decode, DecodeError, Encode},
+	Error,
+};
+
+use super::Version;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum MessageId {
 	SubscribeUpdate,
 	Subscribe,
@@ -19,11 +27,12 @@ pub enum MessageId {
 	SubscribeAnnouncesOk,
 	SubscribeAnnouncesError,
 	UnsubscribeAnnounces,
-	MaxSubscribeId,
+	MaxRequestId,
 	Fetch,
 	FetchCancel,
 	FetchOk,
 	FetchError,
+	RequestsBlocked,
 	ClientSetup,
 	ServerSetup,
 }
@@ -47,11 +56,14 @@ pub enum MessageId {
 0x12	SUBSCRIBE_ANNOUNCES_OK (Section 6.24)
 0x13	SUBSCRIBE_ANNOUNCES_ERROR (Section 6.25
 0x14	UNSUBSCRIBE_ANNOUNCES (Section 6.14)
-0x15	MAX_SUBSCRIBE_ID (Section 6.20)
+0x15	MAX_SUBSCRIBE_ID (Section 6.20), renamed MAX_REQUEST_ID in draft-11
 0x16	FETCH (Section 6.7)
 0x17	FETCH_CANCEL (Section 6.8)
 0x18	FETCH_OK (Section 6.17)
 0x19	FETCH_ERROR (Section 6.18)
+0x1A	REQUESTS_BLOCKED (draft-11)
+0x20	CLIENT_SETUP (draft-11)
+0x21	SERVER_SETUP (draft-11)
 0x40	CLIENT_SETUP (Section 6.2)
 0x41	SERVER_SETUP (Section 6.2)
 */
@@ -77,13 +89,15 @@ impl Encode for MessageId {
 			Self::SubscribeAnnouncesOk => 0x12,
 			Self::SubscribeAnnouncesError => 0x13,
 			Self::UnsubscribeAnnounces => 0x14,
-			Self::MaxSubscribeId => 0x15,
+			Self::MaxRequestId => 0x15,
 			Self::Fetch => 0x16,
 			Self::FetchCancel => 0x17,
 			Self::FetchOk => 0x18,
 			Self::FetchError => 0x19,
-			Self::ClientSetup => 0x40,
-			Self::ServerSetup => 0x41,
+			Self::RequestsBlocked => 0x1A,
+			// Draft-07 setup messages are encoded by moq-lite instead.
+			Self::ClientSetup => 0x20,
+			Self::ServerSetup => 0x21,
 		};
 		id.encode(w)
 	}
@@ -111,14 +125,79 @@ impl Decode for MessageId {
 			0x12 => Self::SubscribeAnnouncesOk,
 			0x13 => Self::SubscribeAnnouncesError,
 			0x14 => Self::UnsubscribeAnnounces,
-			0x15 => Self::MaxSubscribeId,
+			0x15 => Self::MaxRequestId,
 			0x16 => Self::Fetch,
 			0x17 => Self::FetchCancel,
 			0x18 => Self::FetchOk,
 			0x19 => Self::FetchError,
-			0x40 => Self::ClientSetup,
-			0x41 => Self::ServerSetup,
+			0x1A => Self::RequestsBlocked,
+			0x20 | 0x40 => Self::ClientSetup,
+			0x21 | 0x41 => Self::ServerSetup,
 			_ => return Err(DecodeError::InvalidValue),
 		})
 	}
 }
+
+/// A control message, encoded differently depending on the negotiated [Version].
+///
+/// On the wire, each message is prefixed by its [MessageId] and the length of the payload.
+/// Draft-07 uses a varint for the length while draft-11 uses a 16-bit integer.
+pub trait Message: Sized {
+	const ID: MessageId;
+
+	/// Encode the payload, without the ID or length.
+	fn encode<W: BufMut>(&self, w: &mut W, version: Version);
+
+	/// Decode the payload, without the ID or length.
+	fn decode<R: Buf>(r: &mut R, version: Version) -> Result<Self, DecodeError>;
+}
+
+/// Encode a message along with its ID and length.
+pub fn encode_message<M: Message, W: BufMut>(msg: &M, w: &mut W, version: Version) -> Result<(), Error> {
+	let mut payload = Vec::new();
+	msg.encode(&mut payload, version);
+
+	M::ID.encode(w);
+
+	match version {
+		Version::Draft07 => payload.len().encode(w),
+		Version::Draft11 => {
+			let size = u16::try_from(payload.len()).map_err(|_| Error::WrongSize)?;
+			w.put_u16(size);
+		}
+	}
+
+	w.put_slice(&payload);
+
+	Ok(())
+}
+
+/// Decode the length and payload of a message, after the ID has been decoded.
+pub fn decode_payload<R: Buf>(r: &mut R, version: Version) -> Result<Bytes, DecodeError> {
+	let size = match version {
+		Version::Draft07 => usize::decode(r)?,
+		Version::Draft11 => {
+			if r.remaining() < 2 {
+				return Err(DecodeError::Short);
+			}
+
+			r.get_u16() as usize
+		}
+	};
+
+	if r.remaining() < size {
+		return Err(DecodeError::Short);
+	}
+
+	Ok(r.copy_to_bytes(size))
+}
+
+/// Decode a message from its payload, ensuring the entire payload is consumed.
+pub fn decode_message<M: Message>(mut payload: Bytes, version: Version) -> Result<M, DecodeError> {
+	let msg = M::decode(&mut payload, version)?;
+	if payload.has_remaining() {
+		return Err(DecodeError::TooManyBytes);
+	}
+
+	Ok(msg)
+}
@@ -1,26 +1,35 @@
 mod announce;
 mod control;
+mod fetch;
 mod goaway;
 mod message;
 mod object;
 mod publisher;
+mod request;
 mod role;
 mod session;
+mod setup;
 mod subscribe;
 mod subscribe_announces;
 mod subscriber;
 mod track;
 mod util;
+mod version;
 
 pub use announce::*;
+pub(crate) use control::RequestLimits;
 use control::*;
+pub use fetch::*;
 pub use goaway::*;
 pub use message::*;
 pub use object::*;
 use publisher::*;
+pub use request::*;
 pub use role::*;
 pub(crate) use session::*;
+pub use setup::*;
 pub use subscribe::*;
 pub use subscribe_announces::*;
 use subscriber::*;
 pub use track::*;
+pub use version::*;
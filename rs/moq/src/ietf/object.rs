@@ -1,94 +1,608 @@
-use bytes::Buf;
+use std::ops::RangeInclusive;
 
-use crate::coding::{Decode, DecodeError, Encode};
+use crate::{
+	coding::{Decode, DecodeError, Encode},
+	FrameExtensions,
+};
 
-const SUBGROUP_ID: u8 = 0x0;
-const GROUP_END: u8 = 0x03;
+use super::Version;
 
+/// The header of a subgroup stream, followed by each [Frame].
+#[derive(Clone, Debug)]
 pub struct Group {
+	/// Only encoded for draft-07, draft-11 identifies the subscription by the alias alone.
 	pub subscribe_id: u64,
 	pub track_alias: u64,
 	pub group_id: u64,
+
+	/// The subgroup within the group, or None if it's the ID of the first object.
+	///
+	/// Objects in the same subgroup are delivered in order, while separate subgroups are independent.
+	/// Only draft-11 can leave the subgroup ID implicit.
+	pub subgroup_id: Option<u64>,
+
 	pub publisher_priority: u8,
+
+	/// Whether each object has extension headers, only supported by draft-11.
+	pub extensions: bool,
 }
 
 impl Group {
+	/// STREAM_HEADER_SUBGROUP in draft-07.
 	pub const STREAM_TYPE: u64 = 0x04;
-}
 
-impl Encode for Group {
-	fn encode<W: bytes::BufMut>(&self, w: &mut W) {
-		self.subscribe_id.encode(w);
+	/// SUBGROUP_HEADER in draft-11.
+	///
+	/// The type indicates how the subgroup ID is encoded and whether objects have extension headers.
+	pub const STREAM_TYPES: RangeInclusive<u64> = 0x08..=0x0D;
+
+	/// The stream type to use when encoding this header.
+	pub fn stream_type(&self, version: Version) -> u64 {
+		match version {
+			Version::Draft07 => Self::STREAM_TYPE,
+			// Extension headers are indicated by the odd types.
+			Version::Draft11 => {
+				let kind = match self.subgroup_id {
+					Some(0) => 0x08,
+					None => 0x0A,
+					Some(_) => 0x0C,
+				};
+				kind | self.extensions as u64
+			}
+		}
+	}
+
+	/// Encode the header, after the [Group::stream_type].
+	pub fn encode<W: bytes::BufMut>(&self, w: &mut W, version: Version) {
+		if version == Version::Draft07 {
+			self.subscribe_id.encode(w);
+		}
+
 		self.track_alias.encode(w);
 		self.group_id.encode(w);
-		SUBGROUP_ID.encode(w);
+
+		match (version, self.subgroup_id) {
+			(Version::Draft07, subgroup_id) => subgroup_id.expect("subgroup ID required for draft-07").encode(w),
+			// Zero and implicit subgroup IDs are indicated by the stream type.
+			(Version::Draft11, Some(0) | None) => {}
+			(Version::Draft11, Some(subgroup_id)) => subgroup_id.encode(w),
+		}
+
 		self.publisher_priority.encode(w);
 	}
-}
 
-impl Decode for Group {
-	fn decode<R: bytes::Buf>(r: &mut R) -> Result<Self, DecodeError> {
-		let subscribe_id = u64::decode(r)?;
+	/// Decode the header, after the stream type has been decoded.
+	pub fn decode<R: bytes::Buf>(r: &mut R, version: Version, stream_type: u64) -> Result<Self, DecodeError> {
+		let subscribe_id = match version {
+			Version::Draft07 => u64::decode(r)?,
+			Version::Draft11 => 0,
+		};
+
 		let track_alias = u64::decode(r)?;
 		let group_id = u64::decode(r)?;
-		let subgroup_id = u8::decode(r)?;
-		if subgroup_id != SUBGROUP_ID {
-			return Err(DecodeError::InvalidValue);
-		}
+
+		let subgroup_id = match (version, stream_type) {
+			(Version::Draft07, _) => Some(u64::decode(r)?),
+			(Version::Draft11, 0x08 | 0x09) => Some(0),
+			// The subgroup ID is the ID of the first object, which hasn't been decoded yet.
+			(Version::Draft11, 0x0A | 0x0B) => None,
+			(Version::Draft11, _) => Some(u64::decode(r)?),
+		};
+
 		let publisher_priority = u8::decode(r)?;
+
 		Ok(Self {
 			subscribe_id,
 			track_alias,
 			group_id,
+			subgroup_id,
 			publisher_priority,
+			extensions: version == Version::Draft11 && stream_type % 2 == 1,
 		})
 	}
 }
 
+/// The header of each object within a subgroup stream, followed by the payload.
 pub struct Frame {
 	pub id: u64,
-	pub payload: Option<Vec<u8>>,
+
+	/// The size of the payload.
+	pub size: u64,
+
+	/// The status of the object, only encoded when the payload is empty.
+	pub status: u64,
+
+	/// Extension headers, only encoded when the [Group] has extensions.
+	pub extensions: FrameExtensions,
 }
 
-impl Encode for Frame {
-	fn encode<W: bytes::BufMut>(&self, w: &mut W) {
+impl Frame {
+	pub const STATUS_NORMAL: u64 = 0x00;
+	/// The object was never produced, for example a sparse object ID.
+	pub const STATUS_DOES_NOT_EXIST: u64 = 0x01;
+	/// There are no more objects in the group, using the next object ID.
+	pub const STATUS_END_OF_GROUP: u64 = 0x03;
+	/// There are no more objects in the track, called End of Track and Group in draft-07.
+	pub const STATUS_END_OF_TRACK: u64 = 0x04;
+
+	/// Decode the status of an empty object, rejecting unknown values.
+	fn decode_status<R: bytes::Buf>(r: &mut R) -> Result<u64, DecodeError> {
+		match u64::decode(r)? {
+			status @ (Self::STATUS_NORMAL
+			| Self::STATUS_DOES_NOT_EXIST
+			| Self::STATUS_END_OF_GROUP
+			| Self::STATUS_END_OF_TRACK) => Ok(status),
+			_ => Err(DecodeError::InvalidValue),
+		}
+	}
+
+	/// Encode the header, using the same encoding for draft-07 and draft-11 when there are no extensions.
+	pub fn encode<W: bytes::BufMut>(&self, w: &mut W, extensions: bool) {
 		self.id.encode(w);
 
-		let size = self.payload.as_ref().map(|p| p.len()).unwrap_or(0);
-		size.encode(w);
+		if extensions {
+			self.extensions.encode(w);
+		}
+
+		self.size.encode(w);
 
-		match &self.payload {
-			Some(payload) if !payload.is_empty() => w.put_slice(payload),
-			Some(_) => 0u8.encode(w),
-			None => GROUP_END.encode(w),
+		if self.size == 0 {
+			self.status.encode(w);
 		}
 	}
-}
 
-impl Decode for Frame {
-	fn decode<R: bytes::Buf>(r: &mut R) -> Result<Self, DecodeError> {
+	/// Decode the header, including any extension headers.
+	pub fn decode<R: bytes::Buf>(r: &mut R, extensions: bool) -> Result<Self, DecodeError> {
 		let id = u64::decode(r)?;
+
+		let extensions = match extensions {
+			true => FrameExtensions::decode(r)?,
+			false => FrameExtensions::default(),
+		};
+
 		let size = u64::decode(r)?;
+		let status = match size {
+			0 => Self::decode_status(r)?,
+			_ => Self::STATUS_NORMAL,
+		};
+
+		Ok(Self {
+			id,
+			size,
+			status,
+			extensions,
+		})
+	}
+}
+
+/// An object delivered as a datagram, including the payload.
+///
+/// Unlike a subgroup stream, each datagram identifies its own group.
+#[derive(Clone, Debug)]
+pub struct Datagram {
+	/// Only encoded for draft-07, draft-11 identifies the subscription by the alias alone.
+	pub subscribe_id: u64,
+	pub track_alias: u64,
+	pub group_id: u64,
+	pub object_id: u64,
+	pub publisher_priority: u8,
+
+	/// The status of the object, only encoded when the payload is empty.
+	pub status: u64,
+
+	/// Extension headers, only encoded for draft-11.
+	pub extensions: FrameExtensions,
+
+	pub payload: bytes::Bytes,
+}
+
+impl Datagram {
+	/// OBJECT_DATAGRAM in draft-07.
+	pub const DRAFT07_TYPE: u64 = 0x01;
+
+	/// Encode the entire datagram, including extension headers for draft-11.
+	pub fn encode<W: bytes::BufMut>(&self, w: &mut W, version: Version) {
+		let extensions = version == Version::Draft11 && !self.extensions.is_empty();
+
+		match version {
+			Version::Draft07 => {
+				Self::DRAFT07_TYPE.encode(w);
+				self.subscribe_id.encode(w);
+			}
+			// OBJECT_DATAGRAM_STATUS (0x02) replaces the payload with the status.
+			Version::Draft11 => match self.status {
+				Frame::STATUS_NORMAL => (extensions as u64).encode(w),
+				_ => (0x02 | extensions as u64).encode(w),
+			},
+		}
+
+		self.track_alias.encode(w);
+		self.group_id.encode(w);
+		self.object_id.encode(w);
+		self.publisher_priority.encode(w);
 
-		if r.remaining() < size as usize {
-			return Err(DecodeError::Short);
+		if extensions {
+			self.extensions.encode(w);
 		}
 
-		if size > 0 {
-			let payload = r.copy_to_bytes(size as usize).to_vec();
-			Ok(Self {
-				id,
-				payload: Some(payload),
-			})
-		} else {
-			match u8::decode(r)? {
-				0 => Ok(Self {
-					id,
-					payload: Some(Vec::new()),
-				}),
-				GROUP_END => Ok(Self { id, payload: None }),
-				_ => Err(DecodeError::InvalidValue),
+		match version {
+			// Draft-07 uses the same encoding as a subgroup stream.
+			Version::Draft07 => {
+				self.payload.len().encode(w);
+				if self.payload.is_empty() {
+					self.status.encode(w);
+				}
+			}
+			Version::Draft11 if self.status != Frame::STATUS_NORMAL => {
+				self.status.encode(w);
+				return;
 			}
+			// The payload is the remainder of the datagram.
+			Version::Draft11 => {}
+		}
+
+		w.put_slice(&self.payload);
+	}
+
+	/// Decode the entire datagram, including any extension headers.
+	pub fn decode<R: bytes::Buf>(r: &mut R, version: Version) -> Result<Self, DecodeError> {
+		let kind = u64::decode(r)?;
+
+		// Draft-11 uses the low bit for extension headers and the next for the status.
+		let (subscribe_id, extensions) = match (version, kind) {
+			(Version::Draft07, Self::DRAFT07_TYPE) => (u64::decode(r)?, false),
+			(Version::Draft11, 0x00..=0x03) => (0, kind & 0x01 != 0),
+			_ => return Err(DecodeError::InvalidValue),
+		};
+
+		let track_alias = u64::decode(r)?;
+		let group_id = u64::decode(r)?;
+		let object_id = u64::decode(r)?;
+		let publisher_priority = u8::decode(r)?;
+
+		let extensions = match extensions {
+			true => FrameExtensions::decode(r)?,
+			false => FrameExtensions::default(),
+		};
+
+		let (status, payload) = match version {
+			Version::Draft07 => match u64::decode(r)? {
+				0 => (Frame::decode_status(r)?, bytes::Bytes::new()),
+				size if r.remaining() as u64 == size => (Frame::STATUS_NORMAL, r.copy_to_bytes(r.remaining())),
+				_ => return Err(DecodeError::InvalidValue),
+			},
+			Version::Draft11 if kind & 0x02 != 0 => (Frame::decode_status(r)?, bytes::Bytes::new()),
+			Version::Draft11 => (Frame::STATUS_NORMAL, r.copy_to_bytes(r.remaining())),
+		};
+
+		if r.has_remaining() {
+			return Err(DecodeError::InvalidValue);
+		}
+
+		Ok(Self {
+			subscribe_id,
+			track_alias,
+			group_id,
+			object_id,
+			publisher_priority,
+			status,
+			extensions,
+			payload,
+		})
+	}
+}
+
+/// The header of a fetch stream, followed by each [FetchObject].
+pub struct FetchHeader {
+	/// The Subscribe ID in draft-07, or the Request ID in draft-11.
+	pub request_id: u64,
+}
+
+impl FetchHeader {
+	/// FETCH_HEADER in both draft-07 and draft-11.
+	pub const STREAM_TYPE: u64 = 0x05;
+
+	/// Encode the header, after the [FetchHeader::STREAM_TYPE].
+	pub fn encode<W: bytes::BufMut>(&self, w: &mut W) {
+		self.request_id.encode(w);
+	}
+
+	/// Decode the header, after the stream type has been decoded.
+	pub fn decode<R: bytes::Buf>(r: &mut R) -> Result<Self, DecodeError> {
+		let request_id = u64::decode(r)?;
+		Ok(Self { request_id })
+	}
+}
+
+/// The header of each object within a fetch stream, followed by the payload.
+///
+/// Unlike a subgroup stream, each object identifies its own group.
+pub struct FetchObject {
+	pub group_id: u64,
+	pub subgroup_id: u64,
+	pub object_id: u64,
+	pub publisher_priority: u8,
+
+	/// The size of the payload.
+	pub size: u64,
+
+	/// The status of the object, only encoded when the payload is empty.
+	pub status: u64,
+
+	/// Extension headers, only encoded for draft-11.
+	pub extensions: FrameExtensions,
+}
+
+impl FetchObject {
+	/// Encode the header, including extension headers for draft-11.
+	pub fn encode<W: bytes::BufMut>(&self, w: &mut W, version: Version) {
+		self.group_id.encode(w);
+		self.subgroup_id.encode(w);
+		self.object_id.encode(w);
+		self.publisher_priority.encode(w);
+
+		if version == Version::Draft11 {
+			self.extensions.encode(w);
+		}
+
+		self.size.encode(w);
+
+		if self.size == 0 {
+			self.status.encode(w);
+		}
+	}
+
+	/// Decode the header, including extension headers for draft-11.
+	pub fn decode<R: bytes::Buf>(r: &mut R, version: Version) -> Result<Self, DecodeError> {
+		let group_id = u64::decode(r)?;
+		let subgroup_id = u64::decode(r)?;
+		let object_id = u64::decode(r)?;
+		let publisher_priority = u8::decode(r)?;
+
+		let extensions = match version {
+			Version::Draft11 => FrameExtensions::decode(r)?,
+			Version::Draft07 => FrameExtensions::default(),
+		};
+
+		let size = u64::decode(r)?;
+		let status = match size {
+			0 => Frame::decode_status(r)?,
+			_ => Frame::STATUS_NORMAL,
+		};
+
+		Ok(Self {
+			group_id,
+			subgroup_id,
+			object_id,
+			publisher_priority,
+			size,
+			status,
+			extensions,
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_group_draft11() {
+		let group = Group {
+			subscribe_id: 1,
+			track_alias: 2,
+			group_id: 3,
+			subgroup_id: Some(0),
+			publisher_priority: 4,
+			extensions: false,
+		};
+
+		let mut buf = Vec::new();
+		group.encode(&mut buf, Version::Draft11);
+
+		// The subscribe ID and subgroup ID are not encoded.
+		assert_eq!(buf, vec![0x02, 0x03, 0x04]);
+		assert_eq!(group.stream_type(Version::Draft11), 0x08);
+
+		let decoded = Group::decode(&mut buf.as_slice(), Version::Draft11, 0x08).unwrap();
+		assert_eq!(decoded.track_alias, 2);
+		assert_eq!(decoded.group_id, 3);
+		assert!(!decoded.extensions);
+
+		// An explicit subgroup ID is included for 0x0C and 0x0D.
+		let buf = [0x02, 0x03, 0x00, 0x04];
+		let decoded = Group::decode(&mut buf.as_slice(), Version::Draft11, 0x0D).unwrap();
+		assert_eq!(decoded.publisher_priority, 4);
+		assert!(decoded.extensions);
+	}
+
+	#[test]
+	fn test_group_draft07() {
+		let group = Group {
+			subscribe_id: 1,
+			track_alias: 2,
+			group_id: 3,
+			subgroup_id: Some(0),
+			publisher_priority: 4,
+			extensions: false,
+		};
+
+		let mut buf = Vec::new();
+		group.encode(&mut buf, Version::Draft07);
+		assert_eq!(buf, vec![0x01, 0x02, 0x03, 0x00, 0x04]);
+
+		let decoded = Group::decode(&mut buf.as_slice(), Version::Draft07, Group::STREAM_TYPE).unwrap();
+		assert_eq!(decoded.subscribe_id, 1);
+		assert_eq!(decoded.group_id, 3);
+	}
+
+	#[test]
+	fn test_subgroups() {
+		let group = Group {
+			subscribe_id: 1,
+			track_alias: 2,
+			group_id: 3,
+			subgroup_id: Some(5),
+			publisher_priority: 4,
+			extensions: false,
+		};
+
+		// A non-zero subgroup ID is encoded explicitly.
+		let mut buf = Vec::new();
+		group.encode(&mut buf, Version::Draft11);
+		assert_eq!(group.stream_type(Version::Draft11), 0x0C);
+		assert_eq!(buf, vec![0x02, 0x03, 0x05, 0x04]);
+
+		let decoded = Group::decode(&mut buf.as_slice(), Version::Draft11, 0x0C).unwrap();
+		assert_eq!(decoded.subgroup_id, Some(5));
+
+		// The subgroup ID can be the first object ID instead.
+		let buf = [0x02, 0x03, 0x04];
+		let decoded = Group::decode(&mut buf.as_slice(), Version::Draft11, 0x0A).unwrap();
+		assert_eq!(decoded.subgroup_id, None);
+
+		let mut buf = Vec::new();
+		group.encode(&mut buf, Version::Draft07);
+		let decoded = Group::decode(&mut buf.as_slice(), Version::Draft07, Group::STREAM_TYPE).unwrap();
+		assert_eq!(decoded.subgroup_id, Some(5));
+	}
+
+	#[test]
+	fn test_object_status() {
+		for status in [
+			Frame::STATUS_NORMAL,
+			Frame::STATUS_DOES_NOT_EXIST,
+			Frame::STATUS_END_OF_GROUP,
+			Frame::STATUS_END_OF_TRACK,
+		] {
+			let buf = [0x01, 0x00, status as u8];
+			let decoded = Frame::decode(&mut buf.as_slice(), false).unwrap();
+			assert_eq!(decoded.status, status);
 		}
+
+		// Unknown statuses are rejected, including the removed Group Does Not Exist.
+		let buf = [0x01, 0x00, 0x02];
+		assert!(Frame::decode(&mut buf.as_slice(), false).is_err());
+	}
+
+	#[test]
+	fn test_frame_extensions() {
+		#[rustfmt::skip]
+		let buf = [
+			0x05, // object id
+			0x03, 0x01, 0x01, 0xaa, // extension headers
+			0x03, // payload size
+		];
+
+		let frame = Frame::decode(&mut buf.as_slice(), true).unwrap();
+		assert_eq!(frame.id, 5);
+		assert_eq!(frame.size, 3);
+		assert_eq!(frame.status, Frame::STATUS_NORMAL);
+		assert_eq!(frame.extensions.get_bytes(0x01), Some(&[0xaa][..]));
+
+		// The extension headers are preserved when re-encoded.
+		let mut encoded = Vec::new();
+		frame.encode(&mut encoded, true);
+		assert_eq!(encoded, buf);
+
+		let frame = Frame {
+			id: 6,
+			size: 0,
+			status: Frame::STATUS_END_OF_GROUP,
+			extensions: FrameExtensions::default(),
+		};
+
+		let mut buf = Vec::new();
+		frame.encode(&mut buf, false);
+		assert_eq!(buf, vec![0x06, 0x00, 0x03]);
+
+		let decoded = Frame::decode(&mut buf.as_slice(), false).unwrap();
+		assert_eq!(decoded.status, Frame::STATUS_END_OF_GROUP);
+	}
+
+	#[test]
+	fn test_datagram() {
+		let datagram = Datagram {
+			subscribe_id: 1,
+			track_alias: 2,
+			group_id: 3,
+			object_id: 0,
+			publisher_priority: 4,
+			status: Frame::STATUS_NORMAL,
+			extensions: FrameExtensions::default(),
+			payload: bytes::Bytes::from_static(b"hi"),
+		};
+
+		// The draft-11 payload isn't length prefixed.
+		let mut buf = Vec::new();
+		datagram.encode(&mut buf, Version::Draft11);
+		assert_eq!(buf, vec![0x00, 0x02, 0x03, 0x00, 0x04, b'h', b'i']);
+
+		let decoded = Datagram::decode(&mut buf.as_slice(), Version::Draft11).unwrap();
+		assert_eq!(decoded.group_id, 3);
+		assert_eq!(decoded.payload, "hi");
+
+		let mut buf = Vec::new();
+		datagram.encode(&mut buf, Version::Draft07);
+		assert_eq!(buf, vec![0x01, 0x01, 0x02, 0x03, 0x00, 0x04, 0x02, b'h', b'i']);
+
+		let decoded = Datagram::decode(&mut buf.as_slice(), Version::Draft07).unwrap();
+		assert_eq!(decoded.subscribe_id, 1);
+		assert_eq!(decoded.payload, "hi");
+
+		// A status replaces the payload.
+		let status = Datagram {
+			status: Frame::STATUS_END_OF_TRACK,
+			payload: bytes::Bytes::new(),
+			..datagram
+		};
+
+		let mut buf = Vec::new();
+		status.encode(&mut buf, Version::Draft11);
+		assert_eq!(buf, vec![0x02, 0x02, 0x03, 0x00, 0x04, 0x04]);
+
+		let decoded = Datagram::decode(&mut buf.as_slice(), Version::Draft11).unwrap();
+		assert_eq!(decoded.status, Frame::STATUS_END_OF_TRACK);
+		assert!(decoded.payload.is_empty());
+
+		// Extension headers use the odd types.
+		let mut extensions = FrameExtensions::default();
+		extensions.set_int(0x02, 5);
+		let datagram = Datagram { extensions, ..datagram };
+
+		let mut buf = Vec::new();
+		datagram.encode(&mut buf, Version::Draft11);
+		assert_eq!(buf, vec![0x01, 0x02, 0x03, 0x00, 0x04, 0x02, 0x02, 0x05, b'h', b'i']);
+
+		let decoded = Datagram::decode(&mut buf.as_slice(), Version::Draft11).unwrap();
+		assert_eq!(decoded.extensions.get_int(0x02), Some(5));
+		assert_eq!(decoded.payload, "hi");
+	}
+
+	#[test]
+	fn test_fetch_object() {
+		let object = FetchObject {
+			group_id: 1,
+			subgroup_id: 0,
+			object_id: 2,
+			publisher_priority: 3,
+			size: 4,
+			status: Frame::STATUS_NORMAL,
+			extensions: FrameExtensions::default(),
+		};
+
+		let mut buf = Vec::new();
+		object.encode(&mut buf, Version::Draft11);
+		assert_eq!(buf, vec![0x01, 0x00, 0x02, 0x03, 0x00, 0x04]);
+
+		let decoded = FetchObject::decode(&mut buf.as_slice(), Version::Draft11).unwrap();
+		assert_eq!(decoded.group_id, 1);
+		assert_eq!(decoded.object_id, 2);
+		assert_eq!(decoded.size, 4);
+
+		// Draft-07 has no extension headers.
+		let mut buf = Vec::new();
+		object.encode(&mut buf, Version::Draft07);
+		assert_eq!(buf, vec![0x01, 0x00, 0x02, 0x03, 0x04]);
 	}
 }
@@ -1,14 +1,21 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+	collections::{HashMap, HashSet},
+	ops::Range,
+	sync::Arc,
+};
 
-use tokio::sync::oneshot;
+use bytes::BytesMut;
+use futures::FutureExt;
+use tokio::sync::{oneshot, watch};
 use web_async::{FuturesExt, Lock};
 use web_transport_trait::SendStream;
 
 use crate::{
 	coding::Writer,
 	ietf::{self, Control},
-	model::GroupConsumer,
-	Error, Origin, OriginConsumer, Track, TrackConsumer,
+	model::{FrameConsumer, GroupConsumer},
+	BroadcastConsumer, Error, Origin, OriginConsumer, Path, PathOwned, Stats, Track, TrackConsumer, TrackEndReason,
+	TrackStatus,
 };
 
 #[derive(Clone)]
@@ -16,7 +23,93 @@ pub(super) struct Publisher<S: web_transport_trait::Session> {
 	session: S,
 	origin: OriginConsumer,
 	control: Control,
-	subscribes: Lock<HashMap<u64, oneshot::Sender<()>>>,
+	subscribes: Lock<HashMap<u64, Subscribed>>,
+	fetches: Lock<HashMap<u64, oneshot::Sender<()>>>,
+
+	// The namespace of each draft-11 announcement, as ANNOUNCE_ERROR only includes the Request ID.
+	announces: Lock<HashMap<u64, PathOwned>>,
+
+	// Announcements rejected or cancelled by the peer, which are no longer served to it until announced again.
+	rejected: AnnounceFilter,
+}
+
+/// A per-session filter of announcements that the peer rejected or cancelled.
+///
+/// The broadcast stays in our origin because other sessions may still want it; only this peer stops being served.
+/// The filter is cleared when the broadcast is announced again, giving the peer another chance to accept it.
+#[derive(Clone, Default)]
+struct AnnounceFilter(Lock<HashSet<PathOwned>>);
+
+impl AnnounceFilter {
+	// The peer doesn't want this broadcast.
+	fn reject(&self, path: PathOwned) {
+		self.0.lock().insert(path);
+	}
+
+	// The broadcast was announced again, so the peer may accept it this time.
+	fn announce(&self, path: &PathOwned) {
+		self.0.lock().remove(path);
+	}
+
+	// The broadcast was unannounced, returning true if the peer already rejected it.
+	fn unannounce(&self, path: &PathOwned) -> bool {
+		self.0.lock().remove(path)
+	}
+
+	// Returns true unless the peer rejected the broadcast.
+	fn allows(&self, path: &Path<'_>) -> bool {
+		!self.0.lock().contains(&path.to_owned())
+	}
+}
+
+// The objects requested by a subscription filter, see [ietf::SubscribeFilter].
+#[derive(Clone, Copy, Debug, Default)]
+struct Requested {
+	// The subscriber priority, which may be changed by an update.
+	priority: u8,
+	// The first group and object ID.
+	start: Option<(u64, u64)>,
+	// The last group and the last object ID plus one, or zero for the entire group.
+	end: Option<(u64, u64)>,
+}
+
+impl Requested {
+	// The object IDs to serve for the given group.
+	fn objects(&self, sequence: u64) -> Range<u64> {
+		let start = match self.start {
+			Some((group, object)) if group == sequence => object,
+			_ => 0,
+		};
+
+		let end = match self.end {
+			Some((group, object)) if group == sequence && object > 0 => object,
+			_ => u64::MAX,
+		};
+
+		start..end
+	}
+
+	// Returns true if the update only narrows the range, as required by [ietf::SubscribeUpdate].
+	fn narrows(&self, update: &Requested) -> bool {
+		// An end object of zero means the entire group.
+		let last = |end: Option<(u64, u64)>| end.map(|(group, object)| (group, object.wrapping_sub(1)));
+
+		let start = update.start >= self.start;
+		let end = match (last(self.end), last(update.end)) {
+			(_, None) => self.end.is_none(),
+			(None, Some(_)) => true,
+			(Some(old), Some(new)) => new <= old,
+		};
+
+		start && end && update.start.zip(update.end).is_none_or(|(start, end)| start.0 <= end.0)
+	}
+}
+
+// An active subscription, kept around so it can be cancelled or joined by a fetch.
+struct Subscribed {
+	track: TrackConsumer,
+	requested: watch::Sender<Requested>,
+	cancel: oneshot::Sender<()>,
 }
 
 impl<S: web_transport_trait::Session> Publisher<S> {
@@ -28,6 +121,9 @@ impl<S: web_transport_trait::Session> Publisher<S> {
 			origin,
 			control,
 			subscribes: Default::default(),
+			fetches: Default::default(),
+			announces: Default::default(),
+			rejected: Default::default(),
 		}
 	}
 
@@ -37,16 +133,42 @@ impl<S: web_transport_trait::Session> Publisher<S> {
 
 			if active.is_some() {
 				tracing::debug!(broadcast = %self.origin.absolute(&path), "announce");
-				let msg = ietf::Announce {
-					track_namespace: suffix,
-				};
-				self.control.send(ietf::MessageId::Announce, msg)?;
+
+				// The peer may accept the new broadcast even if it rejected the previous one.
+				self.rejected.announce(&suffix);
+
+				// Draft-07 doesn't identify announcements.
+				match self.control.version().request_ids() {
+					true => {
+						let announces = self.announces.clone();
+						self.control
+							.request(|request_id| {
+								announces.lock().insert(request_id, suffix.clone());
+								ietf::Announce {
+									request_id,
+									track_namespace: suffix,
+								}
+							})
+							.await?;
+					}
+					false => self.control.send(ietf::Announce {
+						request_id: 0,
+						track_namespace: suffix,
+					})?,
+				}
 			} else {
 				tracing::debug!(broadcast = %self.origin.absolute(&path), "unannounce");
+				self.announces.lock().retain(|_, namespace| *namespace != suffix);
+
+				// Don't unannounce a broadcast the peer already rejected.
+				if self.rejected.unannounce(&suffix) {
+					continue;
+				}
+
 				let msg = ietf::Unannounce {
 					track_namespace: suffix,
 				};
-				self.control.send(ietf::MessageId::Unannounce, msg)?;
+				self.control.send(msg)?;
 			}
 		}
 
@@ -55,24 +177,23 @@ impl<S: web_transport_trait::Session> Publisher<S> {
 
 	pub fn recv_subscribe(&mut self, msg: ietf::Subscribe<'_>) -> Result<(), Error> {
 		let id = msg.subscribe_id;
+		self.control.recv_request(id)?;
 
 		let track = msg.track_name.clone();
 		let absolute = self.origin.absolute(&msg.track_namespace).to_owned();
 
 		tracing::info!(%id, broadcast = %absolute, %track, "subscribed started");
 
-		let broadcast = match self.origin.consume_broadcast(&msg.track_namespace) {
+		let broadcast = match self.consume_broadcast(&msg.track_namespace) {
 			Some(consumer) => consumer,
 			None => {
-				self.control.send(
-					ietf::MessageId::SubscribeError,
-					ietf::SubscribeError {
-						subscribe_id: id,
-						error_code: 404,
-						reason_phrase: "Broadcast not found".into(),
-						track_alias: msg.track_alias,
-					},
-				)?;
+				self.control.send(ietf::SubscribeError {
+					subscribe_id: id,
+					error_code: 404,
+					reason_phrase: "Broadcast not found".into(),
+					track_alias: msg.track_alias,
+				})?;
+				self.control.request_done();
 				return Ok(());
 			}
 		};
@@ -82,54 +203,131 @@ impl<S: web_transport_trait::Session> Publisher<S> {
 			priority: msg.subscriber_priority,
 		};
 
-		let track = broadcast.subscribe_track(&track);
+		let mut track = broadcast.subscribe_track(&track);
+
+		// Map the filter onto the group sequence numbers, serving the entire current group when possible.
+		let requested = match msg.filter {
+			ietf::SubscribeFilter::LatestGroup if self.control.version() == ietf::Version::Draft11 => {
+				// Draft-11 calls this Next Group Start.
+				if let Some(latest) = track.status().latest {
+					track.start_at(latest + 1);
+				}
+				(None, None)
+			}
+			ietf::SubscribeFilter::LatestGroup | ietf::SubscribeFilter::LatestObject => (None, None),
+			ietf::SubscribeFilter::AbsoluteStart { start } => {
+				track.start_at(start.0);
+				(Some(start), None)
+			}
+			ietf::SubscribeFilter::AbsoluteRange { start, end } => {
+				track.start_at(start.0);
+				(Some(start), Some(end))
+			}
+		};
+
+		let requested = Requested {
+			priority: msg.subscriber_priority,
+			start: requested.0,
+			end: requested.1,
+		};
 
 		let (tx, rx) = oneshot::channel();
-		let mut subscribes = self.subscribes.lock();
-		subscribes.insert(id, tx);
+		let (requested, updates) = watch::channel(requested);
 
-		self.control.send(
-			ietf::MessageId::SubscribeOk,
-			ietf::SubscribeOk {
-				subscribe_id: id,
-				largest: None,
+		let mut subscribes = self.subscribes.lock();
+		subscribes.insert(
+			id,
+			Subscribed {
+				track: track.clone(),
+				requested,
+				cancel: tx,
 			},
-		)?;
+		);
+
+		self.control.send(ietf::SubscribeOk {
+			subscribe_id: id,
+			largest: None,
+		})?;
 
 		let session = self.session.clone();
 		let control = self.control.clone();
 		let subscribe_id = msg.subscribe_id;
-		let track_alias = msg.track_alias;
 		let subscribes = self.subscribes.clone();
 
+		let header = ietf::Group {
+			subscribe_id,
+			track_alias: msg.track_alias,
+			group_id: 0,
+			// Each group is served as a single subgroup.
+			subgroup_id: Some(0),
+			publisher_priority: track.info.priority,
+			// Always signal extension headers on draft-11 so they can be forwarded per frame.
+			extensions: self.control.version() == ietf::Version::Draft11,
+		};
+
 		web_async::spawn(async move {
-			if let Err(err) = Self::run_track(session, track, subscribe_id, track_alias, rx).await {
+			let _subscription = control.stats().map(|stats| stats.subscribe(absolute.as_str()));
+
+			// The number of data streams, so the subscriber knows when it has received everything.
+			let mut streams = 0;
+
+			let res = Self::run_track(
+				session,
+				&control,
+				track.clone(),
+				header,
+				updates.clone(),
+				&mut streams,
+				rx,
+			)
+			.await;
+
+			// The subscription is removed on unsubscribe.
+			let unsubscribed = subscribes.lock().remove(&subscribe_id).is_none();
+			let requested = *updates.borrow();
+
+			// The subscriber can make another request.
+			control.request_done();
+
+			if let Err(err) = res {
 				control
-					.send(
-						ietf::MessageId::SubscribeError,
-						ietf::SubscribeError {
-							subscribe_id,
-							error_code: 500,
-							reason_phrase: err.to_string().into(),
-							track_alias: msg.track_alias,
-						},
-					)
+					.send(ietf::SubscribeError {
+						subscribe_id,
+						error_code: 500,
+						reason_phrase: err.to_string().into(),
+						track_alias: msg.track_alias,
+					})
 					.ok();
 			} else {
+				// The track is still open if the subscriber unsubscribed.
+				let (status_code, final_sequence) = match track.closed().now_or_never() {
+					Some(Ok(end)) => {
+						let status_code = match end.reason {
+							TrackEndReason::Ended => ietf::SubscribeDone::STATUS_TRACK_ENDED,
+							TrackEndReason::Unpublished => ietf::SubscribeDone::STATUS_SUBSCRIPTION_ENDED,
+							TrackEndReason::Expired => ietf::SubscribeDone::STATUS_EXPIRED,
+						};
+						(status_code, end.final_sequence)
+					}
+					// The requested range was served in full.
+					_ if !unsubscribed && requested.end.is_some() => (
+						ietf::SubscribeDone::STATUS_SUBSCRIPTION_ENDED,
+						requested.end.map(|(group, _)| group),
+					),
+					_ => (ietf::SubscribeDone::STATUS_UNSUBSCRIBED, track.status().latest),
+				};
+
 				control
-					.send(
-						ietf::MessageId::SubscribeDone,
-						ietf::SubscribeDone {
-							subscribe_id,
-							status_code: 200,
-							reason_phrase: "OK".into(),
-							final_group_object: None,
-						},
-					)
+					.send(ietf::SubscribeDone {
+						subscribe_id,
+						status_code,
+						reason_phrase: "OK".into(),
+						// We don't track the number of objects in the final group.
+						final_group_object: final_sequence.map(|group| (group, 0)),
+						stream_count: streams,
+					})
 					.ok();
 			}
-
-			subscribes.lock().remove(&subscribe_id);
 		});
 
 		Ok(())
@@ -137,11 +335,20 @@ impl<S: web_transport_trait::Session> Publisher<S> {
 
 	async fn run_track(
 		session: S,
+		control: &Control,
 		mut track: TrackConsumer,
-		subscribe_id: u64,
-		track_alias: u64,
+		header: ietf::Group,
+		mut requested: watch::Receiver<Requested>,
+		streams: &mut u64,
 		mut cancel: oneshot::Receiver<()>,
 	) -> Result<(), Error> {
+		let subscribe_id = header.subscribe_id;
+		let version = control.version();
+		let stats = control.stats().cloned();
+
+		// Set once the final requested group has been served, waiting for any groups in flight.
+		let mut done = false;
+
 		// TODO use a BTreeMap serve the latest N groups by sequence.
 		// Until then, we'll implement N=2 manually.
 		// Also, this is more complicated because we can't use tokio because of WASM.
@@ -159,7 +366,30 @@ impl<S: web_transport_trait::Session> Publisher<S> {
 			let group = tokio::select! {
 				biased;
 				_ = &mut cancel => return Ok(()),
-				Some(group) = track.next_group().transpose() => group,
+				Some(group) = track.next_group().transpose(), if !done => group,
+				Ok(()) = requested.changed() => {
+					let update = *requested.borrow_and_update();
+					if let Some((start, _)) = update.start {
+						track.start_at(start);
+					}
+
+					if let Some((end, _)) = update.end {
+						// Abort any groups past the new end.
+						if old_sequence.is_some_and(|sequence| sequence > end) {
+							old_group = None;
+							old_sequence = None;
+						}
+
+						if new_sequence.is_some_and(|sequence| sequence > end) {
+							new_group = old_group.take();
+							new_sequence = old_sequence.take();
+						}
+
+						done = new_sequence.is_some_and(|sequence| sequence >= end);
+					}
+
+					continue;
+				},
 				Some(_) = async { Some(old_group.as_mut()?.await) } => {
 					old_group = None;
 					old_sequence = None;
@@ -177,6 +407,16 @@ impl<S: web_transport_trait::Session> Publisher<S> {
 
 			let sequence = group.info.sequence;
 			let latest = new_sequence.as_ref().unwrap_or(&0);
+			let requested = *requested.borrow();
+
+			if let Some((end, _)) = requested.end {
+				done = sequence >= end;
+
+				if sequence > end {
+					tracing::debug!(subscribe = %subscribe_id, track = %track.info.name, %sequence, %end, "past requested range");
+					continue;
+				}
+			}
 
 			tracing::debug!(subscribe = %subscribe_id, track = %track.info.name, sequence, latest, "serving group");
 
@@ -184,25 +424,40 @@ impl<S: web_transport_trait::Session> Publisher<S> {
 			// We always serve at most two groups, but maybe we should serve only sequence >= MAX-1.
 			if sequence < *old_sequence.as_ref().unwrap_or(&0) {
 				tracing::debug!(subscribe = %subscribe_id, track = %track.info.name, old = %sequence, %latest, "skipping group");
+				stats.iter().for_each(Stats::drop_group);
 				continue;
 			}
 
-			let priority = stream_priority(track.info.priority, sequence);
+			let priority = stream_priority(requested.priority, sequence);
 			let msg = ietf::Group {
-				subscribe_id,
-				track_alias,
 				group_id: sequence,
-				publisher_priority: track.info.priority,
+				..header.clone()
 			};
 
 			// Spawn a task to serve this group, ignoring any errors because they don't really matter.
 			// TODO add some logging at least.
-			let handle = Box::pin(Self::run_group(session.clone(), msg, priority, group));
+			let objects = requested.objects(sequence);
+
+			// Datagrams don't count towards the number of streams.
+			let datagrams = track.datagrams() && control.datagrams();
+			if !datagrams {
+				*streams += 1;
+			}
+
+			let session = session.clone();
+			let group_stats = stats.clone();
+			let handle = Box::pin(async move {
+				match datagrams {
+					true => Self::run_datagrams(session, version, group_stats, msg, group, objects).await,
+					false => Self::run_group(session, version, group_stats, msg, priority, group, objects).await,
+				}
+			});
 
 			// Terminate the old group if it's still running.
 			if let Some(old_sequence) = old_sequence.take() {
 				tracing::debug!(subscribe = %subscribe_id, track = %track.info.name, old = %old_sequence, %latest, "aborting group");
 				old_group.take(); // Drop the future to cancel it.
+				stats.iter().for_each(Stats::drop_group);
 			}
 
 			assert!(old_group.is_none());
@@ -220,7 +475,15 @@ impl<S: web_transport_trait::Session> Publisher<S> {
 		}
 	}
 
-	async fn run_group(session: S, msg: ietf::Group, priority: i32, mut group: GroupConsumer) -> Result<(), Error> {
+	async fn run_group(
+		session: S,
+		version: ietf::Version,
+		stats: Option<Stats>,
+		msg: ietf::Group,
+		priority: i32,
+		mut group: GroupConsumer,
+		objects: Range<u64>,
+	) -> Result<(), Error> {
 		// TODO add a way to open in priority order.
 		let mut stream = session
 			.open_uni()
@@ -228,53 +491,376 @@ impl<S: web_transport_trait::Session> Publisher<S> {
 			.map_err(|err| Error::Transport(Arc::new(err)))?;
 		stream.set_priority(priority);
 
-		let mut stream = Writer::new(stream);
-		stream.encode(&ietf::Group::STREAM_TYPE).await?;
-		stream.encode(&msg).await?;
+		let mut stream = Writer::new(stream, stats);
+		stream.encode(&msg.stream_type(version)).await?;
 
-		loop {
+		let mut buf = BytesMut::new();
+		msg.encode(&mut buf, version);
+		stream.write_all(&mut buf).await?;
+
+		for id in 0..objects.end {
 			let frame = tokio::select! {
 				biased;
 				_ = stream.closed() => return Err(Error::Cancel),
 				frame = group.next_frame() => frame,
 			};
 
-			let mut frame = match frame? {
+			let frame = match frame? {
 				Some(frame) => frame,
 				None => break,
 			};
 
+			if id < objects.start {
+				continue;
+			}
+
 			tracing::trace!(size = %frame.info.size, "writing frame");
 
-			stream.encode(&frame.info.size).await?;
+			let header = ietf::Frame {
+				id,
+				size: frame.info.size,
+				status: ietf::Frame::STATUS_NORMAL,
+				extensions: frame.info.extensions.clone(),
+			};
+
+			header.encode(&mut buf, msg.extensions);
+			stream.write_all(&mut buf).await?;
+
+			Self::run_frame(&mut stream, frame).await?;
+		}
+
+		stream.finish().await?;
+
+		tracing::debug!(sequence = %msg.group_id, "finished group");
+
+		Ok(())
+	}
+
+	async fn run_datagrams(
+		session: S,
+		version: ietf::Version,
+		stats: Option<Stats>,
+		msg: ietf::Group,
+		mut group: GroupConsumer,
+		objects: Range<u64>,
+	) -> Result<(), Error> {
+		for id in 0..objects.end {
+			let mut frame = match group.next_frame().await? {
+				Some(frame) => frame,
+				None => break,
+			};
+
+			if id < objects.start {
+				continue;
+			}
+
+			let datagram = ietf::Datagram {
+				subscribe_id: msg.subscribe_id,
+				track_alias: msg.track_alias,
+				group_id: msg.group_id,
+				object_id: id,
+				publisher_priority: msg.publisher_priority,
+				status: ietf::Frame::STATUS_NORMAL,
+				extensions: frame.info.extensions.clone(),
+				payload: frame.read_all().await?,
+			};
+
+			let mut buf = BytesMut::new();
+			datagram.encode(&mut buf, version);
+
+			// Datagrams are unreliable anyway, so drop any that are too large.
+			if buf.len() > session.max_datagram_size() {
+				tracing::debug!(sequence = %msg.group_id, object = %id, size = %buf.len(), "datagram too large");
+				continue;
+			}
+
+			if let Some(stats) = &stats {
+				stats.sent(buf.len());
+			}
+
+			session
+				.send_datagram(buf.freeze())
+				.map_err(|err| Error::Transport(Arc::new(err)))?;
+		}
+
+		tracing::debug!(sequence = %msg.group_id, "finished datagrams");
+
+		Ok(())
+	}
+
+	async fn run_frame(stream: &mut Writer<S::SendStream>, mut frame: FrameConsumer) -> Result<(), Error> {
+		loop {
+			let chunk = tokio::select! {
+				biased;
+				_ = stream.closed() => return Err(Error::Cancel),
+				chunk = frame.read_chunk() => chunk,
+			};
+
+			match chunk? {
+				Some(mut chunk) => stream.write_all(&mut chunk).await?,
+				None => break,
+			}
+		}
+
+		tracing::trace!(size = %frame.info.size, "wrote frame");
+
+		Ok(())
+	}
+
+	pub fn recv_fetch(&mut self, msg: ietf::Fetch<'_>) -> Result<(), Error> {
+		let request_id = msg.request_id;
+		self.control.recv_request(request_id)?;
+
+		let (track, start, end) = match msg.fetch_type {
+			ietf::FetchType::Standalone {
+				track_namespace,
+				track_name,
+				start,
+				end,
+			} => {
+				tracing::debug!(%request_id, broadcast = %self.origin.absolute(&track_namespace), track = %track_name, ?start, ?end, "fetch");
+
+				let broadcast = match self.consume_broadcast(&track_namespace) {
+					Some(broadcast) => broadcast,
+					None => {
+						self.control.send(ietf::FetchError {
+							request_id,
+							error_code: 404,
+							reason_phrase: "Broadcast not found".into(),
+						})?;
+						self.control.request_done();
+						return Ok(());
+					}
+				};
+
+				let track = Track {
+					name: track_name.to_string(),
+					priority: msg.subscriber_priority,
+				};
+
+				(broadcast.subscribe_track(&track), start, end)
+			}
+			ietf::FetchType::RelativeJoining { subscribe_id, .. }
+			| ietf::FetchType::AbsoluteJoining { subscribe_id, .. } => {
+				let track = match self.subscribes.lock().get(&subscribe_id) {
+					Some(subscribed) => subscribed.track.clone(),
+					None => {
+						self.control.send(ietf::FetchError {
+							request_id,
+							error_code: 404,
+							reason_phrase: "Subscription not found".into(),
+						})?;
+						self.control.request_done();
+						return Ok(());
+					}
+				};
+
+				// A joining fetch ends with the largest group, which may also be served by the subscription.
+				let largest = track.status().latest.unwrap_or(0);
+				let start = match msg.fetch_type {
+					ietf::FetchType::RelativeJoining { group_offset, .. } => largest.saturating_sub(group_offset),
+					ietf::FetchType::AbsoluteJoining { start_group, .. } => start_group,
+					ietf::FetchType::Standalone { .. } => unreachable!(),
+				};
+
+				tracing::debug!(%request_id, %subscribe_id, track = %track.info.name, %start, %largest, "joining fetch");
+
+				(track, (start, 0), (largest, 0))
+			}
+		};
+
+		let status = track.status();
+
+		let (tx, rx) = oneshot::channel();
+		self.fetches.lock().insert(request_id, tx);
+
+		self.control.send(ietf::FetchOk {
+			request_id,
+			end_of_track: status.ended && status.latest.unwrap_or(0) <= end.0,
+			// We don't track the number of objects in each group.
+			largest: (status.latest.unwrap_or(0), 0),
+		})?;
+
+		let session = self.session.clone();
+		let control = self.control.clone();
+		let fetches = self.fetches.clone();
+
+		web_async::spawn(async move {
+			if let Err(err) = Self::run_fetch(session, &control, request_id, &track, start, end, rx).await {
+				tracing::debug!(%err, %request_id, "fetch error");
+			}
+
+			fetches.lock().remove(&request_id);
+			control.request_done();
+		});
+
+		Ok(())
+	}
 
-			loop {
-				let chunk = tokio::select! {
+	async fn run_fetch(
+		session: S,
+		control: &Control,
+		request_id: u64,
+		track: &TrackConsumer,
+		start: (u64, u64),
+		end: (u64, u64),
+		mut cancel: oneshot::Receiver<()>,
+	) -> Result<(), Error> {
+		let mut stream = session
+			.open_uni()
+			.await
+			.map_err(|err| Error::Transport(Arc::new(err)))?;
+		stream.set_priority(stream_priority(track.info.priority, start.0));
+
+		let version = control.version();
+		let mut stream = Writer::new(stream, control.stats().cloned());
+		stream.encode(&ietf::FetchHeader::STREAM_TYPE).await?;
+
+		let mut buf = BytesMut::new();
+		ietf::FetchHeader { request_id }.encode(&mut buf);
+		stream.write_all(&mut buf).await?;
+
+		// We can only serve the groups that are still cached.
+		for mut group in track.groups(start.0..=end.0) {
+			let sequence = group.info.sequence;
+
+			for id in 0.. {
+				// An end object of zero means the entire group.
+				if sequence == end.0 && end.1 > 0 && id >= end.1 {
+					break;
+				}
+
+				let frame = tokio::select! {
 					biased;
+					_ = &mut cancel => return Ok(()),
 					_ = stream.closed() => return Err(Error::Cancel),
-					chunk = frame.read_chunk() => chunk,
+					frame = group.next_frame() => frame,
 				};
 
-				match chunk? {
-					Some(mut chunk) => stream.write_all(&mut chunk).await?,
+				let frame = match frame? {
+					Some(frame) => frame,
 					None => break,
+				};
+
+				if sequence == start.0 && id < start.1 {
+					continue;
 				}
-			}
 
-			tracing::trace!(size = %frame.info.size, "wrote frame");
+				let header = ietf::FetchObject {
+					group_id: sequence,
+					subgroup_id: 0,
+					object_id: id,
+					publisher_priority: track.info.priority,
+					size: frame.info.size,
+					status: ietf::Frame::STATUS_NORMAL,
+					extensions: frame.info.extensions.clone(),
+				};
+
+				header.encode(&mut buf, version);
+				stream.write_all(&mut buf).await?;
+
+				Self::run_frame(&mut stream, frame).await?;
+			}
 		}
 
 		stream.finish().await?;
 
-		tracing::debug!(sequence = %msg.group_id, "finished group");
+		tracing::debug!(%request_id, "finished fetch");
+
+		Ok(())
+	}
+
+	pub fn recv_fetch_cancel(&mut self, msg: ietf::FetchCancel) -> Result<(), Error> {
+		if let Some(tx) = self.fetches.lock().remove(&msg.request_id) {
+			let _ = tx.send(());
+		}
+		Ok(())
+	}
+
+	pub fn recv_track_status_request(&mut self, msg: ietf::TrackStatusRequest<'_>) -> Result<(), Error> {
+		let request_id = msg.request_id;
+		let track_namespace = msg.track_namespace.to_owned();
+
+		// Draft-07 track status requests don't count towards the limit.
+		let limited = self.control.version().request_ids();
+		if limited {
+			self.control.recv_request(request_id)?;
+		}
+
+		let track_name = msg.track_name.to_string();
+
+		tracing::debug!(broadcast = %self.origin.absolute(&track_namespace), track = %track_name, "track status");
+
+		let broadcast = self.consume_broadcast(&track_namespace);
+		let control = self.control.clone();
+
+		// The answer may need to be fetched from upstream, so don't block the control stream.
+		web_async::spawn(async move {
+			let status = match broadcast {
+				Some(broadcast) => broadcast.track_status(&track_name).await,
+				None => Err(Error::NotFound),
+			};
+
+			let (status_code, last_group_id) = match status {
+				Ok(TrackStatus { latest: None, .. }) => (ietf::TrackStatus::STATUS_NOT_BEGUN, 0),
+				Ok(TrackStatus {
+					latest: Some(latest),
+					ended: true,
+				}) => (ietf::TrackStatus::STATUS_ENDED, latest),
+				Ok(TrackStatus {
+					latest: Some(latest), ..
+				}) => (ietf::TrackStatus::STATUS_IN_PROGRESS, latest),
+				Err(_) => (ietf::TrackStatus::STATUS_NOT_FOUND, 0),
+			};
+
+			let msg = ietf::TrackStatus {
+				request_id,
+				track_namespace,
+				track_name: track_name.into(),
+				status_code,
+				last_group_id,
+				last_object_id: 0,
+			};
+
+			control.send(msg).ok();
+
+			if limited {
+				control.request_done();
+			}
+		});
+
+		Ok(())
+	}
+
+	pub fn recv_subscribe_update(&mut self, msg: ietf::SubscribeUpdate) -> Result<(), Error> {
+		let subscribes = self.subscribes.lock();
+
+		// The subscription may have already ended, which is fine.
+		let Some(subscribed) = subscribes.get(&msg.subscribe_id) else {
+			return Ok(());
+		};
+
+		let update = Requested {
+			priority: msg.subscriber_priority,
+			start: Some(msg.start),
+			end: msg.end,
+		};
+
+		tracing::debug!(subscribe = %msg.subscribe_id, ?update, "subscribe update");
+
+		if !subscribed.requested.borrow().narrows(&update) {
+			return Err(Error::ProtocolViolation);
+		}
+
+		subscribed.requested.send_replace(update);
 
 		Ok(())
 	}
 
 	pub fn recv_unsubscribe(&mut self, msg: ietf::Unsubscribe) -> Result<(), Error> {
 		let mut subscribes = self.subscribes.lock();
-		if let Some(tx) = subscribes.remove(&msg.subscribe_id) {
-			let _ = tx.send(());
+		if let Some(subscribed) = subscribes.remove(&msg.subscribe_id) {
+			let _ = subscribed.cancel.send(());
 		}
 		Ok(())
 	}
@@ -284,8 +870,57 @@ impl<S: web_transport_trait::Session> Publisher<S> {
 		Ok(())
 	}
 
-	pub fn recv_subscribe_announces(&mut self, _msg: ietf::SubscribeAnnounces<'_>) -> Result<(), Error> {
-		// We don't care, we're sending all announcements anyway.
+	pub fn recv_announce_error(&mut self, msg: ietf::AnnounceError<'_>) -> Result<(), Error> {
+		// Draft-11 only identifies the announcement by Request ID.
+		let namespace = match self.control.version().request_ids() {
+			true => self.announces.lock().remove(&msg.request_id),
+			false => Some(msg.track_namespace.to_owned()),
+		};
+
+		// The peer doesn't want our broadcast, which is fine.
+		tracing::debug!(request_id = %msg.request_id, namespace = ?namespace, code = %msg.error_code, reason = %msg.reason_phrase, "announce rejected");
+
+		if let Some(namespace) = namespace {
+			self.rejected.reject(namespace);
+		}
+
+		Ok(())
+	}
+
+	pub fn recv_announce_cancel(&mut self, msg: ietf::AnnounceCancel<'_>) -> Result<(), Error> {
+		let namespace = msg.track_namespace.to_owned();
+		tracing::debug!(%namespace, code = %msg.error_code, reason = %msg.reason_phrase, "announce cancelled");
+
+		self.announces.lock().retain(|_, announced| *announced != namespace);
+		self.rejected.reject(namespace);
+
+		Ok(())
+	}
+
+	// Returns the broadcast unless the peer rejected or cancelled its announcement.
+	fn consume_broadcast(&self, path: &Path<'_>) -> Option<BroadcastConsumer> {
+		if !self.rejected.allows(path) {
+			return None;
+		}
+
+		self.origin.consume_broadcast(path)
+	}
+
+	pub fn recv_subscribe_announces(&mut self, msg: ietf::SubscribeAnnounces<'_>) -> Result<(), Error> {
+		if self.control.version().request_ids() {
+			self.control.recv_request(msg.request_id)?;
+		}
+
+		// We're sending all announcements anyway, so just acknowledge the request.
+		self.control.send(ietf::SubscribeAnnouncesOk {
+			request_id: msg.request_id,
+			namespace: msg.namespace,
+		})?;
+
+		if self.control.version().request_ids() {
+			self.control.request_done();
+		}
+
 		Ok(())
 	}
 
@@ -304,3 +939,60 @@ fn stream_priority(track_priority: u8, group_sequence: u64) -> i32 {
 	let sequence = 0xFFFFFF - (group_sequence as u32 & 0xFFFFFF);
 	((track_priority as i32) << 24) | sequence as i32
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn requested(start: Option<(u64, u64)>, end: Option<(u64, u64)>) -> Requested {
+		Requested {
+			priority: 0,
+			start,
+			end,
+		}
+	}
+
+	#[test]
+	fn test_update_narrows() {
+		let current = requested(Some((5, 0)), Some((10, 0)));
+
+		assert!(current.narrows(&requested(Some((6, 0)), Some((8, 2)))));
+		assert!(current.narrows(&requested(Some((5, 0)), Some((10, 0)))));
+
+		// Updates can't widen the range in either direction.
+		assert!(!current.narrows(&requested(Some((4, 0)), Some((10, 0)))));
+		assert!(!current.narrows(&requested(Some((5, 0)), Some((11, 0)))));
+		assert!(!current.narrows(&requested(Some((5, 0)), None)));
+
+		// An end object of zero means the entire group.
+		let partial = requested(Some((5, 0)), Some((10, 3)));
+		assert!(!partial.narrows(&requested(Some((5, 0)), Some((10, 0)))));
+
+		// An open-ended subscription can be given an end, but not before the start.
+		let open = requested(None, None);
+		assert!(open.narrows(&requested(Some((3, 0)), Some((7, 0)))));
+		assert!(!open.narrows(&requested(Some((8, 0)), Some((7, 0)))));
+	}
+
+	#[test]
+	fn test_announce_filter() {
+		let filter = AnnounceFilter::default();
+		let demo = Path::new("demo").to_owned();
+		assert!(filter.allows(&demo));
+
+		// A rejected broadcast isn't served to the peer, even though it's still in the origin.
+		filter.reject(demo.clone());
+		assert!(!filter.allows(&demo));
+		assert!(filter.allows(&Path::new("other")));
+
+		// Until it's announced again.
+		filter.announce(&demo);
+		assert!(filter.allows(&demo));
+
+		// A rejected broadcast is not unannounced to the peer.
+		filter.reject(demo.clone());
+		assert!(filter.unannounce(&demo));
+		assert!(!filter.unannounce(&demo));
+		assert!(filter.allows(&demo));
+	}
+}
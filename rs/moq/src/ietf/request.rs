@@ -0,0 +1,47 @@
+//! IETF moq-transport request flow control messages
+
+use crate::coding::*;
+
+use super::{Message, MessageId, Version};
+
+/// MaxRequestId message (0x15), named MAX_SUBSCRIBE_ID in draft-07.
+///
+/// Increases the maximum ID the peer may use for new requests.
+#[derive(Clone, Debug)]
+pub struct MaxRequestId {
+	pub request_id: u64,
+}
+
+impl Message for MaxRequestId {
+	const ID: MessageId = MessageId::MaxRequestId;
+
+	fn encode<W: bytes::BufMut>(&self, w: &mut W, _version: Version) {
+		self.request_id.encode(w);
+	}
+
+	fn decode<R: bytes::Buf>(r: &mut R, _version: Version) -> Result<Self, DecodeError> {
+		let request_id = u64::decode(r)?;
+		Ok(Self { request_id })
+	}
+}
+
+/// RequestsBlocked message (0x1a)
+///
+/// Sent when the peer would like to make a request but has reached the maximum ID.
+#[derive(Clone, Debug)]
+pub struct RequestsBlocked {
+	pub max_request_id: u64,
+}
+
+impl Message for RequestsBlocked {
+	const ID: MessageId = MessageId::RequestsBlocked;
+
+	fn encode<W: bytes::BufMut>(&self, w: &mut W, _version: Version) {
+		self.max_request_id.encode(w);
+	}
+
+	fn decode<R: bytes::Buf>(r: &mut R, _version: Version) -> Result<Self, DecodeError> {
+		let max_request_id = u64::decode(r)?;
+		Ok(Self { max_request_id })
+	}
+}
@@ -1,11 +1,39 @@
 use crate::coding::{Decode, DecodeError, Encode, Extension};
 
+/// The ROLE setup extension, declaring whether an endpoint publishes, subscribes, or both.
+///
+/// A peer that omits the extension is treated as [Role::Both].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
 pub enum Role {
 	Publisher,
 	Subscriber,
+	#[default]
 	Both,
 }
 
+impl Role {
+	/// The role to declare given whether we have anything to publish and somewhere to put subscriptions.
+	///
+	/// An endpoint with neither still declares [Role::Both], as there's no value for nothing.
+	pub fn new(publish: bool, subscribe: bool) -> Self {
+		match (publish, subscribe) {
+			(true, false) => Role::Publisher,
+			(false, true) => Role::Subscriber,
+			_ => Role::Both,
+		}
+	}
+
+	/// Returns true if the endpoint will accept subscriptions.
+	pub fn is_publisher(&self) -> bool {
+		matches!(self, Role::Publisher | Role::Both)
+	}
+
+	/// Returns true if the endpoint will subscribe and accept announcements.
+	pub fn is_subscriber(&self) -> bool {
+		matches!(self, Role::Subscriber | Role::Both)
+	}
+}
+
 impl Encode for Role {
 	fn encode<W: bytes::BufMut>(&self, w: &mut W) {
 		let v: u64 = match self {
@@ -34,3 +62,27 @@ impl Extension for Role {
 		0x00
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::coding::Extensions;
+
+	#[test]
+	fn test_role() {
+		assert_eq!(Role::new(true, false), Role::Publisher);
+		assert_eq!(Role::new(false, true), Role::Subscriber);
+		assert_eq!(Role::new(true, true), Role::Both);
+		assert_eq!(Role::new(false, false), Role::Both);
+
+		assert!(Role::Publisher.is_publisher());
+		assert!(!Role::Publisher.is_subscriber());
+		assert!(!Role::Subscriber.is_publisher());
+
+		let mut extensions = Extensions::default();
+		assert_eq!(extensions.get::<Role>().unwrap(), None);
+
+		extensions.set(Role::Subscriber);
+		assert_eq!(extensions.get::<Role>().unwrap(), Some(Role::Subscriber));
+	}
+}
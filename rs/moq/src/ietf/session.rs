@@ -1,19 +1,34 @@
 use crate::{
-	coding::{Reader, Stream, Writer},
-	ietf::{self, Control, MessageId},
+	coding::{Decode, Reader, Stream, Writer},
+	ietf::{self, decode_message, Control, MessageId, RequestLimits},
+	session::SessionOptions,
 	Error, OriginConsumer, OriginProducer,
 };
 
+use tokio::sync::{mpsc, watch};
+
 use super::{Publisher, Subscriber};
 
+#[allow(clippy::too_many_arguments)]
 pub(crate) async fn start<S: web_transport_trait::Session + Sync>(
 	session: S,
 	setup: Stream<S>,
+	version: ietf::Version,
+	client: bool,
+	limits: RequestLimits,
+	mut options: SessionOptions,
 	publish: Option<OriginConsumer>,
 	subscribe: Option<OriginProducer>,
-) -> Result<(), Error> {
+) -> Result<watch::Receiver<Option<String>>, Error> {
+	// Only check the transport if it wasn't already ruled out, as some panic instead of returning zero.
+	options.datagrams = options.datagrams && session.max_datagram_size() > 0;
+
+	let (tx, rx) = mpsc::unbounded_channel();
+	let control = Control::new(tx, version, client, limits, options);
+	let goaway = control.goaway();
+
 	web_async::spawn(async move {
-		match run(session.clone(), setup, publish, subscribe).await {
+		match run(session.clone(), setup, control, rx, publish, subscribe).await {
 			Err(Error::Transport(_)) => {
 				tracing::info!("session terminated");
 				session.close(1, "");
@@ -29,89 +44,72 @@ pub(crate) async fn start<S: web_transport_trait::Session + Sync>(
 		}
 	});
 
-	Ok(())
+	Ok(goaway)
 }
 
 async fn run<S: web_transport_trait::Session + Sync>(
 	session: S,
 	setup: Stream<S>,
+	control: Control,
+	rx: mpsc::UnboundedReceiver<Vec<u8>>,
 	publish: Option<OriginConsumer>,
 	subscribe: Option<OriginProducer>,
 ) -> Result<(), Error> {
-	let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
-	let control = Control::new(tx);
 	let publisher = Publisher::new(session.clone(), publish, control.clone());
-	let subscriber = Subscriber::new(session.clone(), subscribe, control);
+	let subscriber = Subscriber::new(session.clone(), subscribe, control.clone());
 
 	tokio::select! {
 		res = subscriber.clone().run() => res,
 		res = publisher.clone().run() => res,
-		res = run_control_read(setup.reader, publisher, subscriber) => res,
+		res = run_control_read(setup.reader, control, publisher, subscriber) => res,
 		res = run_control_write::<S>(setup.writer, rx) => res,
 	}
 }
 
 async fn run_control_read<S: web_transport_trait::Session + Sync>(
-	mut control: Reader<S::RecvStream>,
+	mut stream: Reader<S::RecvStream>,
+	control: Control,
 	mut publisher: Publisher<S>,
 	mut subscriber: Subscriber<S>,
 ) -> Result<(), Error> {
+	let version = control.version();
+
 	loop {
-		let id: MessageId = control.decode().await?;
+		let (id, payload) = stream
+			.decode_with(|r| {
+				let id = MessageId::decode(r)?;
+				let payload = ietf::decode_payload(r, version)?;
+				Ok((id, payload))
+			})
+			.await?;
+
 		match id {
-			MessageId::Subscribe => {
-				let msg: ietf::Subscribe = control.decode().await?;
-				publisher.recv_subscribe(msg)?;
-			}
-			MessageId::SubscribeUpdate => return Err(Error::Unsupported),
-			MessageId::SubscribeOk => {
-				let msg: ietf::SubscribeOk = control.decode().await?;
-				subscriber.recv_subscribe_ok(msg)?;
-			}
-			MessageId::SubscribeError => {
-				let msg: ietf::SubscribeError = control.decode().await?;
-				subscriber.recv_subscribe_error(msg)?;
-			}
-			MessageId::Announce => {
-				let msg: ietf::Announce = control.decode().await?;
-				subscriber.recv_announce(msg)?;
-			}
-			MessageId::AnnounceOk => {
-				let msg: ietf::AnnounceOk = control.decode().await?;
-				publisher.recv_announce_ok(msg)?;
-			}
-			MessageId::AnnounceError => return Err(Error::Unsupported),
-			MessageId::Unannounce => {
-				let msg: ietf::Unannounce = control.decode().await?;
-				subscriber.recv_unannounce(msg)?;
-			}
-			MessageId::Unsubscribe => {
-				let msg: ietf::Unsubscribe = control.decode().await?;
-				publisher.recv_unsubscribe(msg)?;
-			}
-			MessageId::SubscribeDone => {
-				let msg: ietf::SubscribeDone = control.decode().await?;
-				subscriber.recv_subscribe_done(msg)?;
-			}
-			MessageId::AnnounceCancel => return Err(Error::Unsupported),
-			MessageId::TrackStatusRequest => return Err(Error::Unsupported),
-			MessageId::TrackStatus => return Err(Error::Unsupported),
-			MessageId::GoAway => return Err(Error::Unsupported),
-			MessageId::SubscribeAnnounces => {
-				let msg: ietf::SubscribeAnnounces = control.decode().await?;
-				publisher.recv_subscribe_announces(msg)?;
-			}
+			MessageId::Subscribe => publisher.recv_subscribe(decode_message(payload, version)?)?,
+			MessageId::SubscribeUpdate => publisher.recv_subscribe_update(decode_message(payload, version)?)?,
+			MessageId::SubscribeOk => subscriber.recv_subscribe_ok(decode_message(payload, version)?)?,
+			MessageId::SubscribeError => subscriber.recv_subscribe_error(decode_message(payload, version)?)?,
+			MessageId::Announce => subscriber.recv_announce(decode_message(payload, version)?)?,
+			MessageId::AnnounceOk => publisher.recv_announce_ok(decode_message(payload, version)?)?,
+			MessageId::AnnounceError => publisher.recv_announce_error(decode_message(payload, version)?)?,
+			MessageId::Unannounce => subscriber.recv_unannounce(decode_message(payload, version)?)?,
+			MessageId::Unsubscribe => publisher.recv_unsubscribe(decode_message(payload, version)?)?,
+			MessageId::SubscribeDone => subscriber.recv_subscribe_done(decode_message(payload, version)?)?,
+			MessageId::AnnounceCancel => publisher.recv_announce_cancel(decode_message(payload, version)?)?,
+			MessageId::TrackStatusRequest => publisher.recv_track_status_request(decode_message(payload, version)?)?,
+			MessageId::TrackStatus => subscriber.recv_track_status(decode_message(payload, version)?)?,
+			MessageId::GoAway => control.recv_goaway(decode_message(payload, version)?)?,
+			MessageId::SubscribeAnnounces => publisher.recv_subscribe_announces(decode_message(payload, version)?)?,
 			MessageId::SubscribeAnnouncesOk => return Err(Error::Unsupported),
 			MessageId::SubscribeAnnouncesError => return Err(Error::Unsupported),
 			MessageId::UnsubscribeAnnounces => {
-				let msg: ietf::UnsubscribeAnnounces = control.decode().await?;
-				publisher.recv_unsubscribe_announces(msg)?;
+				publisher.recv_unsubscribe_announces(decode_message(payload, version)?)?
 			}
-			MessageId::MaxSubscribeId => return Err(Error::Unsupported),
-			MessageId::Fetch => return Err(Error::Unsupported),
-			MessageId::FetchCancel => return Err(Error::Unsupported),
-			MessageId::FetchOk => return Err(Error::Unsupported),
-			MessageId::FetchError => return Err(Error::Unsupported),
+			MessageId::MaxRequestId => control.recv_max_request_id(decode_message(payload, version)?)?,
+			MessageId::RequestsBlocked => control.recv_requests_blocked(decode_message(payload, version)?)?,
+			MessageId::Fetch => publisher.recv_fetch(decode_message(payload, version)?)?,
+			MessageId::FetchCancel => publisher.recv_fetch_cancel(decode_message(payload, version)?)?,
+			MessageId::FetchOk => subscriber.recv_fetch_ok(decode_message(payload, version)?)?,
+			MessageId::FetchError => subscriber.recv_fetch_error(decode_message(payload, version)?)?,
 			MessageId::ClientSetup | MessageId::ServerSetup => return Err(Error::UnexpectedMessage),
 		}
 	}
@@ -119,7 +117,7 @@ async fn run_control_read<S: web_transport_trait::Session + Sync>(
 
 async fn run_control_write<S: web_transport_trait::Session + Sync>(
 	mut control: Writer<S::SendStream>,
-	mut rx: tokio::sync::mpsc::UnboundedReceiver<Vec<u8>>,
+	mut rx: mpsc::UnboundedReceiver<Vec<u8>>,
 ) -> Result<(), Error> {
 	while let Some(msg) = rx.recv().await {
 		let mut buf = std::io::Cursor::new(msg);
@@ -0,0 +1,170 @@
+//! IETF moq-transport-11 setup messages
+//!
+//! Draft-07 shares its setup messages with moq-lite, see [crate::lite::ClientSetup].
+
+use crate::coding::{self, *};
+
+use super::{
+	util::{decode_parameters, encode_parameters},
+	Message, MessageId, Version,
+};
+
+/// ClientSetup message (0x20)
+#[derive(Clone, Debug)]
+pub struct ClientSetup {
+	/// The list of supported versions in preferred order.
+	pub versions: Versions,
+
+	/// Setup parameters.
+	pub parameters: Extensions,
+}
+
+impl Message for ClientSetup {
+	const ID: MessageId = MessageId::ClientSetup;
+
+	fn encode<W: bytes::BufMut>(&self, w: &mut W, version: Version) {
+		self.versions.encode(w);
+		encode_parameters(w, &self.parameters, version);
+	}
+
+	fn decode<R: bytes::Buf>(r: &mut R, version: Version) -> Result<Self, DecodeError> {
+		let versions = Versions::decode(r)?;
+		let parameters = decode_parameters(r, version)?;
+
+		Ok(Self { versions, parameters })
+	}
+}
+
+/// ServerSetup message (0x21)
+#[derive(Clone, Debug)]
+pub struct ServerSetup {
+	/// The selected version.
+	pub version: coding::Version,
+
+	/// Setup parameters.
+	pub parameters: Extensions,
+}
+
+impl Message for ServerSetup {
+	const ID: MessageId = MessageId::ServerSetup;
+
+	fn encode<W: bytes::BufMut>(&self, w: &mut W, version: Version) {
+		self.version.encode(w);
+		encode_parameters(w, &self.parameters, version);
+	}
+
+	fn decode<R: bytes::Buf>(r: &mut R, version: Version) -> Result<Self, DecodeError> {
+		let selected = coding::Version::decode(r)?;
+		let parameters = decode_parameters(r, version)?;
+
+		Ok(Self {
+			version: selected,
+			parameters,
+		})
+	}
+}
+
+/// The MAX_REQUEST_ID setup parameter (MAX_SUBSCRIBE_ID in draft-07).
+///
+/// The peer may not make a request with an ID greater than or equal to this value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MaxRequests(pub u64);
+
+impl MaxRequests {
+	/// The number of concurrent requests we allow the peer to make by default.
+	pub const CONCURRENT: u64 = 100;
+
+	/// Allow the peer to make the given number of requests before any are completed.
+	///
+	/// Draft-11 endpoints only use every other ID, so the limit is scaled accordingly.
+	pub fn concurrent(version: Version, count: u64) -> Self {
+		Self(count * version.request_step())
+	}
+}
+
+impl Encode for MaxRequests {
+	fn encode<W: bytes::BufMut>(&self, w: &mut W) {
+		self.0.encode(w);
+	}
+}
+
+impl Decode for MaxRequests {
+	fn decode<R: bytes::Buf>(r: &mut R) -> Result<Self, DecodeError> {
+		Ok(Self(u64::decode(r)?))
+	}
+}
+
+impl Extension for MaxRequests {
+	fn id() -> u64 {
+		0x02
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use crate::ietf::{decode_message, decode_payload, encode_message, Role};
+
+	#[test]
+	fn test_client_setup_draft11() {
+		let mut parameters = Extensions::default();
+		parameters.set(Role::Both);
+		parameters.set(MaxRequests(100));
+
+		let msg = ClientSetup {
+			versions: [coding::Version::IETF_11].into(),
+			parameters,
+		};
+
+		let mut buf = Vec::new();
+		encode_message(&msg, &mut buf, Version::Draft11).unwrap();
+
+		// The ID is 0x20, followed by a 16-bit length.
+		assert_eq!(buf[0], 0x20);
+		assert_eq!(u16::from_be_bytes([buf[1], buf[2]]) as usize, buf.len() - 3);
+
+		let mut r = &buf[1..];
+		let payload = decode_payload(&mut r, Version::Draft11).unwrap();
+		let decoded: ClientSetup = decode_message(payload, Version::Draft11).unwrap();
+
+		assert_eq!(*decoded.versions, vec![coding::Version::IETF_11]);
+		assert_eq!(decoded.parameters.get::<MaxRequests>().unwrap(), Some(MaxRequests(100)));
+		assert!(decoded.parameters.contains::<Role>());
+	}
+
+	#[test]
+	fn test_parameters_draft11() {
+		#[rustfmt::skip]
+		let bytes = vec![
+			0x01, // version count
+			0xc0, 0x00, 0x00, 0x00, 0xff, 0x00, 0x00, 0x0b, // IETF_11
+			0x02, // parameter count
+			0x02, 0x40, 0x64, // MAX_REQUEST_ID = 100, even so no length
+			0x01, 0x02, 0x2f, 0x61, // PATH = "/a", odd so length prefixed
+		];
+
+		let decoded: ClientSetup = decode_message(bytes.into(), Version::Draft11).unwrap();
+		assert_eq!(decoded.parameters.get::<MaxRequests>().unwrap(), Some(MaxRequests(100)));
+	}
+
+	#[test]
+	fn test_server_setup_draft11() {
+		let mut parameters = Extensions::default();
+		parameters.set(MaxRequests::concurrent(Version::Draft11, MaxRequests::CONCURRENT));
+
+		let msg = ServerSetup {
+			version: coding::Version::IETF_11,
+			parameters,
+		};
+
+		let mut buf = Vec::new();
+		msg.encode(&mut buf, Version::Draft11);
+
+		let decoded: ServerSetup = decode_message(buf.into(), Version::Draft11).unwrap();
+		assert_eq!(decoded.version, coding::Version::IETF_11);
+
+		// The client uses every other ID, so the limit is doubled.
+		assert_eq!(decoded.parameters.get::<MaxRequests>().unwrap(), Some(MaxRequests(200)));
+	}
+}
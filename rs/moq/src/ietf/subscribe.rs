@@ -1,30 +1,108 @@
-//! IETF moq-transport-07 subscribe messages
+//! IETF moq-transport subscribe messages
 
 use std::borrow::Cow;
 
 use crate::{coding::*, Path};
 
-use super::util::{decode_namespace, encode_namespace};
+use super::{
+	util::{decode_namespace, decode_parameters, encode_namespace, encode_parameters},
+	Message, MessageId, Version,
+};
 
-// We only support Latest Group (0x1)
-const FILTER_TYPE: u8 = 0x01;
+const FILTER_LATEST_GROUP: u64 = 0x01;
+const FILTER_LATEST_OBJECT: u64 = 0x02;
+const FILTER_ABSOLUTE_START: u64 = 0x03;
+const FILTER_ABSOLUTE_RANGE: u64 = 0x04;
 
-// We only support Group Order descending (0x02)
+// We always deliver groups in descending order (0x02), but accept any requested order.
 const GROUP_ORDER: u8 = 0x02;
 
+/// The range of objects requested by a [Subscribe].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SubscribeFilter {
+	/// Start at the current group in draft-07, or the next group in draft-11 (Next Group Start).
+	#[default]
+	LatestGroup,
+
+	/// Start at the next object.
+	LatestObject,
+
+	/// Start at the given group and object ID, with no end.
+	AbsoluteStart { start: (u64, u64) },
+
+	/// Start at the given group and object ID, ending with the given group.
+	///
+	/// The end object ID is plus one, or zero for the entire group.
+	/// Draft-11 only encodes the end group, always requesting the entire group.
+	AbsoluteRange { start: (u64, u64), end: (u64, u64) },
+}
+
+impl SubscribeFilter {
+	fn encode<W: bytes::BufMut>(&self, w: &mut W, version: Version) {
+		match self {
+			Self::LatestGroup => FILTER_LATEST_GROUP.encode(w),
+			Self::LatestObject => FILTER_LATEST_OBJECT.encode(w),
+			Self::AbsoluteStart { start } => {
+				FILTER_ABSOLUTE_START.encode(w);
+				start.0.encode(w);
+				start.1.encode(w);
+			}
+			Self::AbsoluteRange { start, end } => {
+				FILTER_ABSOLUTE_RANGE.encode(w);
+				start.0.encode(w);
+				start.1.encode(w);
+				end.0.encode(w);
+
+				if version == Version::Draft07 {
+					end.1.encode(w);
+				}
+			}
+		}
+	}
+
+	fn decode<R: bytes::Buf>(r: &mut R, version: Version) -> Result<Self, DecodeError> {
+		Ok(match u64::decode(r)? {
+			FILTER_LATEST_GROUP => Self::LatestGroup,
+			FILTER_LATEST_OBJECT => Self::LatestObject,
+			FILTER_ABSOLUTE_START => Self::AbsoluteStart {
+				start: (u64::decode(r)?, u64::decode(r)?),
+			},
+			FILTER_ABSOLUTE_RANGE => {
+				let start = (u64::decode(r)?, u64::decode(r)?);
+				let end = match version {
+					Version::Draft07 => (u64::decode(r)?, u64::decode(r)?),
+					Version::Draft11 => (u64::decode(r)?, 0),
+				};
+
+				if end.0 < start.0 {
+					return Err(DecodeError::InvalidValue);
+				}
+
+				Self::AbsoluteRange { start, end }
+			}
+			_ => return Err(DecodeError::InvalidValue),
+		})
+	}
+}
+
 /// Subscribe message (0x03)
 /// Sent by the subscriber to request all future objects for the given track.
 #[derive(Clone, Debug)]
 pub struct Subscribe<'a> {
+	/// The Subscribe ID in draft-07, or the Request ID in draft-11.
 	pub subscribe_id: u64,
 	pub track_alias: u64,
 	pub track_namespace: Path<'a>,
 	pub track_name: Cow<'a, str>,
 	pub subscriber_priority: u8,
+	pub filter: SubscribeFilter,
+	pub parameters: Extensions,
 }
 
 impl<'a> Message for Subscribe<'a> {
-	fn decode<R: bytes::Buf>(r: &mut R) -> Result<Self, DecodeError> {
+	const ID: MessageId = MessageId::Subscribe;
+
+	fn decode<R: bytes::Buf>(r: &mut R, version: Version) -> Result<Self, DecodeError> {
 		let subscribe_id = u64::decode(r)?;
 		let track_alias = u64::decode(r)?;
 
@@ -35,19 +113,20 @@ impl<'a> Message for Subscribe<'a> {
 		let subscriber_priority = u8::decode(r)?;
 
 		let group_order = u8::decode(r)?;
-		if group_order != 0 && group_order != GROUP_ORDER {
+		if group_order > GROUP_ORDER {
 			return Err(DecodeError::InvalidValue);
 		}
 
-		let filter_type = u8::decode(r)?;
-		if filter_type != FILTER_TYPE {
-			return Err(DecodeError::InvalidValue);
+		if version == Version::Draft11 {
+			// We don't support paused subscriptions.
+			let forward = u8::decode(r)?;
+			if forward != 1 {
+				return Err(DecodeError::InvalidValue);
+			}
 		}
 
-		let num_params = u8::decode(r)?;
-		if num_params != 0 {
-			return Err(DecodeError::InvalidValue);
-		}
+		let filter = SubscribeFilter::decode(r, version)?;
+		let parameters = decode_parameters(r, version)?;
 
 		Ok(Self {
 			subscribe_id,
@@ -55,18 +134,25 @@ impl<'a> Message for Subscribe<'a> {
 			track_namespace,
 			track_name,
 			subscriber_priority,
+			filter,
+			parameters,
 		})
 	}
 
-	fn encode<W: bytes::BufMut>(&self, w: &mut W) {
+	fn encode<W: bytes::BufMut>(&self, w: &mut W, version: Version) {
 		self.subscribe_id.encode(w);
 		self.track_alias.encode(w);
 		encode_namespace(w, &self.track_namespace);
 		self.track_name.encode(w);
 		self.subscriber_priority.encode(w);
 		GROUP_ORDER.encode(w);
-		FILTER_TYPE.encode(w);
-		0u8.encode(w); // no parameters
+
+		if version == Version::Draft11 {
+			1u8.encode(w); // forward
+		}
+
+		self.filter.encode(w, version);
+		encode_parameters(w, &self.parameters, version);
 	}
 }
 
@@ -79,7 +165,9 @@ pub struct SubscribeOk {
 }
 
 impl Message for SubscribeOk {
-	fn encode<W: bytes::BufMut>(&self, w: &mut W) {
+	const ID: MessageId = MessageId::SubscribeOk;
+
+	fn encode<W: bytes::BufMut>(&self, w: &mut W, _version: Version) {
 		self.subscribe_id.encode(w);
 		0u8.encode(w); // expires = 0
 		GROUP_ORDER.encode(w);
@@ -95,7 +183,7 @@ impl Message for SubscribeOk {
 		0u8.encode(w); // no parameters
 	}
 
-	fn decode<R: bytes::Buf>(r: &mut R) -> Result<Self, DecodeError> {
+	fn decode<R: bytes::Buf>(r: &mut R, version: Version) -> Result<Self, DecodeError> {
 		let subscribe_id = u64::decode(r)?;
 
 		let expires = u64::decode(r)?;
@@ -115,10 +203,8 @@ impl Message for SubscribeOk {
 			return Err(DecodeError::InvalidValue);
 		}
 
-		let num_params = u8::decode(r)?;
-		if num_params != 0 {
-			return Err(DecodeError::InvalidValue);
-		}
+		// We don't support any parameters yet.
+		decode_parameters(r, version)?;
 
 		Ok(Self { subscribe_id, largest })
 	}
@@ -134,14 +220,16 @@ pub struct SubscribeError<'a> {
 }
 
 impl<'a> Message for SubscribeError<'a> {
-	fn encode<W: bytes::BufMut>(&self, w: &mut W) {
+	const ID: MessageId = MessageId::SubscribeError;
+
+	fn encode<W: bytes::BufMut>(&self, w: &mut W, _version: Version) {
 		self.subscribe_id.encode(w);
 		self.error_code.encode(w);
 		self.reason_phrase.encode(w);
 		self.track_alias.encode(w);
 	}
 
-	fn decode<R: bytes::Buf>(r: &mut R) -> Result<Self, DecodeError> {
+	fn decode<R: bytes::Buf>(r: &mut R, _version: Version) -> Result<Self, DecodeError> {
 		let subscribe_id = u64::decode(r)?;
 		let error_code = u64::decode(r)?;
 		let reason_phrase = Cow::<str>::decode(r)?;
@@ -156,6 +244,83 @@ impl<'a> Message for SubscribeError<'a> {
 	}
 }
 
+/// SubscribeUpdate message (0x02)
+/// Sent by the subscriber to narrow the range or change the priority of an existing subscription.
+#[derive(Clone, Debug)]
+pub struct SubscribeUpdate {
+	/// The Subscribe ID in draft-07, or the Request ID of the subscription in draft-11.
+	pub subscribe_id: u64,
+	/// The first group and object ID.
+	pub start: (u64, u64),
+	/// The last group and the last object ID plus one (zero for the entire group), or None if open-ended.
+	///
+	/// Draft-11 only encodes the end group, always requesting the entire group.
+	pub end: Option<(u64, u64)>,
+	pub subscriber_priority: u8,
+	pub parameters: Extensions,
+}
+
+impl Message for SubscribeUpdate {
+	const ID: MessageId = MessageId::SubscribeUpdate;
+
+	fn encode<W: bytes::BufMut>(&self, w: &mut W, version: Version) {
+		self.subscribe_id.encode(w);
+		self.start.0.encode(w);
+		self.start.1.encode(w);
+
+		// The end group is offset by one so zero can mean open-ended.
+		self.end.map_or(0, |(group, _)| group + 1).encode(w);
+
+		if version == Version::Draft07 {
+			self.end.map_or(0, |(_, object)| object).encode(w);
+		}
+
+		self.subscriber_priority.encode(w);
+
+		if version == Version::Draft11 {
+			1u8.encode(w); // forward
+		}
+
+		encode_parameters(w, &self.parameters, version);
+	}
+
+	fn decode<R: bytes::Buf>(r: &mut R, version: Version) -> Result<Self, DecodeError> {
+		let subscribe_id = u64::decode(r)?;
+		let start = (u64::decode(r)?, u64::decode(r)?);
+
+		let end_group = u64::decode(r)?;
+		let end_object = match version {
+			Version::Draft07 => u64::decode(r)?,
+			Version::Draft11 => 0,
+		};
+
+		let end = match end_group {
+			0 => None,
+			group => Some((group - 1, end_object)),
+		};
+
+		let subscriber_priority = u8::decode(r)?;
+
+		if version == Version::Draft11 {
+			// We don't support paused subscriptions.
+			let forward = u8::decode(r)?;
+			if forward != 1 {
+				return Err(DecodeError::InvalidValue);
+			}
+		}
+
+		let parameters = decode_parameters(r, version)?;
+
+		Ok(Self {
+			subscribe_id,
+			start,
+			end,
+			subscriber_priority,
+			parameters,
+		})
+	}
+}
+
 /// Unsubscribe message (0x0a)
 #[derive(Clone, Debug)]
 pub struct Unsubscribe {
@@ -163,11 +328,13 @@ pub struct Unsubscribe {
 }
 
 impl Message for Unsubscribe {
-	fn encode<W: bytes::BufMut>(&self, w: &mut W) {
+	const ID: MessageId = MessageId::Unsubscribe;
+
+	fn encode<W: bytes::BufMut>(&self, w: &mut W, _version: Version) {
 		self.subscribe_id.encode(w);
 	}
 
-	fn decode<R: bytes::Buf>(r: &mut R) -> Result<Self, DecodeError> {
+	fn decode<R: bytes::Buf>(r: &mut R, _version: Version) -> Result<Self, DecodeError> {
 		let subscribe_id = u64::decode(r)?;
 		Ok(Self { subscribe_id })
 	}
@@ -179,37 +346,67 @@ pub struct SubscribeDone<'a> {
 	pub subscribe_id: u64,
 	pub status_code: u64,
 	pub reason_phrase: Cow<'a, str>,
+	/// Only encoded for draft-07.
 	pub final_group_object: Option<(u64, u64)>,
+	/// The number of data streams opened for the subscription, only encoded for draft-11.
+	pub stream_count: u64,
+}
+
+impl<'a> SubscribeDone<'a> {
+	pub const STATUS_UNSUBSCRIBED: u64 = 0x00;
+	pub const STATUS_INTERNAL_ERROR: u64 = 0x01;
+	pub const STATUS_UNAUTHORIZED: u64 = 0x02;
+	pub const STATUS_TRACK_ENDED: u64 = 0x03;
+	pub const STATUS_SUBSCRIPTION_ENDED: u64 = 0x04;
+	pub const STATUS_GOING_AWAY: u64 = 0x05;
+	pub const STATUS_EXPIRED: u64 = 0x06;
 }
 
 impl<'a> Message for SubscribeDone<'a> {
-	fn encode<W: bytes::BufMut>(&self, w: &mut W) {
+	const ID: MessageId = MessageId::SubscribeDone;
+
+	fn encode<W: bytes::BufMut>(&self, w: &mut W, version: Version) {
 		self.subscribe_id.encode(w);
 		self.status_code.encode(w);
+
+		if version == Version::Draft11 {
+			self.stream_count.encode(w);
+		}
+
 		self.reason_phrase.encode(w);
 
-		if let Some((group, object)) = self.final_group_object {
-			1u8.encode(w); // content exists
-			group.encode(w);
-			object.encode(w);
-		} else {
-			0u8.encode(w); // no content
+		if version == Version::Draft07 {
+			if let Some((group, object)) = self.final_group_object {
+				1u8.encode(w); // content exists
+				group.encode(w);
+				object.encode(w);
+			} else {
+				0u8.encode(w); // no content
+			}
 		}
 	}
 
-	fn decode<R: bytes::Buf>(r: &mut R) -> Result<Self, DecodeError> {
+	fn decode<R: bytes::Buf>(r: &mut R, version: Version) -> Result<Self, DecodeError> {
 		let subscribe_id = u64::decode(r)?;
 		let status_code = u64::decode(r)?;
+
+		let stream_count = match version {
+			Version::Draft07 => 0,
+			Version::Draft11 => u64::decode(r)?,
+		};
+
 		let reason_phrase = Cow::<str>::decode(r)?;
 
 		let mut final_group_object = None;
-		let content_exists = u64::decode(r)?;
-		if content_exists == 1 {
-			let group = u64::decode(r)?;
-			let object = u64::decode(r)?;
-			final_group_object = Some((group, object));
-		} else if content_exists != 0 {
-			return Err(DecodeError::InvalidValue);
+		if version == Version::Draft07 {
+			let content_exists = u64::decode(r)?;
+			if content_exists == 1 {
+				let group = u64::decode(r)?;
+				let object = u64::decode(r)?;
+				final_group_object = Some((group, object));
+			} else if content_exists != 0 {
+				return Err(DecodeError::InvalidValue);
+			}
 		}
 
 		Ok(Self {
@@ -217,6 +414,7 @@ impl<'a> Message for SubscribeDone<'a> {
 			status_code,
 			reason_phrase,
 			final_group_object,
+			stream_count,
 		})
 	}
 }
@@ -227,14 +425,21 @@ mod tests {
 	use bytes::BytesMut;
 
 	fn encode_message<M: Message>(msg: &M) -> Vec<u8> {
+		encode_version(msg, Version::Draft07)
+	}
+
+	fn decode_message<M: Message>(bytes: &[u8]) -> Result<M, DecodeError> {
+		decode_version(bytes, Version::Draft07)
+	}
+
+	fn encode_version<M: Message>(msg: &M, version: Version) -> Vec<u8> {
 		let mut buf = BytesMut::new();
-		msg.encode(&mut buf);
+		msg.encode(&mut buf, version);
 		buf.to_vec()
 	}
 
-	fn decode_message<M: Message>(bytes: &[u8]) -> Result<M, DecodeError> {
-		let mut buf = bytes::Bytes::from(bytes.to_vec());
-		M::decode(&mut buf)
+	fn decode_version<M: Message>(bytes: &[u8], version: Version) -> Result<M, DecodeError> {
+		crate::ietf::decode_message(bytes.to_vec().into(), version)
 	}
 
 	#[test]
@@ -245,6 +450,8 @@ mod tests {
 			track_namespace: Path::new("test"),
 			track_name: "video".into(),
 			subscriber_priority: 128,
+			filter: SubscribeFilter::LatestGroup,
+			parameters: Default::default(),
 		};
 
 		let encoded = encode_message(&msg);
@@ -265,6 +472,8 @@ mod tests {
 			track_namespace: Path::new("conference/room123"),
 			track_name: "audio".into(),
 			subscriber_priority: 255,
+			filter: SubscribeFilter::LatestGroup,
+			parameters: Default::default(),
 		};
 
 		let encoded = encode_message(&msg);
@@ -336,6 +545,7 @@ mod tests {
 			status_code: 0,
 			reason_phrase: "complete".into(),
 			final_group_object: Some((5, 10)),
+			stream_count: 0,
 		};
 
 		let encoded = encode_message(&msg);
@@ -354,6 +564,7 @@ mod tests {
 			status_code: 1,
 			reason_phrase: "error".into(),
 			final_group_object: None,
+			stream_count: 0,
 		};
 
 		let encoded = encode_message(&msg);
@@ -395,4 +606,170 @@ mod tests {
 		let result: Result<SubscribeOk, _> = decode_message(&invalid_bytes);
 		assert!(result.is_err());
 	}
+
+	#[test]
+	fn test_subscribe_draft11() {
+		let msg = Subscribe {
+			subscribe_id: 2,
+			track_alias: 2,
+			track_namespace: Path::new("conference/room123"),
+			track_name: "audio".into(),
+			subscriber_priority: 1,
+			filter: SubscribeFilter::LatestObject,
+			parameters: Default::default(),
+		};
+
+		let encoded = encode_version(&msg, Version::Draft11);
+		let decoded: Subscribe = decode_version(&encoded, Version::Draft11).unwrap();
+
+		assert_eq!(decoded.subscribe_id, 2);
+		assert_eq!(decoded.track_namespace.as_str(), "conference/room123");
+		assert_eq!(decoded.track_name, "audio");
+
+		// Draft-11 has an extra forward field, so the encodings are not compatible.
+		assert!(decode_version::<Subscribe>(&encoded, Version::Draft07).is_err());
+	}
+
+	#[test]
+	fn test_subscribe_accepts_parameters() {
+		#[rustfmt::skip]
+		let bytes = vec![
+			0x02, // request_id
+			0x02, // track_alias
+			0x01, // namespace length
+			0x04, 0x74, 0x65, 0x73, 0x74, // "test"
+			0x05, 0x76, 0x69, 0x64, 0x65, 0x6f, // "video"
+			0x80, // subscriber_priority
+			0x01, // group_order = ascending
+			0x01, // forward
+			0x02, // filter_type = latest object
+			0x02, // num_params
+			0x02, 0x40, 0x64, // delivery timeout = 100, even so no length
+			0x03, 0x01, 0x00, // unknown odd parameter with a length
+		];
+
+		let decoded: Subscribe = decode_version(&bytes, Version::Draft11).unwrap();
+		assert_eq!(decoded.track_name, "video");
+		assert_eq!(decoded.filter, SubscribeFilter::LatestObject);
+		assert_eq!(decoded.parameters.0.get(&0x02), Some(&vec![0x40, 0x64]));
+	}
+
+	#[test]
+	fn test_subscribe_filters() {
+		let filters = [
+			SubscribeFilter::AbsoluteStart { start: (5, 1) },
+			SubscribeFilter::AbsoluteRange {
+				start: (5, 0),
+				end: (8, 0),
+			},
+		];
+
+		for filter in filters {
+			for version in [Version::Draft07, Version::Draft11] {
+				let msg = Subscribe {
+					subscribe_id: 1,
+					track_alias: 1,
+					track_namespace: Path::new("test"),
+					track_name: "video".into(),
+					subscriber_priority: 0,
+					filter,
+					parameters: Default::default(),
+				};
+
+				let encoded = encode_version(&msg, version);
+				let decoded: Subscribe = decode_version(&encoded, version).unwrap();
+				assert_eq!(decoded.filter, filter);
+			}
+		}
+
+		// Draft-11 doesn't encode the end object.
+		let msg = Subscribe {
+			subscribe_id: 1,
+			track_alias: 1,
+			track_namespace: Path::new("test"),
+			track_name: "video".into(),
+			subscriber_priority: 0,
+			filter: SubscribeFilter::AbsoluteRange {
+				start: (5, 0),
+				end: (8, 3),
+			},
+			parameters: Default::default(),
+		};
+
+		let decoded: Subscribe = decode_version(&encode_version(&msg, Version::Draft07), Version::Draft07).unwrap();
+		assert_eq!(decoded.filter, msg.filter);
+
+		let decoded: Subscribe = decode_version(&encode_version(&msg, Version::Draft11), Version::Draft11).unwrap();
+		assert_eq!(
+			decoded.filter,
+			SubscribeFilter::AbsoluteRange {
+				start: (5, 0),
+				end: (8, 0)
+			}
+		);
+	}
+
+	#[test]
+	fn test_subscribe_rejects_reversed_range() {
+		#[rustfmt::skip]
+		let invalid_bytes = vec![
+			0x01, // subscribe_id
+			0x02, // track_alias
+			0x01, // namespace length
+			0x04, 0x74, 0x65, 0x73, 0x74, // "test"
+			0x05, 0x76, 0x69, 0x64, 0x65, 0x6f, // "video"
+			0x80, // subscriber_priority
+			0x02, // group_order
+			0x04, // filter_type = absolute range
+			0x05, 0x00, // start
+			0x03, 0x00, // INVALID: end before start
+			0x00, // num_params
+		];
+
+		let result: Result<Subscribe, _> = decode_message(&invalid_bytes);
+		assert!(result.is_err());
+	}
+
+	#[test]
+	fn test_subscribe_done_draft11() {
+		let msg = SubscribeDone {
+			subscribe_id: 10,
+			status_code: SubscribeDone::STATUS_TRACK_ENDED,
+			reason_phrase: "complete".into(),
+			final_group_object: Some((5, 10)),
+			stream_count: 6,
+		};
+
+		let encoded = encode_version(&msg, Version::Draft11);
+		let decoded: SubscribeDone = decode_version(&encoded, Version::Draft11).unwrap();
+
+		assert_eq!(decoded.status_code, SubscribeDone::STATUS_TRACK_ENDED);
+		assert_eq!(decoded.reason_phrase, "complete");
+		assert_eq!(decoded.stream_count, 6);
+		assert_eq!(decoded.final_group_object, None);
+	}
+
+	#[test]
+	fn test_subscribe_update() {
+		let msg = SubscribeUpdate {
+			subscribe_id: 4,
+			start: (10, 2),
+			end: Some((12, 0)),
+			subscriber_priority: 7,
+			parameters: Default::default(),
+		};
+
+		let encoded = encode_version(&msg, Version::Draft11);
+		assert_eq!(encoded, vec![0x04, 0x0a, 0x02, 0x0d, 0x07, 0x01, 0x00]);
+
+		let decoded: SubscribeUpdate = decode_version(&encoded, Version::Draft11).unwrap();
+		assert_eq!(decoded.start, (10, 2));
+		assert_eq!(decoded.end, Some((12, 0)));
+		assert_eq!(decoded.subscriber_priority, 7);
+
+		let msg = SubscribeUpdate { end: None, ..msg };
+		let decoded: SubscribeUpdate = decode_message(&encode_message(&msg)).unwrap();
+		assert_eq!(decoded.subscribe_id, 4);
+		assert_eq!(decoded.end, None);
+	}
 }
@@ -1,73 +1,117 @@
-//! IETF moq-transport-07 subscribe announces messages
+//! IETF moq-transport subscribe announces messages
 
 use std::borrow::Cow;
 
 use crate::{coding::*, Path};
 
-use super::util::{decode_namespace, encode_namespace};
+use super::{
+	util::{decode_namespace, decode_parameters, encode_namespace},
+	Message, MessageId, Version,
+};
 
 /// SubscribeAnnounces message (0x11)
 #[derive(Clone, Debug)]
 pub struct SubscribeAnnounces<'a> {
+	/// Only encoded for draft-11.
+	pub request_id: u64,
 	pub namespace: Path<'a>,
 }
 
 impl<'a> Message for SubscribeAnnounces<'a> {
-	fn encode<W: bytes::BufMut>(&self, w: &mut W) {
+	const ID: MessageId = MessageId::SubscribeAnnounces;
+
+	fn encode<W: bytes::BufMut>(&self, w: &mut W, version: Version) {
+		if version.request_ids() {
+			self.request_id.encode(w);
+		}
+
 		encode_namespace(w, &self.namespace);
 		0u8.encode(w); // no parameters
 	}
 
-	fn decode<R: bytes::Buf>(r: &mut R) -> Result<Self, DecodeError> {
+	fn decode<R: bytes::Buf>(r: &mut R, version: Version) -> Result<Self, DecodeError> {
+		let request_id = match version.request_ids() {
+			true => u64::decode(r)?,
+			false => 0,
+		};
+
 		let namespace = decode_namespace(r)?;
 
-		let num_params = u8::decode(r)?;
-		if num_params != 0 {
-			return Err(DecodeError::InvalidValue);
-		}
+		// We don't support any parameters yet.
+		decode_parameters(r, version)?;
 
-		Ok(Self { namespace })
+		Ok(Self { request_id, namespace })
 	}
 }
 
 /// SubscribeAnnouncesOk message (0x12)
 #[derive(Clone, Debug)]
 pub struct SubscribeAnnouncesOk<'a> {
+	/// Only encoded for draft-11.
+	pub request_id: u64,
+	/// Only encoded for draft-07.
 	pub namespace: Path<'a>,
 }
 
 impl<'a> Message for SubscribeAnnouncesOk<'a> {
-	fn encode<W: bytes::BufMut>(&self, w: &mut W) {
-		encode_namespace(w, &self.namespace);
+	const ID: MessageId = MessageId::SubscribeAnnouncesOk;
+
+	fn encode<W: bytes::BufMut>(&self, w: &mut W, version: Version) {
+		match version.request_ids() {
+			true => self.request_id.encode(w),
+			false => encode_namespace(w, &self.namespace),
+		}
 	}
 
-	fn decode<R: bytes::Buf>(r: &mut R) -> Result<Self, DecodeError> {
-		let namespace = decode_namespace(r)?;
-		Ok(Self { namespace })
+	fn decode<R: bytes::Buf>(r: &mut R, version: Version) -> Result<Self, DecodeError> {
+		Ok(match version.request_ids() {
+			true => Self {
+				request_id: u64::decode(r)?,
+				namespace: Path::new(""),
+			},
+			false => Self {
+				request_id: 0,
+				namespace: decode_namespace(r)?,
+			},
+		})
 	}
 }
 
 /// SubscribeAnnouncesError message (0x13)
 #[derive(Clone, Debug)]
 pub struct SubscribeAnnouncesError<'a> {
+	/// Only encoded for draft-11.
+	pub request_id: u64,
+	/// Only encoded for draft-07.
 	pub namespace: Path<'a>,
 	pub error_code: u64,
 	pub reason_phrase: Cow<'a, str>,
 }
 
 impl<'a> Message for SubscribeAnnouncesError<'a> {
-	fn encode<W: bytes::BufMut>(&self, w: &mut W) {
-		encode_namespace(w, &self.namespace);
+	const ID: MessageId = MessageId::SubscribeAnnouncesError;
+
+	fn encode<W: bytes::BufMut>(&self, w: &mut W, version: Version) {
+		match version.request_ids() {
+			true => self.request_id.encode(w),
+			false => encode_namespace(w, &self.namespace),
+		}
+
 		self.error_code.encode(w);
 		self.reason_phrase.encode(w);
 	}
 
-	fn decode<R: bytes::Buf>(r: &mut R) -> Result<Self, DecodeError> {
-		let namespace = decode_namespace(r)?;
+	fn decode<R: bytes::Buf>(r: &mut R, version: Version) -> Result<Self, DecodeError> {
+		let (request_id, namespace) = match version.request_ids() {
+			true => (u64::decode(r)?, Path::new("")),
+			false => (0, decode_namespace(r)?),
+		};
+
 		let error_code = u64::decode(r)?;
 		let reason_phrase = Cow::<str>::decode(r)?;
 
 		Ok(Self {
+			request_id,
 			namespace,
 			error_code,
 			reason_phrase,
@@ -82,11 +126,13 @@ pub struct UnsubscribeAnnounces<'a> {
 }
 
 impl<'a> Message for UnsubscribeAnnounces<'a> {
-	fn encode<W: bytes::BufMut>(&self, w: &mut W) {
+	const ID: MessageId = MessageId::UnsubscribeAnnounces;
+
+	fn encode<W: bytes::BufMut>(&self, w: &mut W, _version: Version) {
 		encode_namespace(w, &self.namespace);
 	}
 
-	fn decode<R: bytes::Buf>(r: &mut R) -> Result<Self, DecodeError> {
+	fn decode<R: bytes::Buf>(r: &mut R, _version: Version) -> Result<Self, DecodeError> {
 		let namespace = decode_namespace(r)?;
 		Ok(Self { namespace })
 	}
@@ -1,13 +1,14 @@
 use std::{
 	collections::{hash_map::Entry, HashMap},
-	sync::{atomic, Arc},
+	sync::Arc,
 };
 
 use crate::{
 	coding::Reader,
 	ietf::{self, Control},
 	model::BroadcastProducer,
-	Broadcast, Error, Frame, FrameProducer, Group, GroupProducer, OriginProducer, Path, PathOwned, TrackProducer,
+	Broadcast, Error, Frame, FrameProducer, Group, GroupProducer, OriginProducer, Path, PathOwned, TrackEnd,
+	TrackEndReason, TrackProducer, TrackStatus, TrackStatusRequest,
 };
 
 use web_async::Lock;
@@ -18,10 +19,30 @@ pub(super) struct Subscriber<S: web_transport_trait::Session> {
 
 	origin: Option<OriginProducer>,
 	subscribes: Lock<HashMap<u64, TrackProducer>>,
-	next_id: Arc<atomic::AtomicU64>,
+	fetches: Lock<HashMap<u64, TrackProducer>>,
+
+	// Groups with open subgroup streams, keyed by subscription and group ID.
+	groups: Lock<HashMap<(u64, u64), Subgroups>>,
 
 	producers: Lock<HashMap<PathOwned, BroadcastProducer>>,
 	control: Control,
+
+	// Draft-07 TRACK_STATUS has no request ID, so pending queries are matched by broadcast and track name.
+	statuses: Lock<HashMap<(PathOwned, String), PendingStatus>>,
+}
+
+// A group shared by multiple subgroup streams, closed when the last one finishes.
+struct Subgroups {
+	group: GroupProducer,
+	streams: usize,
+}
+
+// The track status queries waiting for a response from the peer.
+#[derive(Default)]
+struct PendingStatus {
+	// Only used for draft-11, None until the request has been sent.
+	request_id: Option<u64>,
+	requests: Vec<TrackStatusRequest>,
 }
 
 impl<S: web_transport_trait::Session> Subscriber<S> {
@@ -30,24 +51,39 @@ impl<S: web_transport_trait::Session> Subscriber<S> {
 			session,
 			origin,
 			subscribes: Default::default(),
-			next_id: Default::default(),
+			fetches: Default::default(),
+			groups: Default::default(),
 			producers: Default::default(),
 			control,
+			statuses: Default::default(),
 		}
 	}
 
 	pub fn recv_announce(&mut self, msg: ietf::Announce) -> Result<(), Error> {
+		// Draft-07 announcements don't count towards the limit.
+		if self.control.version().request_ids() {
+			self.control.recv_request(msg.request_id)?;
+		}
+
+		self.reply_announce(msg)?;
+
+		if self.control.version().request_ids() {
+			self.control.request_done();
+		}
+
+		Ok(())
+	}
+
+	fn reply_announce(&mut self, msg: ietf::Announce) -> Result<(), Error> {
 		let origin = match &self.origin {
 			Some(origin) => origin,
 			None => {
-				self.control.send(
-					ietf::MessageId::AnnounceError,
-					ietf::AnnounceError {
-						track_namespace: msg.track_namespace,
-						error_code: 404,
-						reason_phrase: "Publish only".into(),
-					},
-				)?;
+				self.control.send(ietf::AnnounceError {
+					request_id: msg.request_id,
+					track_namespace: msg.track_namespace,
+					error_code: 404,
+					reason_phrase: "Publish only".into(),
+				})?;
 
 				return Ok(());
 			}
@@ -56,7 +92,10 @@ impl<S: web_transport_trait::Session> Subscriber<S> {
 		let path = msg.track_namespace.to_owned();
 		tracing::debug!(broadcast = %origin.absolute(&path), suffix = %path, "announce");
 
-		let broadcast = Broadcast::produce();
+		let mut broadcast = Broadcast::produce();
+
+		// The remote knows about tracks that we haven't subscribed to yet.
+		broadcast.producer.forward_status();
 
 		// Make sure the peer doesn't double announce.
 		match self.producers.lock().entry(path.to_owned()) {
@@ -67,12 +106,10 @@ impl<S: web_transport_trait::Session> Subscriber<S> {
 		// Run the broadcast in the background until all consumers are dropped.
 		origin.publish_broadcast(path.clone(), broadcast.consumer);
 
-		self.control.send(
-			ietf::MessageId::AnnounceOk,
-			ietf::AnnounceOk {
-				track_namespace: path.clone(),
-			},
-		)?;
+		self.control.send(ietf::AnnounceOk {
+			request_id: msg.request_id,
+			track_namespace: path.clone(),
+		})?;
 
 		web_async::spawn(self.clone().run_broadcast(path, broadcast.producer));
 
@@ -92,6 +129,7 @@ impl<S: web_transport_trait::Session> Subscriber<S> {
 		let mut producer = self.producers.lock().remove(&path).ok_or(Error::NotFound)?;
 
 		producer.close();
+		self.cancel_track_status(&path, Error::NotFound);
 
 		Ok(())
 	}
@@ -110,14 +148,169 @@ impl<S: web_transport_trait::Session> Subscriber<S> {
 	}
 
 	pub fn recv_subscribe_done(&mut self, msg: ietf::SubscribeDone<'_>) -> Result<(), Error> {
-		if let Some(track) = self.subscribes.lock().remove(&msg.subscribe_id) {
-			track.close();
+		// Keep the subscription around so trailing groups are still accepted until it's unused.
+		let track = match self.subscribes.lock().get(&msg.subscribe_id) {
+			Some(track) => track.clone(),
+			None => return Ok(()),
+		};
+
+		let reason = match msg.status_code {
+			ietf::SubscribeDone::STATUS_TRACK_ENDED => TrackEndReason::Ended,
+			ietf::SubscribeDone::STATUS_SUBSCRIPTION_ENDED | ietf::SubscribeDone::STATUS_GOING_AWAY => {
+				TrackEndReason::Unpublished
+			}
+			ietf::SubscribeDone::STATUS_EXPIRED => TrackEndReason::Expired,
+			ietf::SubscribeDone::STATUS_UNSUBSCRIBED => TrackEndReason::Ended,
+			_ => {
+				track.abort(Error::Cancel);
+				return Ok(());
+			}
+		};
+
+		track.end(TrackEnd {
+			final_sequence: msg.final_group_object.map(|(group, _)| group),
+			reason,
+		});
+
+		Ok(())
+	}
+
+	pub fn recv_fetch_ok(&mut self, _msg: ietf::FetchOk) -> Result<(), Error> {
+		// Don't care, the objects arrive on a separate stream.
+		Ok(())
+	}
+
+	pub fn recv_fetch_error(&mut self, msg: ietf::FetchError<'_>) -> Result<(), Error> {
+		// The subscription still works without the backfill.
+		tracing::debug!(request_id = %msg.request_id, code = %msg.error_code, reason = %msg.reason_phrase, "fetch rejected");
+		self.fetches.lock().remove(&msg.request_id);
+		Ok(())
+	}
+
+	pub fn recv_track_status(&mut self, msg: ietf::TrackStatus<'_>) -> Result<(), Error> {
+		let requests = {
+			let mut statuses = self.statuses.lock();
+
+			let key = match self.control.version().request_ids() {
+				true => statuses
+					.iter()
+					.find(|(_, pending)| pending.request_id == Some(msg.request_id))
+					.map(|(key, _)| key.clone()),
+				false => Some((msg.track_namespace.to_owned(), msg.track_name.to_string())),
+			};
+
+			key.and_then(|key| statuses.remove(&key))
+				.map(|pending| pending.requests)
+				.unwrap_or_default()
+		};
+
+		for request in requests {
+			let status = match msg.status_code {
+				ietf::TrackStatus::STATUS_IN_PROGRESS => Ok(TrackStatus {
+					latest: Some(msg.last_group_id),
+					ended: false,
+				}),
+				ietf::TrackStatus::STATUS_ENDED => Ok(TrackStatus {
+					latest: Some(msg.last_group_id),
+					ended: true,
+				}),
+				ietf::TrackStatus::STATUS_NOT_BEGUN => Ok(TrackStatus {
+					latest: None,
+					ended: false,
+				}),
+				_ => Err(Error::NotFound),
+			};
+
+			request.respond(status);
 		}
 
 		Ok(())
 	}
 
+	fn send_track_status_request(&self, broadcast: PathOwned, request: TrackStatusRequest) {
+		let mut statuses = self.statuses.lock();
+		let pending = statuses.entry((broadcast.clone(), request.name.clone())).or_default();
+
+		// Only send one request at a time for the same track.
+		if pending.requests.is_empty() {
+			// Draft-07 doesn't identify track status requests.
+			if !self.control.version().request_ids() {
+				let msg = ietf::TrackStatusRequest {
+					request_id: 0,
+					track_namespace: broadcast,
+					track_name: request.name.as_str().into(),
+				};
+
+				if let Err(err) = self.control.send(msg) {
+					request.respond(Err(err));
+					return;
+				}
+			} else {
+				let control = self.control.clone();
+				let statuses = self.statuses.clone();
+				let track_name = request.name.clone();
+
+				// The request may be blocked by the peer's limit, so don't block the caller.
+				web_async::spawn(async move {
+					let key = (broadcast.clone(), track_name.clone());
+
+					let res = control
+						.request(|request_id| {
+							if let Some(pending) = statuses.lock().get_mut(&key) {
+								pending.request_id = Some(request_id);
+							}
+
+							ietf::TrackStatusRequest {
+								request_id,
+								track_namespace: broadcast,
+								track_name: track_name.as_str().into(),
+							}
+						})
+						.await;
+
+					if let Err(err) = res {
+						if let Some(pending) = statuses.lock().remove(&key) {
+							for request in pending.requests {
+								request.respond(Err(err.clone()));
+							}
+						}
+					}
+				});
+			}
+		}
+
+		pending.requests.push(request);
+	}
+
+	// Fail any pending track status queries for a broadcast, as the peer will never respond.
+	fn cancel_track_status(&self, broadcast: &Path, err: Error) {
+		let mut cancelled = Vec::new();
+
+		self.statuses.lock().retain(|(path, _), pending| {
+			let keep = path != broadcast;
+			if !keep {
+				cancelled.append(&mut pending.requests);
+			}
+			keep
+		});
+
+		for request in cancelled {
+			request.respond(Err(err.clone()));
+		}
+	}
+
 	pub async fn run(self) -> Result<(), Error> {
+		if !self.control.datagrams() {
+			return self.run_uni_streams().await;
+		}
+
+		tokio::select! {
+			res = self.clone().run_uni_streams() => res,
+			res = self.clone().run_datagrams() => res,
+		}
+	}
+
+	async fn run_uni_streams(self) -> Result<(), Error> {
 		loop {
 			let stream = self
 				.session
@@ -125,7 +318,7 @@ impl<S: web_transport_trait::Session> Subscriber<S> {
 				.await
 				.map_err(|err| Error::Transport(Arc::new(err)))?;
 
-			let stream = Reader::new(stream);
+			let stream = Reader::new(stream, self.control.stats().cloned());
 			let this = self.clone();
 
 			web_async::spawn(async move {
@@ -136,11 +329,85 @@ impl<S: web_transport_trait::Session> Subscriber<S> {
 		}
 	}
 
+	async fn run_datagrams(mut self) -> Result<(), Error> {
+		let version = self.control.version();
+
+		loop {
+			let datagram = self
+				.session
+				.recv_datagram()
+				.await
+				.map_err(|err| Error::Transport(Arc::new(err)))?;
+			if let Some(stats) = self.control.stats() {
+				stats.received(datagram.len());
+			}
+
+			// A malformed datagram is dropped like any other lost datagram.
+			match ietf::Datagram::decode(&mut datagram.as_ref(), version) {
+				Ok(datagram) => self.recv_datagram(datagram),
+				Err(err) => tracing::debug!(%err, "dropping malformed datagram"),
+			}
+		}
+	}
+
+	fn recv_datagram(&mut self, datagram: ietf::Datagram) {
+		let id = match self.control.version() {
+			ietf::Version::Draft07 => datagram.subscribe_id,
+			ietf::Version::Draft11 => datagram.track_alias,
+		};
+
+		let mut subs = self.subscribes.lock();
+		let track = match subs.get_mut(&id) {
+			Some(track) => track,
+			None => {
+				tracing::trace!(%id, "datagram for unknown subscription");
+				return;
+			}
+		};
+
+		// Let any downstream IETF subscribers know to use datagrams too.
+		track.set_datagrams(true);
+
+		match datagram.status {
+			ietf::Frame::STATUS_NORMAL => {}
+			ietf::Frame::STATUS_END_OF_TRACK => {
+				track.clone().end(TrackEnd {
+					final_sequence: Some(datagram.group_id),
+					reason: TrackEndReason::Ended,
+				});
+				return;
+			}
+			_ => return,
+		}
+
+		// Each datagram is mapped to a single-frame group, so any other objects in the same group are dropped.
+		match track.create_group(Group {
+			sequence: datagram.group_id,
+		}) {
+			Some(mut group) => {
+				let mut frame = group.create_frame(Frame {
+					size: datagram.payload.len() as u64,
+					extensions: datagram.extensions,
+				});
+				frame.write_chunk(datagram.payload);
+				frame.close();
+				group.close();
+			}
+			None => {
+				tracing::trace!(group = %datagram.group_id, object = %datagram.object_id, "dropping datagram");
+			}
+		}
+	}
+
 	async fn run_uni_stream(mut self, mut stream: Reader<S::RecvStream>) -> Result<(), Error> {
-		let kind = stream.decode().await?;
+		let kind: u64 = stream.decode().await?;
 
-		let res = match kind {
-			ietf::Group::STREAM_TYPE => self.recv_group(&mut stream).await,
+		let res = match self.control.version() {
+			_ if kind == ietf::FetchHeader::STREAM_TYPE => self.recv_fetch(&mut stream).await,
+			ietf::Version::Draft07 if kind == ietf::Group::STREAM_TYPE => self.recv_group(&mut stream, kind).await,
+			ietf::Version::Draft11 if ietf::Group::STREAM_TYPES.contains(&kind) => {
+				self.recv_group(&mut stream, kind).await
+			}
 			_ => return Err(Error::UnexpectedStream),
 		};
 
@@ -151,7 +418,7 @@ impl<S: web_transport_trait::Session> Subscriber<S> {
 		Ok(())
 	}
 
-	async fn run_broadcast(self, path: PathOwned, mut broadcast: BroadcastProducer) {
+	async fn run_broadcast(self, path: PathOwned, broadcast: BroadcastProducer) {
 		// Actually start serving subscriptions.
 		loop {
 			// Keep serving requests until there are no more consumers.
@@ -162,62 +429,167 @@ impl<S: web_transport_trait::Session> Subscriber<S> {
 					Some(producer) => producer,
 					None => break,
 				},
+				request = broadcast.requested_status() => match request {
+					Some(request) => {
+						self.send_track_status_request(path.clone(), request);
+						continue;
+					}
+					None => break,
+				},
 				_ = self.session.closed() => break,
 			};
 
-			let id = self.next_id.fetch_add(1, atomic::Ordering::Relaxed);
 			let mut this = self.clone();
 
 			let path = path.clone();
 			web_async::spawn(async move {
-				this.run_subscribe(id, path, track).await;
-				this.subscribes.lock().remove(&id);
+				this.run_subscribe(path, track).await;
 			});
 		}
+
+		self.cancel_track_status(&path, Error::Cancel);
 	}
 
-	async fn run_subscribe(&mut self, id: u64, broadcast: Path<'_>, track: TrackProducer) {
-		self.subscribes.lock().insert(id, track.clone());
-
-		self.control
-			.send(
-				ietf::MessageId::Subscribe,
-				ietf::Subscribe {
-					subscribe_id: id,
-					track_alias: 0,
-					track_namespace: broadcast.to_owned(),
-					track_name: (&track.info.name).into(),
-					subscriber_priority: track.info.priority,
+	async fn run_subscribe(&mut self, broadcast: Path<'_>, track: TrackProducer) {
+		let version = self.control.version();
+		let subscribes = self.subscribes.clone();
+
+		// Draft-11 data streams only include the track alias, so use the ID to make it unique.
+		let request = self.control.request(|id| {
+			subscribes.lock().insert(id, track.clone());
+
+			ietf::Subscribe {
+				subscribe_id: id,
+				track_alias: id,
+				track_namespace: broadcast.to_owned(),
+				track_name: (&track.info.name).into(),
+				subscriber_priority: track.info.priority,
+				// Draft-11 renamed Latest Group to Next Group Start, so ask for the latest object instead.
+				filter: match version {
+					ietf::Version::Draft07 => ietf::SubscribeFilter::LatestGroup,
+					ietf::Version::Draft11 => ietf::SubscribeFilter::LatestObject,
 				},
-			)
-			.ok();
+				parameters: Default::default(),
+			}
+		});
+
+		// We may be blocked by the peer's request limit.
+		let id = tokio::select! {
+			res = request => match res {
+				Ok(id) => id,
+				Err(err) => return track.abort(err),
+			},
+			_ = track.unused() => return track.abort(Error::Cancel),
+		};
 
 		tracing::info!(id, broadcast = %self.origin.as_ref().unwrap().absolute(&broadcast), track = %track.info.name, "subscribe started");
 
+		// Backfill the current group with a joining fetch, in case the publisher starts at the latest object.
+		// Draft-07 doesn't support joining fetches.
+		let fetch = match self.control.version() {
+			ietf::Version::Draft07 => None,
+			ietf::Version::Draft11 => {
+				let fetches = self.fetches.clone();
+				let request = self.control.request(|fetch_id| {
+					fetches.lock().insert(fetch_id, track.clone());
+
+					ietf::Fetch {
+						request_id: fetch_id,
+						subscriber_priority: track.info.priority,
+						fetch_type: ietf::FetchType::RelativeJoining {
+							subscribe_id: id,
+							group_offset: 0,
+						},
+					}
+				});
+
+				// Don't bother backfilling if the track is no longer needed.
+				tokio::select! {
+					res = request => res.ok(),
+					_ = track.unused() => None,
+				}
+			}
+		};
+
 		track.unused().await;
+
+		if let Some(fetch_id) = fetch {
+			if self.fetches.lock().remove(&fetch_id).is_some() {
+				self.control.send(ietf::FetchCancel { request_id: fetch_id }).ok();
+			}
+		}
+
 		tracing::info!(id, broadcast = %self.origin.as_ref().unwrap().absolute(&broadcast), track = %track.info.name, "subscribe cancelled");
 
+		self.subscribes.lock().remove(&id);
 		track.abort(Error::Cancel);
 	}
 
-	pub async fn recv_group(&mut self, stream: &mut Reader<S::RecvStream>) -> Result<(), Error> {
-		let group: ietf::Group = stream.decode().await?;
+	pub async fn recv_group(&mut self, stream: &mut Reader<S::RecvStream>, kind: u64) -> Result<(), Error> {
+		let version = self.control.version();
+		let header = stream.decode_with(|r| ietf::Group::decode(r, version, kind)).await?;
 
-		let group = {
-			let mut subs = self.subscribes.lock();
-			let track = subs.get_mut(&group.subscribe_id).ok_or(Error::Cancel)?;
+		let id = match version {
+			ietf::Version::Draft07 => header.subscribe_id,
+			ietf::Version::Draft11 => header.track_alias,
+		};
 
-			let group = Group {
-				sequence: group.group_id,
-			};
-			track.create_group(group).ok_or(Error::Old)?
+		// Each subgroup is a separate stream, merged into the same group as objects arrive.
+		let key = (id, header.group_id);
+		let group = match self.groups.lock().entry(key) {
+			Entry::Occupied(mut entry) => {
+				let subgroups = entry.get_mut();
+				subgroups.streams += 1;
+				subgroups.group.clone()
+			}
+			Entry::Vacant(entry) => {
+				let mut subs = self.subscribes.lock();
+				let track = subs.get_mut(&id).ok_or(Error::Cancel)?;
+
+				let group = Group {
+					sequence: header.group_id,
+				};
+				let group = track.create_group(group).ok_or(Error::Old)?;
+
+				entry.insert(Subgroups {
+					group: group.clone(),
+					streams: 1,
+				});
+				group
+			}
 		};
 
 		let res = tokio::select! {
 			_ = group.unused() => Err(Error::Cancel),
-			res = self.run_group(stream, group.clone()) => res,
+			res = self.run_group(stream, header.extensions, group.clone()) => res,
 		};
 
+		if let Ok(ietf::Frame::STATUS_END_OF_TRACK) = res {
+			if let Some(track) = self.subscribes.lock().get(&id) {
+				tracing::debug!(group = %header.group_id, "end of track");
+				track.clone().end(TrackEnd {
+					final_sequence: Some(header.group_id),
+					reason: TrackEndReason::Ended,
+				});
+			}
+		}
+
+		// Only the last subgroup stream can close the group, as the others may still append objects.
+		{
+			let mut groups = self.groups.lock();
+			if let Some(subgroups) = groups.get_mut(&key) {
+				subgroups.streams -= 1;
+				if subgroups.streams > 0 {
+					if let Err(err) = res {
+						tracing::debug!(%err, group = %group.info.sequence, subgroup = ?header.subgroup_id, "subgroup error");
+					}
+					return Ok(());
+				}
+
+				groups.remove(&key);
+			}
+		}
+
 		match res {
 			Err(Error::Cancel) | Err(Error::Transport(_)) => {
 				tracing::trace!(group = %group.info.sequence, "group cancelled");
@@ -236,9 +608,113 @@ impl<S: web_transport_trait::Session> Subscriber<S> {
 		Ok(())
 	}
 
-	async fn run_group(&mut self, stream: &mut Reader<S::RecvStream>, mut group: GroupProducer) -> Result<(), Error> {
-		while let Some(size) = stream.decode_maybe::<u64>().await? {
-			let frame = group.create_frame(Frame { size });
+	pub async fn recv_fetch(&mut self, stream: &mut Reader<S::RecvStream>) -> Result<(), Error> {
+		let version = self.control.version();
+		let header = stream.decode_with(|r| ietf::FetchHeader::decode(r)).await?;
+
+		let mut track = self
+			.fetches
+			.lock()
+			.get(&header.request_id)
+			.cloned()
+			.ok_or(Error::Cancel)?;
+
+		// Objects are ordered by group, so only one group is written at a time.
+		let mut group: Option<GroupProducer> = None;
+		let mut sequence = None;
+
+		let res = 'fetch: loop {
+			let object = tokio::select! {
+				_ = track.unused() => break Err(Error::Cancel),
+				object = stream.decode_maybe_with(|r| ietf::FetchObject::decode(r, version)) => object,
+			};
+
+			let object = match object {
+				Ok(Some(object)) => object,
+				Ok(None) => break Ok(()),
+				Err(err) => break Err(err),
+			};
+
+			if sequence != Some(object.group_id) {
+				if let Some(group) = group.take() {
+					group.close();
+				}
+
+				// The group may have already arrived via the subscription, in which case we skip it.
+				sequence = Some(object.group_id);
+				group = track.create_group(Group {
+					sequence: object.group_id,
+				});
+			}
+
+			if object.status != ietf::Frame::STATUS_NORMAL {
+				continue;
+			}
+
+			match group.as_mut() {
+				Some(group) => {
+					let frame = group.create_frame(Frame {
+						size: object.size,
+						extensions: object.extensions,
+					});
+					if let Err(err) = self.run_frame(stream, frame.clone()).await {
+						frame.abort(err.clone());
+						break Err(err);
+					}
+				}
+				None => {
+					// Read and discard the payload.
+					let mut remain = object.size;
+					while remain > 0 {
+						let chunk = match stream.read(remain as usize).await {
+							Ok(Some(chunk)) => chunk,
+							Ok(None) => break 'fetch Err(Error::WrongSize),
+							Err(err) => break 'fetch Err(err),
+						};
+						remain -= chunk.len() as u64;
+					}
+				}
+			}
+		};
+
+		self.fetches.lock().remove(&header.request_id);
+
+		match res {
+			Ok(()) => {
+				if let Some(group) = group {
+					group.close();
+				}
+				Ok(())
+			}
+			Err(err) => {
+				if let Some(group) = group {
+					group.abort(err.clone());
+				}
+				Err(err)
+			}
+		}
+	}
+
+	async fn run_group(
+		&mut self,
+		stream: &mut Reader<S::RecvStream>,
+		extensions: bool,
+		mut group: GroupProducer,
+	) -> Result<u64, Error> {
+		// Returns the status that ended the subgroup, or STATUS_NORMAL if the stream was finished.
+		while let Some(header) = stream.decode_maybe_with(|r| ietf::Frame::decode(r, extensions)).await? {
+			match header.status {
+				ietf::Frame::STATUS_NORMAL => {}
+				// Object IDs may be sparse, which doesn't matter because we don't expose them.
+				ietf::Frame::STATUS_DOES_NOT_EXIST => continue,
+				// The stream should be finished after these, but don't wait for it.
+				status => return Ok(status),
+			}
+
+			let frame = group.create_frame(Frame {
+				size: header.size,
+				extensions: header.extensions,
+			});
 
 			let res = tokio::select! {
 				_ = frame.unused() => Err(Error::Cancel),
@@ -251,9 +727,7 @@ impl<S: web_transport_trait::Session> Subscriber<S> {
 			}
 		}
 
-		group.close();
-
-		Ok(())
+		Ok(ietf::Frame::STATUS_NORMAL)
 	}
 
 	async fn run_frame(&mut self, stream: &mut Reader<S::RecvStream>, mut frame: FrameProducer) -> Result<(), Error> {
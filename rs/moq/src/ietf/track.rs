@@ -1,29 +1,54 @@
-//! IETF moq-transport-07 track status messages
+//! IETF moq-transport track status messages
 
 use std::borrow::Cow;
 
 use crate::{coding::*, Path};
 
-use super::util::{decode_namespace, encode_namespace};
+use super::{
+	util::{decode_namespace, decode_parameters, encode_namespace},
+	Message, MessageId, Version,
+};
 
 /// TrackStatusRequest message (0x0d)
 #[derive(Clone, Debug)]
 pub struct TrackStatusRequest<'a> {
+	/// Only encoded for draft-11.
+	pub request_id: u64,
 	pub track_namespace: Path<'a>,
 	pub track_name: Cow<'a, str>,
 }
 
 impl<'a> Message for TrackStatusRequest<'a> {
-	fn encode<W: bytes::BufMut>(&self, w: &mut W) {
+	const ID: MessageId = MessageId::TrackStatusRequest;
+
+	fn encode<W: bytes::BufMut>(&self, w: &mut W, version: Version) {
+		if version.request_ids() {
+			self.request_id.encode(w);
+		}
+
 		encode_namespace(w, &self.track_namespace);
 		self.track_name.encode(w);
+
+		if version.request_ids() {
+			0u8.encode(w); // no parameters
+		}
 	}
 
-	fn decode<R: bytes::Buf>(r: &mut R) -> Result<Self, DecodeError> {
+	fn decode<R: bytes::Buf>(r: &mut R, version: Version) -> Result<Self, DecodeError> {
+		let request_id = match version.request_ids() {
+			true => u64::decode(r)?,
+			false => 0,
+		};
+
 		let track_namespace = decode_namespace(r)?;
 		let track_name = Cow::<str>::decode(r)?;
 
+		if version.request_ids() {
+			decode_parameters(r, version)?;
+		}
+
 		Ok(Self {
+			request_id,
 			track_namespace,
 			track_name,
 		})
@@ -34,7 +59,11 @@ impl<'a> Message for TrackStatusRequest<'a> {
 /// Sent to communicate track-level state
 #[derive(Clone, Debug)]
 pub struct TrackStatus<'a> {
+	/// Only encoded for draft-11.
+	pub request_id: u64,
+	/// Only encoded for draft-07.
 	pub track_namespace: Path<'a>,
+	/// Only encoded for draft-07.
 	pub track_name: Cow<'a, str>,
 	pub status_code: u64,
 	pub last_group_id: u64,
@@ -44,27 +73,48 @@ pub struct TrackStatus<'a> {
 impl<'a> TrackStatus<'a> {
 	pub const STATUS_IN_PROGRESS: u64 = 0x00;
 	pub const STATUS_NOT_FOUND: u64 = 0x01;
-	pub const STATUS_NOT_AUTHORIZED: u64 = 0x02;
+	/// The track exists but has no content yet, so the group and object are zero.
+	pub const STATUS_NOT_BEGUN: u64 = 0x02;
 	pub const STATUS_ENDED: u64 = 0x03;
 }
 
 impl<'a> Message for TrackStatus<'a> {
-	fn encode<W: bytes::BufMut>(&self, w: &mut W) {
-		encode_namespace(w, &self.track_namespace);
-		self.track_name.encode(w);
+	const ID: MessageId = MessageId::TrackStatus;
+
+	fn encode<W: bytes::BufMut>(&self, w: &mut W, version: Version) {
+		match version.request_ids() {
+			true => self.request_id.encode(w),
+			false => {
+				encode_namespace(w, &self.track_namespace);
+				self.track_name.encode(w);
+			}
+		}
+
 		self.status_code.encode(w);
 		self.last_group_id.encode(w);
 		self.last_object_id.encode(w);
+
+		if version.request_ids() {
+			0u8.encode(w); // no parameters
+		}
 	}
 
-	fn decode<R: bytes::Buf>(r: &mut R) -> Result<Self, DecodeError> {
-		let track_namespace = decode_namespace(r)?;
-		let track_name = Cow::<str>::decode(r)?;
+	fn decode<R: bytes::Buf>(r: &mut R, version: Version) -> Result<Self, DecodeError> {
+		let (request_id, track_namespace, track_name) = match version.request_ids() {
+			true => (u64::decode(r)?, Path::new(""), Cow::default()),
+			false => (0, decode_namespace(r)?, Cow::<str>::decode(r)?),
+		};
+
 		let status_code = u64::decode(r)?;
 		let last_group_id = u64::decode(r)?;
 		let last_object_id = u64::decode(r)?;
 
+		if version.request_ids() {
+			decode_parameters(r, version)?;
+		}
+
 		Ok(Self {
+			request_id,
 			track_namespace,
 			track_name,
 			status_code,
@@ -1,5 +1,7 @@
 use crate::{coding::*, Path};
 
+use super::Version;
+
 /// Helper function to encode namespace as tuple of strings
 pub fn encode_namespace<W: bytes::BufMut>(w: &mut W, namespace: &Path) {
 	// Split the path by '/' to get individual parts
@@ -31,3 +33,45 @@ pub fn decode_namespace<R: bytes::Buf>(r: &mut R) -> Result<Path<'static>, Decod
 
 	Ok(Path::from(parts.join("/")))
 }
+
+/// Helper function to encode setup or message parameters.
+///
+/// Draft-11 encodes parameters with an even type as a single varint without a length.
+/// Values are stored as bytes either way, so an even parameter must contain exactly one varint.
+pub fn encode_parameters<W: bytes::BufMut>(w: &mut W, parameters: &Extensions, version: Version) {
+	parameters.0.len().encode(w);
+
+	for (kind, value) in parameters.0.iter() {
+		kind.encode(w);
+
+		match version {
+			Version::Draft11 if kind % 2 == 0 => w.put_slice(value),
+			_ => value.encode(w),
+		}
+	}
+}
+
+/// Helper function to decode setup or message parameters, see [encode_parameters].
+pub fn decode_parameters<R: bytes::Buf>(r: &mut R, version: Version) -> Result<Extensions, DecodeError> {
+	let mut parameters = Extensions::default();
+
+	let count = u64::decode(r)?;
+	for _ in 0..count {
+		let kind = u64::decode(r)?;
+
+		let value = match version {
+			Version::Draft11 if kind % 2 == 0 => {
+				let mut value = Vec::new();
+				u64::decode(r)?.encode(&mut value);
+				value
+			}
+			_ => Vec::<u8>::decode(r)?,
+		};
+
+		if parameters.0.insert(kind, value).is_some() {
+			return Err(DecodeError::DupliateParameter);
+		}
+	}
+
+	Ok(parameters)
+}
@@ -0,0 +1,56 @@
+use crate::coding;
+
+/// The moq-transport drafts supported by the IETF backend.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Version {
+	/// <https://www.ietf.org/archive/id/draft-ietf-moq-transport-07.html>
+	Draft07,
+
+	/// <https://www.ietf.org/archive/id/draft-ietf-moq-transport-11.html>
+	Draft11,
+}
+
+impl Version {
+	/// Draft-07 uses the same setup messages as moq-lite (0x40 and 0x41).
+	///
+	/// Later drafts use new IDs (0x20 and 0x21), so the client must pick the draft before connecting.
+	pub fn legacy_setup(self) -> bool {
+		self < Self::Draft11
+	}
+
+	/// Every request, including announcements and track status, is identified by a Request ID.
+	///
+	/// The client uses even IDs and the server uses odd IDs.
+	pub fn request_ids(self) -> bool {
+		self >= Self::Draft11
+	}
+
+	/// The difference between consecutive IDs used by the same endpoint.
+	pub fn request_step(self) -> u64 {
+		match self.request_ids() {
+			true => 2,
+			false => 1,
+		}
+	}
+}
+
+impl TryFrom<coding::Version> for Version {
+	type Error = ();
+
+	fn try_from(version: coding::Version) -> Result<Self, Self::Error> {
+		match version {
+			coding::Version::IETF_07 => Ok(Self::Draft07),
+			coding::Version::IETF_11 => Ok(Self::Draft11),
+			_ => Err(()),
+		}
+	}
+}
+
+impl From<Version> for coding::Version {
+	fn from(version: Version) -> Self {
+		match version {
+			Version::Draft07 => coding::Version::IETF_07,
+			Version::Draft11 => coding::Version::IETF_11,
+		}
+	}
+}
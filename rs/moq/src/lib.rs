@@ -14,18 +14,32 @@
 //!
 //! While designed for media, the transport is generic and can handle any live data streams.
 
+mod auth;
 mod error;
 mod lite;
 mod model;
 mod path;
 mod session;
+mod stats;
 
 pub mod coding;
 pub mod ietf;
 
+pub use auth::*;
 pub use error::*;
+pub use lite::proto;
 pub use model::*;
 pub use path::*;
 pub use session::*;
+pub use stats::*;
 
 pub const ALPN: &str = coding::Alpn::LITE_LATEST.0;
+
+/// All ALPNs accepted for raw QUIC connections, in preferred order.
+///
+/// The MoQ version is still negotiated during the setup, so older clients only differ by ALPN.
+pub const ALPNS: [&str; 3] = [
+	coding::Alpn::LITE_02.0,
+	coding::Alpn::LITE_01.0,
+	coding::Alpn::LITE_00.0,
+];
@@ -1,12 +1,15 @@
 mod announce;
 mod group;
 mod info;
+pub mod proto;
 mod publisher;
 mod session;
 mod setup;
+mod status;
 mod stream;
 mod subscribe;
 mod subscriber;
+mod version;
 
 pub use announce::*;
 pub use group::*;
@@ -14,6 +17,8 @@ pub use info::*;
 use publisher::*;
 pub(crate) use session::*;
 pub use setup::*;
+pub use status::*;
 pub use stream::*;
 pub use subscribe::*;
 use subscriber::*;
+pub use version::*;
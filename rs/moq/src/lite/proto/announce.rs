@@ -0,0 +1,254 @@
+use std::collections::HashSet;
+
+use crate::{
+	lite::{Announce, AnnounceInit, AnnouncePlease, Version},
+	AsPath, Error, Path, PathOwned,
+};
+
+/// A message sent by the publisher on an announce stream.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AnnounceMessage {
+	Init(AnnounceInit<'static>),
+	Update(Announce<'static>),
+}
+
+/// The publisher side of an announce stream.
+///
+/// Broadcasts are collected until [Self::init] is called, then each change produces an [Announce].
+pub struct Announcer {
+	version: Version,
+	prefix: PathOwned,
+	active: HashSet<PathOwned>,
+	initialized: bool,
+}
+
+impl Announcer {
+	pub fn new(version: Version, please: &AnnouncePlease<'_>) -> Self {
+		Self {
+			version,
+			prefix: please.prefix.to_owned(),
+			active: HashSet::new(),
+			initialized: false,
+		}
+	}
+
+	/// The prefix requested by the subscriber.
+	pub fn prefix(&self) -> Path<'_> {
+		self.prefix.borrow()
+	}
+
+	/// Record that a broadcast is active or has ended, returning the message to send if any.
+	///
+	/// The path must start with the requested prefix.
+	/// Nothing is returned before [Self::init] or if the state did not change.
+	pub fn update(&mut self, path: impl AsPath, active: bool) -> Result<Option<AnnounceMessage>, Error> {
+		let path = path.as_path();
+		let suffix = path.strip_prefix(&self.prefix).ok_or(Error::NotFound)?.to_owned();
+
+		let changed = match active {
+			true => self.active.insert(suffix.clone()),
+			false => self.active.remove(&suffix),
+		};
+
+		if !changed || !self.initialized {
+			return Ok(None);
+		}
+
+		Ok(Some(AnnounceMessage::Update(match active {
+			true => Announce::Active { suffix },
+			false => Announce::Ended { suffix },
+		})))
+	}
+
+	/// Finish collecting the initial broadcasts, returning the messages to send.
+	pub fn init(&mut self) -> Vec<AnnounceMessage> {
+		if self.initialized {
+			return Vec::new();
+		}
+
+		self.initialized = true;
+		let suffixes: Vec<_> = self.active.iter().cloned().collect();
+
+		if self.version.announce_init() {
+			return vec![AnnounceMessage::Init(AnnounceInit { suffixes })];
+		}
+
+		// Older versions expect each active path as a separate message.
+		suffixes
+			.into_iter()
+			.map(|suffix| AnnounceMessage::Update(Announce::Active { suffix }))
+			.collect()
+	}
+}
+
+/// A change to the set of broadcasts, produced by [AnnounceListener].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AnnounceEvent {
+	/// The broadcast is now available, relative to the requested prefix.
+	Active(PathOwned),
+
+	/// The broadcast is no longer available.
+	Ended(PathOwned),
+}
+
+/// The subscriber side of an announce stream.
+pub struct AnnounceListener {
+	prefix: PathOwned,
+	active: HashSet<PathOwned>,
+	initialized: bool,
+}
+
+impl AnnounceListener {
+	pub fn new(version: Version, prefix: impl AsPath) -> Self {
+		Self {
+			prefix: prefix.as_path().to_owned(),
+			active: HashSet::new(),
+			// Older versions don't tell us when the initial set of announcements is complete.
+			initialized: !version.announce_init(),
+		}
+	}
+
+	/// The message to send when opening the stream.
+	pub fn please(&self) -> AnnouncePlease<'_> {
+		AnnouncePlease {
+			prefix: self.prefix.borrow(),
+		}
+	}
+
+	/// Returns true once the initial set of broadcasts has been received.
+	pub fn is_initialized(&self) -> bool {
+		self.initialized
+	}
+
+	/// Process the initial set of broadcasts.
+	pub fn recv_init(&mut self, msg: AnnounceInit<'_>) -> Result<Vec<AnnounceEvent>, Error> {
+		if self.initialized {
+			return Err(Error::UnexpectedMessage);
+		}
+
+		self.initialized = true;
+
+		msg.suffixes
+			.into_iter()
+			.map(|suffix| self.active(suffix.into_owned()))
+			.collect()
+	}
+
+	/// Process a change to the set of broadcasts.
+	pub fn recv(&mut self, msg: Announce<'_>) -> Result<AnnounceEvent, Error> {
+		if !self.initialized {
+			return Err(Error::UnexpectedMessage);
+		}
+
+		match msg {
+			Announce::Active { suffix } => self.active(suffix.into_owned()),
+			Announce::Ended { suffix } => {
+				let suffix = suffix.into_owned();
+				if !self.active.remove(&suffix) {
+					return Err(Error::NotFound);
+				}
+
+				Ok(AnnounceEvent::Ended(suffix))
+			}
+		}
+	}
+
+	fn active(&mut self, suffix: PathOwned) -> Result<AnnounceEvent, Error> {
+		// Make sure the peer doesn't double announce.
+		if !self.active.insert(suffix.clone()) {
+			return Err(Error::Duplicate);
+		}
+
+		Ok(AnnounceEvent::Active(suffix))
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	use crate::coding::{Decode, Encode};
+
+	fn round_trip<T: Encode + Decode>(msg: &T) -> T {
+		let mut buf = Vec::new();
+		msg.encode(&mut buf);
+		T::decode(&mut buf.as_slice()).unwrap()
+	}
+
+	fn init(version: Version, announcer: &mut Announcer, listener: &mut AnnounceListener) -> Vec<AnnounceEvent> {
+		let mut events = Vec::new();
+
+		for msg in announcer.init() {
+			match msg {
+				AnnounceMessage::Init(msg) => events.extend(listener.recv_init(round_trip(&msg)).unwrap()),
+				AnnounceMessage::Update(msg) => {
+					assert!(!version.announce_init());
+					events.push(listener.recv(round_trip(&msg)).unwrap());
+				}
+			}
+		}
+
+		events
+	}
+
+	#[test]
+	fn announce() {
+		for version in [Version::Lite00, Version::Lite01] {
+			let mut listener = AnnounceListener::new(version, "room");
+			let mut announcer = Announcer::new(version, &round_trip(&listener.please()));
+			assert_eq!(announcer.prefix().as_str(), "room");
+
+			// Nothing is sent until the initial set is complete.
+			assert_eq!(announcer.update("room/alice", true).unwrap(), None);
+			assert_eq!(announcer.update("room/bob", true).unwrap(), None);
+			assert_eq!(announcer.update("room/bob", false).unwrap(), None);
+			assert!(announcer.update("other/carol", true).is_err());
+
+			let events = init(version, &mut announcer, &mut listener);
+			assert_eq!(events, vec![AnnounceEvent::Active("alice".into())]);
+			assert!(listener.is_initialized());
+
+			let msg = announcer.update("room/bob", true).unwrap();
+			let Some(AnnounceMessage::Update(msg)) = msg else {
+				panic!("expected an update");
+			};
+			assert_eq!(
+				listener.recv(round_trip(&msg)).unwrap(),
+				AnnounceEvent::Active("bob".into())
+			);
+
+			// Duplicates are filtered by the announcer.
+			assert_eq!(announcer.update("room/bob", true).unwrap(), None);
+
+			let msg = announcer.update("room/alice", false).unwrap();
+			let Some(AnnounceMessage::Update(msg)) = msg else {
+				panic!("expected an update");
+			};
+			assert_eq!(
+				listener.recv(round_trip(&msg)).unwrap(),
+				AnnounceEvent::Ended("alice".into())
+			);
+		}
+	}
+
+	#[test]
+	fn listener_errors() {
+		let mut listener = AnnounceListener::new(Version::Lite01, "");
+
+		// ANNOUNCE_INIT must come first.
+		let active = Announce::Active { suffix: "a".into() };
+		assert!(matches!(listener.recv(active.clone()), Err(Error::UnexpectedMessage)));
+
+		listener.recv_init(AnnounceInit { suffixes: vec![] }).unwrap();
+		assert!(matches!(
+			listener.recv_init(AnnounceInit { suffixes: vec![] }),
+			Err(Error::UnexpectedMessage)
+		));
+
+		listener.recv(active.clone()).unwrap();
+		assert!(matches!(listener.recv(active), Err(Error::Duplicate)));
+
+		let ended = Announce::Ended { suffix: "b".into() };
+		assert!(matches!(listener.recv(ended), Err(Error::NotFound)));
+	}
+}
@@ -0,0 +1,357 @@
+use std::collections::VecDeque;
+
+use bytes::{Buf, Bytes, BytesMut};
+
+use crate::{
+	coding::{Decode, DecodeError},
+	lite::Version,
+	Error, FrameExtensions,
+};
+
+/// The decision made by [GroupScheduler] for a new group.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Schedule {
+	/// The group is too old to be worth sending.
+	Skip,
+
+	/// Serve the group, aborting an older group that is still being served.
+	Serve { abort: Option<u64> },
+}
+
+/// Decides which groups a publisher should serve for a subscription.
+///
+/// At most two groups are served at once, preferring the newest, so a slow subscriber skips ahead instead of falling behind.
+#[derive(Debug, Default)]
+pub struct GroupScheduler {
+	old: Option<u64>,
+	new: Option<u64>,
+}
+
+impl GroupScheduler {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Decide whether to serve a new group.
+	pub fn push(&mut self, sequence: u64) -> Schedule {
+		let latest = self.new.unwrap_or(0);
+
+		// If this group is older than the oldest group we're serving, skip it.
+		// We always serve at most two groups, but maybe we should serve only sequence >= MAX-1.
+		if sequence < self.old.unwrap_or(0) {
+			return Schedule::Skip;
+		}
+
+		// Terminate the old group if it's still running.
+		let abort = self.old.take();
+
+		if sequence >= latest {
+			self.old = self.new;
+			self.new = Some(sequence);
+		} else {
+			self.old = Some(sequence);
+		}
+
+		Schedule::Serve { abort }
+	}
+
+	/// Mark a group as no longer being served, either because it finished or failed.
+	pub fn finished(&mut self, sequence: u64) {
+		if self.old == Some(sequence) {
+			self.old = None;
+		} else if self.new == Some(sequence) {
+			self.new = self.old.take();
+		}
+	}
+
+	/// Returns the groups currently being served, oldest first.
+	pub fn serving(&self) -> impl Iterator<Item = u64> {
+		self.old.into_iter().chain(self.new)
+	}
+}
+
+/// Compute the transport priority for a group stream.
+///
+/// Quinn takes a i32 priority.
+/// We do our best to distill 70 bits of information into 32 bits, but overflows will happen.
+/// Specifically, group sequence 2^24 will overflow and be incorrectly prioritized.
+/// But even with a group per frame, it will take ~6 days to reach that point.
+// TODO The behavior when two tracks share the same priority is undefined. Should we round-robin?
+pub fn stream_priority(track_priority: u8, group_sequence: u64) -> i32 {
+	let sequence = 0xFFFFFF - (group_sequence as u32 & 0xFFFFFF);
+	((track_priority as i32) << 24) | sequence as i32
+}
+
+/// An event produced by [GroupDecoder].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GroupEvent {
+	/// A new frame of the given size.
+	Frame { size: u64, extensions: FrameExtensions },
+
+	/// A chunk of the current frame's payload.
+	Chunk(Bytes),
+
+	/// The current frame is complete.
+	FrameEnd,
+}
+
+/// Decodes the frames of a group stream, following the [super::Group] header.
+///
+/// Bytes can be provided in arbitrary chunks; payloads are returned without copying when possible.
+#[derive(Debug)]
+pub struct GroupDecoder {
+	version: Version,
+	chunks: VecDeque<Bytes>,
+
+	// The remaining size of the current frame, if any.
+	remain: Option<u64>,
+}
+
+impl GroupDecoder {
+	pub fn new(version: Version) -> Self {
+		Self {
+			version,
+			chunks: Default::default(),
+			remain: None,
+		}
+	}
+
+	/// Provide more bytes received from the stream.
+	pub fn push(&mut self, data: Bytes) {
+		if !data.is_empty() {
+			self.chunks.push_back(data);
+		}
+	}
+
+	/// Return the next event, or None if more bytes are needed.
+	pub fn next_event(&mut self) -> Result<Option<GroupEvent>, Error> {
+		match self.remain {
+			None => self.decode_header(),
+			Some(0) => {
+				self.remain = None;
+				Ok(Some(GroupEvent::FrameEnd))
+			}
+			Some(remain) => {
+				let mut chunk = match self.chunks.pop_front() {
+					Some(chunk) => chunk,
+					None => return Ok(None),
+				};
+
+				if chunk.len() as u64 > remain {
+					let rest = chunk.split_off(remain as usize);
+					self.chunks.push_front(rest);
+				}
+
+				self.remain = Some(remain - chunk.len() as u64);
+				Ok(Some(GroupEvent::Chunk(chunk)))
+			}
+		}
+	}
+
+	fn decode_header(&mut self) -> Result<Option<GroupEvent>, Error> {
+		loop {
+			let front = match self.chunks.front_mut() {
+				Some(front) => front,
+				None => return Ok(None),
+			};
+
+			let mut cursor = &front[..];
+			match Self::decode_frame(&mut cursor, self.version) {
+				Ok((size, extensions)) => {
+					let used = front.len() - cursor.len();
+					front.advance(used);
+
+					if front.is_empty() {
+						self.chunks.pop_front();
+					}
+
+					self.remain = Some(size);
+					return Ok(Some(GroupEvent::Frame { size, extensions }));
+				}
+				Err(DecodeError::Short) if self.chunks.len() >= 2 => {
+					// The header spans multiple chunks, so merge the first two.
+					let first = self.chunks.pop_front().unwrap();
+					let second = self.chunks.pop_front().unwrap();
+
+					let mut merged = BytesMut::with_capacity(first.len() + second.len());
+					merged.extend_from_slice(&first);
+					merged.extend_from_slice(&second);
+					self.chunks.push_front(merged.freeze());
+				}
+				Err(DecodeError::Short) => return Ok(None),
+				Err(err) => return Err(err.into()),
+			}
+		}
+	}
+
+	// Decode the frame header before the payload.
+	fn decode_frame(r: &mut &[u8], version: Version) -> Result<(u64, FrameExtensions), DecodeError> {
+		let extensions = match version.frame_extensions() {
+			true => FrameExtensions::decode(r)?,
+			false => FrameExtensions::default(),
+		};
+
+		let size = u64::decode(r)?;
+		Ok((size, extensions))
+	}
+
+	/// Call when the stream is finished, returning an error if it ended in the middle of a frame.
+	pub fn finish(&self) -> Result<(), Error> {
+		match self.remain.is_some() || !self.chunks.is_empty() {
+			true => Err(Error::WrongSize),
+			false => Ok(()),
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	use crate::coding::Encode;
+
+	#[test]
+	fn priority() {
+		let assert = |track_priority, group_sequence, expected| {
+			assert_eq!(stream_priority(track_priority, group_sequence), expected);
+		};
+
+		const U24: i32 = (1 << 24) - 1;
+
+		// NOTE: The lower the value, the higher the priority for Quinn.
+		// MoQ does the opposite, so we invert the values.
+		assert(0, 50, U24 - 50);
+		assert(0, 0, U24);
+		assert(1, 50, 2 * U24 - 49);
+		assert(1, 0, 2 * U24 + 1);
+	}
+
+	#[test]
+	fn scheduler() {
+		let mut scheduler = GroupScheduler::new();
+
+		assert_eq!(scheduler.push(0), Schedule::Serve { abort: None });
+		assert_eq!(scheduler.push(1), Schedule::Serve { abort: None });
+		assert_eq!(scheduler.serving().collect::<Vec<_>>(), vec![0, 1]);
+
+		// A third group aborts the oldest.
+		assert_eq!(scheduler.push(2), Schedule::Serve { abort: Some(0) });
+		assert_eq!(scheduler.serving().collect::<Vec<_>>(), vec![1, 2]);
+
+		// Anything older than the oldest group is skipped.
+		assert_eq!(scheduler.push(0), Schedule::Skip);
+
+		// The newest group finishing promotes the old group.
+		scheduler.finished(2);
+		assert_eq!(scheduler.serving().collect::<Vec<_>>(), vec![1]);
+
+		scheduler.finished(1);
+		assert_eq!(scheduler.serving().count(), 0);
+
+		// Unknown groups are ignored.
+		scheduler.finished(7);
+	}
+
+	fn encode_frames(frames: &[&[u8]]) -> Vec<u8> {
+		let mut buf = Vec::new();
+		for frame in frames {
+			(frame.len() as u64).encode(&mut buf);
+			buf.extend_from_slice(frame);
+		}
+		buf
+	}
+
+	// Lite02 prefixes each frame with its extensions.
+	fn encode_frames_extensions(frames: &[(&[u8], FrameExtensions)]) -> Vec<u8> {
+		let mut buf = Vec::new();
+		for (frame, extensions) in frames {
+			extensions.encode(&mut buf);
+			(frame.len() as u64).encode(&mut buf);
+			buf.extend_from_slice(frame);
+		}
+		buf
+	}
+
+	fn decode_all(decoder: &mut GroupDecoder) -> Vec<Vec<u8>> {
+		let mut frames = Vec::new();
+
+		while let Some(event) = decoder.next_event().unwrap() {
+			match event {
+				GroupEvent::Frame { size, .. } => frames.push(Vec::with_capacity(size as usize)),
+				GroupEvent::Chunk(chunk) => frames.last_mut().unwrap().extend_from_slice(&chunk),
+				GroupEvent::FrameEnd => {}
+			}
+		}
+
+		frames
+	}
+
+	#[test]
+	fn decode_byte_by_byte() {
+		let large = vec![7u8; 300];
+		let expected: Vec<&[u8]> = vec![b"hello", b"", &large, b"world"];
+		let encoded = encode_frames(&expected);
+
+		let mut decoder = GroupDecoder::new(Version::Lite01);
+		let mut frames = Vec::new();
+
+		let mut current: Option<Vec<u8>> = None;
+		for byte in encoded {
+			decoder.push(Bytes::copy_from_slice(&[byte]));
+
+			while let Some(event) = decoder.next_event().unwrap() {
+				match event {
+					GroupEvent::Frame { size, .. } => current = Some(Vec::with_capacity(size as usize)),
+					GroupEvent::Chunk(chunk) => current.as_mut().unwrap().extend_from_slice(&chunk),
+					GroupEvent::FrameEnd => frames.push(current.take().unwrap()),
+				}
+			}
+		}
+
+		decoder.finish().unwrap();
+		assert_eq!(frames, expected);
+	}
+
+	#[test]
+	fn decode_chunks() {
+		let expected: Vec<&[u8]> = vec![b"hello", b"world"];
+		let encoded = Bytes::from(encode_frames(&expected));
+
+		// Everything at once, with frames split out of a single chunk.
+		let mut decoder = GroupDecoder::new(Version::Lite01);
+		decoder.push(encoded.clone());
+		assert_eq!(decode_all(&mut decoder), expected);
+		decoder.finish().unwrap();
+
+		// The stream ended in the middle of a frame.
+		let mut decoder = GroupDecoder::new(Version::Lite01);
+		decoder.push(encoded.slice(..encoded.len() - 1));
+		decode_all(&mut decoder);
+		assert!(matches!(decoder.finish(), Err(Error::WrongSize)));
+	}
+
+	#[test]
+	fn decode_extensions() {
+		let mut extensions = FrameExtensions::default();
+		extensions.set_int(2, 1234);
+
+		let encoded = encode_frames_extensions(&[(b"hello", extensions.clone()), (b"world", Default::default())]);
+
+		// Feed it byte by byte so the extensions span multiple chunks.
+		let mut decoder = GroupDecoder::new(Version::Lite02);
+		let mut headers = Vec::new();
+
+		for byte in encoded {
+			decoder.push(Bytes::copy_from_slice(&[byte]));
+
+			while let Some(event) = decoder.next_event().unwrap() {
+				if let GroupEvent::Frame { size, extensions } = event {
+					headers.push((size, extensions));
+				}
+			}
+		}
+
+		decoder.finish().unwrap();
+		assert_eq!(headers, vec![(5, extensions), (5, FrameExtensions::default())]);
+	}
+}
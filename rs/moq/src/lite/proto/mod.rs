@@ -0,0 +1,20 @@
+//! A sans-IO implementation of the moq-lite announce, subscribe, and group streams.
+//!
+//! These state machines consume decoded messages (or raw bytes for group streams) and return the messages to send and events to act on.
+//! They never perform any I/O or spawn tasks, so they can be embedded in any event loop or transport and tested byte by byte.
+//!
+//! The async [crate::Session] is one driver on top of this layer, backed by `web_transport_trait` and the model types such as [crate::TrackProducer].
+//! The setup handshake and track status requests are still handled by the driver directly.
+
+mod announce;
+mod group;
+mod subscribe;
+
+pub use announce::*;
+pub use group::*;
+pub use subscribe::*;
+
+// The version and messages exchanged by the state machines, encoded with [crate::coding::Message].
+pub use super::{
+	Announce, AnnounceInit, AnnouncePlease, Group, Subscribe, SubscribeDone, SubscribeDoneReason, SubscribeOk, Version,
+};
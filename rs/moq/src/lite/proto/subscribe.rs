@@ -0,0 +1,286 @@
+use std::collections::HashMap;
+
+use crate::{
+	lite::{Group, Subscribe, SubscribeDone, SubscribeOk, Version},
+	Error, TrackEnd,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum State {
+	Requested,
+	Active,
+	Done(TrackEnd),
+}
+
+/// The subscriber side of a subscribe stream.
+#[derive(Debug)]
+pub struct Subscription {
+	version: Version,
+	id: u64,
+	state: State,
+}
+
+impl Subscription {
+	/// Start a subscription after sending the [Subscribe] message.
+	pub fn new(version: Version, msg: &Subscribe<'_>) -> Self {
+		Self {
+			version,
+			id: msg.id,
+			state: State::Requested,
+		}
+	}
+
+	pub fn id(&self) -> u64 {
+		self.id
+	}
+
+	/// Process the publisher's response.
+	pub fn recv_ok(&mut self, _msg: SubscribeOk) -> Result<(), Error> {
+		match self.state {
+			State::Requested => {
+				self.state = State::Active;
+				Ok(())
+			}
+			_ => Err(Error::UnexpectedMessage),
+		}
+	}
+
+	/// Process the publisher's explanation for why the track ended.
+	pub fn recv_done(&mut self, msg: SubscribeDone) -> Result<(), Error> {
+		if !self.version.subscribe_done() {
+			return Err(Error::UnexpectedMessage);
+		}
+
+		match self.state {
+			State::Active => {
+				self.state = State::Done(msg.into());
+				Ok(())
+			}
+			_ => Err(Error::UnexpectedMessage),
+		}
+	}
+
+	/// Called when the publisher finishes the stream, returning how the track ended.
+	///
+	/// Returns None if the publisher did not say, in which case the latest group is the final group.
+	pub fn finish(self) -> Result<Option<TrackEnd>, Error> {
+		match self.state {
+			State::Requested => Err(Error::ProtocolViolation),
+			State::Active => Ok(None),
+			State::Done(end) => Ok(Some(end)),
+		}
+	}
+}
+
+/// The publisher side of a subscribe stream.
+#[derive(Debug)]
+pub struct Subscribed {
+	version: Version,
+	id: u64,
+	state: State,
+}
+
+impl Subscribed {
+	/// Start serving a [Subscribe] received from the subscriber.
+	pub fn new(version: Version, msg: &Subscribe<'_>) -> Self {
+		Self {
+			version,
+			id: msg.id,
+			state: State::Requested,
+		}
+	}
+
+	pub fn id(&self) -> u64 {
+		self.id
+	}
+
+	/// Accept the subscription, returning the message to send.
+	pub fn ok(&mut self, priority: u8) -> Result<SubscribeOk, Error> {
+		match self.state {
+			State::Requested => {
+				self.state = State::Active;
+				Ok(SubscribeOk { priority })
+			}
+			_ => Err(Error::UnexpectedMessage),
+		}
+	}
+
+	/// Return the header for a group stream belonging to this subscription.
+	pub fn group(&self, sequence: u64) -> Result<Group, Error> {
+		match self.state {
+			State::Active => Ok(Group {
+				subscribe: self.id,
+				sequence,
+			}),
+			_ => Err(Error::ProtocolViolation),
+		}
+	}
+
+	/// End the subscription, returning the message to send before finishing the stream.
+	///
+	/// Returns None if the negotiated version doesn't support [SubscribeDone].
+	pub fn done(&mut self, end: TrackEnd) -> Result<Option<SubscribeDone>, Error> {
+		match self.state {
+			State::Active => self.state = State::Done(end.clone()),
+			_ => return Err(Error::ProtocolViolation),
+		}
+
+		Ok(self.version.subscribe_done().then(|| end.into()))
+	}
+}
+
+/// The subscriber's active subscriptions, used to allocate IDs and route incoming groups.
+#[derive(Debug)]
+pub struct Subscriptions<T> {
+	next_id: u64,
+	active: HashMap<u64, T>,
+}
+
+impl<T> Default for Subscriptions<T> {
+	fn default() -> Self {
+		Self {
+			next_id: 0,
+			active: HashMap::new(),
+		}
+	}
+}
+
+impl<T> Subscriptions<T> {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Insert a new subscription, returning its ID.
+	pub fn insert(&mut self, value: T) -> u64 {
+		let id = self.next_id;
+		self.next_id += 1;
+		self.active.insert(id, value);
+		id
+	}
+
+	pub fn get(&self, id: u64) -> Option<&T> {
+		self.active.get(&id)
+	}
+
+	pub fn remove(&mut self, id: u64) -> Option<T> {
+		self.active.remove(&id)
+	}
+
+	/// Find the subscription for an incoming group.
+	///
+	/// Returns [Error::Cancel] if the subscription is no longer active.
+	pub fn route(&mut self, group: &Group) -> Result<&mut T, Error> {
+		self.active.get_mut(&group.subscribe).ok_or(Error::Cancel)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	use crate::{
+		coding::{Decode, Encode},
+		lite::SubscribeDoneReason,
+		TrackEndReason,
+	};
+
+	fn round_trip<T: Encode + Decode>(msg: &T) -> T {
+		let mut buf = Vec::new();
+		msg.encode(&mut buf);
+		T::decode(&mut buf.as_slice()).unwrap()
+	}
+
+	fn subscribe(id: u64) -> Subscribe<'static> {
+		Subscribe {
+			id,
+			broadcast: "room/alice".into(),
+			track: "video".into(),
+			priority: 3,
+		}
+	}
+
+	#[test]
+	fn subscribe_done() {
+		let msg = subscribe(7);
+		let mut subscription = Subscription::new(Version::Lite02, &msg);
+		let mut subscribed = Subscribed::new(Version::Lite02, &round_trip(&msg));
+		assert_eq!(subscribed.id(), 7);
+
+		// Groups can't be sent before the subscription is accepted.
+		assert!(subscribed.group(0).is_err());
+
+		let ok = subscribed.ok(3).unwrap();
+		subscription.recv_ok(round_trip(&ok)).unwrap();
+
+		let group = subscribed.group(4).unwrap();
+		assert_eq!((group.subscribe, group.sequence), (7, 4));
+
+		let end = TrackEnd {
+			final_sequence: Some(4),
+			reason: TrackEndReason::Unpublished,
+		};
+		let done = subscribed
+			.done(end.clone())
+			.unwrap()
+			.expect("should send SUBSCRIBE_DONE");
+		assert_eq!(done.reason, SubscribeDoneReason::Unpublished);
+
+		subscription.recv_done(round_trip(&done)).unwrap();
+		assert_eq!(subscription.finish().unwrap(), Some(end));
+	}
+
+	#[test]
+	fn subscribe_legacy() {
+		// Deployed versions finish the stream without SUBSCRIBE_DONE.
+		for version in [Version::Lite00, Version::Lite01] {
+			let msg = subscribe(0);
+			let mut subscription = Subscription::new(version, &msg);
+			let mut subscribed = Subscribed::new(version, &msg);
+
+			subscription.recv_ok(subscribed.ok(0).unwrap()).unwrap();
+			assert_eq!(subscribed.done(TrackEnd::default()).unwrap(), None);
+
+			let done = SubscribeDone {
+				final_sequence: None,
+				reason: SubscribeDoneReason::Ended,
+			};
+			assert!(matches!(subscription.recv_done(done), Err(Error::UnexpectedMessage)));
+			assert_eq!(subscription.finish().unwrap(), None);
+		}
+	}
+
+	#[test]
+	fn subscribe_errors() {
+		let msg = subscribe(0);
+
+		// The stream was finished before SUBSCRIBE_OK.
+		let subscription = Subscription::new(Version::Lite01, &msg);
+		assert!(matches!(subscription.finish(), Err(Error::ProtocolViolation)));
+
+		// A duplicate SUBSCRIBE_OK.
+		let mut subscription = Subscription::new(Version::Lite01, &msg);
+		subscription.recv_ok(SubscribeOk { priority: 0 }).unwrap();
+		assert!(matches!(
+			subscription.recv_ok(SubscribeOk { priority: 0 }),
+			Err(Error::UnexpectedMessage)
+		));
+	}
+
+	#[test]
+	fn subscriptions() {
+		let mut subscriptions = Subscriptions::new();
+		let a = subscriptions.insert("a");
+		let b = subscriptions.insert("b");
+		assert_ne!(a, b);
+
+		let group = Group {
+			subscribe: b,
+			sequence: 0,
+		};
+		assert_eq!(*subscriptions.route(&group).unwrap(), "b");
+
+		subscriptions.remove(b);
+		assert!(matches!(subscriptions.route(&group), Err(Error::Cancel)));
+		assert_eq!(subscriptions.get(a), Some(&"a"));
+	}
+}
@@ -1,41 +1,57 @@
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
+use futures::{
+	future::{AbortHandle, Abortable},
+	stream::FuturesUnordered,
+	FutureExt, StreamExt,
+};
 use web_async::FuturesExt;
 use web_transport_trait::SendStream;
 
 use crate::{
 	coding::{Stream, Writer},
-	lite,
+	lite::{self, proto},
 	model::GroupConsumer,
-	AsPath, BroadcastConsumer, Error, Origin, OriginConsumer, Track, TrackConsumer,
+	AsPath, BroadcastConsumer, Error, Origin, OriginConsumer, Stats, Track, TrackConsumer, TrackEnd, TrackEndReason,
 };
 
 pub(super) struct Publisher<S: web_transport_trait::Session> {
 	session: S,
+	version: lite::Version,
 	origin: OriginConsumer,
+	stats: Option<Stats>,
 }
 
 impl<S: web_transport_trait::Session> Publisher<S> {
-	pub fn new(session: S, origin: Option<OriginConsumer>) -> Self {
+	pub fn new(session: S, version: lite::Version, origin: Option<OriginConsumer>, stats: Option<Stats>) -> Self {
 		// Default to a dummy origin that is immediately closed.
 		let origin = origin.unwrap_or_else(|| Origin::produce().consumer);
-		Self { session, origin }
+		Self {
+			session,
+			version,
+			origin,
+			stats,
+		}
 	}
 
 	pub async fn run(mut self) -> Result<(), Error> {
 		loop {
-			let mut stream = Stream::accept(&self.session).await?;
+			let mut stream = Stream::accept(&self.session, self.stats.clone()).await?;
 
 			// To avoid cloning the origin, we process each control stream in received order.
 			// This adds some head-of-line blocking but it delays an expensive clone.
 			let kind = stream.reader.decode().await?;
 
 			if let Err(err) = match kind {
-				lite::ControlType::Session | lite::ControlType::ClientCompat | lite::ControlType::ServerCompat => {
-					Err(Error::UnexpectedStream)
-				}
+				lite::ControlType::Session
+				| lite::ControlType::ClientCompat
+				| lite::ControlType::ServerCompat
+				| lite::ControlType::ClientIetf
+				| lite::ControlType::ServerIetf => Err(Error::UnexpectedStream),
 				lite::ControlType::Announce => self.recv_announce(stream).await,
 				lite::ControlType::Subscribe => self.recv_subscribe(stream).await,
+				lite::ControlType::TrackStatus if self.version.track_status() => self.recv_track_status(stream).await,
+				lite::ControlType::TrackStatus => Err(Error::UnexpectedStream),
 			} {
 				tracing::warn!(%err, "control stream error");
 			}
@@ -44,7 +60,8 @@ impl<S: web_transport_trait::Session> Publisher<S> {
 
 	pub async fn recv_announce(&mut self, mut stream: Stream<S>) -> Result<(), Error> {
 		let interest = stream.reader.decode::<lite::AnnouncePlease>().await?;
-		let prefix = interest.prefix.to_owned();
+		let mut announcer = proto::Announcer::new(self.version, &interest);
+		let prefix = announcer.prefix().to_owned();
 
 		// For logging, show the full path that we're announcing.
 		tracing::trace!(root = %self.origin.absolute(&prefix), "announcing start");
@@ -55,22 +72,22 @@ impl<S: web_transport_trait::Session> Publisher<S> {
 			.ok_or(Error::Unauthorized)?;
 
 		web_async::spawn(async move {
-			if let Err(err) = Self::run_announce(&mut stream, &mut origin, &prefix).await {
+			if let Err(err) = Self::run_announce(&mut stream, &mut announcer, &mut origin).await {
 				match &err {
 					Error::Cancel => {
-						tracing::debug!(prefix = %origin.absolute(prefix), "announcing cancelled");
+						tracing::debug!(prefix = %origin.absolute(&prefix), "announcing cancelled");
 					}
 					Error::Transport(_) => {
-						tracing::debug!(prefix = %origin.absolute(prefix), "announcing cancelled");
+						tracing::debug!(prefix = %origin.absolute(&prefix), "announcing cancelled");
 					}
 					err => {
-						tracing::warn!(%err, prefix = %origin.absolute(prefix), "announcing error");
+						tracing::warn!(%err, prefix = %origin.absolute(&prefix), "announcing error");
 					}
 				}
 
 				stream.writer.abort(&err);
 			} else {
-				tracing::trace!(prefix = %origin.absolute(prefix), "announcing complete");
+				tracing::trace!(prefix = %origin.absolute(&prefix), "announcing complete");
 			}
 		});
 
@@ -79,31 +96,26 @@ impl<S: web_transport_trait::Session> Publisher<S> {
 
 	async fn run_announce(
 		stream: &mut Stream<S>,
+		announcer: &mut proto::Announcer,
 		origin: &mut OriginConsumer,
-		prefix: impl AsPath,
 	) -> Result<(), Error> {
-		let prefix = prefix.as_path();
-		let mut init = Vec::new();
-
-		// Send ANNOUNCE_INIT as the first message with all currently active paths
-		// We use `try_next()` to synchronously get the initial updates.
+		// Collect all currently active paths before sending the initial announcements.
+		// We use `try_announced()` to synchronously get the initial updates.
 		while let Some((path, active)) = origin.try_announced() {
-			let suffix = path.strip_prefix(&prefix).expect("origin returned invalid path");
-
 			if active.is_some() {
 				tracing::debug!(broadcast = %origin.absolute(&path), "announce");
-				init.push(suffix.to_owned());
 			} else {
 				// A potential race.
 				tracing::debug!(broadcast = %origin.absolute(&path), "unannounce");
-				init.retain(|path| path != &suffix);
 			}
+
+			announcer.update(&path, active.is_some())?;
 		}
 
-		let announce_init = lite::AnnounceInit { suffixes: init };
-		stream.writer.encode(&announce_init).await?;
+		for msg in announcer.init() {
+			Self::write_announce(stream, msg).await?;
+		}
 
-		// Flush any synchronously announced paths
 		loop {
 			tokio::select! {
 				biased;
@@ -111,16 +123,14 @@ impl<S: web_transport_trait::Session> Publisher<S> {
 				announced = origin.announced() => {
 					match announced {
 						Some((path, active)) => {
-							let suffix = path.strip_prefix(&prefix).expect("origin returned invalid path").to_owned();
-
 							if active.is_some() {
 								tracing::debug!(broadcast = %origin.absolute(&path), "announce");
-								let msg = lite::Announce::Active { suffix };
-								stream.writer.encode(&msg).await?;
 							} else {
 								tracing::debug!(broadcast = %origin.absolute(&path), "unannounce");
-								let msg = lite::Announce::Ended { suffix };
-								stream.writer.encode(&msg).await?;
+							}
+
+							if let Some(msg) = announcer.update(&path, active.is_some())? {
+								Self::write_announce(stream, msg).await?;
 							}
 						},
 						None => return stream.writer.finish().await,
@@ -130,6 +140,13 @@ impl<S: web_transport_trait::Session> Publisher<S> {
 		}
 	}
 
+	async fn write_announce(stream: &mut Stream<S>, msg: proto::AnnounceMessage) -> Result<(), Error> {
+		match msg {
+			proto::AnnounceMessage::Init(msg) => stream.writer.encode(&msg).await,
+			proto::AnnounceMessage::Update(msg) => stream.writer.encode(&msg).await,
+		}
+	}
+
 	pub async fn recv_subscribe(&mut self, mut stream: Stream<S>) -> Result<(), Error> {
 		let subscribe = stream.reader.decode::<lite::Subscribe>().await?;
 
@@ -142,8 +159,12 @@ impl<S: web_transport_trait::Session> Publisher<S> {
 		let broadcast = self.origin.consume_broadcast(&subscribe.broadcast);
 
 		let session = self.session.clone();
+		let version = self.version;
+		let stats = self.stats.clone();
 		web_async::spawn(async move {
-			if let Err(err) = Self::run_subscribe(session, &mut stream, &subscribe, broadcast).await {
+			let _subscription = stats.as_ref().map(|stats| stats.subscribe(absolute.as_str()));
+
+			if let Err(err) = Self::run_subscribe(session, version, stats, &mut stream, &subscribe, broadcast).await {
 				match &err {
 					// TODO better classify WebTransport errors.
 					Error::Cancel | Error::Transport(_) => {
@@ -162,12 +183,55 @@ impl<S: web_transport_trait::Session> Publisher<S> {
 		Ok(())
 	}
 
+	pub async fn recv_track_status(&mut self, mut stream: Stream<S>) -> Result<(), Error> {
+		let request = stream.reader.decode::<lite::TrackStatusRequest>().await?;
+
+		let track = request.track.to_string();
+		let absolute = self.origin.absolute(&request.broadcast).to_owned();
+
+		tracing::debug!(broadcast = %absolute, %track, "track status");
+
+		let broadcast = self.origin.consume_broadcast(&request.broadcast);
+
+		web_async::spawn(async move {
+			if let Err(err) = Self::run_track_status(&mut stream, &track, broadcast).await {
+				tracing::debug!(broadcast = %absolute, %track, %err, "track status error");
+				stream.writer.abort(&err);
+			}
+		});
+
+		Ok(())
+	}
+
+	async fn run_track_status(
+		stream: &mut Stream<S>,
+		track: &str,
+		broadcast: Option<BroadcastConsumer>,
+	) -> Result<(), Error> {
+		let status = match broadcast {
+			Some(broadcast) => broadcast.track_status(track).await,
+			None => Err(Error::NotFound),
+		};
+
+		let status = match status {
+			Err(Error::NotFound) | Ok(_) => lite::TrackStatus::from(status),
+			Err(err) => return Err(err),
+		};
+
+		stream.writer.encode(&status).await?;
+		stream.writer.finish().await
+	}
+
 	async fn run_subscribe(
 		session: S,
+		version: lite::Version,
+		stats: Option<Stats>,
 		stream: &mut Stream<S>,
 		subscribe: &lite::Subscribe<'_>,
 		consumer: Option<BroadcastConsumer>,
 	) -> Result<(), Error> {
+		let mut subscribed = proto::Subscribed::new(version, subscribe);
+
 		let track = Track {
 			name: subscribe.track.to_string(),
 			priority: subscribe.priority,
@@ -177,98 +241,103 @@ impl<S: web_transport_trait::Session> Publisher<S> {
 		let track = broadcast.subscribe_track(&track);
 
 		// TODO wait until track.info() to get the *real* priority
+		let info = subscribed.ok(track.info.priority)?;
+		stream.writer.encode(&info).await?;
 
-		let info = lite::SubscribeOk {
-			priority: track.info.priority,
+		let end = tokio::select! {
+			res = Self::run_track(session, version, stats, track.clone(), &subscribed) => {
+				res?;
+				// Forward why and where the track ended.
+				track.closed().await?
+			}
+			_ = broadcast.closed() => TrackEnd {
+				final_sequence: track.status().latest,
+				reason: TrackEndReason::Unpublished,
+			},
+			res = stream.reader.closed() => {
+				res?;
+				return stream.writer.finish().await;
+			}
 		};
 
-		stream.writer.encode(&info).await?;
-
-		tokio::select! {
-			res = Self::run_track(session, track, subscribe) => res?,
-			res = stream.reader.closed() => res?,
+		if let Some(done) = subscribed.done(end)? {
+			stream.writer.encode(&done).await?;
 		}
 
 		stream.writer.finish().await
 	}
 
-	async fn run_track(session: S, mut track: TrackConsumer, subscribe: &lite::Subscribe<'_>) -> Result<(), Error> {
-		// TODO use a BTreeMap serve the latest N groups by sequence.
-		// Until then, we'll implement N=2 manually.
-		// Also, this is more complicated because we can't use tokio because of WASM.
-		// We need to drop futures in order to cancel them and keep polling them with select!
-		let mut old_group = None;
-		let mut new_group = None;
+	async fn run_track(
+		session: S,
+		version: lite::Version,
+		stats: Option<Stats>,
+		mut track: TrackConsumer,
+		subscribed: &proto::Subscribed,
+	) -> Result<(), Error> {
+		let mut scheduler = proto::GroupScheduler::new();
 
-		// Annoying that we can't use a tuple here as we need the compiler to infer the type.
-		// Otherwise we'd have to pick Send or !Send...
-		let mut old_sequence = None;
-		let mut new_sequence = None;
+		// The groups currently being served, which are aborted when the scheduler decides.
+		// We can't use tokio::spawn because of WASM, so we drop futures in order to cancel them.
+		let mut serving = FuturesUnordered::new();
+		let mut aborts: HashMap<u64, AbortHandle> = HashMap::new();
 
 		// Keep reading groups from the track, some of which may arrive out of order.
 		loop {
 			let group = tokio::select! {
 				biased;
 				Some(group) = track.next_group().transpose() => group,
-				Some(_) = async { Some(old_group.as_mut()?.await) } => {
-					old_group = None;
-					old_sequence = None;
-					continue;
-				},
-				Some(_) = async { Some(new_group.as_mut()?.await) } => {
-					new_group = old_group;
-					new_sequence = old_sequence;
-					old_group = None;
-					old_sequence = None;
+				Some(sequence) = serving.next() => {
+					aborts.remove(&sequence);
+					scheduler.finished(sequence);
 					continue;
 				},
 				else => return Ok(()),
 			}?;
 
 			let sequence = group.info.sequence;
-			let latest = new_sequence.as_ref().unwrap_or(&0);
-
-			tracing::debug!(subscribe = %subscribe.id, track = %track.info.name, sequence, latest, "serving group");
+			tracing::debug!(subscribe = %subscribed.id(), track = %track.info.name, sequence, "serving group");
 
-			// If this group is older than the oldest group we're serving, skip it.
-			// We always serve at most two groups, but maybe we should serve only sequence >= MAX-1.
-			if sequence < *old_sequence.as_ref().unwrap_or(&0) {
-				tracing::debug!(subscribe = %subscribe.id, track = %track.info.name, old = %sequence, %latest, "skipping group");
-				continue;
-			}
-
-			let priority = stream_priority(track.info.priority, sequence);
-			let msg = lite::Group {
-				subscribe: subscribe.id,
-				sequence,
+			let abort = match scheduler.push(sequence) {
+				proto::Schedule::Skip => {
+					tracing::debug!(subscribe = %subscribed.id(), track = %track.info.name, old = %sequence, "skipping group");
+					stats.iter().for_each(Stats::drop_group);
+					continue;
+				}
+				proto::Schedule::Serve { abort } => abort,
 			};
 
-			// Spawn a task to serve this group, ignoring any errors because they don't really matter.
-			// TODO add some logging at least.
-			let handle = Box::pin(Self::serve_group(session.clone(), msg, priority, group));
-
-			// Terminate the old group if it's still running.
-			if let Some(old_sequence) = old_sequence.take() {
-				tracing::debug!(subscribe = %subscribe.id, track = %track.info.name, old = %old_sequence, %latest, "aborting group");
-				old_group.take(); // Drop the future to cancel it.
+			if let Some(old) = abort {
+				tracing::debug!(subscribe = %subscribed.id(), track = %track.info.name, %old, latest = %sequence, "aborting group");
+				if let Some(handle) = aborts.remove(&old) {
+					handle.abort();
+					stats.iter().for_each(Stats::drop_group);
+				}
 			}
 
-			assert!(old_group.is_none());
+			let msg = subscribed.group(sequence)?;
+			let priority = proto::stream_priority(track.info.priority, sequence);
 
-			if sequence >= *latest {
-				old_group = new_group;
-				old_sequence = new_sequence;
-
-				new_group = Some(handle);
-				new_sequence = Some(sequence);
-			} else {
-				old_group = Some(handle);
-				old_sequence = Some(sequence);
-			}
+			// Serve this group in the background, ignoring any errors because they don't really matter.
+			// TODO add some logging at least.
+			let (handle, registration) = AbortHandle::new_pair();
+			let serve = Abortable::new(
+				Self::serve_group(session.clone(), version, stats.clone(), msg, priority, group),
+				registration,
+			);
+
+			serving.push(serve.map(move |_| sequence));
+			aborts.insert(sequence, handle);
 		}
 	}
 
-	async fn serve_group(session: S, msg: lite::Group, priority: i32, mut group: GroupConsumer) -> Result<(), Error> {
+	async fn serve_group(
+		session: S,
+		version: lite::Version,
+		stats: Option<Stats>,
+		msg: lite::Group,
+		priority: i32,
+		mut group: GroupConsumer,
+	) -> Result<(), Error> {
 		// TODO add a way to open in priority order.
 		let mut stream = session
 			.open_uni()
@@ -276,7 +345,7 @@ impl<S: web_transport_trait::Session> Publisher<S> {
 			.map_err(|err| Error::Transport(Arc::new(err)))?;
 		stream.set_priority(priority);
 
-		let mut stream = Writer::new(stream);
+		let mut stream = Writer::new(stream, stats);
 		stream.encode(&lite::DataType::Group).await?;
 		stream.encode(&msg).await?;
 
@@ -294,6 +363,10 @@ impl<S: web_transport_trait::Session> Publisher<S> {
 
 			tracing::trace!(size = %frame.info.size, "writing frame");
 
+			if version.frame_extensions() {
+				stream.encode(&frame.info.extensions).await?;
+			}
+
 			stream.encode(&frame.info.size).await?;
 
 			loop {
@@ -319,34 +392,3 @@ impl<S: web_transport_trait::Session> Publisher<S> {
 		Ok(())
 	}
 }
-
-// Quinn takes a i32 priority.
-// We do our best to distill 70 bits of information into 32 bits, but overflows will happen.
-// Specifically, group sequence 2^24 will overflow and be incorrectly prioritized.
-// But even with a group per frame, it will take ~6 days to reach that point.
-// TODO The behavior when two tracks share the same priority is undefined. Should we round-robin?
-fn stream_priority(track_priority: u8, group_sequence: u64) -> i32 {
-	let sequence = 0xFFFFFF - (group_sequence as u32 & 0xFFFFFF);
-	((track_priority as i32) << 24) | sequence as i32
-}
-
-#[cfg(test)]
-mod test {
-	use super::*;
-
-	#[test]
-	fn priority() {
-		let assert = |track_priority, group_sequence, expected| {
-			assert_eq!(stream_priority(track_priority, group_sequence), expected);
-		};
-
-		const U24: i32 = (1 << 24) - 1;
-
-		// NOTE: The lower the value, the higher the priority for Quinn.
-		// MoQ does the opposite, so we invert the values.
-		assert(0, 50, U24 - 50);
-		assert(0, 0, U24);
-		assert(1, 50, 2 * U24 - 49);
-		assert(1, 0, 2 * U24 + 1);
-	}
-}
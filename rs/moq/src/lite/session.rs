@@ -1,20 +1,24 @@
 use tokio::sync::oneshot;
 
-use crate::{coding::Stream, lite::SessionInfo, Error, OriginConsumer, OriginProducer};
+use crate::{coding::Stream, lite::SessionInfo, Error, OriginConsumer, OriginProducer, Stats};
 
-use super::{Publisher, Subscriber};
+use super::{Publisher, Subscriber, Version};
 
 pub(crate) async fn start<S: web_transport_trait::Session + Sync>(
 	session: S,
 	// The stream used to setup the session, after exchanging setup messages.
 	setup: Stream<S>,
+	// The negotiated wire version.
+	version: Version,
 	// We will publish any local broadcasts from this origin.
 	publish: Option<OriginConsumer>,
 	// We will consume any remote broadcasts, inserting them into this origin.
 	subscribe: Option<OriginProducer>,
+	// Count the traffic and subscriptions for this session, if provided.
+	stats: Option<Stats>,
 ) -> Result<(), Error> {
-	let publisher = Publisher::new(session.clone(), publish);
-	let subscriber = Subscriber::new(session.clone(), subscribe);
+	let publisher = Publisher::new(session.clone(), version, publish, stats.clone());
+	let subscriber = Subscriber::new(session.clone(), version, subscribe, stats);
 
 	let init = oneshot::channel();
 
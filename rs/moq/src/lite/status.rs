@@ -0,0 +1,111 @@
+use std::borrow::Cow;
+
+use num_enum::{IntoPrimitive, TryFromPrimitive};
+
+use crate::{
+	coding::{Decode, DecodeError, Encode, Message},
+	Path,
+};
+
+/// Sent by the subscriber to query the state of a track without subscribing.
+#[derive(Clone, Debug)]
+pub struct TrackStatusRequest<'a> {
+	pub broadcast: Path<'a>,
+	pub track: Cow<'a, str>,
+}
+
+impl<'a> Message for TrackStatusRequest<'a> {
+	fn decode<R: bytes::Buf>(r: &mut R) -> Result<Self, DecodeError> {
+		let broadcast = Path::decode(r)?;
+		let track = Cow::<str>::decode(r)?;
+
+		Ok(Self { broadcast, track })
+	}
+
+	fn encode<W: bytes::BufMut>(&self, w: &mut W) {
+		self.broadcast.encode(w);
+		self.track.encode(w);
+	}
+}
+
+/// Sent by the publisher in response to a [TrackStatusRequest], then the stream is finished.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TrackStatus {
+	/// The track does not exist.
+	NotFound,
+
+	/// The track exists, with the sequence number of the latest group if any.
+	Active { latest: Option<u64> },
+
+	/// The track has ended, with the sequence number of the final group if any.
+	Ended { latest: Option<u64> },
+}
+
+impl Message for TrackStatus {
+	fn decode<R: bytes::Buf>(r: &mut R) -> Result<Self, DecodeError> {
+		let code = TrackStatusCode::decode(r)?;
+
+		// The latest sequence is offset by one so zero can mean "no groups".
+		let latest = match u64::decode(r)? {
+			0 => None,
+			sequence => Some(sequence - 1),
+		};
+
+		Ok(match code {
+			TrackStatusCode::NotFound => Self::NotFound,
+			TrackStatusCode::Active => Self::Active { latest },
+			TrackStatusCode::Ended => Self::Ended { latest },
+		})
+	}
+
+	fn encode<W: bytes::BufMut>(&self, w: &mut W) {
+		let (code, latest) = match self {
+			Self::NotFound => (TrackStatusCode::NotFound, None),
+			Self::Active { latest } => (TrackStatusCode::Active, *latest),
+			Self::Ended { latest } => (TrackStatusCode::Ended, *latest),
+		};
+
+		code.encode(w);
+		latest.map_or(0, |sequence| sequence + 1).encode(w);
+	}
+}
+
+impl From<crate::Result<crate::TrackStatus>> for TrackStatus {
+	fn from(status: crate::Result<crate::TrackStatus>) -> Self {
+		match status {
+			Ok(status) if status.ended => Self::Ended { latest: status.latest },
+			Ok(status) => Self::Active { latest: status.latest },
+			Err(_) => Self::NotFound,
+		}
+	}
+}
+
+impl From<TrackStatus> for crate::Result<crate::TrackStatus> {
+	fn from(status: TrackStatus) -> Self {
+		match status {
+			TrackStatus::NotFound => Err(crate::Error::NotFound),
+			TrackStatus::Active { latest } => Ok(crate::TrackStatus { latest, ended: false }),
+			TrackStatus::Ended { latest } => Ok(crate::TrackStatus { latest, ended: true }),
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug, IntoPrimitive, TryFromPrimitive)]
+#[repr(u8)]
+enum TrackStatusCode {
+	NotFound = 0,
+	Active = 1,
+	Ended = 2,
+}
+
+impl Decode for TrackStatusCode {
+	fn decode<R: bytes::Buf>(r: &mut R) -> Result<Self, DecodeError> {
+		Self::try_from(u8::decode(r)?).map_err(|_| DecodeError::InvalidValue)
+	}
+}
+
+impl Encode for TrackStatusCode {
+	fn encode<W: bytes::BufMut>(&self, w: &mut W) {
+		(*self as u8).encode(w)
+	}
+}
@@ -5,10 +5,15 @@ pub enum ControlType {
 	Session,
 	Announce,
 	Subscribe,
+	TrackStatus,
 
 	// Backwards compatibility with moq-transport-10
 	ClientCompat,
 	ServerCompat,
+
+	// Compatibility with moq-transport-11, which changed the setup message IDs.
+	ClientIetf,
+	ServerIetf,
 }
 
 impl Decode for ControlType {
@@ -18,6 +23,9 @@ impl Decode for ControlType {
 			0 => Ok(Self::Session),
 			1 => Ok(Self::Announce),
 			2 => Ok(Self::Subscribe),
+			3 => Ok(Self::TrackStatus),
+			0x20 => Ok(Self::ClientIetf),
+			0x21 => Ok(Self::ServerIetf),
 			0x40 => Ok(Self::ClientCompat),
 			0x41 => Ok(Self::ServerCompat),
 			_ => Err(DecodeError::InvalidMessage(t)),
@@ -31,6 +39,9 @@ impl Encode for ControlType {
 			Self::Session => 0,
 			Self::Announce => 1,
 			Self::Subscribe => 2,
+			Self::TrackStatus => 3,
+			Self::ClientIetf => 0x20,
+			Self::ServerIetf => 0x21,
 			Self::ClientCompat => 0x40,
 			Self::ServerCompat => 0x41,
 		};
@@ -1,8 +1,10 @@
 use std::borrow::Cow;
 
+use num_enum::{IntoPrimitive, TryFromPrimitive};
+
 use crate::{
 	coding::{Decode, DecodeError, Encode, Message},
-	Path,
+	Path, TrackEnd, TrackEndReason,
 };
 
 /// Sent by the subscriber to request all future objects for the given track.
@@ -54,3 +56,89 @@ impl Message for SubscribeOk {
 		Ok(Self { priority })
 	}
 }
+
+/// Sent by the publisher after all groups have been sent, then the stream is finished.
+///
+/// A stream that is finished without this message is treated as [SubscribeDoneReason::Ended] with no final group.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SubscribeDone {
+	/// The sequence number of the final group, if any.
+	pub final_sequence: Option<u64>,
+	pub reason: SubscribeDoneReason,
+}
+
+impl Message for SubscribeDone {
+	fn encode<W: bytes::BufMut>(&self, w: &mut W) {
+		// The final sequence is offset by one so zero can mean "no groups".
+		self.final_sequence.map_or(0, |sequence| sequence + 1).encode(w);
+		self.reason.encode(w);
+	}
+
+	fn decode<R: bytes::Buf>(r: &mut R) -> Result<Self, DecodeError> {
+		let final_sequence = match u64::decode(r)? {
+			0 => None,
+			sequence => Some(sequence - 1),
+		};
+		let reason = SubscribeDoneReason::decode(r)?;
+
+		Ok(Self { final_sequence, reason })
+	}
+}
+
+impl From<TrackEnd> for SubscribeDone {
+	fn from(end: TrackEnd) -> Self {
+		Self {
+			final_sequence: end.final_sequence,
+			reason: end.reason.into(),
+		}
+	}
+}
+
+impl From<SubscribeDone> for TrackEnd {
+	fn from(done: SubscribeDone) -> Self {
+		Self {
+			final_sequence: done.final_sequence,
+			reason: done.reason.into(),
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, IntoPrimitive, TryFromPrimitive)]
+#[repr(u8)]
+pub enum SubscribeDoneReason {
+	Ended = 0,
+	Unpublished = 1,
+	Expired = 2,
+}
+
+impl Decode for SubscribeDoneReason {
+	fn decode<R: bytes::Buf>(r: &mut R) -> Result<Self, DecodeError> {
+		Self::try_from(u8::decode(r)?).map_err(|_| DecodeError::InvalidValue)
+	}
+}
+
+impl Encode for SubscribeDoneReason {
+	fn encode<W: bytes::BufMut>(&self, w: &mut W) {
+		(*self as u8).encode(w)
+	}
+}
+
+impl From<TrackEndReason> for SubscribeDoneReason {
+	fn from(reason: TrackEndReason) -> Self {
+		match reason {
+			TrackEndReason::Ended => Self::Ended,
+			TrackEndReason::Unpublished => Self::Unpublished,
+			TrackEndReason::Expired => Self::Expired,
+		}
+	}
+}
+
+impl From<SubscribeDoneReason> for TrackEndReason {
+	fn from(reason: SubscribeDoneReason) -> Self {
+		match reason {
+			SubscribeDoneReason::Ended => Self::Ended,
+			SubscribeDoneReason::Unpublished => Self::Unpublished,
+			SubscribeDoneReason::Expired => Self::Expired,
+		}
+	}
+}
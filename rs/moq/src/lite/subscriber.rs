@@ -1,14 +1,11 @@
-use std::{
-	collections::{hash_map::Entry, HashMap},
-	sync::{atomic, Arc},
-};
+use std::{collections::HashMap, sync::Arc};
 
 use crate::{
 	coding::{Reader, Stream},
-	lite,
+	lite::{self, proto},
 	model::BroadcastProducer,
-	AsPath, Broadcast, Error, Frame, FrameProducer, Group, GroupProducer, OriginProducer, Path, PathOwned,
-	TrackProducer,
+	AsPath, Broadcast, Error, Frame, FrameProducer, Group, GroupProducer, OriginProducer, Path, PathOwned, Stats,
+	TrackEnd, TrackProducer, TrackStatusRequest,
 };
 
 use tokio::sync::oneshot;
@@ -17,19 +14,21 @@ use web_async::Lock;
 #[derive(Clone)]
 pub(super) struct Subscriber<S: web_transport_trait::Session> {
 	session: S,
+	version: lite::Version,
 
 	origin: Option<OriginProducer>,
-	subscribes: Lock<HashMap<u64, TrackProducer>>,
-	next_id: Arc<atomic::AtomicU64>,
+	subscribes: Lock<proto::Subscriptions<TrackProducer>>,
+	stats: Option<Stats>,
 }
 
 impl<S: web_transport_trait::Session> Subscriber<S> {
-	pub fn new(session: S, origin: Option<OriginProducer>) -> Self {
+	pub fn new(session: S, version: lite::Version, origin: Option<OriginProducer>, stats: Option<Stats>) -> Self {
 		Self {
 			session,
+			version,
 			origin,
 			subscribes: Default::default(),
-			next_id: Default::default(),
+			stats,
 		}
 	}
 
@@ -49,7 +48,7 @@ impl<S: web_transport_trait::Session> Subscriber<S> {
 				.await
 				.map_err(|err| Error::Transport(Arc::new(err)))?;
 
-			let stream = Reader::new(stream);
+			let stream = Reader::new(stream, self.stats.clone());
 			let this = self.clone();
 
 			web_async::spawn(async move {
@@ -81,58 +80,61 @@ impl<S: web_transport_trait::Session> Subscriber<S> {
 			return Ok(());
 		}
 
-		let mut stream = Stream::open(&self.session).await?;
+		let mut stream = Stream::open(&self.session, self.stats.clone()).await?;
 		stream.writer.encode(&lite::ControlType::Announce).await?;
 
 		tracing::trace!(root = %self.log_path(""), "announced start");
 
 		// Ask for everything.
 		// TODO This should actually ask for each root.
-		let msg = lite::AnnouncePlease { prefix: "".into() };
-		stream.writer.encode(&msg).await?;
+		let mut listener = proto::AnnounceListener::new(self.version, "");
+		stream.writer.encode(&listener.please()).await?;
 
 		let mut producers = HashMap::new();
 
-		let msg: lite::AnnounceInit = stream.reader.decode().await?;
-		for path in msg.suffixes {
-			self.start_announce(path, &mut producers)?;
+		if !listener.is_initialized() {
+			let msg: lite::AnnounceInit = stream.reader.decode().await?;
+			for event in listener.recv_init(msg)? {
+				self.recv_announce(event, &mut producers);
+			}
 		}
 
 		let _ = init.send(());
 
 		while let Some(announce) = stream.reader.decode_maybe::<lite::Announce>().await? {
-			match announce {
-				lite::Announce::Active { suffix: path } => {
-					self.start_announce(path, &mut producers)?;
-				}
-				lite::Announce::Ended { suffix: path } => {
-					tracing::debug!(broadcast = %self.log_path(&path), "unannounced");
+			let event = listener.recv(announce)?;
+			self.recv_announce(event, &mut producers);
+		}
+
+		// Close the stream when there's nothing more to announce.
+		stream.writer.finish().await
+	}
 
-					// Close the producer.
-					let mut producer = producers.remove(&path.into_owned()).ok_or(Error::NotFound)?;
+	fn recv_announce(&mut self, event: proto::AnnounceEvent, producers: &mut HashMap<PathOwned, BroadcastProducer>) {
+		match event {
+			proto::AnnounceEvent::Active(path) => self.start_announce(path, producers),
+			proto::AnnounceEvent::Ended(path) => {
+				tracing::debug!(broadcast = %self.log_path(&path), "unannounced");
+
+				// Close the producer.
+				if let Some(mut producer) = producers.remove(&path) {
 					producer.close();
 				}
 			}
 		}
-
-		// Close the stream when there's nothing more to announce.
-		stream.writer.finish().await
 	}
 
-	fn start_announce(
-		&mut self,
-		path: PathOwned,
-		producers: &mut HashMap<PathOwned, BroadcastProducer>,
-	) -> Result<(), Error> {
+	fn start_announce(&mut self, path: PathOwned, producers: &mut HashMap<PathOwned, BroadcastProducer>) {
 		tracing::debug!(broadcast = %self.log_path(&path), suffix = %path, "announce");
 
-		let broadcast = Broadcast::produce();
+		let mut broadcast = Broadcast::produce();
 
-		// Make sure the peer doesn't double announce.
-		match producers.entry(path.to_owned()) {
-			Entry::Occupied(_) => return Err(Error::Duplicate),
-			Entry::Vacant(entry) => entry.insert(broadcast.producer.clone()),
-		};
+		// The remote knows about tracks that we haven't subscribed to yet.
+		if self.version.track_status() {
+			broadcast.producer.forward_status();
+		}
+
+		producers.insert(path.clone(), broadcast.producer.clone());
 
 		// Run the broadcast in the background until all consumers are dropped.
 		self.origin
@@ -141,11 +143,9 @@ impl<S: web_transport_trait::Session> Subscriber<S> {
 			.publish_broadcast(path.clone(), broadcast.consumer);
 
 		web_async::spawn(self.clone().run_broadcast(path, broadcast.producer));
-
-		Ok(())
 	}
 
-	async fn run_broadcast(self, path: PathOwned, mut broadcast: BroadcastProducer) {
+	async fn run_broadcast(self, path: PathOwned, broadcast: BroadcastProducer) {
 		// Actually start serving subscriptions.
 		loop {
 			// Keep serving requests until there are no more consumers.
@@ -156,23 +156,28 @@ impl<S: web_transport_trait::Session> Subscriber<S> {
 					Some(producer) => producer,
 					None => break,
 				},
+				request = broadcast.requested_status() => match request {
+					Some(request) => {
+						web_async::spawn(self.clone().run_track_status(path.clone(), request));
+						continue;
+					}
+					None => break,
+				},
 				_ = self.session.closed() => break,
 			};
 
-			let id = self.next_id.fetch_add(1, atomic::Ordering::Relaxed);
+			let id = self.subscribes.lock().insert(track.clone());
 			let mut this = self.clone();
 
 			let path = path.clone();
 			web_async::spawn(async move {
 				this.run_subscribe(id, path, track).await;
-				this.subscribes.lock().remove(&id);
+				this.subscribes.lock().remove(id);
 			});
 		}
 	}
 
 	async fn run_subscribe(&mut self, id: u64, broadcast: Path<'_>, track: TrackProducer) {
-		self.subscribes.lock().insert(id, track.clone());
-
 		let msg = lite::Subscribe {
 			id,
 			broadcast: broadcast.to_owned(),
@@ -196,35 +201,87 @@ impl<S: web_transport_trait::Session> Subscriber<S> {
 				tracing::warn!(id, broadcast = %self.log_path(&broadcast), track = %track.info.name, %err, "subscribe error");
 				track.abort(err);
 			}
-			_ => {
+			Ok(Some(end)) => {
+				tracing::info!(id, broadcast = %self.log_path(&broadcast), track = %track.info.name, reason = ?end.reason, final_sequence = ?end.final_sequence, "subscribe complete");
+
+				// Keep accepting trailing groups until the track is no longer used.
+				let unused = track.unused();
+				track.end(end);
+
+				tokio::select! {
+					_ = unused => {}
+					_ = self.session.closed() => {}
+				}
+			}
+			Ok(None) => {
 				tracing::info!(id, broadcast = %self.log_path(&broadcast), track = %track.info.name, "subscribe complete");
 				track.close();
 			}
 		}
 	}
 
-	async fn run_track(&mut self, msg: lite::Subscribe<'_>) -> Result<(), Error> {
-		let mut stream = Stream::open(&self.session).await?;
+	async fn run_track(&mut self, msg: lite::Subscribe<'_>) -> Result<Option<TrackEnd>, Error> {
+		let mut stream = Stream::open(&self.session, self.stats.clone()).await?;
 		stream.writer.encode(&lite::ControlType::Subscribe).await?;
 
-		if let Err(err) = self.run_track_stream(&mut stream, msg).await {
-			stream.writer.abort(&err);
-			return Err(err);
-		}
+		let done = match self.run_track_stream(&mut stream, msg).await {
+			Ok(done) => done,
+			Err(err) => {
+				stream.writer.abort(&err);
+				return Err(err);
+			}
+		};
 
-		stream.writer.finish().await
+		stream.writer.finish().await?;
+
+		Ok(done)
 	}
 
-	async fn run_track_stream(&mut self, stream: &mut Stream<S>, msg: lite::Subscribe<'_>) -> Result<(), Error> {
+	async fn run_track_stream(
+		&mut self,
+		stream: &mut Stream<S>,
+		msg: lite::Subscribe<'_>,
+	) -> Result<Option<TrackEnd>, Error> {
+		let mut subscription = proto::Subscription::new(self.version, &msg);
 		stream.writer.encode(&msg).await?;
 
 		// TODO use the response correctly populate the track info
-		let _info: lite::SubscribeOk = stream.reader.decode().await?;
+		subscription.recv_ok(stream.reader.decode().await?)?;
 
-		// Wait until the stream is closed
-		stream.reader.closed().await?;
+		// The publisher may say why the track ended before closing the stream.
+		if let Some(done) = stream.reader.decode_maybe::<lite::SubscribeDone>().await? {
+			subscription.recv_done(done)?;
 
-		Ok(())
+			// Wait until the stream is closed
+			stream.reader.closed().await?;
+		}
+
+		subscription.finish()
+	}
+
+	async fn run_track_status(self, broadcast: PathOwned, request: TrackStatusRequest) {
+		let msg = lite::TrackStatusRequest {
+			broadcast: broadcast.as_path(),
+			track: request.name.as_str().into(),
+		};
+
+		let res = self.run_track_status_stream(msg).await;
+		if let Err(err) = &res {
+			tracing::debug!(broadcast = %self.log_path(&broadcast), track = %request.name, %err, "track status error");
+		}
+
+		request.respond(res.and_then(|status| status.into()));
+	}
+
+	async fn run_track_status_stream(&self, msg: lite::TrackStatusRequest<'_>) -> Result<lite::TrackStatus, Error> {
+		let mut stream = Stream::open(&self.session, self.stats.clone()).await?;
+		stream.writer.encode(&lite::ControlType::TrackStatus).await?;
+		stream.writer.encode(&msg).await?;
+
+		let status = stream.reader.decode().await?;
+		stream.writer.finish().await?;
+
+		Ok(status)
 	}
 
 	pub async fn recv_group(&mut self, stream: &mut Reader<S::RecvStream>) -> Result<(), Error> {
@@ -232,7 +289,7 @@ impl<S: web_transport_trait::Session> Subscriber<S> {
 
 		let group = {
 			let mut subs = self.subscribes.lock();
-			let track = subs.get_mut(&hdr.subscribe).ok_or(Error::Cancel)?;
+			let track = subs.route(&hdr)?;
 
 			let group = Group { sequence: hdr.sequence };
 			track.create_group(group).ok_or(Error::Old)?
@@ -262,43 +319,52 @@ impl<S: web_transport_trait::Session> Subscriber<S> {
 	}
 
 	async fn run_group(&mut self, stream: &mut Reader<S::RecvStream>, mut group: GroupProducer) -> Result<(), Error> {
-		while let Some(size) = stream.decode_maybe::<u64>().await? {
-			let frame = group.create_frame(Frame { size });
+		let mut decoder = proto::GroupDecoder::new(self.version);
+		let mut frame: Option<FrameProducer> = None;
 
-			let res = tokio::select! {
-				_ = frame.unused() => Err(Error::Cancel),
-				res = self.run_frame(stream, frame.clone()) => res,
-			};
+		const MAX_CHUNK: usize = 1024 * 1024; // 1 MiB
 
-			if let Err(err) = res {
-				frame.abort(err.clone());
-				return Err(err);
+		loop {
+			while let Some(event) = decoder.next_event()? {
+				match event {
+					proto::GroupEvent::Frame { size, extensions } => {
+						tracing::trace!(%size, "reading frame");
+						frame = Some(group.create_frame(Frame { size, extensions }));
+					}
+					proto::GroupEvent::Chunk(chunk) => {
+						frame.as_mut().expect("chunk without a frame").write_chunk(chunk);
+					}
+					proto::GroupEvent::FrameEnd => {
+						let frame = frame.take().expect("end without a frame");
+						tracing::trace!(size = %frame.info.size, "read frame");
+						frame.close();
+					}
+				}
 			}
-		}
-
-		group.close();
-
-		Ok(())
-	}
 
-	async fn run_frame(&mut self, stream: &mut Reader<S::RecvStream>, mut frame: FrameProducer) -> Result<(), Error> {
-		let mut remain = frame.info.size;
+			let chunk = match &frame {
+				Some(current) => tokio::select! {
+					_ = current.unused() => Err(Error::Cancel),
+					chunk = stream.read(MAX_CHUNK) => chunk,
+				},
+				None => stream.read(MAX_CHUNK).await,
+			};
 
-		tracing::trace!(size = %frame.info.size, "reading frame");
+			match chunk {
+				Ok(Some(chunk)) => decoder.push(chunk),
+				Ok(None) => break,
+				Err(err) => {
+					if let Some(frame) = frame.take() {
+						frame.abort(err.clone());
+					}
 
-		const MAX_CHUNK: usize = 1024 * 1024; // 1 MiB
-		while remain > 0 {
-			let chunk = stream
-				.read(MAX_CHUNK.min(remain as usize))
-				.await?
-				.ok_or(Error::WrongSize)?;
-			remain = remain.checked_sub(chunk.len() as u64).ok_or(Error::WrongSize)?;
-			frame.write_chunk(chunk);
+					return Err(err);
+				}
+			}
 		}
 
-		tracing::trace!(size = %frame.info.size, "read frame");
-
-		frame.close();
+		decoder.finish()?;
+		group.close();
 
 		Ok(())
 	}
@@ -0,0 +1,61 @@
+use crate::coding;
+
+/// The moq-lite wire versions we can speak.
+///
+/// Newer versions are a superset of older ones, so features are gated with a comparison.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Version {
+	/// The original moq-lite wire format.
+	Lite00,
+
+	/// Adds ANNOUNCE_INIT.
+	Lite01,
+
+	/// Adds TRACK_STATUS, SUBSCRIBE_DONE, and per-frame extension headers.
+	Lite02,
+}
+
+impl Version {
+	/// Whether the publisher sends [super::AnnounceInit] as the first announce message.
+	pub fn announce_init(self) -> bool {
+		self >= Self::Lite01
+	}
+
+	/// Whether TRACK_STATUS streams are supported.
+	pub fn track_status(self) -> bool {
+		self >= Self::Lite02
+	}
+
+	/// Whether the publisher sends [super::SubscribeDone] before finishing a subscription.
+	pub fn subscribe_done(self) -> bool {
+		self >= Self::Lite02
+	}
+
+	/// Whether each frame is prefixed with its [crate::FrameExtensions].
+	pub fn frame_extensions(self) -> bool {
+		self >= Self::Lite02
+	}
+}
+
+impl TryFrom<coding::Version> for Version {
+	type Error = ();
+
+	fn try_from(version: coding::Version) -> Result<Self, Self::Error> {
+		match version {
+			coding::Version::LITE_00 => Ok(Self::Lite00),
+			coding::Version::LITE_01 => Ok(Self::Lite01),
+			coding::Version::LITE_02 => Ok(Self::Lite02),
+			_ => Err(()),
+		}
+	}
+}
+
+impl From<Version> for coding::Version {
+	fn from(version: Version) -> Self {
+		match version {
+			Version::Lite00 => coding::Version::LITE_00,
+			Version::Lite01 => coding::Version::LITE_01,
+			Version::Lite02 => coding::Version::LITE_02,
+		}
+	}
+}
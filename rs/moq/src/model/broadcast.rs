@@ -7,8 +7,8 @@ use std::{
 	},
 };
 
-use crate::{Error, Produce, TrackConsumer, TrackProducer};
-use tokio::sync::watch;
+use crate::{Error, Produce, Result, TrackConsumer, TrackProducer, TrackStatus};
+use tokio::sync::{oneshot, watch};
 use web_async::Lock;
 
 use super::Track;
@@ -21,6 +21,9 @@ struct State {
 	// When requesting, we hold a reference to the producer for dynamic tracks.
 	// The track will be marked as "unused" when the last consumer is dropped.
 	requested: HashMap<String, TrackProducer>,
+
+	// When true, status queries for unknown tracks are forwarded to the producer.
+	forward_status: bool,
 }
 
 #[derive(Clone, Default)]
@@ -44,6 +47,10 @@ pub struct BroadcastProducer {
 		async_channel::Sender<TrackProducer>,
 		async_channel::Receiver<TrackProducer>,
 	),
+	statuses: (
+		async_channel::Sender<TrackStatusRequest>,
+		async_channel::Receiver<TrackStatusRequest>,
+	),
 	cloned: Arc<AtomicUsize>,
 }
 
@@ -59,18 +66,35 @@ impl BroadcastProducer {
 			state: Lock::new(State {
 				published: HashMap::new(),
 				requested: HashMap::new(),
+				forward_status: false,
 			}),
 			closed: Default::default(),
 			requested: async_channel::unbounded(),
+			statuses: async_channel::unbounded(),
 			cloned: Default::default(),
 		}
 	}
 
 	/// Return the next requested track.
-	pub async fn requested_track(&mut self) -> Option<TrackProducer> {
+	pub async fn requested_track(&self) -> Option<TrackProducer> {
 		self.requested.1.recv().await.ok()
 	}
 
+	/// Forward status queries for unknown tracks to [Self::requested_status].
+	///
+	/// By default, [BroadcastConsumer::track_status] returns [Error::NotFound] for a track that is not published or requested.
+	/// This is used by sessions, where the real state of the track lives on the remote.
+	pub fn forward_status(&mut self) {
+		self.state.lock().forward_status = true;
+	}
+
+	/// Return the next status query that could not be answered locally.
+	///
+	/// Only used after calling [Self::forward_status].
+	pub async fn requested_status(&self) -> Option<TrackStatusRequest> {
+		self.statuses.1.recv().await.ok()
+	}
+
 	/// Produce a new track and insert it into the broadcast.
 	pub fn create_track(&mut self, track: Track) -> TrackProducer {
 		let track = track.clone().produce();
@@ -98,6 +122,7 @@ impl BroadcastProducer {
 			state: self.state.clone(),
 			closed: self.closed.subscribe(),
 			requested: self.requested.0.clone(),
+			statuses: self.statuses.0.clone(),
 		}
 	}
 
@@ -116,6 +141,10 @@ impl BroadcastProducer {
 	pub fn is_clone(&self, other: &Self) -> bool {
 		self.closed.same_channel(&other.closed)
 	}
+
+	pub(crate) fn is_closed(&self) -> bool {
+		*self.closed.borrow()
+	}
 }
 
 impl Clone for BroadcastProducer {
@@ -125,6 +154,7 @@ impl Clone for BroadcastProducer {
 			state: self.state.clone(),
 			closed: self.closed.clone(),
 			requested: self.requested.clone(),
+			statuses: self.statuses.clone(),
 			cloned: self.cloned.clone(),
 		}
 	}
@@ -146,6 +176,12 @@ impl Drop for BroadcastProducer {
 			producer.abort(Error::Cancel);
 		}
 
+		// Same for status queries, which will return Error::Cancel when dropped.
+		self.statuses.0.close();
+		while let Ok(request) = self.statuses.1.try_recv() {
+			request.respond(Err(Error::Cancel));
+		}
+
 		let mut state = self.state.lock();
 
 		// Cleanup any published tracks.
@@ -177,6 +213,13 @@ impl BroadcastProducer {
 	pub fn assert_no_request(&mut self) {
 		assert!(self.requested_track().now_or_never().is_none(), "should have blocked");
 	}
+
+	pub fn assert_status_request(&mut self) -> TrackStatusRequest {
+		self.requested_status()
+			.now_or_never()
+			.expect("should not have blocked")
+			.expect("should be a status request")
+	}
 }
 
 /// Subscribe to abitrary broadcast/tracks.
@@ -185,6 +228,7 @@ pub struct BroadcastConsumer {
 	state: Lock<State>,
 	closed: watch::Receiver<bool>,
 	requested: async_channel::Sender<TrackProducer>,
+	statuses: async_channel::Sender<TrackStatusRequest>,
 }
 
 impl BroadcastConsumer {
@@ -231,6 +275,47 @@ impl BroadcastConsumer {
 		consumer
 	}
 
+	/// Query the state of a track without subscribing to it.
+	///
+	/// Published tracks are answered immediately.
+	/// Otherwise the query is forwarded to the producer if enabled via [BroadcastProducer::forward_status].
+	/// Returns [Error::NotFound] if the track does not exist.
+	pub async fn track_status(&self, name: &str) -> Result<TrackStatus> {
+		let reply = {
+			let state = self.state.lock();
+
+			if let Some(track) = state.published.get(name) {
+				return Ok(track.status());
+			}
+
+			// An active subscription already knows the answer, unless it's still waiting for the first group.
+			let requested = state.requested.get(name).map(|track| track.consume().status());
+			if let Some(status) = &requested {
+				if status.latest.is_some() || status.ended {
+					return Ok(status.clone());
+				}
+			}
+
+			if !state.forward_status {
+				return requested.ok_or(Error::NotFound);
+			}
+
+			let (tx, rx) = oneshot::channel();
+			let request = TrackStatusRequest {
+				name: name.to_string(),
+				reply: tx,
+			};
+
+			if self.statuses.try_send(request).is_err() {
+				return Err(Error::Cancel);
+			}
+
+			rx
+		};
+
+		reply.await.map_err(|_| Error::Cancel)?
+	}
+
 	pub fn closed(&self) -> impl Future<Output = ()> {
 		// A hacky way to check if the broadcast is closed.
 		let mut closed = self.closed.clone();
@@ -247,6 +332,23 @@ impl BroadcastConsumer {
 	}
 }
 
+/// A status query for a track that could not be answered locally.
+///
+/// Dropping the request without responding returns [Error::Cancel] to the caller.
+pub struct TrackStatusRequest {
+	/// The name of the track.
+	pub name: String,
+
+	reply: oneshot::Sender<Result<TrackStatus>>,
+}
+
+impl TrackStatusRequest {
+	/// Reply to the query, returning [Error::NotFound] if the track does not exist.
+	pub fn respond(self, status: Result<TrackStatus>) {
+		self.reply.send(status).ok();
+	}
+}
+
 #[cfg(test)]
 impl BroadcastConsumer {
 	pub fn assert_not_closed(&self) {
@@ -256,6 +358,13 @@ impl BroadcastConsumer {
 	pub fn assert_closed(&self) {
 		assert!(self.closed().now_or_never().is_some(), "should be closed");
 	}
+
+	pub fn assert_status(&self, track: &str) -> TrackStatus {
+		self.track_status(track)
+			.now_or_never()
+			.expect("status would have blocked")
+			.expect("status would have errored")
+	}
 }
 
 #[cfg(test)]
@@ -355,12 +464,13 @@ mod test {
 
 	#[tokio::test]
 	async fn select() {
-		let mut producer = BroadcastProducer::new();
+		let producer = BroadcastProducer::new();
 
 		// Make sure this compiles; it's actually more involved than it should be.
 		tokio::select! {
 			_ = producer.unused() => {}
 			_ = producer.requested_track() => {}
+			_ = producer.requested_status() => {}
 		}
 	}
 
@@ -456,4 +566,62 @@ mod test {
 			"track producer should be unused after consumer is dropped"
 		);
 	}
+
+	#[tokio::test]
+	async fn track_status() {
+		let mut broadcast = Broadcast::produce();
+
+		// Unknown tracks don't exist by default.
+		let res = broadcast.consumer.track_status("unknown").now_or_never();
+		assert!(matches!(res, Some(Err(Error::NotFound))));
+
+		// Published tracks are answered locally.
+		let mut track = broadcast.producer.create_track(Track::new("track"));
+		let status = broadcast.consumer.assert_status("track");
+		assert_eq!(status, TrackStatus::default());
+
+		track.append_group();
+		track.append_group();
+		let status = broadcast.consumer.assert_status("track");
+		assert_eq!(status.latest, Some(1));
+		assert!(!status.ended);
+
+		track.close();
+		let status = broadcast.consumer.assert_status("track");
+		assert_eq!(status.latest, Some(1));
+		assert!(status.ended);
+
+		// Status queries don't create a subscription.
+		broadcast.producer.assert_no_request();
+	}
+
+	#[tokio::test]
+	async fn track_status_forward() {
+		let mut broadcast = Broadcast::produce();
+		broadcast.producer.forward_status();
+
+		let consumer = broadcast.consumer.clone();
+		let query = tokio::spawn(async move { consumer.track_status("remote").await });
+
+		// Give the query a chance to be sent.
+		tokio::time::sleep(std::time::Duration::from_millis(1)).await;
+
+		let request = broadcast.producer.assert_status_request();
+		assert_eq!(request.name, "remote");
+		request.respond(Ok(TrackStatus {
+			latest: Some(5),
+			ended: false,
+		}));
+
+		let status = query.await.unwrap().unwrap();
+		assert_eq!(status.latest, Some(5));
+
+		// Dropping the producer cancels any pending queries.
+		let consumer = broadcast.consumer.clone();
+		let query = tokio::spawn(async move { consumer.track_status("remote").await });
+		tokio::time::sleep(std::time::Duration::from_millis(1)).await;
+
+		drop(broadcast.producer);
+		assert!(matches!(query.await.unwrap(), Err(Error::Cancel)));
+	}
 }
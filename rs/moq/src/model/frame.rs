@@ -3,12 +3,19 @@ use std::future::Future;
 use bytes::{Bytes, BytesMut};
 use tokio::sync::watch;
 
-use crate::{Error, Produce, Result};
+use crate::{
+	coding::{Decode, DecodeError, Encode},
+	Error, Produce, Result,
+};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Frame {
 	pub size: u64,
+
+	/// Metadata attached to the frame, preserved end-to-end by relays.
+	#[cfg_attr(feature = "serde", serde(default))]
+	pub extensions: FrameExtensions,
 }
 
 impl Frame {
@@ -21,25 +28,133 @@ impl Frame {
 
 impl From<usize> for Frame {
 	fn from(size: usize) -> Self {
-		Self { size: size as u64 }
+		Self {
+			size: size as u64,
+			..Default::default()
+		}
 	}
 }
 
 impl From<u64> for Frame {
 	fn from(size: u64) -> Self {
-		Self { size }
+		Self {
+			size,
+			..Default::default()
+		}
+	}
+}
+
+/// The value of a [FrameExtensions] entry.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FrameExtension {
+	/// Used by even types.
+	Int(u64),
+	/// Used by odd types.
+	Bytes(Vec<u8>),
+}
+
+/// Extension headers attached to a [Frame], such as a capture timestamp, without changing the payload.
+///
+/// Following the IETF convention, even types carry an integer and odd types carry bytes.
+/// Entries keep their order, and setting a type replaces any existing entry with that type.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FrameExtensions(Vec<(u64, FrameExtension)>);
+
+impl FrameExtensions {
+	pub fn is_empty(&self) -> bool {
+		self.0.is_empty()
+	}
+
+	/// Add an integer extension, which requires an even type.
+	pub fn set_int(&mut self, kind: u64, value: u64) {
+		assert!(kind.is_multiple_of(2), "integer extensions require an even type");
+		self.0.retain(|(k, _)| *k != kind);
+		self.0.push((kind, FrameExtension::Int(value)));
+	}
+
+	/// Add a bytes extension, which requires an odd type.
+	pub fn set_bytes<B: Into<Vec<u8>>>(&mut self, kind: u64, value: B) {
+		assert!(kind % 2 == 1, "bytes extensions require an odd type");
+		self.0.retain(|(k, _)| *k != kind);
+		self.0.push((kind, FrameExtension::Bytes(value.into())));
+	}
+
+	/// Return the first integer extension with the given type.
+	pub fn get_int(&self, kind: u64) -> Option<u64> {
+		self.iter().find_map(|(k, value)| match value {
+			FrameExtension::Int(value) if k == kind => Some(*value),
+			_ => None,
+		})
+	}
+
+	/// Return the first bytes extension with the given type.
+	pub fn get_bytes(&self, kind: u64) -> Option<&[u8]> {
+		self.iter().find_map(|(k, value)| match value {
+			FrameExtension::Bytes(value) if k == kind => Some(value.as_slice()),
+			_ => None,
+		})
+	}
+
+	pub fn iter(&self) -> impl Iterator<Item = (u64, &FrameExtension)> {
+		self.0.iter().map(|(kind, value)| (*kind, value))
+	}
+
+	fn encode_pairs<W: bytes::BufMut>(&self, w: &mut W) {
+		for (kind, value) in &self.0 {
+			kind.encode(w);
+			match value {
+				FrameExtension::Int(value) => value.encode(w),
+				FrameExtension::Bytes(value) => value.encode(w),
+			}
+		}
+	}
+}
+
+/// Encoded as the total length followed by each type and value, the same for IETF and moq-lite.
+impl Encode for FrameExtensions {
+	fn encode<W: bytes::BufMut>(&self, w: &mut W) {
+		let mut buf = Vec::new();
+		self.encode_pairs(&mut buf);
+		buf.encode(w);
+	}
+}
+
+impl Decode for FrameExtensions {
+	fn decode<R: bytes::Buf>(r: &mut R) -> std::result::Result<Self, DecodeError> {
+		let buf = Vec::<u8>::decode(r)?;
+		let mut r = buf.as_slice();
+
+		let mut extensions = Vec::new();
+		while !r.is_empty() {
+			let kind = u64::decode(&mut r)?;
+			let value = match kind % 2 {
+				0 => FrameExtension::Int(u64::decode(&mut r)?),
+				_ => FrameExtension::Bytes(Vec::<u8>::decode(&mut r)?),
+			};
+			extensions.push((kind, value));
+		}
+
+		Ok(Self(extensions))
 	}
 }
 
 impl From<u32> for Frame {
 	fn from(size: u32) -> Self {
-		Self { size: size as u64 }
+		Self {
+			size: size as u64,
+			..Default::default()
+		}
 	}
 }
 
 impl From<u16> for Frame {
 	fn from(size: u16) -> Self {
-		Self { size: size as u64 }
+		Self {
+			size: size as u64,
+			..Default::default()
+		}
 	}
 }
 
@@ -189,3 +304,30 @@ impl FrameConsumer {
 		Ok(buf.freeze())
 	}
 }
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn extensions() {
+		let mut extensions = FrameExtensions::default();
+		extensions.set_int(2, 1000);
+		extensions.set_bytes(3, b"hi".to_vec());
+
+		let mut buf = Vec::new();
+		extensions.encode(&mut buf);
+		assert_eq!(buf, vec![0x07, 0x02, 0x43, 0xe8, 0x03, 0x02, b'h', b'i']);
+
+		let decoded = FrameExtensions::decode(&mut buf.as_slice()).unwrap();
+		assert_eq!(decoded, extensions);
+		assert_eq!(decoded.get_int(2), Some(1000));
+		assert_eq!(decoded.get_bytes(3), Some(&b"hi"[..]));
+		assert_eq!(decoded.get_int(3), None);
+
+		// No extensions is a single zero byte.
+		let mut buf = Vec::new();
+		FrameExtensions::default().encode(&mut buf);
+		assert_eq!(buf, vec![0x00]);
+	}
+}
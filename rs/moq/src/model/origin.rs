@@ -1,11 +1,14 @@
 use std::{
 	collections::HashMap,
-	sync::atomic::{AtomicU64, Ordering},
+	sync::{
+		atomic::{AtomicU64, Ordering},
+		Arc,
+	},
 };
 use tokio::sync::mpsc;
 use web_async::Lock;
 
-use super::BroadcastConsumer;
+use super::{Broadcast, BroadcastConsumer, BroadcastProducer};
 use crate::{AsPath, Path, PathOwned, Produce};
 
 static NEXT_CONSUMER_ID: AtomicU64 = AtomicU64::new(0);
@@ -228,8 +231,10 @@ impl OriginNode {
 				return;
 			}
 
-			// Okay so it must be the active broadcast or else we fucked up.
-			assert!(entry.active.is_clone(&broadcast));
+			// Otherwise it must be the active broadcast, unless it was already unpublished and replaced.
+			if !entry.active.is_clone(&broadcast) {
+				return;
+			}
 
 			// If there's a backup broadcast, then announce it.
 			if let Some(active) = entry.backup.pop() {
@@ -243,14 +248,91 @@ impl OriginNode {
 		}
 	}
 
+	// Forcibly remove the broadcast and any backups, returning true if there was one.
+	fn unpublish(&mut self, full: impl AsPath, relative: impl AsPath) -> bool {
+		let full = full.as_path();
+		let relative = relative.as_path();
+
+		if let Some((dir, relative)) = relative.next_part() {
+			let nested = match self.nested.get(dir) {
+				Some(nested) => nested.clone(),
+				None => return false,
+			};
+
+			let mut locked = nested.lock();
+			let removed = locked.unpublish(&full, &relative);
+
+			if locked.is_empty() {
+				drop(locked);
+				self.nested.remove(dir);
+			}
+
+			removed
+		} else if self.broadcast.take().is_some() {
+			self.notify.lock().unannounce(full);
+			true
+		} else {
+			false
+		}
+	}
+
 	fn is_empty(&self) -> bool {
 		self.broadcast.is_none() && self.nested.is_empty() && self.notify.lock().consumers.is_empty()
 	}
 }
 
+// Broadcasts created on demand when a consumer looks up a path that isn't published.
+struct OriginRequests {
+	// None until enabled via [OriginProducer::request_missing].
+	// Otherwise the requested broadcasts that haven't been closed, keyed by absolute path so consumers share them.
+	pending: Lock<Option<HashMap<PathOwned, BroadcastProducer>>>,
+	requested: (
+		async_channel::Sender<OriginRequest>,
+		async_channel::Receiver<OriginRequest>,
+	),
+}
+
+impl OriginRequests {
+	fn request(&self, path: PathOwned) -> Option<BroadcastConsumer> {
+		let mut pending = self.pending.lock();
+		let pending = pending.as_mut()?;
+
+		// Forget any broadcasts that were closed, ex. because nothing could serve them.
+		pending.retain(|_, broadcast| !broadcast.is_closed());
+
+		if let Some(broadcast) = pending.get(&path) {
+			return Some(broadcast.consume());
+		}
+
+		// The state of the broadcast is unknown until it's served, so status queries are forwarded too.
+		let mut broadcast = Broadcast::produce();
+		broadcast.producer.forward_status();
+
+		self.requested
+			.0
+			.try_send((path.clone(), broadcast.producer.clone()))
+			.ok()?;
+		pending.insert(path, broadcast.producer);
+
+		Some(broadcast.consumer)
+	}
+}
+
+impl Default for OriginRequests {
+	fn default() -> Self {
+		Self {
+			pending: Lock::new(None),
+			requested: async_channel::unbounded(),
+		}
+	}
+}
+
 #[derive(Clone)]
 struct OriginNodes {
 	nodes: Vec<(PathOwned, Lock<OriginNode>)>,
+
+	// Shared by every scoped copy of the nodes.
+	requests: Arc<OriginRequests>,
 }
 
 impl OriginNodes {
@@ -278,7 +360,10 @@ impl OriginNodes {
 		if roots.is_empty() {
 			None
 		} else {
-			Some(Self { nodes: roots })
+			Some(Self {
+				nodes: roots,
+				requests: self.requests.clone(),
+			})
 		}
 	}
 
@@ -305,7 +390,10 @@ impl OriginNodes {
 		if roots.is_empty() {
 			None
 		} else {
-			Some(Self { nodes: roots })
+			Some(Self {
+				nodes: roots,
+				requests: self.requests.clone(),
+			})
 		}
 	}
 
@@ -327,6 +415,7 @@ impl Default for OriginNodes {
 	fn default() -> Self {
 		Self {
 			nodes: vec![("".into(), Lock::new(OriginNode::new(None)))],
+			requests: Default::default(),
 		}
 	}
 }
@@ -334,6 +423,9 @@ impl Default for OriginNodes {
 /// A broadcast path and its associated consumer, or None if closed.
 pub type OriginAnnounce = (PathOwned, Option<BroadcastConsumer>);
 
+/// The absolute path of a broadcast requested by a consumer, and the producer used to serve it.
+pub type OriginRequest = (PathOwned, BroadcastProducer);
+
 pub struct Origin {}
 
 impl Origin {
@@ -385,6 +477,23 @@ impl OriginProducer {
 		true
 	}
 
+	/// Unannounce a broadcast to all consumers, even though it's still open.
+	///
+	/// Any older broadcasts with the same path are removed too, rather than being reannounced.
+	/// Returns false if there's no broadcast with the path or it's not allowed to be published.
+	pub fn unpublish_broadcast(&self, path: impl AsPath) -> bool {
+		let path = path.as_path();
+
+		let (root, rest) = match self.nodes.get(&path) {
+			Some(root) => root,
+			None => return false,
+		};
+
+		let full = self.root.join(&path);
+		let mut root = root.lock();
+		root.unpublish(&full, &rest)
+	}
+
 	/// Returns a new OriginProducer where all published broadcasts MUST match one of the prefixes.
 	///
 	/// Returns None if there are no legal prefixes.
@@ -395,6 +504,23 @@ impl OriginProducer {
 		})
 	}
 
+	/// Create broadcasts on demand when a consumer looks up a path that isn't published.
+	///
+	/// By default, [OriginConsumer::consume_broadcast] returns None for a path that isn't published.
+	/// Once enabled, it instead returns a new broadcast, shared by every consumer of the path until it's closed.
+	/// Status queries for its tracks are forwarded, see [BroadcastProducer::forward_status].
+	/// The producer is returned by [Self::requested_broadcast], which should serve the broadcast and close it when unused.
+	pub fn request_missing(&self) {
+		self.nodes.requests.pending.lock().get_or_insert_with(HashMap::new);
+	}
+
+	/// Return the next broadcast requested by a consumer.
+	///
+	/// Only used after calling [Self::request_missing].
+	pub async fn requested_broadcast(&self) -> Option<OriginRequest> {
+		self.nodes.requests.requested.1.recv().await.ok()
+	}
+
 	/// Subscribe to all announced broadcasts.
 	pub fn consume(&self) -> OriginConsumer {
 		OriginConsumer::new(self.root.clone(), self.nodes.clone())
@@ -497,12 +623,16 @@ impl OriginConsumer {
 	///
 	/// TODO This should include announcement support.
 	///
-	/// Returns None if the path hasn't been announced yet.
+	/// Returns None if the path hasn't been announced yet, unless enabled via [OriginProducer::request_missing].
 	pub fn consume_broadcast(&self, path: impl AsPath) -> Option<BroadcastConsumer> {
 		let path = path.as_path();
 		let (root, rest) = self.nodes.get(&path)?;
-		let state = root.lock();
-		state.consume_broadcast(&rest)
+
+		if let Some(broadcast) = root.lock().consume_broadcast(&rest) {
+			return Some(broadcast);
+		}
+
+		self.nodes.requests.request(self.absolute(&path).to_owned())
 	}
 
 	/// Returns a new OriginConsumer that only consumes broadcasts matching one of the prefixes.
@@ -1321,4 +1451,78 @@ mod tests {
 		narrow_consumer.assert_next("worm-node/data", &broadcast1.consumer);
 		narrow_consumer.assert_next_wait(); // Should not see foobar
 	}
+
+	#[tokio::test]
+	async fn test_unpublish() {
+		let origin = Origin::produce();
+		let broadcast1 = Broadcast::produce();
+		let broadcast2 = Broadcast::produce();
+
+		let mut consumer = origin.producer.consume();
+
+		origin.producer.publish_broadcast("test/a", broadcast1.consumer.clone());
+		origin.producer.publish_broadcast("test/a", broadcast2.consumer.clone());
+		consumer.assert_next("test/a", &broadcast1.consumer);
+		consumer.assert_next_none("test/a");
+		consumer.assert_next("test/a", &broadcast2.consumer);
+
+		// Both the active and backup broadcasts are removed, even though they're still open.
+		assert!(origin.producer.unpublish_broadcast("test/a"));
+		consumer.assert_next_none("test/a");
+		consumer.assert_next_wait();
+		assert!(origin.consumer.consume_broadcast("test/a").is_none());
+		assert!(!origin.producer.unpublish_broadcast("test/a"));
+		assert!(!origin.producer.unpublish_broadcast("missing/b"));
+
+		// Publishing again works, and closing the old broadcasts doesn't affect it.
+		let broadcast3 = Broadcast::produce();
+		origin.producer.publish_broadcast("test/a", broadcast3.consumer.clone());
+		consumer.assert_next("test/a", &broadcast3.consumer);
+
+		drop(broadcast1.producer);
+		drop(broadcast2.producer);
+		tokio::time::sleep(tokio::time::Duration::from_millis(1)).await;
+
+		consumer.assert_next_wait();
+		assert!(origin.consumer.consume_broadcast("test/a").is_some());
+	}
+	#[tokio::test]
+	async fn test_request_missing() {
+		let origin = Origin::produce();
+		let published = Broadcast::produce();
+		origin.producer.publish_broadcast("room/a", published.consumer.clone());
+
+		// Disabled by default.
+		assert!(origin.consumer.consume_broadcast("room/b").is_none());
+		assert!(origin.producer.requested_broadcast().now_or_never().is_none());
+
+		origin.producer.request_missing();
+
+		// Published broadcasts are returned as usual.
+		let found = origin.consumer.consume_broadcast("room/a").unwrap();
+		assert!(found.is_clone(&published.consumer));
+
+		// Consumers share a missing broadcast, using the absolute path.
+		let scoped = origin.producer.with_root("room").unwrap().consume();
+		let first = scoped.consume_broadcast("b").unwrap();
+		let second = origin.consumer.consume_broadcast("room/b").unwrap();
+		assert!(first.is_clone(&second));
+
+		let (path, mut requested) = origin.producer.requested_broadcast().now_or_never().unwrap().unwrap();
+		assert_eq!(path, "room/b".as_path());
+		assert!(origin.producer.requested_broadcast().now_or_never().is_none());
+
+		requested.assert_used();
+		drop(first);
+		drop(second);
+		requested.assert_unused();
+
+		// Once closed, the broadcast is requested again.
+		requested.close();
+		let third = origin.consumer.consume_broadcast("room/b").unwrap();
+		assert!(!third.is_clone(&requested.consume()));
+
+		let (path, _) = origin.producer.requested_broadcast().now_or_never().unwrap().unwrap();
+		assert_eq!(path, "room/b".as_path());
+	}
 }
@@ -18,7 +18,7 @@ use crate::{Error, Produce, Result};
 
 use super::{Group, GroupConsumer, GroupProducer};
 
-use std::{cmp::Ordering, future::Future};
+use std::{cmp::Ordering, future::Future, ops::RangeInclusive};
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -42,10 +42,51 @@ impl Track {
 	}
 }
 
+/// A snapshot of a track's state, returned without subscribing.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TrackStatus {
+	/// The sequence number of the latest group, if any.
+	pub latest: Option<u64>,
+
+	/// True if the track has ended and no more groups will be produced.
+	pub ended: bool,
+}
+
+/// Why a track ended without an error, see [TrackEnd].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TrackEndReason {
+	/// The publisher finished the track.
+	#[default]
+	Ended,
+
+	/// The broadcast containing the track was unpublished.
+	Unpublished,
+
+	/// The publisher stopped serving the track, for example after a time limit.
+	Expired,
+}
+
+/// Returned by [TrackConsumer::closed] when a track finishes cleanly.
+///
+/// A network failure or abort is instead returned as an [Error].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TrackEnd {
+	/// The sequence number of the final group, if any.
+	///
+	/// Groups up to this sequence may still be in flight when the track ends.
+	/// They will be returned by [TrackConsumer::next_group] if they arrive.
+	pub final_sequence: Option<u64>,
+
+	/// Why the track ended.
+	pub reason: TrackEndReason,
+}
+
 #[derive(Default)]
 struct TrackState {
 	latest: Option<GroupConsumer>,
-	closed: Option<Result<()>>,
+	closed: Option<Result<TrackEnd>>,
+	datagrams: bool,
 }
 
 /// A producer for a track, used to create new groups.
@@ -66,7 +107,12 @@ impl TrackProducer {
 	/// Insert a group into the track, returning true if this is the latest group.
 	pub fn insert_group(&mut self, group: GroupConsumer) -> bool {
 		self.state.send_if_modified(|state| {
-			assert!(state.closed.is_none());
+			match &state.closed {
+				None => {}
+				// Trailing groups may still arrive after the publisher ends the track.
+				Some(Ok(end)) if Some(group.info.sequence) <= end.final_sequence => {}
+				Some(_) => return false,
+			}
 
 			if let Some(latest) = &state.latest {
 				match group.info.cmp(&latest.info) {
@@ -83,7 +129,7 @@ impl TrackProducer {
 
 	/// Create a new group with the given sequence number.
 	///
-	/// If the sequence number is not the latest, this method will return None.
+	/// If the sequence number is not the latest, or the track has ended before this group, this method will return None.
 	pub fn create_group(&mut self, info: Group) -> Option<GroupProducer> {
 		let group = info.produce();
 		self.insert_group(group.consumer).then_some(group.producer)
@@ -114,8 +160,34 @@ impl TrackProducer {
 		group.close();
 	}
 
+	/// Prefer delivering each frame as a datagram, for example low-latency telemetry.
+	///
+	/// Datagrams are unreliable and limited in size, and only supported by the IETF protocol.
+	/// moq-lite always uses streams.
+	pub fn set_datagrams(&mut self, datagrams: bool) {
+		self.state.send_if_modified(|state| {
+			let modified = state.datagrams != datagrams;
+			state.datagrams = datagrams;
+			modified
+		});
+	}
+
+	/// Close the track, using the latest group as the final group.
 	pub fn close(self) {
-		self.state.send_modify(|state| state.closed = Some(Ok(())));
+		self.state.send_modify(|state| {
+			let final_sequence = state.latest.as_ref().map(|group| group.info.sequence);
+			state.closed = Some(Ok(TrackEnd {
+				final_sequence,
+				reason: TrackEndReason::Ended,
+			}));
+		});
+	}
+
+	/// Close the track with an explicit final group and reason.
+	///
+	/// Groups up to the final sequence may still be inserted afterwards.
+	pub fn end(self, end: TrackEnd) {
+		self.state.send_modify(|state| state.closed = Some(Ok(end)));
 	}
 
 	pub fn abort(self, err: Error) {
@@ -176,21 +248,59 @@ impl TrackConsumer {
 			Err(_) => return Err(Error::Cancel),
 		};
 
-		match &state.closed {
-			Some(Ok(_)) => return Ok(None),
-			Some(Err(err)) => return Err(err.clone()),
-			_ => {}
+		if let Some(Err(err)) = &state.closed {
+			return Err(err.clone());
 		}
 
-		// If there's a new latest group, return it.
-		let group = state.latest.clone().unwrap();
-		self.prev = Some(group.info.sequence);
+		// If there's a new latest group, return it even if the track has since ended.
+		match &state.latest {
+			Some(group) if Some(group.info.sequence) > self.prev => {
+				self.prev = Some(group.info.sequence);
+				Ok(Some(group.clone()))
+			}
+			_ => Ok(None),
+		}
+	}
 
-		Ok(Some(group))
+	/// Skip any groups with a sequence number before the given start.
+	///
+	/// [TrackConsumer::next_group] will wait until a group with at least this sequence number is available.
+	pub fn start_at(&mut self, sequence: u64) {
+		self.prev = self.prev.max(sequence.checked_sub(1));
+	}
+
+	/// Return the current state of the track without blocking.
+	pub fn status(&self) -> TrackStatus {
+		let state = self.state.borrow();
+		TrackStatus {
+			latest: state.latest.as_ref().map(|group| group.info.sequence),
+			// An aborted track may still be resumed elsewhere, so only a clean end counts.
+			ended: matches!(state.closed, Some(Ok(_))),
+		}
+	}
+
+	/// Returns true if the producer prefers frames to be delivered as datagrams.
+	pub fn datagrams(&self) -> bool {
+		self.state.borrow().datagrams
+	}
+
+	/// Return the groups still held by the track within the given range, in order, without blocking.
+	///
+	/// NOTE: Only the latest group is retained, so older groups are never returned.
+	pub fn groups(&self, range: RangeInclusive<u64>) -> Vec<GroupConsumer> {
+		let state = self.state.borrow();
+		state
+			.latest
+			.iter()
+			.filter(|group| range.contains(&group.info.sequence))
+			.cloned()
+			.collect()
 	}
 
 	/// Block until the track is closed.
-	pub async fn closed(&self) -> Result<()> {
+	///
+	/// Returns the final group and reason if the track ended cleanly, or an error if it was aborted.
+	pub async fn closed(&self) -> Result<TrackEnd> {
 		match self.state.clone().wait_for(|state| state.closed.is_some()).await {
 			Ok(state) => state.closed.clone().unwrap(),
 			Err(_) => Err(Error::Cancel),
@@ -246,3 +356,145 @@ impl TrackConsumer {
 		assert!(!self.is_clone(other), "should not be clone");
 	}
 }
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[tokio::test]
+	async fn close() {
+		let mut track = Track::new("track").produce();
+		track.producer.append_group();
+		track.producer.append_group();
+
+		let mut consumer = track.consumer.clone();
+		track.producer.close();
+
+		// The latest group is still returned after the track is closed.
+		assert_eq!(consumer.assert_group().info.sequence, 1);
+		assert!(consumer.next_group().now_or_never().unwrap().unwrap().is_none());
+
+		let end = consumer.closed().now_or_never().unwrap().unwrap();
+		assert_eq!(
+			end,
+			TrackEnd {
+				final_sequence: Some(1),
+				reason: TrackEndReason::Ended,
+			}
+		);
+	}
+
+	#[tokio::test]
+	async fn end_trailing() {
+		let mut track = Track::new("track").produce();
+		track.producer.create_group(Group { sequence: 3 }).unwrap();
+
+		let mut consumer = track.consumer.clone();
+		assert_eq!(consumer.assert_group().info.sequence, 3);
+
+		let mut producer = track.producer.clone();
+		track.producer.end(TrackEnd {
+			final_sequence: Some(5),
+			reason: TrackEndReason::Unpublished,
+		});
+
+		let end = consumer.closed().now_or_never().unwrap().unwrap();
+		assert_eq!(end.reason, TrackEndReason::Unpublished);
+		assert_eq!(end.final_sequence, Some(5));
+
+		// Groups up to the final sequence can still arrive, but nothing after it.
+		assert!(producer.create_group(Group { sequence: 5 }).is_some());
+		assert!(producer.create_group(Group { sequence: 6 }).is_none());
+
+		assert_eq!(consumer.assert_group().info.sequence, 5);
+		assert!(consumer.next_group().now_or_never().unwrap().unwrap().is_none());
+	}
+
+	#[tokio::test]
+	async fn abort() {
+		let mut track = Track::new("track").produce();
+		track.producer.append_group();
+
+		let mut consumer = track.consumer.clone();
+		track.producer.abort(Error::Cancel);
+
+		// An abort is not a clean end, so pending groups are not returned.
+		consumer.assert_error();
+		assert!(consumer.next_group().now_or_never().unwrap().is_err());
+	}
+
+	#[test]
+	fn status() {
+		let mut track = Track::new("track").produce();
+		assert_eq!(track.consumer.status(), TrackStatus::default());
+
+		track.producer.create_group(Group { sequence: 2 }).unwrap();
+		track.producer.close();
+		assert_eq!(
+			track.consumer.status(),
+			TrackStatus {
+				latest: Some(2),
+				ended: true,
+			}
+		);
+
+		// An aborted track has not ended.
+		let mut track = Track::new("track").produce();
+		track.producer.create_group(Group { sequence: 2 }).unwrap();
+		track.producer.abort(Error::Cancel);
+		assert_eq!(
+			track.consumer.status(),
+			TrackStatus {
+				latest: Some(2),
+				ended: false,
+			}
+		);
+	}
+
+	#[test]
+	fn groups() {
+		let mut track = Track::new("track").produce();
+		assert!(track.consumer.groups(0..=u64::MAX).is_empty());
+
+		track.producer.create_group(Group { sequence: 2 }).unwrap();
+		track.producer.create_group(Group { sequence: 4 }).unwrap();
+
+		// Only the latest group is held.
+		let groups = track.consumer.groups(0..=u64::MAX);
+		assert_eq!(groups.len(), 1);
+		assert_eq!(groups[0].info.sequence, 4);
+
+		assert!(track.consumer.groups(0..=3).is_empty());
+	}
+
+	#[tokio::test]
+	async fn start_at() {
+		let mut track = Track::new("track").produce();
+		track.producer.create_group(Group { sequence: 2 }).unwrap();
+
+		let mut consumer = track.consumer.clone();
+		consumer.start_at(4);
+
+		// The latest group is before the start, so wait for a newer one.
+		consumer.assert_no_group();
+		track.producer.create_group(Group { sequence: 3 }).unwrap();
+		consumer.assert_no_group();
+
+		track.producer.create_group(Group { sequence: 5 }).unwrap();
+		assert_eq!(consumer.assert_group().info.sequence, 5);
+
+		// Starting before the previous group has no effect.
+		consumer.start_at(0);
+		consumer.assert_no_group();
+	}
+
+	#[test]
+	fn datagrams() {
+		let mut track = Track::new("track").produce();
+		assert!(!track.consumer.datagrams());
+
+		// The preference is visible to existing consumers.
+		track.producer.set_datagrams(true);
+		assert!(track.consumer.datagrams());
+	}
+}
@@ -1,20 +1,47 @@
 use std::sync::Arc;
 
+use bytes::BytesMut;
+use tokio::sync::watch;
+
 use crate::{
 	coding::{self, Stream},
-	ietf, lite, Error, OriginConsumer, OriginProducer,
+	ietf, lite, Authorization, Error, OriginConsumer, OriginProducer, Stats,
 };
 
 pub struct Session<S: web_transport_trait::Session> {
 	session: S,
+	version: coding::Version,
+	extensions: coding::Extensions,
+	role: ietf::Role,
+	goaway: watch::Receiver<Option<String>>,
 }
 
-/// The versions of MoQ that are supported by this implementation.
-const SUPPORTED: [coding::Version; 2] = [coding::Version::LITE_LATEST, coding::Version::IETF_LATEST];
+/// The versions of MoQ that are supported by this implementation, in preferred order.
+///
+/// Older lite versions are still accepted so deployed clients keep working during upgrades.
+const SUPPORTED: [coding::Version; 5] = [
+	coding::Version::LITE_02,
+	coding::Version::LITE_01,
+	coding::Version::LITE_00,
+	coding::Version::IETF_11,
+	coding::Version::IETF_07,
+];
 
 impl<S: web_transport_trait::Session> Session<S> {
-	fn new(session: S) -> Self {
-		Self { session }
+	fn new(
+		session: S,
+		version: coding::Version,
+		extensions: coding::Extensions,
+		role: ietf::Role,
+		goaway: watch::Receiver<Option<String>>,
+	) -> Self {
+		Self {
+			session,
+			version,
+			extensions,
+			role,
+			goaway,
+		}
 	}
 
 	/// Perform the MoQ handshake as a client.
@@ -26,43 +53,142 @@ impl<S: web_transport_trait::Session> Session<S> {
 		publish: impl Into<Option<OriginConsumer>>,
 		subscribe: impl Into<Option<OriginProducer>>,
 	) -> Result<Self, Error> {
-		let mut stream = Stream::open(&session).await?;
+		Self::connect_with(session, Default::default(), publish, subscribe).await
+	}
 
-		// Encode 0x40 on the wire so it's backwards compatible with moq-transport
-		stream.writer.encode(&lite::ControlType::ClientCompat).await?;
+	/// Perform the MoQ handshake as a client, offering the given setup extensions.
+	///
+	/// For example, set [Authorization] to authenticate without a token in the URL.
+	pub async fn connect_with(
+		session: S,
+		mut extensions: coding::Extensions,
+		publish: impl Into<Option<OriginConsumer>>,
+		subscribe: impl Into<Option<OriginProducer>>,
+	) -> Result<Self, Error> {
+		let publish = publish.into();
+		let subscribe = subscribe.into();
+
+		// moq-transport requires the ROLE extension, so declare what we actually do unless overridden.
+		if !extensions.contains::<ietf::Role>() {
+			extensions.set(ietf::Role::new(publish.is_some(), subscribe.is_some()));
+		}
+
+		// Draft-07 is the only IETF version offered, so the limit is in Subscribe IDs.
+		if !extensions.contains::<ietf::MaxRequests>() {
+			extensions.set(ietf::MaxRequests::concurrent(
+				ietf::Version::Draft07,
+				ietf::MaxRequests::CONCURRENT,
+			));
+		}
+
+		// Newer IETF drafts use a different setup encoding, so they can't be offered at the same time.
+		let versions: Vec<_> = SUPPORTED.into_iter().filter(|v| legacy_setup(*v)).collect();
+
+		Self::connect_versions(session, versions.into(), extensions, publish, subscribe).await
+	}
+
+	/// Perform the MoQ handshake as a client, offering only the given IETF draft.
+	///
+	/// This is useful to connect to servers that don't support moq-lite.
+	pub async fn connect_ietf(
+		session: S,
+		version: ietf::Version,
+		publish: impl Into<Option<OriginConsumer>>,
+		subscribe: impl Into<Option<OriginProducer>>,
+	) -> Result<Self, Error> {
+		let publish = publish.into();
+		let subscribe = subscribe.into();
 
-		// moq-rs currently requires the ROLE extension to be set.
 		let mut extensions = coding::Extensions::default();
-		extensions.set(ietf::Role::Both);
+		extensions.set(ietf::Role::new(publish.is_some(), subscribe.is_some()));
+		extensions.set(ietf::MaxRequests::concurrent(version, ietf::MaxRequests::CONCURRENT));
 
-		let client = lite::ClientSetup {
-			versions: SUPPORTED.into(),
-			extensions,
-		};
+		Self::connect_versions(session, [version.into()].into(), extensions, publish, subscribe).await
+	}
 
-		stream.writer.encode(&client).await?;
+	async fn connect_versions(
+		session: S,
+		versions: coding::Versions,
+		extensions: coding::Extensions,
+		publish: Option<OriginConsumer>,
+		subscribe: Option<OriginProducer>,
+	) -> Result<Self, Error> {
+		let mut stream = Stream::open(&session, None).await?;
+		let max_requests = extensions.get()?;
 
-		// We expect 0x41 as the response.
-		let server_compat: lite::ControlType = stream.reader.decode().await?;
-		if server_compat != lite::ControlType::ServerCompat {
-			return Err(Error::UnexpectedStream);
-		}
+		let server = match versions.iter().all(|v| legacy_setup(*v)) {
+			true => {
+				// Encode 0x40 on the wire so it's backwards compatible with moq-transport
+				stream.writer.encode(&lite::ControlType::ClientCompat).await?;
 
-		let server: lite::ServerSetup = stream.reader.decode().await?;
+				let client = lite::ClientSetup {
+					versions: versions.clone(),
+					extensions,
+				};
 
-		tracing::debug!(version = ?server.version, "connected");
+				stream.writer.encode(&client).await?;
+
+				// We expect 0x41 as the response.
+				let server_compat: lite::ControlType = stream.reader.decode().await?;
+				if server_compat != lite::ControlType::ServerCompat {
+					return Err(Error::UnexpectedStream);
+				}
 
-		match server.version {
-			coding::Version::LITE_LATEST => {
-				lite::start(session.clone(), stream, publish.into(), subscribe.into()).await?;
+				stream.reader.decode::<lite::ServerSetup>().await?
 			}
-			coding::Version::IETF_LATEST => {
-				ietf::start(session.clone(), stream, publish.into(), subscribe.into()).await?;
+			false => {
+				let client = ietf::ClientSetup {
+					versions: versions.clone(),
+					parameters: extensions,
+				};
+
+				let mut buf = BytesMut::new();
+				ietf::encode_message(&client, &mut buf, ietf::Version::Draft11)?;
+				stream.writer.write_all(&mut buf).await?;
+
+				// We expect 0x21 as the response.
+				let kind: lite::ControlType = stream.reader.decode().await?;
+				if kind != lite::ControlType::ServerIetf {
+					return Err(Error::UnexpectedStream);
+				}
+
+				let server: ietf::ServerSetup = stream.reader.decode_with(|r| decode_ietf_setup(r)).await?;
+				lite::ServerSetup {
+					version: server.version,
+					extensions: server.parameters,
+				}
 			}
-			_ => return Err(Error::Version(client.versions, [server.version].into())),
+		};
+
+		if !versions.contains(&server.version) {
+			return Err(Error::Version(versions, [server.version].into()));
 		}
 
-		Ok(Self::new(session))
+		tracing::debug!(version = ?server.version, "connected");
+
+		let limits = ietf::RequestLimits {
+			local: max_requests,
+			remote: server.extensions.get()?,
+		};
+
+		// Don't publish to a server that won't subscribe, or subscribe to a server that won't publish.
+		let role = server.extensions.get::<ietf::Role>()?.unwrap_or_default();
+		let publish = publish.filter(|_| role.is_subscriber());
+		let subscribe = subscribe.filter(|_| role.is_publisher());
+
+		let goaway = start(
+			session.clone(),
+			stream,
+			server.version,
+			true,
+			limits,
+			SessionOptions::default(),
+			publish,
+			subscribe,
+		)
+		.await?;
+
+		Ok(Self::new(session, server.version, server.extensions, role, goaway))
 	}
 
 	/// Perform the MoQ handshake as a server.
@@ -74,60 +200,432 @@ impl<S: web_transport_trait::Session> Session<S> {
 		publish: impl Into<Option<OriginConsumer>>,
 		subscribe: impl Into<Option<OriginProducer>>,
 	) -> Result<Self, Error> {
-		let mut stream = Stream::accept(&session).await?;
+		Self::request(session).await?.ok(publish, subscribe).await
+	}
+
+	/// Receive the client's setup message without accepting the session yet.
+	///
+	/// This is useful to authenticate the client via [SessionRequest::authorization] before choosing the origins.
+	pub async fn request(session: S) -> Result<SessionRequest<S>, Error> {
+		let mut stream = Stream::accept(&session, None).await?;
 		let kind: lite::ControlType = stream.reader.decode().await?;
 
-		if kind != lite::ControlType::Session && kind != lite::ControlType::ClientCompat {
-			return Err(Error::UnexpectedStream);
+		let client = match kind {
+			lite::ControlType::Session | lite::ControlType::ClientCompat => stream.reader.decode().await?,
+			lite::ControlType::ClientIetf => {
+				let client: ietf::ClientSetup = stream.reader.decode_with(|r| decode_ietf_setup(r)).await?;
+				lite::ClientSetup {
+					versions: client.versions,
+					extensions: client.parameters,
+				}
+			}
+			_ => return Err(Error::UnexpectedStream),
+		};
+
+		Ok(SessionRequest {
+			session,
+			stream,
+			kind,
+			client,
+			options: SessionOptions::default(),
+		})
+	}
+
+	/// Return the negotiated version.
+	pub fn version(&self) -> coding::Version {
+		self.version
+	}
+
+	/// Return the extensions sent by the server in response to the client's setup.
+	///
+	/// This is the negotiated result and is the same on both sides of the session.
+	pub fn extensions(&self) -> &coding::Extensions {
+		&self.extensions
+	}
+
+	/// Return the [ietf::Role] declared by the peer, defaulting to [ietf::Role::Both] if it didn't declare one.
+	///
+	/// We don't publish to a peer that isn't a subscriber, nor accept announcements from a peer that isn't a publisher.
+	pub fn role(&self) -> ietf::Role {
+		self.role
+	}
+
+	/// Close the underlying transport session.
+	pub fn close(self, err: Error) {
+		self.session.close(err.to_code(), err.to_string().as_ref());
+	}
+
+	/// Block until the server asks us to migrate via GOAWAY, returning the new session URI.
+	///
+	/// An empty URI means reconnect to the same URL.
+	/// Returns None if the session closes first, or immediately for moq-lite which has no GOAWAY.
+	pub async fn goaway(&self) -> Option<String> {
+		let mut goaway = self.goaway.clone();
+		let uri = goaway.wait_for(Option::is_some).await.ok()?;
+		uri.clone()
+	}
+
+	/// Block until the transport session is closed.
+	pub async fn closed(&self) -> Result<(), Error> {
+		match self.session.closed().await {
+			Ok(()) => Ok(()),
+			Err(err) => Err(Error::Transport(Arc::new(err))),
+		}
+	}
+}
+
+/// Start the publisher and subscriber for the negotiated version, returning the GOAWAY channel.
+#[allow(clippy::too_many_arguments)]
+async fn start<S: web_transport_trait::Session>(
+	session: S,
+	stream: Stream<S>,
+	version: coding::Version,
+	client: bool,
+	limits: ietf::RequestLimits,
+	options: SessionOptions,
+	publish: Option<OriginConsumer>,
+	subscribe: Option<OriginProducer>,
+) -> Result<watch::Receiver<Option<String>>, Error> {
+	if let Ok(version) = ietf::Version::try_from(version) {
+		return ietf::start(session, stream, version, client, limits, options, publish, subscribe).await;
+	}
+
+	match lite::Version::try_from(version) {
+		Ok(version) => lite::start(session, stream, version, publish, subscribe, options.stats).await?,
+		Err(()) => return Err(Error::Version(SUPPORTED.into(), [version].into())),
+	}
+
+	// moq-lite doesn't support GOAWAY, so the sender is dropped immediately.
+	Ok(watch::channel(None).1)
+}
+
+/// Local settings for a session, which aren't negotiated with the peer.
+#[derive(Clone)]
+pub(crate) struct SessionOptions {
+	/// Whether the transport supports datagrams.
+	pub datagrams: bool,
+
+	/// Count the traffic and subscriptions for the session, if provided.
+	pub stats: Option<Stats>,
+}
+
+impl Default for SessionOptions {
+	fn default() -> Self {
+		Self {
+			datagrams: true,
+			stats: None,
 		}
+	}
+}
+
+/// Returns true if the version is negotiated with the setup messages shared by moq-lite and draft-07.
+fn legacy_setup(version: coding::Version) -> bool {
+	ietf::Version::try_from(version).map_or(true, |version| version.legacy_setup())
+}
+
+/// Decode a draft-11 setup message, after the message ID.
+fn decode_ietf_setup<M: ietf::Message, B: bytes::Buf>(r: &mut B) -> Result<M, coding::DecodeError> {
+	let payload = ietf::decode_payload(r, ietf::Version::Draft11)?;
+	ietf::decode_message(payload, ietf::Version::Draft11)
+}
+
+/// A client's setup message, received by [Session::request] before the session is accepted.
+pub struct SessionRequest<S: web_transport_trait::Session> {
+	session: S,
+	stream: Stream<S>,
+	kind: lite::ControlType,
+	client: lite::ClientSetup,
+	options: SessionOptions,
+}
 
-		let client: lite::ClientSetup = stream.reader.decode().await?;
+impl<S: web_transport_trait::Session> SessionRequest<S> {
+	/// Return the [Authorization] extension sent by the client, if any.
+	pub fn authorization(&self) -> Result<Option<Authorization>, Error> {
+		Ok(self.client.extensions.get()?)
+	}
+
+	/// Return the extensions offered by the client.
+	pub fn extensions(&self) -> &coding::Extensions {
+		&self.client.extensions
+	}
+
+	/// Return the [ietf::Role] declared by the client, defaulting to [ietf::Role::Both].
+	///
+	/// For example, a relay can refuse to accept broadcasts from a client that declared itself a subscriber.
+	pub fn role(&self) -> Result<ietf::Role, Error> {
+		Ok(self.client.extensions.get()?.unwrap_or_default())
+	}
 
-		let version = client
+	/// Set whether the transport supports datagrams, defaulting to true.
+	///
+	/// Disable this for transports that can't send or receive datagrams, like the WebSocket polyfill.
+	/// Otherwise, datagrams are only used when [web_transport_trait::Session::max_datagram_size] is non-zero.
+	pub fn datagrams(mut self, supported: bool) -> Self {
+		self.options.datagrams = supported;
+		self
+	}
+
+	/// Count the traffic and subscriptions for this session in the given [Stats].
+	///
+	/// The same [Stats] can be attached to multiple sessions to aggregate them.
+	pub fn stats(mut self, stats: Stats) -> Self {
+		self.options.stats = Some(stats);
+		self
+	}
+
+	/// Accept the session, negotiating a version and starting the publisher and subscriber.
+	///
+	/// Publishing is performed with [OriginConsumer] and subscribing with [OriginProducer].
+	pub async fn ok(
+		self,
+		publish: impl Into<Option<OriginConsumer>>,
+		subscribe: impl Into<Option<OriginProducer>>,
+	) -> Result<Session<S>, Error> {
+		self.ok_with(Default::default(), publish, subscribe).await
+	}
+
+	/// Accept the session, responding with the given setup extensions.
+	///
+	/// These become the negotiated extensions, available via [Session::extensions] on both sides.
+	pub async fn ok_with(
+		mut self,
+		mut extensions: coding::Extensions,
+		publish: impl Into<Option<OriginConsumer>>,
+		subscribe: impl Into<Option<OriginProducer>>,
+	) -> Result<Session<S>, Error> {
+		let publish = publish.into();
+		let subscribe = subscribe.into();
+
+		// Only negotiate versions that use the same setup encoding as the client.
+		let ietf_setup = self.kind == lite::ControlType::ClientIetf;
+
+		let version = self
+			.client
 			.versions
 			.iter()
-			.find(|v| SUPPORTED.contains(v))
+			.find(|v| SUPPORTED.contains(v) && legacy_setup(**v) != ietf_setup)
 			.copied()
-			.ok_or_else(|| Error::Version(client.versions, SUPPORTED.into()))?;
+			.ok_or_else(|| Error::Version(self.client.versions.clone(), SUPPORTED.into()))?;
 
-		let server = lite::ServerSetup {
-			version,
-			extensions: Default::default(),
-		};
+		// IETF peers can't make any requests unless we raise the limit.
+		if let Ok(version) = ietf::Version::try_from(version) {
+			if !extensions.contains::<ietf::MaxRequests>() {
+				extensions.set(ietf::MaxRequests::concurrent(version, ietf::MaxRequests::CONCURRENT));
+			}
+		}
 
-		// Backwards compatibility with moq-transport-07
-		if kind == lite::ControlType::ClientCompat {
-			// Write a 0x41 just to be backwards compatible.
-			stream.writer.encode(&lite::ControlType::ServerCompat).await?;
+		// Respond with our own role, as moq-transport expects one from both sides.
+		if !extensions.contains::<ietf::Role>() {
+			extensions.set(ietf::Role::new(publish.is_some(), subscribe.is_some()));
 		}
 
-		stream.writer.encode(&server).await?;
+		let server = lite::ServerSetup { version, extensions };
 
-		tracing::debug!(version = ?server.version, "connected");
+		match self.kind {
+			lite::ControlType::ClientIetf => {
+				let server = ietf::ServerSetup {
+					version,
+					parameters: server.extensions.clone(),
+				};
 
-		match version {
-			coding::Version::LITE_LATEST => {
-				lite::start(session.clone(), stream, publish.into(), subscribe.into()).await?;
+				let mut buf = BytesMut::new();
+				ietf::encode_message(&server, &mut buf, ietf::Version::Draft11)?;
+				self.stream.writer.write_all(&mut buf).await?;
 			}
-			coding::Version::IETF_LATEST => {
-				ietf::start(session.clone(), stream, publish.into(), subscribe.into()).await?;
+			kind => {
+				// Backwards compatibility with moq-transport-07
+				if kind == lite::ControlType::ClientCompat {
+					// Write a 0x41 just to be backwards compatible.
+					self.stream.writer.encode(&lite::ControlType::ServerCompat).await?;
+				}
+
+				self.stream.writer.encode(&server).await?;
 			}
-			_ => unreachable!(),
 		}
 
-		Ok(Self::new(session))
+		tracing::debug!(version = ?server.version, "connected");
+
+		let limits = ietf::RequestLimits {
+			local: server.extensions.get()?,
+			remote: self.client.extensions.get()?,
+		};
+
+		// Don't publish to a client that won't subscribe, or accept announcements from a client that won't publish.
+		let role = self.role()?;
+		let publish = publish.filter(|_| role.is_subscriber());
+		let subscribe = subscribe.filter(|_| role.is_publisher());
+
+		let goaway = start(
+			self.session.clone(),
+			self.stream,
+			version,
+			false,
+			limits,
+			self.options,
+			publish,
+			subscribe,
+		)
+		.await?;
+
+		Ok(Session::new(
+			self.session,
+			server.version,
+			server.extensions,
+			role,
+			goaway,
+		))
 	}
 
-	/// Close the underlying transport session.
+	/// Reject the session, closing the underlying transport session.
 	pub fn close(self, err: Error) {
 		self.session.close(err.to_code(), err.to_string().as_ref());
 	}
+}
 
-	/// Block until the transport session is closed.
-	pub async fn closed(&self) -> Result<(), Error> {
-		match self.session.closed().await {
-			Ok(()) => Ok(()),
-			Err(err) => Err(Error::Transport(Arc::new(err))),
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	use crate::{Broadcast, Origin, Track};
+	use web_transport_ws::tokio_tungstenite::{tungstenite::protocol::Role, WebSocketStream};
+
+	// Connect a client and server over an in-memory WebSocket.
+	async fn transport() -> (web_transport_ws::Session, web_transport_ws::Session) {
+		let (client, server) = tokio::io::duplex(64 * 1024);
+		let client = WebSocketStream::from_raw_socket(client, Role::Client, None).await;
+		let server = WebSocketStream::from_raw_socket(server, Role::Server, None).await;
+
+		(
+			web_transport_ws::Session::new(client, false),
+			web_transport_ws::Session::new(server, true),
+		)
+	}
+
+	#[tokio::test]
+	async fn negotiate_legacy() {
+		// Deployed clients only offer their own version, and the server offers everything.
+		for version in [coding::Version::LITE_00, coding::Version::LITE_01] {
+			let (client, server) = transport().await;
+
+			let mut broadcast = Broadcast::produce();
+			let mut track = broadcast.producer.create_track(Track::new("video"));
+
+			let publish = Origin::produce();
+			publish.producer.publish_broadcast("demo", broadcast.consumer);
+
+			let subscribe = Origin::produce();
+			let mut announced = subscribe.consumer.consume();
+
+			let (client, server) = tokio::join!(
+				Session::connect_versions(
+					client,
+					[version].into(),
+					Default::default(),
+					None,
+					Some(subscribe.producer),
+				),
+				Session::accept(server, publish.consumer, None),
+			);
+			let (client, server) = (client.unwrap(), server.unwrap());
+			assert_eq!(client.version(), version);
+			assert_eq!(server.version(), version);
+
+			let (path, remote) = announced.announced().await.unwrap();
+			assert_eq!(path.as_str(), "demo");
+
+			let mut consumer = remote.unwrap().subscribe_track(&Track::new("video"));
+			track.write_frame(b"hello".as_slice());
+
+			let mut group = consumer.next_group().await.unwrap().unwrap();
+			let frame = group.read_frame().await.unwrap().unwrap();
+			assert_eq!(frame.as_ref(), b"hello");
+		}
+	}
+
+	#[tokio::test]
+	async fn session_stats() {
+		let (client, server) = transport().await;
+
+		let mut broadcast = Broadcast::produce();
+		let mut track = broadcast.producer.create_track(Track::new("video"));
+
+		let publish = Origin::produce();
+		publish.producer.publish_broadcast("demo", broadcast.consumer);
+
+		let subscribe = Origin::produce();
+		let stats = Stats::default();
+
+		let accept = async {
+			Session::request(server)
+				.await?
+				.stats(stats.clone())
+				.ok(publish.consumer, None)
+				.await
+		};
+		let (client, server) = tokio::join!(Session::connect(client, None, subscribe.producer), accept);
+		let (_client, _server) = (client.unwrap(), server.unwrap());
+
+		let remote = subscribe.consumer.consume_broadcast("demo").unwrap();
+		let mut consumer = remote.subscribe_track(&Track::new("video"));
+		track.write_frame(b"hello".as_slice());
+
+		let mut group = consumer.next_group().await.unwrap().unwrap();
+		let frame = group.read_frame().await.unwrap().unwrap();
+		assert_eq!(frame.as_ref(), b"hello");
+
+		// Only the server's session has stats attached.
+		assert_eq!(stats.subscribers().get("demo"), Some(&1));
+		assert!(stats.bytes_sent() > b"hello".len() as u64);
+		assert!(stats.bytes_received() > 0);
+	}
+
+	// An application-defined extension.
+	#[derive(Debug, PartialEq)]
+	struct Greeting(String);
+
+	impl coding::Encode for Greeting {
+		fn encode<W: bytes::BufMut>(&self, w: &mut W) {
+			self.0.encode(w)
+		}
+	}
+
+	impl coding::Decode for Greeting {
+		fn decode<R: bytes::Buf>(r: &mut R) -> Result<Self, coding::DecodeError> {
+			Ok(Self(String::decode(r)?))
 		}
 	}
+
+	impl coding::Extension for Greeting {
+		fn id() -> u64 {
+			0x7e57
+		}
+	}
+
+	#[tokio::test]
+	async fn negotiate_extension() {
+		let (client, server) = transport().await;
+
+		let mut offer = coding::Extensions::default();
+		offer.set(Greeting("hello".to_string()));
+
+		let accept = async {
+			let request = Session::request(server).await?;
+			assert_eq!(
+				request.extensions().get::<Greeting>()?,
+				Some(Greeting("hello".to_string()))
+			);
+
+			let mut answer = coding::Extensions::default();
+			answer.set(Greeting("welcome".to_string()));
+			request.ok_with(answer, None, None).await
+		};
+
+		let (client, server) = tokio::join!(Session::connect_with(client, offer, None, None), accept);
+		let (client, server) = (client.unwrap(), server.unwrap());
+
+		// The server's answer is the negotiated result on both sides.
+		let expected = Some(Greeting("welcome".to_string()));
+		assert_eq!(client.extensions().get::<Greeting>().unwrap(), expected);
+		assert_eq!(server.extensions().get::<Greeting>().unwrap(), expected);
+	}
 }
@@ -0,0 +1,95 @@
+use std::{
+	collections::BTreeMap,
+	sync::{
+		atomic::{AtomicU64, Ordering},
+		Arc, Mutex,
+	},
+};
+
+/// Counters for the sessions they're attached to, useful for exporting metrics.
+///
+/// Cloning shares the counters, so a server can aggregate every session it accepts via [crate::SessionRequest::stats].
+/// Sessions without [Stats] attached don't count anything.
+#[derive(Clone, Default)]
+pub struct Stats(Arc<State>);
+
+#[derive(Default)]
+struct State {
+	// Active subscriptions keyed by the absolute broadcast path.
+	subscriptions: Mutex<BTreeMap<String, u64>>,
+	groups_dropped: AtomicU64,
+	bytes_sent: AtomicU64,
+	bytes_received: AtomicU64,
+}
+
+impl Stats {
+	/// The number of subscriptions currently being served to peers.
+	pub fn subscriptions(&self) -> u64 {
+		self.0.subscriptions.lock().unwrap().values().sum()
+	}
+
+	/// The number of subscriptions currently being served for each broadcast, keyed by absolute path.
+	pub fn subscribers(&self) -> BTreeMap<String, u64> {
+		self.0.subscriptions.lock().unwrap().clone()
+	}
+
+	/// The number of groups skipped or aborted instead of being delivered to a subscriber.
+	pub fn groups_dropped(&self) -> u64 {
+		self.0.groups_dropped.load(Ordering::Relaxed)
+	}
+
+	/// The number of bytes written to streams and datagrams.
+	pub fn bytes_sent(&self) -> u64 {
+		self.0.bytes_sent.load(Ordering::Relaxed)
+	}
+
+	/// The number of bytes read from streams and datagrams.
+	pub fn bytes_received(&self) -> u64 {
+		self.0.bytes_received.load(Ordering::Relaxed)
+	}
+
+	/// Count a subscription to the broadcast until the returned guard is dropped.
+	pub(crate) fn subscribe(&self, broadcast: &str) -> Subscription {
+		*self
+			.0
+			.subscriptions
+			.lock()
+			.unwrap()
+			.entry(broadcast.to_string())
+			.or_default() += 1;
+		Subscription {
+			stats: self.clone(),
+			broadcast: broadcast.to_string(),
+		}
+	}
+
+	pub(crate) fn drop_group(&self) {
+		self.0.groups_dropped.fetch_add(1, Ordering::Relaxed);
+	}
+
+	pub(crate) fn sent(&self, size: usize) {
+		self.0.bytes_sent.fetch_add(size as u64, Ordering::Relaxed);
+	}
+
+	pub(crate) fn received(&self, size: usize) {
+		self.0.bytes_received.fetch_add(size as u64, Ordering::Relaxed);
+	}
+}
+
+/// Decrements the active subscriptions when dropped.
+pub(crate) struct Subscription {
+	stats: Stats,
+	broadcast: String,
+}
+
+impl Drop for Subscription {
+	fn drop(&mut self) {
+		let mut subscriptions = self.stats.0.subscriptions.lock().unwrap();
+		if let Some(count) = subscriptions.get_mut(&self.broadcast) {
+			*count -= 1;
+			if *count == 0 {
+				subscriptions.remove(&self.broadcast);
+			}
+		}
+	}
+}